@@ -34,9 +34,17 @@ fi
 
 set -x
 
-# test the default build (crate has no [features] table)
+# test the default build
 cargo build
 cargo nextest run $NEXTEST_PROFILE
 
+# test the blocking facade, the fake server and the terminal emulation
+cargo build --features blocking,fake,terminal
+cargo nextest run $NEXTEST_PROFILE --features blocking,fake,terminal
+
+# test the other async runtimes
+cargo nextest run $NEXTEST_PROFILE --no-default-features --features tokio,blocking,fake,terminal
+cargo nextest run $NEXTEST_PROFILE --no-default-features --features async-std,blocking,fake,terminal
+
 # doc tests (not supported by nextest)
-cargo test --doc
+cargo test --doc --features blocking,fake,terminal
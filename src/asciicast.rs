@@ -0,0 +1,383 @@
+//! Record the activity of a pane as an [asciicast v2] file, replayable with asciinema.
+//!
+//! An asciicast starts with a header line giving the size of the terminal, followed by one line
+//! per event, timestamped in seconds since the start of the recording:
+//!
+//! ```text
+//! {"version": 2, "width": 80, "height": 24, "timestamp": 1700000000}
+//! [0.248848, "o", "hello\r\n"]
+//! [1.001376, "r", "100x24"]
+//! ```
+//!
+//! A [`Recorder`] writes the output of a pane as `"o"` events, and its size changes as `"r"`
+//! events. It is fed with the notifications of a control mode client, from which it keeps the
+//! `%output` of the pane and the `%layout-change` of its window. Output read from another source,
+//! such as the log of `pipe-pane`, is fed with [`Recorder::output`].
+//!
+//! [`record`] attaches a control mode client and records a pane until it is closed:
+//!
+//! ```no_run
+//! # async fn example(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+//! use std::{fs::File, io::BufWriter};
+//! use tmux_lib::asciicast;
+//!
+//! let file = BufWriter::new(File::create("pane.cast")?);
+//! asciicast::record(pane_id, file).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Result,
+    control::{Connection, Notification},
+    error::Error,
+    format,
+    pane_id::PaneId,
+    session_id::SessionId,
+    window_id::WindowId,
+};
+
+/// Writes the activity of a pane as an asciicast v2 stream.
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+    writer: W,
+    /// Pane whose activity is recorded.
+    pane_id: PaneId,
+    /// Current width and height of the pane.
+    size: (u16, u16),
+    /// Start of the recording, which event times are relative to.
+    start: Instant,
+    /// Start of a utf-8 character whose remaining bytes were not received yet.
+    partial: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start recording the pane `pane_id`, of `width` columns and `height` rows, by writing the
+    /// header of the asciicast to `writer`.
+    pub fn new(mut writer: W, pane_id: &PaneId, width: u16, height: u16) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        writeln!(
+            writer,
+            r#"{{"version": 2, "width": {width}, "height": {height}, "timestamp": {timestamp}}}"#
+        )?;
+
+        Ok(Self {
+            writer,
+            pane_id: pane_id.clone(),
+            size: (width, height),
+            start: Instant::now(),
+            partial: vec![],
+        })
+    }
+
+    /// Record the output or the size change of the pane carried by `notification`. Other
+    /// notifications are ignored.
+    ///
+    /// Returns `true` if an event was written.
+    pub fn process(&mut self, notification: &Notification) -> io::Result<bool> {
+        match notification {
+            Notification::Output { pane_id, data }
+            | Notification::ExtendedOutput { pane_id, data, .. }
+                if *pane_id == self.pane_id =>
+            {
+                self.output(data)
+            }
+            // The visible layout gives the size of the pane when it is zoomed.
+            Notification::LayoutChange { visible_layout, .. } => {
+                match visible_layout.pane_size(&self.pane_id) {
+                    Some((width, height)) => self.resize(width, height),
+                    None => Ok(false),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Record `data` written by the pane, as an `"o"` event.
+    ///
+    /// The bytes of a utf-8 character split across calls are kept until the character is
+    /// complete. Invalid utf-8 is replaced with `U+FFFD`.
+    ///
+    /// Returns `true` if an event was written, `false` if `data` only started a character.
+    pub fn output(&mut self, data: &[u8]) -> io::Result<bool> {
+        self.partial.extend_from_slice(data);
+        let text = take_utf8(&mut self.partial);
+        if text.is_empty() {
+            return Ok(false);
+        }
+        self.event("o", &text)?;
+        Ok(true)
+    }
+
+    /// Record that the pane was resized to `width` columns and `height` rows, as an `"r"` event.
+    ///
+    /// Returns `true` if an event was written, `false` if the pane already had this size.
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<bool> {
+        if self.size == (width, height) {
+            return Ok(false);
+        }
+        self.size = (width, height);
+        self.event("r", &format!("{width}x{height}"))?;
+        Ok(true)
+    }
+
+    /// Current width and height of the pane.
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    /// Flush the writer and return it.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Write an event of type `code`, timestamped now.
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(
+            self.writer,
+            "[{time:.6}, \"{code}\", {}]",
+            json_string(data)
+        )
+    }
+}
+
+/// Record the pane `pane_id` to `writer` until it is closed, and return the writer.
+///
+/// A control mode client is attached to the session of the pane, without selecting the pane or
+/// its window; the recording starts with the pane output following the attachment, without the
+/// content already displayed.
+pub async fn record<W: Write>(pane_id: &PaneId, writer: W) -> Result<W> {
+    // Attaching to the pane itself would make it the active pane of the current window.
+    let session_id = format::expand(pane_id, "#{session_id}").await?;
+    let session_id = SessionId::from_str(&session_id)?;
+    let mut connection = Connection::attach(&session_id).await?;
+
+    let format = "#{window_id}:#{pane_width}:#{pane_height}";
+    let output = connection
+        .command(["display-message", "-p", "-t", pane_id.as_str(), format])
+        .await?;
+    let (window_id, width, height) = parse_pane_window_size(&output.concat())?;
+
+    let mut recorder = Recorder::new(writer, pane_id, width, height)?;
+    while let Some(notification) = connection.next().await? {
+        if pane_closed(&notification, pane_id, &window_id) {
+            break;
+        }
+        recorder.process(&notification)?;
+    }
+
+    Ok(recorder.into_inner()?)
+}
+
+/// Parse the window, width and height of a pane, printed as `@1:80:24`.
+fn parse_pane_window_size(output: &str) -> Result<(WindowId, u16, u16)> {
+    let unexpected = || Error::UnexpectedTmuxOutput {
+        intent: "display-message",
+        stdout: output.to_string(),
+        stderr: String::new(),
+    };
+
+    let mut fields = output.split(':');
+    let (Some(window_id), Some(width), Some(height), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(unexpected());
+    };
+    let window_id = window_id.parse()?;
+    let width = width.parse().map_err(|_| unexpected())?;
+    let height = height.parse().map_err(|_| unexpected())?;
+    Ok((window_id, width, height))
+}
+
+/// Return `true` if `notification` shows that the pane `pane_id`, in the window `window_id`, was
+/// closed.
+fn pane_closed(notification: &Notification, pane_id: &PaneId, window_id: &WindowId) -> bool {
+    match notification {
+        // The remaining panes of the window were laid out without it.
+        Notification::LayoutChange {
+            window_id: id,
+            layout,
+            ..
+        } => id == window_id && layout.pane_size(pane_id).is_none(),
+        // It was the last pane of its window.
+        Notification::Other(line) => {
+            let mut words = line.split(' ');
+            matches!(
+                (words.next(), words.next()),
+                (Some("%window-close" | "%unlinked-window-close"), Some(id)) if id == window_id.as_str()
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Remove and return the longest valid utf-8 prefix of `bytes`, keeping an incomplete character
+/// at the end. Invalid sequences are replaced with `U+FFFD`.
+fn take_utf8(bytes: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut input = bytes.as_slice();
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(valid) => {
+                text.push_str(valid);
+                input = &[];
+                break;
+            }
+            Err(error) => {
+                let (valid, after) = input.split_at(error.valid_up_to());
+                // The prefix was just checked.
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                match error.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        input = &after[len..];
+                    }
+                    // Incomplete character at the end.
+                    None => {
+                        input = after;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let consumed = bytes.len() - input.len();
+    bytes.drain(..consumed);
+    text
+}
+
+/// Quote `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if u32::from(c) < 0x20 || c == '\u{7f}' => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn events(recorder: Recorder<Vec<u8>>) -> Vec<String> {
+        let cast = String::from_utf8(recorder.into_inner().unwrap()).unwrap();
+        cast.lines()
+            .skip(1)
+            .map(|line| {
+                // Drop the time, which depends on the speed of the test.
+                let (_, event) = line.split_once(", ").unwrap();
+                event.to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_header() {
+        let recorder = Recorder::new(vec![], &PaneId::from(&1), 80, 24).unwrap();
+        let cast = String::from_utf8(recorder.into_inner().unwrap()).unwrap();
+        assert!(cast.starts_with(r#"{"version": 2, "width": 80, "height": 24, "timestamp": "#));
+        assert!(cast.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_record_notifications() {
+        let mut recorder = Recorder::new(vec![], &PaneId::from(&1), 80, 24).unwrap();
+        let lines: [&[u8]; 6] = [
+            br"%output %1 $ echo \134\042hi\042\015\012",
+            br"%output %2 other pane",
+            br"%extended-output %1 10 : tab\011bell\007",
+            b"%layout-change @0 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} *",
+            b"%layout-change @0 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} *",
+            b"%window-add @1",
+        ];
+        let written: Vec<bool> = lines
+            .iter()
+            .map(|line| {
+                let notification = Notification::from_bytes(line).unwrap();
+                recorder.process(&notification).unwrap()
+            })
+            .collect();
+        assert_eq!(written, vec![true, false, true, true, false, false]);
+        assert_eq!(recorder.size(), (39, 24));
+
+        assert_eq!(
+            events(recorder),
+            vec![
+                r#""o", "$ echo \\\"hi\"\r\n"]"#,
+                r#""o", "tab\tbell\u0007"]"#,
+                r#""r", "39x24"]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_split_characters() {
+        let mut recorder = Recorder::new(vec![], &PaneId::from(&1), 80, 24).unwrap();
+        let bytes = "é→".as_bytes();
+        assert!(!recorder.output(&bytes[..1]).unwrap());
+        assert!(recorder.output(&bytes[1..3]).unwrap());
+        assert!(recorder.output(&bytes[3..]).unwrap());
+        assert!(recorder.output(b"\xffok").unwrap());
+
+        assert_eq!(
+            events(recorder),
+            vec![r#""o", "é"]"#, r#""o", "→"]"#, "\"o\", \"\u{fffd}ok\"]"]
+        );
+    }
+
+    #[test]
+    fn test_pane_closed() {
+        let pane_id = PaneId::from(&1);
+        let window_id = WindowId::from_str("@0").unwrap();
+        let closed = |line: &[u8]| {
+            let notification = Notification::from_bytes(line).unwrap();
+            pane_closed(&notification, &pane_id, &window_id)
+        };
+
+        assert!(closed(b"%window-close @0"));
+        assert!(closed(b"%unlinked-window-close @0"));
+        assert!(!closed(b"%window-close @10"));
+        assert!(closed(
+            b"%layout-change @0 b25d,80x24,0,0,0 b25d,80x24,0,0,0 *"
+        ));
+        assert!(!closed(
+            b"%layout-change @0 b25d,80x24,0,0,1 b25d,80x24,0,0,1 *"
+        ));
+        assert!(!closed(
+            b"%layout-change @2 b25d,80x24,0,0,0 b25d,80x24,0,0,0 *"
+        ));
+    }
+
+    #[test]
+    fn test_parse_pane_window_size() {
+        let (window_id, width, height) = parse_pane_window_size("@3:120:40").unwrap();
+        assert_eq!(window_id.as_str(), "@3");
+        assert_eq!((width, height), (120, 40));
+        assert!(parse_pane_window_size("@3:120").is_err());
+    }
+}
@@ -0,0 +1,450 @@
+//! Run many tmux commands with a single tmux invocation.
+//!
+//! Restoring a large server creates hundreds of windows and panes; spawning one tmux process
+//! per command is slow. A [`CommandBatch`] accumulates commands and sends them at once, either
+//! as arguments (`tmux cmd1 \; cmd2`) or as a config file read from stdin (`tmux source-file -`).
+
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+
+use crate::{
+    Result,
+    command::{TmuxCommand, quote},
+    error::{Error, check_process_success},
+    format::unique_delimiter,
+    pane::{RespawnPane, SplitPane},
+    policy::Retry,
+    runtime::{Command, output_with_stdin},
+    session::NewSession,
+    version::{self, Capability, Version},
+    window::{NewWindow, RespawnWindow},
+};
+
+/// Maximum size of the arguments of a single tmux invocation.
+///
+/// The tmux client sends its arguments to the server in a single message, limited to 16 KiB
+/// including headers, and fails with `command too long` beyond.
+const MAX_ARGUMENTS_SIZE: usize = 16_000;
+
+/// How a [`CommandBatch`] passes commands to tmux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Commands are passed as arguments separated by `;`, split into several invocations if
+    /// their total size exceeds what tmux accepts. Execution stops at the first failing command.
+    Arguments,
+    /// Commands are written as a config file to the stdin of `tmux source-file -`, without size
+    /// limit. Arguments must be valid UTF-8. The commands after a failing one still run, but the
+    /// batch fails all the same, without the outputs of the commands which succeeded.
+    SourceFile,
+}
+
+/// A command which can be added to a [`CommandBatch`].
+pub trait BatchCommand {
+    /// Arguments passed to tmux, starting with the command name.
+    fn batch_args(&self) -> Vec<OsString>;
+
+    /// Capability needed by [`batch_args`](BatchCommand::batch_args), along with the arguments
+    /// passed instead to versions of tmux without it.
+    fn batch_fallback(&self) -> Option<(Capability, Vec<OsString>)> {
+        None
+    }
+}
+
+impl BatchCommand for TmuxCommand {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args().into_iter().map(Into::into).collect()
+    }
+}
+
+impl BatchCommand for NewSession {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args()
+    }
+}
+
+impl BatchCommand for NewWindow {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args()
+    }
+}
+
+impl BatchCommand for SplitPane {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args()
+    }
+
+    fn batch_fallback(&self) -> Option<(Capability, Vec<OsString>)> {
+        self.uses_percent_size()
+            .then(|| (Capability::PercentSizes, self.args_with(false)))
+    }
+}
+
+impl BatchCommand for RespawnPane {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args()
+    }
+}
+
+impl BatchCommand for RespawnWindow {
+    fn batch_args(&self) -> Vec<OsString> {
+        self.args()
+    }
+}
+
+/// Commands sent to tmux with a single invocation, or a few if they are too large.
+///
+/// ```no_run
+/// # async fn restore(window_id: &tmux_lib::window_id::WindowId) -> tmux_lib::Result<()> {
+/// use tmux_lib::{batch::CommandBatch, pane::SplitPane};
+///
+/// let mut batch = CommandBatch::new();
+/// batch
+///     .add_command(&SplitPane::new().target_window(window_id))
+///     .add(["select-layout", "-t", window_id.as_str(), "tiled"]);
+///
+/// let outputs = batch.run().await?;
+/// let new_pane_id = outputs[0].trim_end();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandBatch {
+    mode: BatchMode,
+    commands: Vec<Vec<OsString>>,
+    /// Index of the commands needing a capability, with their arguments for older versions.
+    fallbacks: Vec<(usize, Capability, Vec<OsString>)>,
+}
+
+impl Default for CommandBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandBatch {
+    /// Prepare an empty batch, passing commands as arguments.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            mode: BatchMode::Arguments,
+            commands: vec![],
+            fallbacks: vec![],
+        }
+    }
+
+    /// Set how commands are passed to tmux.
+    #[must_use]
+    pub fn mode(mut self, mode: BatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Append a command given as its arguments, e.g. `["select-pane", "-t", "%3"]`.
+    pub fn add<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.commands
+            .push(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Append a command built with one of the builders of this crate.
+    ///
+    /// Commands needing a recent tmux, such as a [`SplitPane`] sized with a percentage, fall back
+    /// to the arguments older versions accept when the batch runs.
+    pub fn add_command(&mut self, command: &impl BatchCommand) -> &mut Self {
+        if let Some((capability, args)) = command.batch_fallback() {
+            self.fallbacks.push((self.commands.len(), capability, args));
+        }
+        self.commands.push(command.batch_args());
+        self
+    }
+
+    /// Number of commands in the batch.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Describes if the batch has no command.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Run all commands, and return the output of each command, in order.
+    ///
+    /// Each output is the raw stdout of the command, e.g. `"%12\n"` for a pane created with
+    /// [`SplitPane`], and is empty for most commands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::UnexpectedTmuxOutput` with the stderr of tmux if any command failed,
+    /// whatever the [`BatchMode`]: the outputs of the other commands are then not returned.
+    pub async fn run(&self) -> Result<Vec<String>> {
+        if self.commands.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let commands = if self.fallbacks.is_empty() {
+            Cow::Borrowed(&self.commands[..])
+        } else {
+            self.commands_for(version::version().await?)
+        };
+
+        // A marker printed after each command delimits the outputs of commands.
+        let marker = unique_delimiter();
+        let mut outputs = Vec::with_capacity(commands.len());
+
+        match self.mode {
+            BatchMode::Arguments => {
+                for chunk in chunks(&commands, &marker, MAX_ARGUMENTS_SIZE) {
+                    // Commands before a failing one already ran, retrying would run them again.
+                    let output = Command::new("tmux")
+                        .args(arguments(chunk, &marker))
+                        .retry(Retry::Never)
+                        .output()
+                        .await?;
+                    check_process_success(&output, "command batch")?;
+                    let stdout = String::from_utf8(output.stdout)?;
+                    outputs.extend(split_outputs(&stdout, &marker, chunk.len())?);
+                }
+            }
+            BatchMode::SourceFile => {
+                let config = source_file(&commands, &marker)?;
+                let mut command = Command::new("tmux");
+                command.args(["source-file", "-"]).retry(Retry::Never);
+                let output = output_with_stdin(command, config.as_bytes()).await?;
+                check_process_success(&output, "command batch")?;
+                let stdout = String::from_utf8(output.stdout)?;
+                outputs.extend(split_outputs(&stdout, &marker, commands.len())?);
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Commands passed to `version` of tmux, using the fallbacks of the capabilities it lacks.
+    fn commands_for(&self, version: Version) -> Cow<'_, [Vec<OsString>]> {
+        let mut commands = Cow::Borrowed(&self.commands[..]);
+        for (index, capability, args) in &self.fallbacks {
+            if !version.supports(*capability) {
+                commands.to_mut()[*index].clone_from(args);
+            }
+        }
+        commands
+    }
+}
+
+/// Command printing `marker`, appended after each command.
+fn marker_command(marker: &str) -> [&str; 3] {
+    ["display-message", "-p", marker]
+}
+
+/// Escape an argument so that tmux does not take a trailing `;` for a command separator.
+fn escape_argument(arg: &OsString) -> OsString {
+    match arg.as_encoded_bytes().strip_suffix(b";") {
+        Some(head) => {
+            let mut escaped = head.to_vec();
+            escaped.extend_from_slice(b"\\;");
+            OsString::from_vec(escaped)
+        }
+        None => arg.clone(),
+    }
+}
+
+/// Size of a command in the message sent by the tmux client, each argument being NUL-terminated.
+fn arguments_size(args: &[OsString]) -> usize {
+    args.iter().map(|arg| arg.len() + 1).sum()
+}
+
+/// Split `commands` into chunks whose arguments, including separators and markers, fit in
+/// `max_size`. A command too large on its own gets its own chunk.
+fn chunks<'a>(
+    commands: &'a [Vec<OsString>],
+    marker: &str,
+    max_size: usize,
+) -> Vec<&'a [Vec<OsString>]> {
+    let marker_size: usize = marker_command(marker).iter().map(|arg| arg.len() + 1).sum();
+    let mut chunks = vec![];
+    let (mut start, mut size) = (0, 0);
+
+    for (index, command) in commands.iter().enumerate() {
+        // Each command is followed by a separator, the marker command and another separator.
+        let command_size = arguments_size(command) + 2 + marker_size + 2;
+        if index > start && size + command_size > max_size {
+            chunks.push(&commands[start..index]);
+            (start, size) = (index, 0);
+        }
+        size += command_size;
+    }
+    chunks.push(&commands[start..]);
+    chunks
+}
+
+/// Arguments of a single tmux invocation running all `commands`, each followed by the marker.
+fn arguments(commands: &[Vec<OsString>], marker: &str) -> Vec<OsString> {
+    let mut args = vec![];
+    for (index, command) in commands.iter().enumerate() {
+        if index > 0 {
+            args.push(";".into());
+        }
+        args.extend(command.iter().map(escape_argument));
+        args.push(";".into());
+        args.extend(marker_command(marker).map(OsString::from));
+    }
+    args
+}
+
+/// Config file running all `commands`, one per line, each followed by the marker.
+fn source_file(commands: &[Vec<OsString>], marker: &str) -> Result<String> {
+    let mut config = String::new();
+    for command in commands {
+        for (index, arg) in command.iter().enumerate() {
+            let arg = arg.to_str().ok_or_else(|| Error::UnexpectedTmuxOutput {
+                intent: "command batch",
+                stdout: String::new(),
+                stderr: format!("argument is not valid utf-8: {}", arg.to_string_lossy()),
+            })?;
+            if index > 0 {
+                config.push(' ');
+            }
+            config.push_str(&quote(arg));
+        }
+        config.push_str(" ; ");
+        config.push_str(&marker_command(marker).map(quote).join(" "));
+        config.push('\n');
+    }
+    Ok(config)
+}
+
+/// Split the stdout of a batch into the outputs of its `count` commands.
+fn split_outputs(stdout: &str, marker: &str, count: usize) -> Result<Vec<String>> {
+    let terminated_marker = format!("{marker}\n");
+    let mut outputs: Vec<String> = stdout.split(&terminated_marker).map(String::from).collect();
+
+    // The last marker is followed by nothing.
+    if outputs.len() != count + 1 || outputs.last().is_some_and(|last| !last.is_empty()) {
+        return Err(Error::UnexpectedTmuxOutput {
+            intent: "command batch",
+            stdout: stdout.to_string(),
+            stderr: format!("expected the outputs of {count} commands"),
+        });
+    }
+    outputs.pop();
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pane::PaneSize;
+
+    fn command(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn escape_trailing_semicolon() {
+        assert_eq!(escape_argument(&"ls".into()), "ls");
+        assert_eq!(escape_argument(&"ls;".into()), "ls\\;");
+        assert_eq!(escape_argument(&"a\\;".into()), "a\\\\;");
+        assert_eq!(escape_argument(&"a;b".into()), "a;b");
+    }
+
+    #[test]
+    fn arguments_with_markers() {
+        let commands = [
+            command(&["select-pane", "-t", "%3"]),
+            command(&["send-keys", "ls;", "Enter"]),
+        ];
+
+        assert_eq!(
+            arguments(&commands, "M"),
+            [
+                "select-pane",
+                "-t",
+                "%3",
+                ";",
+                "display-message",
+                "-p",
+                "M",
+                ";",
+                "send-keys",
+                "ls\\;",
+                "Enter",
+                ";",
+                "display-message",
+                "-p",
+                "M",
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_fit_in_max_size() {
+        let commands = vec![command(&["select-pane", "-t", "%3"]); 10];
+        // Each command takes 12 + 2 + 22 + 2 = 38 bytes with a 3 byte marker.
+        let chunked = chunks(&commands, "MMM", 100);
+
+        assert_eq!(
+            chunked.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+            [2, 2, 2, 2, 2]
+        );
+        assert_eq!(chunks(&commands, "MMM", 10_000).len(), 1);
+
+        // A command too large on its own is not dropped.
+        let large = vec![command(&["display-message", &"x".repeat(200)])];
+        assert_eq!(chunks(&large, "MMM", 100), [&large[..]]);
+    }
+
+    #[test]
+    fn source_file_with_markers() {
+        let commands = [command(&["rename-window", "-t", "@1", "my window"])];
+
+        assert_eq!(
+            source_file(&commands, "M").unwrap(),
+            "rename-window -t @1 \"my window\" ; display-message -p M\n"
+        );
+
+        let commands = [
+            command(&["rename-window", "-t", "%3", "%work"]),
+            command(&["set-option", "@clock", "%H:%M"]),
+        ];
+        assert_eq!(
+            source_file(&commands, "M").unwrap(),
+            "rename-window -t %3 \"%work\" ; display-message -p M\n\
+             set-option @clock \"%H:%M\" ; display-message -p M\n"
+        );
+    }
+
+    #[test]
+    fn percent_sizes_fall_back_before_tmux_3_1() {
+        let mut batch = CommandBatch::new();
+        batch
+            .add(["select-pane", "-t", "%3"])
+            .add_command(&SplitPane::new().size(PaneSize::Percent(30)))
+            .add_command(&SplitPane::new().size(PaneSize::Cells(10)));
+
+        let commands = batch.commands_for(Version::new(3, 4));
+        assert!(matches!(commands, Cow::Borrowed(_)));
+        assert_eq!(commands[1][3..5], ["-l", "30%"]);
+
+        let commands = batch.commands_for(Version::new(3, 0));
+        assert_eq!(commands[0], command(&["select-pane", "-t", "%3"]));
+        assert_eq!(commands[1][3..5], ["-p", "30"]);
+        assert_eq!(commands[2][3..5], ["-l", "10"]);
+    }
+
+    #[test]
+    fn split_command_outputs() {
+        let outputs = split_outputs("%5\nM\nM\na\nb\nM\n", "M", 3).unwrap();
+        assert_eq!(outputs, ["%5\n", "", "a\nb\n"]);
+
+        assert!(split_outputs("%5\nM\n", "M", 2).is_err());
+        assert!(split_outputs("%5\nM\nextra", "M", 1).is_err());
+    }
+}
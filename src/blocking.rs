@@ -0,0 +1,170 @@
+//! Synchronous mirror of the ops, for programs which do not run an async executor.
+//!
+//! Each submodule mirrors the functions of the module with the same name, running tmux with
+//! `std::process::Command` and returning the same types, parsed by the same parsers. The builders
+//! get a `run_blocking` method alongside their async `run`.
+//!
+//! The invocations follow the current [`Policy`](crate::policy::Policy), set for a block of
+//! blocking ops with [`Policy::scope_blocking`](crate::policy::Policy::scope_blocking): they are
+//! killed once they exceed its timeout, and retried when they fail with a transient error.
+//!
+//! This module requires the `blocking` cargo feature.
+//!
+//! ```no_run
+//! # fn list() -> tmux_lib::Result<()> {
+//! use tmux_lib::{blocking, window::NewWindow};
+//!
+//! for session in blocking::session::available_sessions()? {
+//!     println!("{}: {}", session.id.as_str(), session.name);
+//! }
+//!
+//! let (window_id, pane_id) = NewWindow::new().name("logs").run_blocking()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, Read},
+    process::{Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::policy::{self, Policy, Retry};
+
+pub mod client;
+pub mod pane;
+pub mod server;
+pub mod session;
+pub mod version;
+pub mod window;
+
+/// Delay between checks whether an invocation with a timeout has exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A tmux invocation, spawned with `std::process::Command`.
+///
+/// With the `fake` feature, the invocation is answered by the [`FakeServer`] installed for the
+/// current thread, if any.
+///
+/// [`FakeServer`]: crate::fake::FakeServer
+pub(crate) struct Command {
+    inner: std::process::Command,
+    retry: Retry,
+    #[cfg(any(feature = "fake", feature = "tracing"))]
+    args: Vec<OsString>,
+}
+
+impl Command {
+    pub(crate) fn new(program: &str) -> Self {
+        Self {
+            inner: std::process::Command::new(program),
+            retry: Retry::Unsent,
+            #[cfg(any(feature = "fake", feature = "tracing"))]
+            args: vec![],
+        }
+    }
+
+    pub(crate) fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        self.inner.args(&args);
+        #[cfg(any(feature = "fake", feature = "tracing"))]
+        self.args.extend(args);
+        self
+    }
+
+    /// Set the failures after which the command is retried, see [`crate::policy`].
+    pub(crate) fn retry(&mut self, retry: Retry) -> &mut Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run the command and collect its output.
+    pub(crate) fn output(&mut self) -> io::Result<Output> {
+        #[cfg(feature = "fake")]
+        if let Some(output) = crate::fake::intercept(&self.args, None) {
+            return Ok(output);
+        }
+
+        let policy = policy::current();
+        let mut attempt = 0;
+        loop {
+            #[cfg(feature = "tracing")]
+            let invocation = crate::trace::Invocation::start(&self.args);
+            let output = self.bounded_output(&policy);
+            #[cfg(feature = "tracing")]
+            invocation.finish(&output);
+            let output = output?;
+            if output.status.success() {
+                return Ok(output);
+            }
+            attempt += 1;
+            match policy.retry_delay(self.retry, attempt, &output.stderr) {
+                Some(delay) => thread::sleep(delay),
+                None => return Ok(output),
+            }
+        }
+    }
+
+    /// Run the command once, killing it if it exceeds the timeout of `policy`.
+    fn bounded_output(&mut self, policy: &Policy) -> io::Result<Output> {
+        let Some(timeout) = policy.invocation_timeout() else {
+            return self.inner.output();
+        };
+
+        let mut child = self
+            .inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                // The process may exit between the last check and the kill.
+                let _ = child.kill();
+                child.wait()?;
+                return Err(policy::timed_out(timeout));
+            }
+            thread::sleep(EXIT_POLL_INTERVAL);
+        };
+
+        let join = |reader: thread::JoinHandle<io::Result<Vec<u8>>>| {
+            reader
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("the output reader panicked")))
+        };
+        Ok(Output {
+            status,
+            stdout: join(stdout)?,
+            stderr: join(stderr)?,
+        })
+    }
+}
+
+/// Read `pipe` to its end on a new thread, so that the process does not block writing to it.
+fn read_to_end<R: Read + Send + 'static>(
+    pipe: Option<R>,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buffer)?;
+        }
+        Ok(buffer)
+    })
+}
@@ -0,0 +1,107 @@
+//! Blocking mirror of [`crate::client`].
+
+use std::str::FromStr;
+
+use crate::{
+    Result,
+    blocking::Command,
+    client::{Client, DisplayMessage, FORMAT},
+    error::{check_empty_process_output, check_process_success},
+    policy::Retry,
+    target::Target,
+};
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the current client useful attributes.
+pub fn current() -> Result<Client> {
+    let args = vec!["display-message", "-p", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    Client::from_str(buffer.trim_end())
+}
+
+impl DisplayMessage {
+    /// Display the message in the status line of the client, see [`DisplayMessage::run`].
+    pub fn run_blocking(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args(false)).output()?;
+        check_empty_process_output(&output, "display-message")
+    }
+
+    /// Return the expanded message instead of displaying it, see [`DisplayMessage::print`].
+    pub fn print_blocking(&self) -> Result<String> {
+        let output = Command::new("tmux")
+            .args(self.args(true))
+            .retry(Retry::Lookups)
+            .output()?;
+        check_process_success(&output, "display-message")?;
+        let buffer = String::from_utf8(output.stdout)?;
+
+        Ok(Self::parse_printed(&buffer))
+    }
+}
+
+/// Switch to session exactly named `session_name`.
+pub fn switch_client(session_name: &str) -> Result<()> {
+    let exact_session_name = format!("={session_name}");
+    let args = vec!["switch-client", "-t", &exact_session_name];
+
+    Command::new("tmux").args(&args).output()?;
+
+    Ok(())
+}
+
+/// Switch the client named `client_name` to `target`, which can be a session, a window or a
+/// pane.
+pub fn switch_client_to<'a>(client_name: &str, target: impl Into<Target<'a>>) -> Result<()> {
+    let target = target.into();
+    let args = vec!["switch-client", "-c", client_name, "-t", target.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "switch-client")
+}
+
+/// Detach the client named `client_name`.
+pub fn detach_client(client_name: &str) -> Result<()> {
+    let args = vec!["detach-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "detach-client")
+}
+
+/// Redraw the client named `client_name`.
+pub fn refresh_client(client_name: &str) -> Result<()> {
+    let args = vec!["refresh-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Redraw only the status line of the client named `client_name` (`refresh-client -S`).
+pub fn refresh_client_status(client_name: &str) -> Result<()> {
+    let args = vec!["refresh-client", "-S", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Set the size of the control mode client named `client_name` (`refresh-client -C`).
+pub fn set_client_size(client_name: &str, width: u16, height: u16) -> Result<()> {
+    let size = format!("{width}x{height}");
+    let args = vec!["refresh-client", "-C", &size, "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Suspend the client named `client_name`, by sending it a `SIGTSTP` signal.
+pub fn suspend_client(client_name: &str) -> Result<()> {
+    let args = vec!["suspend-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "suspend-client")
+}
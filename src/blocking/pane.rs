@@ -0,0 +1,126 @@
+//! Blocking mirror of [`crate::pane`].
+
+use std::str::FromStr;
+
+use crate::{
+    Result,
+    blocking::{Command, version},
+    error::{check_empty_process_output, check_process_success},
+    pane::{
+        FORMAT, Pane, PipePane, RespawnPane, SplitDirection, SplitPane, parse_pane_pipe,
+        parse_panes,
+    },
+    pane_id::PaneId,
+    policy::Retry,
+    version::Capability,
+    window_id::WindowId,
+};
+
+impl Pane {
+    /// Return the entire Pane content as a `Vec<u8>`, see [`Pane::capture`].
+    pub fn capture_blocking(&self) -> Result<Vec<u8>> {
+        let output = Command::new("tmux")
+            .args(self.capture_args())
+            .retry(Retry::Lookups)
+            .output()?;
+
+        Ok(output.stdout)
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return a list of all `Pane` from all sessions.
+pub fn available_panes() -> Result<Vec<Pane>> {
+    let args = vec!["list-panes", "-a", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    parse_panes(&buffer)
+}
+
+/// Create a new pane (horizontal split) in the window with `window_id`, see
+/// [`crate::pane::new_pane`].
+pub fn new_pane(
+    reference_pane: &Pane,
+    pane_command: Option<&str>,
+    window_id: &WindowId,
+) -> Result<PaneId> {
+    let mut split_pane = SplitPane::new()
+        .target_window(window_id)
+        .direction(SplitDirection::Horizontal)
+        .start_directory(&reference_pane.dirpath)
+        .detached(false);
+    if let Some(pane_command) = pane_command {
+        split_pane = split_pane.command(pane_command);
+    }
+
+    split_pane.run_blocking()
+}
+
+impl SplitPane {
+    /// Split the pane, and return the id of the new pane, see [`SplitPane::run`].
+    pub fn run_blocking(&self) -> Result<PaneId> {
+        let percent_sizes =
+            !self.uses_percent_size() || version::supports(Capability::PercentSizes)?;
+        let output = Command::new("tmux")
+            .args(self.args_with(percent_sizes))
+            .output()?;
+        check_process_success(&output, "split-window")?;
+
+        let buffer = String::from_utf8(output.stdout)?;
+
+        PaneId::from_str(buffer.trim_end())
+    }
+}
+
+/// Select (make active) the pane with `pane_id`.
+pub fn select_pane(pane_id: &PaneId) -> Result<()> {
+    let args = vec!["select-pane", "-t", pane_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "select-pane")
+}
+
+impl RespawnPane {
+    /// Respawn the pane, see [`RespawnPane::run`].
+    pub fn run_blocking(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args()).output()?;
+        check_empty_process_output(&output, "respawn-pane")
+    }
+}
+
+impl PipePane {
+    /// Pipe the pane, see [`PipePane::run`].
+    pub fn run_blocking(&self) -> Result<()> {
+        version::require(Capability::PipeDirections)?;
+
+        let output = Command::new("tmux").args(self.args()).output()?;
+        check_empty_process_output(&output, "pipe-pane")
+    }
+}
+
+/// Close the pipe of the pane with `pane_id`, if any.
+pub fn stop_pipe_pane(pane_id: &PaneId) -> Result<()> {
+    let args = vec!["pipe-pane", "-t", pane_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "pipe-pane")
+}
+
+/// Return `true` if the pane with `pane_id` is piped to a command (`pane_pipe`).
+pub fn is_pane_piped(pane_id: &PaneId) -> Result<bool> {
+    let args = vec![
+        "display-message",
+        "-p",
+        "-t",
+        pane_id.as_str(),
+        "#{pane_pipe}",
+    ];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    parse_pane_pipe(&output)
+}
@@ -0,0 +1,141 @@
+//! Blocking mirror of [`crate::server`].
+
+use std::{collections::HashMap, ffi::OsStr, path::Path, thread, time::Instant};
+
+use crate::{
+    Result,
+    blocking::Command,
+    error::{Error, check_empty_process_output, check_kill_output, check_process_success},
+    policy,
+    server::{
+        CLIENTS_ARGS, INFO_ARGS, Server, ServerInfo, parse_option, parse_options,
+        select_default_command,
+    },
+};
+
+impl Server {
+    /// Run the tmux command `args` on this server, and return its output, see [`Server::run`].
+    pub fn run_blocking<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let output = Command::new("tmux")
+            .args(self.socket_args())
+            .args(args)
+            .output()?;
+        check_process_success(&output, "server-command")?;
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Describe the server, see [`Server::info`].
+    pub fn info_blocking(&self) -> Result<ServerInfo> {
+        let buffer = self.run_blocking(INFO_ARGS)?;
+        let mut info = self.parse_info(&buffer)?;
+        if info.session_count > 0 {
+            info.client_count = self.run_blocking(CLIENTS_ARGS)?.lines().count();
+        }
+
+        Ok(info)
+    }
+
+    /// Start the server, see [`Server::start`].
+    pub fn start_blocking(&self, config_file: Option<&Path>) -> Result<()> {
+        self.run_blocking(self.start_args(config_file)).map(|_| ())
+    }
+
+    /// Kill the server, see [`Server::kill`].
+    pub fn kill_blocking(&self) -> Result<()> {
+        self.run_blocking(["kill-server"]).map(|_| ())
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Start the Tmux server if needed, creating a session named `initial_session_name` in order to
+/// keep the server running, see [`crate::server::start`].
+pub fn start(initial_session_name: &str) -> Result<()> {
+    let args = vec!["new-session", "-d", "-s", initial_session_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "new-session")?;
+
+    // Wait for the server to be fully ready to accept commands.
+    wait_for_server_ready()
+}
+
+/// Wait for the tmux server to be ready to accept commands.
+///
+/// This polls the server using `tmux list-sessions` until it succeeds or times out, as configured
+/// by the current [`Policy`](crate::policy::Policy).
+fn wait_for_server_ready() -> Result<()> {
+    let (ready_timeout, poll_interval) = policy::current().server_ready();
+    let deadline = Instant::now() + ready_timeout;
+    loop {
+        let output = Command::new("tmux")
+            .args(["list-sessions", "-F", "#{session_name}"])
+            .output()?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::UnexpectedTmuxOutput {
+                intent: "wait-for-server-ready",
+                stdout: String::new(),
+                stderr: format!("server did not become ready within {ready_timeout:?}"),
+            });
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Remove the session named `name` used to keep the server alive.
+pub fn kill_session(name: &str) -> Result<()> {
+    let exact_name = format!("={name}");
+    let args = vec!["kill-session", "-t", &exact_name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_kill_output(&output, "kill-session")
+}
+
+/// Return the value of a Tmux option.
+pub fn show_option(option_name: &str, global: bool) -> Result<Option<String>> {
+    let mut args = vec!["show-options", "-w", "-q"];
+    if global {
+        args.push("-g");
+    }
+    args.push(option_name);
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    Ok(parse_option(&buffer))
+}
+
+/// Return all Tmux options as a `HashMap`.
+pub fn show_options(global: bool) -> Result<HashMap<String, String>> {
+    let args = if global {
+        vec!["show-options", "-g"]
+    } else {
+        vec!["show-options"]
+    };
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    Ok(parse_options(&buffer))
+}
+
+/// Return the `"default-command"` used to start a pane, falling back to `"default shell"` if none.
+///
+/// In case of bash, a `-l` flag is added.
+pub fn default_command() -> Result<String> {
+    let all_options = show_options(true)?;
+
+    select_default_command(&all_options)
+}
@@ -0,0 +1,125 @@
+//! Blocking mirror of [`crate::session`].
+
+use crate::{
+    Result,
+    blocking::{Command, version},
+    error::{check_empty_process_output, check_kill_output, check_process_success},
+    pane::Pane,
+    pane_id::PaneId,
+    policy::Retry,
+    session::{FORMAT, NewSession, Session, check_detach_output, parse_sessions},
+    session_id::SessionId,
+    window::Window,
+    window_id::WindowId,
+};
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return a list of all `Session` from the current tmux session.
+pub fn available_sessions() -> Result<Vec<Session>> {
+    let args = vec!["list-sessions", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    parse_sessions(&buffer)
+}
+
+/// Create a Tmux session (and thus a window & pane), see [`crate::session::new_session`].
+pub fn new_session(
+    session: &Session,
+    window: &Window,
+    pane: &Pane,
+    pane_command: Option<&str>,
+) -> Result<(SessionId, WindowId, PaneId)> {
+    let mut new_session = NewSession::new()
+        .name(&session.name)
+        .window_name(&window.name)
+        .start_directory(&pane.dirpath);
+    if let Some(pane_command) = pane_command {
+        new_session = new_session.command(pane_command);
+    }
+
+    new_session.run_blocking()
+}
+
+impl NewSession {
+    /// Create the session, and return the ids of the session, its window and its pane, see
+    /// [`NewSession::run`].
+    pub fn run_blocking(&self) -> Result<(SessionId, WindowId, PaneId)> {
+        self.check_options()?;
+        if let Some(capability) = self.capability() {
+            version::require(capability)?;
+        }
+
+        let output = Command::new("tmux").args(self.args()).output()?;
+        check_process_success(&output, "new-session")?;
+
+        let mut buffer = String::from_utf8(output.stdout)?;
+
+        // When attaching to an existing session, tmux does not print the new ids.
+        if let Some(args) = self.existing_session_args(&buffer) {
+            let output = Command::new("tmux").args(&args).output()?;
+            check_process_success(&output, "display-message")?;
+            buffer = String::from_utf8(output.stdout)?;
+        }
+
+        Self::parse_ids(&buffer)
+    }
+}
+
+/// Rename the session with `session_id`.
+pub fn rename_session(session_id: &SessionId, name: &str) -> Result<()> {
+    let args = vec!["rename-session", "-t", session_id.as_str(), name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "rename-session")
+}
+
+/// Kill the session with `session_id`.
+///
+/// Windows which are linked to other sessions are only unlinked from this session.
+pub fn kill_session(session_id: &SessionId) -> Result<()> {
+    let args = vec!["kill-session", "-t", session_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_kill_output(&output, "kill-session")
+}
+
+/// Return `true` if a session exactly named `session_name` exists.
+///
+/// This also returns `false` if no tmux server is running.
+pub fn has_session(session_name: &str) -> Result<bool> {
+    let exact_session_name = format!("={session_name}");
+    let args = vec!["has-session", "-t", &exact_session_name];
+
+    // A missing session is the answer, not a lookup to retry.
+    let output = Command::new("tmux")
+        .args(&args)
+        .retry(Retry::Never)
+        .output()?;
+    Ok(output.status.success())
+}
+
+/// Create a detached session named `session_name`, grouped with the session with `session_id`
+/// (`new-session -t`), and return its id.
+pub fn new_grouped_session(session_id: &SessionId, session_name: &str) -> Result<SessionId> {
+    let (new_session_id, _, _) = NewSession::new()
+        .name(session_name)
+        .group_with(session_id)
+        .run_blocking()?;
+
+    Ok(new_session_id)
+}
+
+/// Detach all clients attached to the session with `session_id`.
+///
+/// This succeeds if no client is attached.
+pub fn detach_clients(session_id: &SessionId) -> Result<()> {
+    let args = vec!["detach-client", "-s", session_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_detach_output(&output)
+}
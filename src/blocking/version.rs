@@ -0,0 +1,31 @@
+//! Blocking mirror of [`crate::version`].
+
+use crate::{
+    Result,
+    blocking::Command,
+    version::{Capability, Version, cached_version, check_capability, parse_version},
+};
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the version of tmux (`tmux -V`), see [`crate::version::version`].
+pub fn version() -> Result<Version> {
+    if let Some(version) = cached_version() {
+        return Ok(version);
+    }
+
+    let output = Command::new("tmux").args(["-V"]).output()?;
+    parse_version(&output)
+}
+
+/// Return `true` if the version of tmux supports `capability`.
+pub fn supports(capability: Capability) -> Result<bool> {
+    Ok(version()?.supports(capability))
+}
+
+/// Ensure that the version of tmux supports `capability`, see [`crate::version::require`].
+pub fn require(capability: Capability) -> Result<()> {
+    check_capability(version()?, capability)
+}
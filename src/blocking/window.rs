@@ -0,0 +1,173 @@
+//! Blocking mirror of [`crate::window`].
+
+use crate::{
+    Result,
+    blocking::Command,
+    error::{check_empty_process_output, check_kill_output, check_process_success},
+    pane::Pane,
+    pane_id::PaneId,
+    session::Session,
+    session_id::SessionId,
+    window::{
+        FORMAT, NewWindow, RespawnWindow, Rotation, Window, parse_windows, window_index_target,
+    },
+    window_id::WindowId,
+};
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return a list of all `Window` from all sessions.
+pub fn available_windows() -> Result<Vec<Window>> {
+    let args = vec!["list-windows", "-a", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    parse_windows(&buffer)
+}
+
+/// Create a Tmux window in the passed `session`, see [`crate::window::new_window`].
+pub fn new_window(
+    session: &Session,
+    window: &Window,
+    pane: &Pane,
+    pane_command: Option<&str>,
+) -> Result<(WindowId, PaneId)> {
+    let mut new_window = NewWindow::new()
+        .target_session(&session.id)
+        .name(&window.name)
+        .start_directory(&pane.dirpath);
+    if let Some(pane_command) = pane_command {
+        new_window = new_window.command(pane_command);
+    }
+
+    new_window.run_blocking()
+}
+
+impl NewWindow {
+    /// Create the window, and return the ids of the window and its pane, see
+    /// [`NewWindow::run`].
+    pub fn run_blocking(&self) -> Result<(WindowId, PaneId)> {
+        let output = Command::new("tmux").args(self.args()).output()?;
+        check_process_success(&output, "new-window")?;
+
+        let buffer = String::from_utf8(output.stdout)?;
+
+        Self::parse_ids(&buffer)
+    }
+}
+
+/// Apply the provided `layout` to the window with `window_id`.
+pub fn set_layout(layout: &str, window_id: &WindowId) -> Result<()> {
+    let args = vec!["select-layout", "-t", window_id.as_str(), layout];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "select-layout")
+}
+
+/// Select (make active) the window with `window_id`.
+pub fn select_window(window_id: &WindowId) -> Result<()> {
+    let args = vec!["select-window", "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "select-window")
+}
+
+/// Rename the window with `window_id`.
+///
+/// This also disables the `automatic-rename` option on that window.
+pub fn rename_window(window_id: &WindowId, name: &str) -> Result<()> {
+    let args = vec!["rename-window", "-t", window_id.as_str(), name];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "rename-window")
+}
+
+/// Kill the window with `window_id`, unlinking it from all the sessions it belongs to.
+pub fn kill_window(window_id: &WindowId) -> Result<()> {
+    let args = vec!["kill-window", "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_kill_output(&output, "kill-window")
+}
+
+/// Kill all windows of the session except the window with `window_id` (`kill-window -a`).
+pub fn kill_other_windows(window_id: &WindowId) -> Result<()> {
+    let args = vec!["kill-window", "-a", "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_kill_output(&output, "kill-window")
+}
+
+/// Move the window with `window_id` to the session with `session_id`, at `index`, or at the next
+/// free index if `index` is `None`.
+pub fn move_window(window_id: &WindowId, session_id: &SessionId, index: Option<u16>) -> Result<()> {
+    let target = window_index_target(session_id, index);
+    let args = vec!["move-window", "-d", "-s", window_id.as_str(), "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "move-window")
+}
+
+/// Renumber the windows of the session with `session_id`, closing gaps in the sequence of indexes
+/// (`move-window -r`).
+pub fn renumber_windows(session_id: &SessionId) -> Result<()> {
+    let args = vec!["move-window", "-r", "-t", session_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "move-window")
+}
+
+/// Swap the windows with `window_id` and `other_id`, without changing the active window.
+pub fn swap_windows(window_id: &WindowId, other_id: &WindowId) -> Result<()> {
+    let args = vec![
+        "swap-window",
+        "-d",
+        "-s",
+        window_id.as_str(),
+        "-t",
+        other_id.as_str(),
+    ];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "swap-window")
+}
+
+/// Link the window with `window_id` into the session with `session_id`, at `index`, or at the
+/// next free index if `index` is `None`.
+pub fn link_window(window_id: &WindowId, session_id: &SessionId, index: Option<u16>) -> Result<()> {
+    let target = window_index_target(session_id, index);
+    let args = vec!["link-window", "-d", "-s", window_id.as_str(), "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "link-window")
+}
+
+/// Unlink the window with `window_id` from the session with `session_id`.
+///
+/// This fails if the window is not linked to any other session: use [`kill_window`] instead.
+pub fn unlink_window(window_id: &WindowId, session_id: &SessionId) -> Result<()> {
+    let target = format!("{}:{}", session_id.as_str(), window_id.as_str());
+    let args = vec!["unlink-window", "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "unlink-window")
+}
+
+/// Rotate the positions of the panes in the window with `window_id`.
+pub fn rotate_window(window_id: &WindowId, rotation: Rotation) -> Result<()> {
+    let args = vec!["rotate-window", rotation.flag(), "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output()?;
+    check_empty_process_output(&output, "rotate-window")
+}
+
+impl RespawnWindow {
+    /// Respawn the window, see [`RespawnWindow::run`].
+    pub fn run_blocking(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args()).output()?;
+        check_empty_process_output(&output, "respawn-window")
+    }
+}
@@ -1,17 +1,24 @@
 //! Client-level functions: for representing client state (`client_session` etc) or reporting information inside Tmux.
 
 use std::str::FromStr;
+use std::time::Duration;
 
 use nom::{Parser, character::complete::char, combinator::all_consuming};
 use serde::{Deserialize, Serialize};
-use smol::process::Command;
 
 use crate::{
     Result,
-    error::{Error, map_add_intent},
+    error::{Error, check_empty_process_output, check_process_success, map_add_intent},
+    format::intent,
     parse::{quoted_nonempty_string, quoted_string},
+    policy::Retry,
+    runtime::Command,
+    target::Target,
 };
 
+/// Format of the current client printed by tmux, parsed by `Client::from_str`.
+pub(crate) const FORMAT: &str = "'#{client_session}':'#{client_last_session}'";
+
 /// A Tmux client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Client {
@@ -45,7 +52,7 @@ impl FromStr for Client {
     /// definitions.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "Client";
-        let intent = "'##{client_session}':'##{client_last_session}'";
+        let intent = intent!(FORMAT);
         let parser = (quoted_nonempty_string, char(':'), quoted_string);
 
         let (_, (session_name, _, last_session_name)) = all_consuming(parser)
@@ -69,12 +76,7 @@ impl FromStr for Client {
 ///
 /// Returns an `io::IOError` in the command failed.
 pub async fn current() -> Result<Client> {
-    let args = vec![
-        "display-message",
-        "-p",
-        "-F",
-        "'#{client_session}':'#{client_last_session}'",
-    ];
+    let args = vec!["display-message", "-p", "-F", FORMAT];
 
     let output = Command::new("tmux").args(&args).output().await?;
     let buffer = String::from_utf8(output.stdout)?;
@@ -82,11 +84,12 @@ pub async fn current() -> Result<Client> {
     Client::from_str(buffer.trim_end())
 }
 
-/// Return a list of all `Pane` from all sessions.
+/// Display `message` in the status line of the current client.
 ///
 /// # Panics
 ///
 /// This function panics if it can't communicate with Tmux.
+#[deprecated(note = "use `DisplayMessage`, which is async and reports errors")]
 pub fn display_message(message: &str) {
     let args = vec!["display-message", message];
 
@@ -96,6 +99,117 @@ pub fn display_message(message: &str) {
         .expect("Cannot communicate with Tmux for displaying message");
 }
 
+/// Options for displaying a message, or evaluating a format.
+///
+/// The message is a tmux format, expanded in the context of the target. It is either shown in
+/// the status line of a client with [`run`](DisplayMessage::run), or returned with
+/// [`print`](DisplayMessage::print).
+///
+/// ```no_run
+/// # async fn show(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+/// use std::time::Duration;
+/// use tmux_lib::client::DisplayMessage;
+///
+/// DisplayMessage::new("Backup done")
+///     .duration(Duration::from_secs(3))
+///     .run()
+///     .await?;
+///
+/// let command = DisplayMessage::new("#{pane_current_command}")
+///     .target(pane_id)
+///     .print()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayMessage {
+    message: String,
+    client: Option<String>,
+    target: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl DisplayMessage {
+    /// Prepare displaying `message`.
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            client: None,
+            target: None,
+            duration: None,
+        }
+    }
+
+    /// Display the message on the client named `client_name` (`-c`).
+    #[must_use]
+    pub fn client(mut self, client_name: impl Into<String>) -> Self {
+        self.client = Some(client_name.into());
+        self
+    }
+
+    /// Expand the message in the context of `target` (`-t`).
+    #[must_use]
+    pub fn target<'a>(mut self, target: impl Into<Target<'a>>) -> Self {
+        self.target = Some(target.into().as_str().to_string());
+        self
+    }
+
+    /// Display the message for `duration` instead of the `display-time` option (`-d`).
+    #[must_use]
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self, print: bool) -> Vec<String> {
+        let mut args = vec![String::from("display-message")];
+        if print {
+            args.push("-p".into());
+        }
+        if let Some(client) = &self.client {
+            args.push("-c".into());
+            args.push(client.clone());
+        }
+        if let Some(target) = &self.target {
+            args.push("-t".into());
+            args.push(target.clone());
+        }
+        if let Some(duration) = self.duration {
+            args.push("-d".into());
+            args.push(duration.as_millis().to_string());
+        }
+        args.push(self.message.clone());
+        args
+    }
+
+    /// Display the message in the status line of the client.
+    pub async fn run(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args(false)).output().await?;
+        check_empty_process_output(&output, "display-message")
+    }
+
+    /// Return the expanded message instead of displaying it (`-p`).
+    pub async fn print(&self) -> Result<String> {
+        let output = Command::new("tmux")
+            .args(self.args(true))
+            .retry(Retry::Lookups)
+            .output()
+            .await?;
+        check_process_success(&output, "display-message")?;
+        let buffer = String::from_utf8(output.stdout)?;
+
+        Ok(Self::parse_printed(&buffer))
+    }
+
+    /// Strip the newline tmux appends to the expanded message.
+    pub(crate) fn parse_printed(buffer: &str) -> String {
+        buffer.strip_suffix('\n').unwrap_or(buffer).to_string()
+    }
+}
+
 /// Switch to session exactly named `session_name`.
 pub async fn switch_client(session_name: &str) -> Result<()> {
     let exact_session_name = format!("={session_name}");
@@ -110,10 +224,67 @@ pub async fn switch_client(session_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Switch the client named `client_name` to `target`, which can be a session, a window or a
+/// pane.
+///
+/// Client names are listed by [`available_clients`](crate::client_info::available_clients).
+pub async fn switch_client_to<'a>(client_name: &str, target: impl Into<Target<'a>>) -> Result<()> {
+    let target = target.into();
+    let args = vec!["switch-client", "-c", client_name, "-t", target.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "switch-client")
+}
+
+/// Detach the client named `client_name`.
+pub async fn detach_client(client_name: &str) -> Result<()> {
+    let args = vec!["detach-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "detach-client")
+}
+
+/// Redraw the client named `client_name`.
+pub async fn refresh_client(client_name: &str) -> Result<()> {
+    let args = vec!["refresh-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Redraw only the status line of the client named `client_name` (`refresh-client -S`).
+pub async fn refresh_client_status(client_name: &str) -> Result<()> {
+    let args = vec!["refresh-client", "-S", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Set the size of the control mode client named `client_name` (`refresh-client -C`).
+///
+/// Control mode clients have no terminal, hence tmux cannot know their size otherwise.
+pub async fn set_client_size(client_name: &str, width: u16, height: u16) -> Result<()> {
+    let size = format!("{width}x{height}");
+    let args = vec!["refresh-client", "-C", &size, "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "refresh-client")
+}
+
+/// Suspend the client named `client_name`, by sending it a `SIGTSTP` signal.
+pub async fn suspend_client(client_name: &str) -> Result<()> {
+    let args = vec!["suspend-client", "-t", client_name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "suspend-client")
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Client;
+    use super::{Client, DisplayMessage};
+    use crate::pane_id::PaneId;
     use std::str::FromStr;
+    use std::time::Duration;
 
     #[test]
     fn parse_client_with_both_sessions() {
@@ -175,4 +346,31 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn display_message_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let message = DisplayMessage::new("#{pane_title}")
+            .client("/dev/pts/3")
+            .target(&pane_id)
+            .duration(Duration::from_millis(1500));
+
+        assert_eq!(
+            message.args(false),
+            [
+                "display-message",
+                "-c",
+                "/dev/pts/3",
+                "-t",
+                "%3",
+                "-d",
+                "1500",
+                "#{pane_title}"
+            ]
+        );
+        assert_eq!(
+            DisplayMessage::new("hello").args(true),
+            ["display-message", "-p", "hello"]
+        );
+    }
 }
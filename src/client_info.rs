@@ -0,0 +1,285 @@
+//! This module provides a description of the clients attached to the Tmux server.
+//!
+//! The main use case is finding clients, in order to control them with the ops in
+//! [`client`](crate::client).
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map_res, opt},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Result,
+    error::{Error, check_process_success, map_add_intent},
+    format::intent,
+    pane_id::{PaneId, parse::pane_id},
+    parse::{boolean, optional_number, quoted_nonempty_string, quoted_string},
+    runtime::Command,
+    session_id::{SessionId, parse::session_id},
+    window_id::{WindowId, parse::window_id},
+};
+
+/// Format of the clients listed by tmux, parsed by `ClientInfo::from_str`.
+pub(crate) const FORMAT: &str = "#{session_id}:#{window_id}:#{pane_id}:#{client_pid}:#{client_width}:#{client_height}:#{?client_readonly,true,false}:#{?client_control_mode,true,false}:#{client_activity}:'#{client_termname}':'#{client_termfeatures}':'#{client_tty}':'#{client_name}'";
+
+/// A client attached to the Tmux server.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::client_info::ClientInfo;
+///
+/// let line = "$1:@2:%3:4321:200:50:false:false:1792326350:'xterm-256color':'256,RGB,title':'/dev/pts/3':'/dev/pts/3'";
+/// let client = ClientInfo::from_str(line).unwrap();
+///
+/// assert_eq!(client.name, "/dev/pts/3");
+/// assert_eq!(client.pid, 4321);
+/// assert_eq!(client.height, Some(50));
+/// assert_eq!(client.termfeatures, vec!["256", "RGB", "title"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    /// Current session of the client.
+    pub session_id: Option<SessionId>,
+    /// Current window of the client.
+    pub window_id: Option<WindowId>,
+    /// Current pane of the client.
+    pub pane_id: Option<PaneId>,
+    /// PID of the client process.
+    pub pid: u32,
+    /// Width of the client, in cells.
+    pub width: Option<u16>,
+    /// Height of the client, in cells. `None` if the client has no terminal (control mode).
+    pub height: Option<u16>,
+    /// Describes if the client is read-only.
+    pub is_readonly: bool,
+    /// Describes if the client is in control mode.
+    pub is_control_mode: bool,
+    /// Time of the last activity, in seconds since the Unix epoch.
+    pub activity: u64,
+    /// Terminal name of the client, e.g. `xterm-256color`.
+    pub termname: String,
+    /// Terminal features of the client, e.g. `RGB` or `title`.
+    pub termfeatures: Vec<String>,
+    /// Terminal of the client. `None` if the client has no terminal (control mode).
+    pub tty: Option<PathBuf>,
+    /// Name of the client, used to target the client, usually the same as its terminal.
+    pub name: String,
+}
+
+impl FromStr for ClientInfo {
+    type Err = Error;
+
+    /// Parse a string containing the tmux client status into a new `ClientInfo`.
+    ///
+    /// This returns a `Result<ClientInfo, Error>` as this call can obviously
+    /// fail if provided an invalid format.
+    ///
+    /// The expected format of the tmux status is
+    ///
+    /// ```text
+    /// $1:@2:%3:4321:200:50:false:false:1792326350:'xterm-256color':'256,RGB,title':'/dev/pts/3':'/dev/pts/3'
+    /// $1:@2:%3:24859:80::false:true:1792326350:'xterm':'':'':'client-24859'
+    /// ```
+    ///
+    /// This status line is obtained with
+    ///
+    /// ```text
+    /// tmux list-clients -F "#{session_id}:#{window_id}:#{pane_id}:#{client_pid}:#{client_width}:#{client_height}:#{?client_readonly,true,false}:#{?client_control_mode,true,false}:#{client_activity}:'#{client_termname}':'#{client_termfeatures}':'#{client_tty}':'#{client_name}'"
+    /// ```
+    ///
+    /// For definitions, look at `ClientInfo` type and the tmux man page for
+    /// definitions.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let desc = "ClientInfo";
+        let intent = intent!(FORMAT);
+
+        let (_, client) = all_consuming(parse::client_info)
+            .parse(input)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(client)
+    }
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    pub(crate) fn client_info(input: &str) -> IResult<&str, ClientInfo> {
+        let (input, (session_id, _, window_id, _, pane_id, _, pid, _, width, _, height, _)) = (
+            opt(session_id),
+            char(':'),
+            opt(window_id),
+            char(':'),
+            opt(pane_id),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            optional_number,
+            char(':'),
+            optional_number,
+            char(':'),
+        )
+            .parse(input)?;
+
+        let (
+            input,
+            (
+                is_readonly,
+                _,
+                is_control_mode,
+                _,
+                activity,
+                _,
+                termname,
+                _,
+                termfeatures,
+                _,
+                tty,
+                _,
+                name,
+            ),
+        ) = (
+            boolean,
+            char(':'),
+            boolean,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            quoted_string,
+            char(':'),
+            quoted_string,
+            char(':'),
+            quoted_string,
+            char(':'),
+            quoted_nonempty_string,
+        )
+            .parse(input)?;
+
+        Ok((
+            input,
+            ClientInfo {
+                session_id,
+                window_id,
+                pane_id,
+                pid,
+                width,
+                height,
+                is_readonly,
+                is_control_mode,
+                activity,
+                termname: termname.to_string(),
+                termfeatures: termfeatures
+                    .split(',')
+                    .filter(|feature| !feature.is_empty())
+                    .map(String::from)
+                    .collect(),
+                tty: (!tty.is_empty()).then(|| tty.into()),
+                name: name.to_string(),
+            },
+        ))
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return a list of all clients attached to the server.
+pub async fn available_clients() -> Result<Vec<ClientInfo>> {
+    let args = vec!["list-clients", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_process_success(&output, "list-clients")?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    // Unlike sessions, there may be no client at all, hence the use of `lines()` which yields
+    // nothing on an empty buffer.
+    buffer.lines().map(ClientInfo::from_str).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientInfo;
+    use crate::{pane_id::PaneId, session_id::SessionId, window_id::WindowId};
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_list_clients() {
+        let output = [
+            "$1:@2:%3:4321:200:50:false:false:1792326350:'xterm-256color':'256,RGB,title':'/dev/pts/3':'/dev/pts/3'",
+            "$1:@2:%3:24859:80::true:true:1792326351:'xterm':'':'':'client-24859'",
+        ];
+        let clients: Vec<ClientInfo> = output
+            .iter()
+            .map(|&line| ClientInfo::from_str(line))
+            .collect::<crate::Result<_>>()
+            .expect("Could not parse tmux clients");
+
+        let expected = vec![
+            ClientInfo {
+                session_id: Some(SessionId::from_str("$1").unwrap()),
+                window_id: Some(WindowId::from_str("@2").unwrap()),
+                pane_id: Some(PaneId::from_str("%3").unwrap()),
+                pid: 4321,
+                width: Some(200),
+                height: Some(50),
+                is_readonly: false,
+                is_control_mode: false,
+                activity: 1792326350,
+                termname: String::from("xterm-256color"),
+                termfeatures: vec![
+                    String::from("256"),
+                    String::from("RGB"),
+                    String::from("title"),
+                ],
+                tty: Some(PathBuf::from("/dev/pts/3")),
+                name: String::from("/dev/pts/3"),
+            },
+            ClientInfo {
+                session_id: Some(SessionId::from_str("$1").unwrap()),
+                window_id: Some(WindowId::from_str("@2").unwrap()),
+                pane_id: Some(PaneId::from_str("%3").unwrap()),
+                pid: 24859,
+                width: Some(80),
+                height: None,
+                is_readonly: true,
+                is_control_mode: true,
+                activity: 1792326351,
+                termname: String::from("xterm"),
+                termfeatures: vec![],
+                tty: None,
+                name: String::from("client-24859"),
+            },
+        ];
+
+        assert_eq!(clients, expected);
+    }
+
+    #[test]
+    fn parse_client_without_session() {
+        let line = ":::4321:80:24:false:false:1792326350:'screen':'':'/dev/pts/3':'/dev/pts/3'";
+        let client = ClientInfo::from_str(line).expect("Should parse client without session");
+
+        assert_eq!(client.session_id, None);
+        assert_eq!(client.window_id, None);
+        assert_eq!(client.pane_id, None);
+    }
+
+    #[test]
+    fn parse_client_fails_on_empty_name() {
+        let line = "$1:@2:%3:4321:80:24:false:false:1792326350:'screen':'':'/dev/pts/3':''";
+        assert!(ClientInfo::from_str(line).is_err());
+    }
+
+    #[test]
+    fn parse_client_fails_on_missing_pid() {
+        let line = "$1:@2:%3::80:24:false:false:1792326350:'screen':'':'/dev/pts/3':'/dev/pts/3'";
+        assert!(ClientInfo::from_str(line).is_err());
+    }
+}
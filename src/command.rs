@@ -0,0 +1,1454 @@
+//! Typed tmux commands.
+//!
+//! A [`TmuxCommand`] renders as the arguments passed to tmux (`args`), or as a line of a tmux
+//! config file (`Display`), and parses back from either (`from_args`, `FromStr`). This is used for
+//! generating config files, inspecting key bindings, and logging the commands sent to tmux.
+//!
+//! Commands which are not modeled here, or use flags not modeled here, are kept as
+//! [`TmuxCommand::Other`].
+
+use std::{fmt, str::FromStr};
+
+use nom::error::{Error as NomError, ErrorKind};
+
+use crate::{
+    Result,
+    error::Error,
+    pane::{PaneSize, SplitDirection},
+};
+
+pub(crate) mod parse;
+
+/// Error of the parsers of this module, owning its input as nested commands are parsed from
+/// owned arguments.
+pub(crate) type ParseError = nom::Err<NomError<String>>;
+
+fn failure(input: &str, kind: ErrorKind) -> ParseError {
+    nom::Err::Failure(NomError::new(input.to_string(), kind))
+}
+
+pub(crate) fn map_parse_error(err: ParseError) -> Error {
+    Error::ParseError {
+        desc: "TmuxCommand",
+        intent: "tmux command",
+        err,
+    }
+}
+
+/// Scope of an option, overriding the scope tmux infers from the option name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionScope {
+    /// Server option (`-s`).
+    Server,
+    /// Window option (`-w`).
+    Window,
+    /// Pane option (`-p`).
+    Pane,
+}
+
+/// Direction of the pane to select, relative to the target pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Pane above (`-U`).
+    Up,
+    /// Pane below (`-D`).
+    Down,
+    /// Pane on the left (`-L`).
+    Left,
+    /// Pane on the right (`-R`).
+    Right,
+}
+
+/// A tmux command, with its flags.
+///
+/// Targets are kept as strings, as commands in config files usually refer to sessions, windows
+/// and panes by name or index rather than by id.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::command::TmuxCommand;
+///
+/// let command = TmuxCommand::from_str("bind -n M-h select-pane -L").unwrap();
+/// assert_eq!(command.to_string(), "bind-key -T root M-h select-pane -L");
+/// assert_eq!(command.args()[..3], ["bind-key", "-T", "root"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TmuxCommand {
+    /// `new-session`.
+    NewSession {
+        /// Attach to the session if it already exists (`-A`).
+        attach_if_exists: bool,
+        /// Do not attach the new session to the current client (`-d`).
+        detached: bool,
+        /// Print information about the new session (`-P`).
+        print: bool,
+        /// Format of the printed information (`-F`).
+        format: Option<String>,
+        /// Working directory of the session (`-c`).
+        start_directory: Option<String>,
+        /// Environment variables of the session (`-e`).
+        environment: Vec<(String, String)>,
+        /// Name of the initial window (`-n`).
+        window_name: Option<String>,
+        /// Name of the session (`-s`).
+        name: Option<String>,
+        /// Session to group with (`-t`).
+        group: Option<String>,
+        /// Width of the initial window (`-x`).
+        width: Option<u16>,
+        /// Height of the initial window (`-y`).
+        height: Option<u16>,
+        /// Command run in the initial pane.
+        command: Vec<String>,
+    },
+    /// `new-window`.
+    NewWindow {
+        /// Insert the window at the next index after the target (`-a`).
+        after: bool,
+        /// Insert the window at the index of the target, shifting windows (`-b`).
+        before: bool,
+        /// Do not make the new window current (`-d`).
+        detached: bool,
+        /// Replace the target window if it exists (`-k`).
+        kill: bool,
+        /// Print information about the new window (`-P`).
+        print: bool,
+        /// Format of the printed information (`-F`).
+        format: Option<String>,
+        /// Working directory of the window (`-c`).
+        start_directory: Option<String>,
+        /// Environment variables of the window (`-e`).
+        environment: Vec<(String, String)>,
+        /// Name of the window (`-n`).
+        name: Option<String>,
+        /// Target session or window index (`-t`).
+        target: Option<String>,
+        /// Command run in the pane.
+        command: Vec<String>,
+    },
+    /// `split-window`.
+    SplitWindow {
+        /// Split direction (`-h`, `-v`).
+        direction: SplitDirection,
+        /// Place the new pane before the target pane (`-b`).
+        before: bool,
+        /// Do not make the new pane active (`-d`).
+        detached: bool,
+        /// Span the full window height or width (`-f`).
+        full: bool,
+        /// Size of the new pane (`-l`).
+        size: Option<PaneSize>,
+        /// Print information about the new pane (`-P`).
+        print: bool,
+        /// Format of the printed information (`-F`).
+        format: Option<String>,
+        /// Working directory of the pane (`-c`).
+        start_directory: Option<String>,
+        /// Environment variables of the pane (`-e`).
+        environment: Vec<(String, String)>,
+        /// Target pane (`-t`).
+        target: Option<String>,
+        /// Command run in the pane.
+        command: Vec<String>,
+    },
+    /// `send-keys`.
+    SendKeys {
+        /// Send keys as hexadecimal ASCII codes (`-H`).
+        hex: bool,
+        /// Send keys literally, without looking up key names (`-l`).
+        literal: bool,
+        /// Reset the terminal state (`-R`).
+        reset: bool,
+        /// Keys are copy mode commands (`-X`).
+        copy_mode: bool,
+        /// Number of repetitions (`-N`).
+        repeat: Option<u32>,
+        /// Target pane (`-t`).
+        target: Option<String>,
+        /// Keys to send.
+        keys: Vec<String>,
+    },
+    /// `set-option`, or `set-window-option` which is parsed as `set-option -w`.
+    SetOption {
+        /// Scope of the option, inferred from the option name if `None`.
+        scope: Option<OptionScope>,
+        /// Set the global option (`-g`).
+        global: bool,
+        /// Unset the option (`-u`).
+        unset: bool,
+        /// Append the value to the existing value (`-a`).
+        append: bool,
+        /// Only set the option if it is not already set (`-o`).
+        only_if_unset: bool,
+        /// Do not report unknown options (`-q`).
+        quiet: bool,
+        /// Expand the value as a format (`-F`).
+        expand: bool,
+        /// Target session, window or pane (`-t`).
+        target: Option<String>,
+        /// Name of the option.
+        option: String,
+        /// Value of the option.
+        value: Option<String>,
+    },
+    /// `bind-key`.
+    BindKey {
+        /// Key table, `prefix` if `None` (`-T`, or `-n` for `root`).
+        table: Option<String>,
+        /// The key may repeat (`-r`).
+        repeat: bool,
+        /// Note describing the binding (`-N`).
+        note: Option<String>,
+        /// Bound key.
+        key: String,
+        /// Commands run by the key.
+        commands: Vec<TmuxCommand>,
+    },
+    /// `unbind-key`.
+    UnbindKey {
+        /// Remove all bindings of the table (`-a`).
+        all: bool,
+        /// Do not report missing bindings (`-q`).
+        quiet: bool,
+        /// Key table, `prefix` if `None` (`-T`, or `-n` for `root`).
+        table: Option<String>,
+        /// Unbound key, absent when removing all bindings.
+        key: Option<String>,
+    },
+    /// `select-pane`.
+    SelectPane {
+        /// Select the pane in a direction from the target pane (`-U`, `-D`, `-L`, `-R`).
+        direction: Option<Direction>,
+        /// Select the last selected pane (`-l`).
+        last: bool,
+        /// Set the pane title (`-T`).
+        title: Option<String>,
+        /// Target pane (`-t`).
+        target: Option<String>,
+    },
+    /// `select-window`.
+    SelectWindow {
+        /// Select the last selected window (`-l`).
+        last: bool,
+        /// Select the next window (`-n`).
+        next: bool,
+        /// Select the previous window (`-p`).
+        previous: bool,
+        /// Select the last window if the target is already current (`-T`).
+        toggle: bool,
+        /// Target window (`-t`).
+        target: Option<String>,
+    },
+    /// `select-layout`.
+    SelectLayout {
+        /// Spread the panes evenly (`-E`).
+        spread: bool,
+        /// Apply the next layout (`-n`).
+        next: bool,
+        /// Apply the previous layout (`-p`).
+        previous: bool,
+        /// Target pane (`-t`).
+        target: Option<String>,
+        /// Layout name or layout string.
+        layout: Option<String>,
+    },
+    /// `rename-session`.
+    RenameSession {
+        /// Target session (`-t`).
+        target: Option<String>,
+        /// New name.
+        name: String,
+    },
+    /// `rename-window`.
+    RenameWindow {
+        /// Target window (`-t`).
+        target: Option<String>,
+        /// New name.
+        name: String,
+    },
+    /// `kill-session`.
+    KillSession {
+        /// Kill all sessions but the target (`-a`).
+        all_others: bool,
+        /// Target session (`-t`).
+        target: Option<String>,
+    },
+    /// `kill-window`.
+    KillWindow {
+        /// Kill all windows but the target (`-a`).
+        all_others: bool,
+        /// Target window (`-t`).
+        target: Option<String>,
+    },
+    /// `kill-pane`.
+    KillPane {
+        /// Kill all panes but the target (`-a`).
+        all_others: bool,
+        /// Target pane (`-t`).
+        target: Option<String>,
+    },
+    /// `source-file`.
+    SourceFile {
+        /// Expand paths as formats (`-F`).
+        expand: bool,
+        /// Only parse the files (`-n`).
+        parse_only: bool,
+        /// Do not report missing files (`-q`).
+        quiet: bool,
+        /// Print parsed commands (`-v`).
+        verbose: bool,
+        /// Paths of the files, which may contain glob patterns.
+        paths: Vec<String>,
+    },
+    /// `display-message`.
+    DisplayMessage {
+        /// Print the message instead of displaying it (`-p`).
+        print: bool,
+        /// Target client (`-c`).
+        client: Option<String>,
+        /// Display duration in milliseconds (`-d`).
+        duration: Option<u32>,
+        /// Target pane (`-t`).
+        target: Option<String>,
+        /// Message, a format.
+        message: Option<String>,
+    },
+    /// `set-environment`.
+    SetEnvironment {
+        /// Set the global environment (`-g`).
+        global: bool,
+        /// Hidden variable, not passed to new processes (`-h`).
+        hidden: bool,
+        /// Remove the variable when starting new processes (`-r`).
+        remove: bool,
+        /// Unset the variable (`-u`).
+        unset: bool,
+        /// Expand the value as a format (`-F`).
+        expand: bool,
+        /// Target session (`-t`).
+        target: Option<String>,
+        /// Name of the variable.
+        name: String,
+        /// Value of the variable.
+        value: Option<String>,
+    },
+    /// `run-shell`.
+    RunShell {
+        /// Run the command in the background (`-b`).
+        background: bool,
+        /// The command is a tmux command (`-C`).
+        tmux_command: bool,
+        /// Target pane (`-t`).
+        target: Option<String>,
+        /// Command to run.
+        command: Option<String>,
+    },
+    /// Any other command, or a command using flags not modeled above.
+    Other {
+        /// Command name, as written.
+        name: String,
+        /// Arguments following the name.
+        args: Vec<String>,
+    },
+}
+
+/// Full names, aliases and flag templates of the modeled commands.
+///
+/// Templates follow tmux (and getopt): a letter followed by `:` takes a value.
+const COMMANDS: [(&str, &str, &str); 20] = [
+    ("new-session", "new", "AdPF:c:e:n:s:t:x:y:"),
+    ("new-window", "neww", "abdkPF:c:e:n:t:"),
+    ("split-window", "splitw", "bdfhvPF:c:e:l:t:"),
+    ("send-keys", "send", "HlRXN:t:"),
+    ("set-option", "set", "agopqsuwFt:"),
+    ("set-window-option", "setw", "agoquFt:"),
+    ("bind-key", "bind", "nrN:T:"),
+    ("unbind-key", "unbind", "anqT:"),
+    ("select-pane", "selectp", "DLRUlT:t:"),
+    ("select-window", "selectw", "lnpTt:"),
+    ("select-layout", "selectl", "Enpt:"),
+    ("rename-session", "rename", "t:"),
+    ("rename-window", "renamew", "t:"),
+    ("kill-session", "kill-session", "at:"),
+    ("kill-window", "killw", "at:"),
+    ("kill-pane", "killp", "at:"),
+    ("source-file", "source", "Fnqv"),
+    ("display-message", "display", "pc:d:t:"),
+    ("set-environment", "setenv", "Fghrut:"),
+    ("run-shell", "run", "bCt:"),
+];
+
+/// Flags and positional arguments of a command, like `struct args` in tmux.
+pub(crate) struct Arguments {
+    flags: Vec<(char, Option<String>)>,
+    pub(crate) positional: Vec<String>,
+}
+
+impl Arguments {
+    /// Parse `args` following `template`, or return `None` if a flag is not in the template.
+    pub(crate) fn parse(
+        args: &[String],
+        template: &str,
+    ) -> std::result::Result<Option<Self>, ParseError> {
+        let mut flags = vec![];
+        let mut rest = args.iter();
+
+        while let Some(arg) = rest.as_slice().first() {
+            if arg == "--" {
+                rest.next();
+                break;
+            }
+            let Some(letters) = arg.strip_prefix('-').filter(|letters| !letters.is_empty()) else {
+                break;
+            };
+            rest.next();
+
+            for (offset, flag) in letters.char_indices() {
+                let Some(position) = template.find(flag).filter(|_| flag != ':') else {
+                    return Ok(None);
+                };
+                if template[position + 1..].starts_with(':') {
+                    let attached = &letters[offset + flag.len_utf8()..];
+                    let value = if attached.is_empty() {
+                        rest.next()
+                            .ok_or_else(|| failure(arg, ErrorKind::Eof))?
+                            .clone()
+                    } else {
+                        attached.to_string()
+                    };
+                    flags.push((flag, Some(value)));
+                    break;
+                }
+                flags.push((flag, None));
+            }
+        }
+
+        Ok(Some(Self {
+            flags,
+            positional: rest.cloned().collect(),
+        }))
+    }
+
+    pub(crate) fn has(&self, flag: char) -> bool {
+        self.flags.iter().any(|(f, _)| *f == flag)
+    }
+
+    /// Last value of `flag`, as tmux keeps the last one.
+    pub(crate) fn value(&self, flag: char) -> Option<String> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(f, _)| *f == flag)
+            .and_then(|(_, value)| value.clone())
+    }
+
+    pub(crate) fn values(&self, flag: char) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter(|(f, _)| *f == flag)
+            .filter_map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn number<T: FromStr>(&self, flag: char) -> std::result::Result<Option<T>, ParseError> {
+        self.value(flag)
+            .map(|value| value.parse().map_err(|_| failure(&value, ErrorKind::Digit)))
+            .transpose()
+    }
+
+    fn environment(&self) -> std::result::Result<Vec<(String, String)>, ParseError> {
+        self.values('e')
+            .into_iter()
+            .map(|assignment| match assignment.split_once('=') {
+                Some((name, value)) => Ok((name.to_string(), value.to_string())),
+                None => Err(failure(&assignment, ErrorKind::Char)),
+            })
+            .collect()
+    }
+
+    /// Key table from `-T`, or `-n` standing for the root table.
+    fn table(&self) -> Option<String> {
+        self.value('T')
+            .or_else(|| self.has('n').then(|| "root".to_string()))
+    }
+
+    /// Check the number of positional arguments.
+    fn expect_positional(&self, min: usize, max: usize) -> std::result::Result<(), ParseError> {
+        let count = self.positional.len();
+        if count < min {
+            return Err(failure("", ErrorKind::Eof));
+        }
+        if count > max {
+            return Err(failure(&self.positional[max], ErrorKind::TooLarge));
+        }
+        Ok(())
+    }
+
+    fn optional_positional(&self, index: usize) -> Option<String> {
+        self.positional.get(index).cloned()
+    }
+}
+
+/// Arguments of a command, built flag by flag.
+struct ArgsBuilder(Vec<String>);
+
+impl ArgsBuilder {
+    fn new(name: &str) -> Self {
+        Self(vec![name.to_string()])
+    }
+
+    fn flag(&mut self, flag: char, enabled: bool) {
+        if enabled {
+            self.0.push(format!("-{flag}"));
+        }
+    }
+
+    fn value(&mut self, flag: char, value: Option<&str>) {
+        if let Some(value) = value {
+            self.0.push(format!("-{flag}"));
+            self.0.push(value.to_string());
+        }
+    }
+
+    fn environment(&mut self, environment: &[(String, String)]) {
+        for (name, value) in environment {
+            self.value('e', Some(&format!("{name}={value}")));
+        }
+    }
+
+    /// Append positional arguments, preceded by `--` if the first one looks like a flag.
+    fn positional<'a>(mut self, positional: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        let mut positional = positional.into_iter().peekable();
+        if positional
+            .peek()
+            .is_some_and(|first| first.starts_with('-') && first.len() > 1)
+        {
+            self.0.push("--".to_string());
+        }
+        self.0.extend(positional.cloned());
+        self.0
+    }
+}
+
+/// Escape an argument of a nested command, so that a trailing `;` is not taken for a separator.
+pub(crate) fn escape_separator(arg: &str) -> String {
+    match arg.strip_suffix(';') {
+        Some(head) => format!("{head}\\;"),
+        None => arg.to_string(),
+    }
+}
+
+/// Split arguments into commands at `;` separators, like `cmd_parse_from_arguments` in tmux.
+fn split_commands(args: &[String]) -> Vec<Vec<String>> {
+    let mut commands = vec![];
+    let mut current = vec![];
+
+    for arg in args {
+        match arg.strip_suffix(';') {
+            Some(head) if head.ends_with('\\') => {
+                current.push(format!("{};", &head[..head.len() - 1]));
+            }
+            Some(head) => {
+                if !head.is_empty() {
+                    current.push(head.to_string());
+                }
+                if !current.is_empty() {
+                    commands.push(std::mem::take(&mut current));
+                }
+            }
+            None => current.push(arg.clone()),
+        }
+    }
+    if !current.is_empty() {
+        commands.push(current);
+    }
+    commands
+}
+
+/// Quote an argument for a tmux config file, if needed.
+///
+/// A bare word starting with `%` is read as a directive, like `%if` or `%hidden`, unless it only
+/// has `%` and digits, like pane ids, so such arguments are quoted.
+pub(crate) fn quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:%@=+,^".contains(c);
+    let is_directive = arg.starts_with('%') && !arg.chars().all(|c| c == '%' || c.is_ascii_digit());
+    if !arg.is_empty() && arg.chars().all(is_safe) && !is_directive {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl TmuxCommand {
+    /// Parse a command from its arguments, starting with the command name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::ParseError` if the name is missing, a flag misses its value, a value is
+    /// invalid, or the number of arguments is wrong.
+    pub fn from_args<I, S>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let args: Vec<String> = args.into_iter().map(Into::into).collect();
+        Self::parse_args(&args).map_err(map_parse_error)
+    }
+
+    pub(crate) fn parse_args(args: &[String]) -> std::result::Result<Self, ParseError> {
+        let Some((name, rest)) = args.split_first() else {
+            return Err(failure("", ErrorKind::Eof));
+        };
+        let other = || Self::Other {
+            name: name.clone(),
+            args: rest.to_vec(),
+        };
+
+        let Some(&(full_name, _, template)) = COMMANDS
+            .iter()
+            .find(|(full_name, alias, _)| name == full_name || name == alias)
+        else {
+            return Ok(other());
+        };
+        let Some(a) = Arguments::parse(rest, template)? else {
+            return Ok(other());
+        };
+
+        let command = match full_name {
+            "new-session" => Self::NewSession {
+                attach_if_exists: a.has('A'),
+                detached: a.has('d'),
+                print: a.has('P'),
+                format: a.value('F'),
+                start_directory: a.value('c'),
+                environment: a.environment()?,
+                window_name: a.value('n'),
+                name: a.value('s'),
+                group: a.value('t'),
+                width: a.number('x')?,
+                height: a.number('y')?,
+                command: a.positional,
+            },
+            "new-window" => Self::NewWindow {
+                after: a.has('a'),
+                before: a.has('b'),
+                detached: a.has('d'),
+                kill: a.has('k'),
+                print: a.has('P'),
+                format: a.value('F'),
+                start_directory: a.value('c'),
+                environment: a.environment()?,
+                name: a.value('n'),
+                target: a.value('t'),
+                command: a.positional,
+            },
+            "split-window" => Self::SplitWindow {
+                direction: if a.has('h') {
+                    SplitDirection::Horizontal
+                } else {
+                    SplitDirection::Vertical
+                },
+                before: a.has('b'),
+                detached: a.has('d'),
+                full: a.has('f'),
+                size: a
+                    .value('l')
+                    .map(|size| parse_pane_size(&size))
+                    .transpose()?,
+                print: a.has('P'),
+                format: a.value('F'),
+                start_directory: a.value('c'),
+                environment: a.environment()?,
+                target: a.value('t'),
+                command: a.positional,
+            },
+            "send-keys" => Self::SendKeys {
+                hex: a.has('H'),
+                literal: a.has('l'),
+                reset: a.has('R'),
+                copy_mode: a.has('X'),
+                repeat: a.number('N')?,
+                target: a.value('t'),
+                keys: a.positional,
+            },
+            "set-option" | "set-window-option" => {
+                a.expect_positional(1, 2)?;
+                let scope = if full_name == "set-window-option" || a.has('w') {
+                    Some(OptionScope::Window)
+                } else if a.has('s') {
+                    Some(OptionScope::Server)
+                } else if a.has('p') {
+                    Some(OptionScope::Pane)
+                } else {
+                    None
+                };
+                Self::SetOption {
+                    scope,
+                    global: a.has('g'),
+                    unset: a.has('u'),
+                    append: a.has('a'),
+                    only_if_unset: a.has('o'),
+                    quiet: a.has('q'),
+                    expand: a.has('F'),
+                    target: a.value('t'),
+                    option: a.positional[0].clone(),
+                    value: a.optional_positional(1),
+                }
+            }
+            "bind-key" => {
+                a.expect_positional(1, usize::MAX)?;
+                // Like tmux, a single argument is a command string, such as a `{ }` block.
+                let commands = match &a.positional[1..] {
+                    [string] => parse::commands(string).map_err(|e| e.to_owned())?.1,
+                    args => split_commands(args),
+                };
+                let commands = commands
+                    .iter()
+                    .map(|command| Self::parse_args(command))
+                    .collect::<std::result::Result<_, _>>()?;
+                Self::BindKey {
+                    table: a.table(),
+                    repeat: a.has('r'),
+                    note: a.value('N'),
+                    key: a.positional[0].clone(),
+                    commands,
+                }
+            }
+            "unbind-key" => {
+                a.expect_positional(usize::from(!a.has('a')), 1)?;
+                Self::UnbindKey {
+                    all: a.has('a'),
+                    quiet: a.has('q'),
+                    table: a.table(),
+                    key: a.optional_positional(0),
+                }
+            }
+            "select-pane" => {
+                a.expect_positional(0, 0)?;
+                let direction = [
+                    ('U', Direction::Up),
+                    ('D', Direction::Down),
+                    ('L', Direction::Left),
+                    ('R', Direction::Right),
+                ]
+                .into_iter()
+                .find(|(flag, _)| a.has(*flag))
+                .map(|(_, direction)| direction);
+                Self::SelectPane {
+                    direction,
+                    last: a.has('l'),
+                    title: a.value('T'),
+                    target: a.value('t'),
+                }
+            }
+            "select-window" => {
+                a.expect_positional(0, 0)?;
+                Self::SelectWindow {
+                    last: a.has('l'),
+                    next: a.has('n'),
+                    previous: a.has('p'),
+                    toggle: a.has('T'),
+                    target: a.value('t'),
+                }
+            }
+            "select-layout" => {
+                a.expect_positional(0, 1)?;
+                Self::SelectLayout {
+                    spread: a.has('E'),
+                    next: a.has('n'),
+                    previous: a.has('p'),
+                    target: a.value('t'),
+                    layout: a.optional_positional(0),
+                }
+            }
+            "rename-session" | "rename-window" => {
+                a.expect_positional(1, 1)?;
+                let (target, name) = (a.value('t'), a.positional[0].clone());
+                if full_name == "rename-session" {
+                    Self::RenameSession { target, name }
+                } else {
+                    Self::RenameWindow { target, name }
+                }
+            }
+            "kill-session" | "kill-window" | "kill-pane" => {
+                a.expect_positional(0, 0)?;
+                let (all_others, target) = (a.has('a'), a.value('t'));
+                match full_name {
+                    "kill-session" => Self::KillSession { all_others, target },
+                    "kill-window" => Self::KillWindow { all_others, target },
+                    _ => Self::KillPane { all_others, target },
+                }
+            }
+            "source-file" => {
+                a.expect_positional(1, usize::MAX)?;
+                Self::SourceFile {
+                    expand: a.has('F'),
+                    parse_only: a.has('n'),
+                    quiet: a.has('q'),
+                    verbose: a.has('v'),
+                    paths: a.positional,
+                }
+            }
+            "display-message" => {
+                a.expect_positional(0, 1)?;
+                Self::DisplayMessage {
+                    print: a.has('p'),
+                    client: a.value('c'),
+                    duration: a.number('d')?,
+                    target: a.value('t'),
+                    message: a.optional_positional(0),
+                }
+            }
+            "set-environment" => {
+                a.expect_positional(1, 2)?;
+                Self::SetEnvironment {
+                    global: a.has('g'),
+                    hidden: a.has('h'),
+                    remove: a.has('r'),
+                    unset: a.has('u'),
+                    expand: a.has('F'),
+                    target: a.value('t'),
+                    name: a.positional[0].clone(),
+                    value: a.optional_positional(1),
+                }
+            }
+            _ => {
+                a.expect_positional(0, 1)?;
+                Self::RunShell {
+                    background: a.has('b'),
+                    tmux_command: a.has('C'),
+                    target: a.value('t'),
+                    command: a.optional_positional(0),
+                }
+            }
+        };
+        Ok(command)
+    }
+
+    /// Name of the command, e.g. `split-window`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::NewSession { .. } => "new-session",
+            Self::NewWindow { .. } => "new-window",
+            Self::SplitWindow { .. } => "split-window",
+            Self::SendKeys { .. } => "send-keys",
+            Self::SetOption { .. } => "set-option",
+            Self::BindKey { .. } => "bind-key",
+            Self::UnbindKey { .. } => "unbind-key",
+            Self::SelectPane { .. } => "select-pane",
+            Self::SelectWindow { .. } => "select-window",
+            Self::SelectLayout { .. } => "select-layout",
+            Self::RenameSession { .. } => "rename-session",
+            Self::RenameWindow { .. } => "rename-window",
+            Self::KillSession { .. } => "kill-session",
+            Self::KillWindow { .. } => "kill-window",
+            Self::KillPane { .. } => "kill-pane",
+            Self::SourceFile { .. } => "source-file",
+            Self::DisplayMessage { .. } => "display-message",
+            Self::SetEnvironment { .. } => "set-environment",
+            Self::RunShell { .. } => "run-shell",
+            Self::Other { name, .. } => name,
+        }
+    }
+
+    /// Arguments passed to tmux, starting with the command name.
+    ///
+    /// Nested commands, such as the commands of `bind-key`, are separated by a `;` argument.
+    #[must_use]
+    pub fn args(&self) -> Vec<String> {
+        let mut b = ArgsBuilder::new(self.name());
+        match self {
+            Self::NewSession {
+                attach_if_exists,
+                detached,
+                print,
+                format,
+                start_directory,
+                environment,
+                window_name,
+                name,
+                group,
+                width,
+                height,
+                command,
+            } => {
+                b.flag('A', *attach_if_exists);
+                b.flag('d', *detached);
+                b.flag('P', *print);
+                b.value('F', format.as_deref());
+                b.value('c', start_directory.as_deref());
+                b.environment(environment);
+                b.value('n', window_name.as_deref());
+                b.value('s', name.as_deref());
+                b.value('t', group.as_deref());
+                b.value('x', width.map(|width| width.to_string()).as_deref());
+                b.value('y', height.map(|height| height.to_string()).as_deref());
+                b.positional(command)
+            }
+            Self::NewWindow {
+                after,
+                before,
+                detached,
+                kill,
+                print,
+                format,
+                start_directory,
+                environment,
+                name,
+                target,
+                command,
+            } => {
+                b.flag('a', *after);
+                b.flag('b', *before);
+                b.flag('d', *detached);
+                b.flag('k', *kill);
+                b.flag('P', *print);
+                b.value('F', format.as_deref());
+                b.value('c', start_directory.as_deref());
+                b.environment(environment);
+                b.value('n', name.as_deref());
+                b.value('t', target.as_deref());
+                b.positional(command)
+            }
+            Self::SplitWindow {
+                direction,
+                before,
+                detached,
+                full,
+                size,
+                print,
+                format,
+                start_directory,
+                environment,
+                target,
+                command,
+            } => {
+                b.flag('h', *direction == SplitDirection::Horizontal);
+                b.flag('b', *before);
+                b.flag('d', *detached);
+                b.flag('f', *full);
+                let size = size.map(|size| match size {
+                    PaneSize::Cells(cells) => cells.to_string(),
+                    PaneSize::Percent(percent) => format!("{percent}%"),
+                });
+                b.value('l', size.as_deref());
+                b.flag('P', *print);
+                b.value('F', format.as_deref());
+                b.value('c', start_directory.as_deref());
+                b.environment(environment);
+                b.value('t', target.as_deref());
+                b.positional(command)
+            }
+            Self::SendKeys {
+                hex,
+                literal,
+                reset,
+                copy_mode,
+                repeat,
+                target,
+                keys,
+            } => {
+                b.flag('H', *hex);
+                b.flag('l', *literal);
+                b.flag('R', *reset);
+                b.flag('X', *copy_mode);
+                b.value('N', repeat.map(|repeat| repeat.to_string()).as_deref());
+                b.value('t', target.as_deref());
+                b.positional(keys)
+            }
+            Self::SetOption {
+                scope,
+                global,
+                unset,
+                append,
+                only_if_unset,
+                quiet,
+                expand,
+                target,
+                option,
+                value,
+            } => {
+                b.flag('s', *scope == Some(OptionScope::Server));
+                b.flag('w', *scope == Some(OptionScope::Window));
+                b.flag('p', *scope == Some(OptionScope::Pane));
+                b.flag('g', *global);
+                b.flag('u', *unset);
+                b.flag('a', *append);
+                b.flag('o', *only_if_unset);
+                b.flag('q', *quiet);
+                b.flag('F', *expand);
+                b.value('t', target.as_deref());
+                b.positional(std::iter::once(option).chain(value))
+            }
+            Self::BindKey {
+                table,
+                repeat,
+                note,
+                key,
+                commands,
+            } => {
+                b.value('T', table.as_deref());
+                b.flag('r', *repeat);
+                b.value('N', note.as_deref());
+                let mut nested = vec![key.clone()];
+                for (index, command) in commands.iter().enumerate() {
+                    if index > 0 {
+                        nested.push(";".to_string());
+                    }
+                    nested.extend(command.args().iter().map(|arg| escape_separator(arg)));
+                }
+                b.positional(&nested)
+            }
+            Self::UnbindKey {
+                all,
+                quiet,
+                table,
+                key,
+            } => {
+                b.flag('a', *all);
+                b.flag('q', *quiet);
+                b.value('T', table.as_deref());
+                b.positional(key)
+            }
+            Self::SelectPane {
+                direction,
+                last,
+                title,
+                target,
+            } => {
+                if let Some(direction) = direction {
+                    b.flag(
+                        match direction {
+                            Direction::Up => 'U',
+                            Direction::Down => 'D',
+                            Direction::Left => 'L',
+                            Direction::Right => 'R',
+                        },
+                        true,
+                    );
+                }
+                b.flag('l', *last);
+                b.value('T', title.as_deref());
+                b.value('t', target.as_deref());
+                b.0
+            }
+            Self::SelectWindow {
+                last,
+                next,
+                previous,
+                toggle,
+                target,
+            } => {
+                b.flag('l', *last);
+                b.flag('n', *next);
+                b.flag('p', *previous);
+                b.flag('T', *toggle);
+                b.value('t', target.as_deref());
+                b.0
+            }
+            Self::SelectLayout {
+                spread,
+                next,
+                previous,
+                target,
+                layout,
+            } => {
+                b.flag('E', *spread);
+                b.flag('n', *next);
+                b.flag('p', *previous);
+                b.value('t', target.as_deref());
+                b.positional(layout)
+            }
+            Self::RenameSession { target, name } | Self::RenameWindow { target, name } => {
+                b.value('t', target.as_deref());
+                b.positional([name])
+            }
+            Self::KillSession { all_others, target }
+            | Self::KillWindow { all_others, target }
+            | Self::KillPane { all_others, target } => {
+                b.flag('a', *all_others);
+                b.value('t', target.as_deref());
+                b.0
+            }
+            Self::SourceFile {
+                expand,
+                parse_only,
+                quiet,
+                verbose,
+                paths,
+            } => {
+                b.flag('F', *expand);
+                b.flag('n', *parse_only);
+                b.flag('q', *quiet);
+                b.flag('v', *verbose);
+                b.positional(paths)
+            }
+            Self::DisplayMessage {
+                print,
+                client,
+                duration,
+                target,
+                message,
+            } => {
+                b.flag('p', *print);
+                b.value('c', client.as_deref());
+                b.value('d', duration.map(|ms| ms.to_string()).as_deref());
+                b.value('t', target.as_deref());
+                b.positional(message)
+            }
+            Self::SetEnvironment {
+                global,
+                hidden,
+                remove,
+                unset,
+                expand,
+                target,
+                name,
+                value,
+            } => {
+                b.flag('g', *global);
+                b.flag('h', *hidden);
+                b.flag('r', *remove);
+                b.flag('u', *unset);
+                b.flag('F', *expand);
+                b.value('t', target.as_deref());
+                b.positional(std::iter::once(name).chain(value))
+            }
+            Self::RunShell {
+                background,
+                tmux_command,
+                target,
+                command,
+            } => {
+                b.flag('b', *background);
+                b.flag('C', *tmux_command);
+                b.value('t', target.as_deref());
+                b.positional(command)
+            }
+            Self::Other { args, .. } => {
+                b.0.extend(args.iter().cloned());
+                b.0
+            }
+        }
+    }
+}
+
+fn parse_pane_size(size: &str) -> std::result::Result<PaneSize, ParseError> {
+    let parsed = match size.strip_suffix('%') {
+        Some(percent) => percent.parse().map(PaneSize::Percent),
+        None => size.parse().map(PaneSize::Cells),
+    };
+    parsed.map_err(|_| failure(size, ErrorKind::Digit))
+}
+
+impl fmt::Display for TmuxCommand {
+    /// Render the command as a line of a tmux config file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, arg) in self.args().iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            if arg == ";" {
+                f.write_str("\\;")?;
+            } else {
+                f.write_str(&quote(arg))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TmuxCommand {
+    type Err = Error;
+
+    /// Parse a single command from a line of a tmux config file.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let mut commands = parse_line(input)?;
+        if commands.len() != 1 {
+            return Err(map_parse_error(failure(input, ErrorKind::Count)));
+        }
+        Ok(commands.remove(0))
+    }
+}
+
+/// Parse the commands of a line of a tmux config file, separated by `;`.
+///
+/// Quotes, escape sequences, `~` and environment variables are handled like tmux does.
+///
+/// # Errors
+///
+/// Returns an `Error::ParseError` on unterminated quotes, invalid escape sequences, or invalid
+/// commands.
+pub fn parse_line(input: &str) -> Result<Vec<TmuxCommand>> {
+    let (_, commands) = parse::commands(input)
+        .map_err(|e| crate::error::map_add_intent("TmuxCommand", "tmux command", e))?;
+
+    commands
+        .iter()
+        .map(|args| TmuxCommand::parse_args(args).map_err(map_parse_error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(line: &str) -> TmuxCommand {
+        let command = TmuxCommand::from_str(line).unwrap();
+        assert_eq!(
+            TmuxCommand::from_str(&command.to_string()).unwrap(),
+            command
+        );
+        assert_eq!(TmuxCommand::from_args(command.args()).unwrap(), command);
+        command
+    }
+
+    #[test]
+    fn parse_new_session() {
+        let command = round_trip("new -d -s main -n 'first window' -e A=1 -x 120 -y 40 htop");
+        assert_eq!(
+            command,
+            TmuxCommand::NewSession {
+                attach_if_exists: false,
+                detached: true,
+                print: false,
+                format: None,
+                start_directory: None,
+                environment: vec![("A".into(), "1".into())],
+                window_name: Some("first window".into()),
+                name: Some("main".into()),
+                group: None,
+                width: Some(120),
+                height: Some(40),
+                command: vec!["htop".into()],
+            }
+        );
+        assert_eq!(
+            command.to_string(),
+            "new-session -d -e A=1 -n \"first window\" -s main -x 120 -y 40 htop"
+        );
+    }
+
+    #[test]
+    fn parse_grouped_flags_and_attached_values() {
+        let command = round_trip("splitw -hdl30% -tmain:1.0 -- -vim");
+        assert_eq!(
+            command,
+            TmuxCommand::SplitWindow {
+                direction: SplitDirection::Horizontal,
+                before: false,
+                detached: true,
+                full: false,
+                size: Some(PaneSize::Percent(30)),
+                print: false,
+                format: None,
+                start_directory: None,
+                environment: vec![],
+                target: Some("main:1.0".into()),
+                command: vec!["-vim".into()],
+            }
+        );
+        assert_eq!(
+            command.args(),
+            [
+                "split-window",
+                "-h",
+                "-d",
+                "-l",
+                "30%",
+                "-t",
+                "main:1.0",
+                "--",
+                "-vim"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_set_option() {
+        let command = round_trip("setw -g mode-keys vi");
+        assert_eq!(command.to_string(), "set-option -w -g mode-keys vi");
+
+        let command = round_trip("set -g status-right '#{?client_prefix,P,} %H:%M $USER'");
+        let TmuxCommand::SetOption { value, scope, .. } = &command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(*scope, None);
+        assert_eq!(value.as_deref(), Some("#{?client_prefix,P,} %H:%M $USER"));
+        assert_eq!(
+            command.to_string(),
+            "set-option -g status-right \"#{?client_prefix,P,} %H:%M \\$USER\""
+        );
+    }
+
+    #[test]
+    fn parse_bind_key() {
+        let command = round_trip("bind -n M-h select-pane -L");
+        assert_eq!(
+            command,
+            TmuxCommand::BindKey {
+                table: Some("root".into()),
+                repeat: false,
+                note: None,
+                key: "M-h".into(),
+                commands: vec![TmuxCommand::SelectPane {
+                    direction: Some(Direction::Left),
+                    last: false,
+                    title: None,
+                    target: None,
+                }],
+            }
+        );
+
+        // Like tmux, a trailing `;` separates nested commands unless escaped.
+        let split = TmuxCommand::from_str(r#"bind x send-keys "ls;" Enter"#).unwrap();
+        assert!(matches!(split, TmuxCommand::BindKey { commands, .. } if commands.len() == 2));
+
+        let command = round_trip(r#"bind -r x split-window -h \; send-keys "ls\\;" Enter"#);
+        let TmuxCommand::BindKey { commands, .. } = &command else {
+            panic!("unexpected command: {command:?}");
+        };
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[1].args(), ["send-keys", "ls;", "Enter"]);
+        assert_eq!(
+            command.args()[3..],
+            ["split-window", "-h", ";", "send-keys", "ls\\;", "Enter"]
+        );
+        assert_eq!(
+            command.to_string(),
+            r#"bind-key -r x split-window -h \; send-keys "ls\\;" Enter"#
+        );
+    }
+
+    #[test]
+    fn parse_other_commands() {
+        // Unknown command.
+        let command = round_trip("list-keys -T prefix");
+        assert_eq!(
+            command,
+            TmuxCommand::Other {
+                name: "list-keys".into(),
+                args: vec!["-T".into(), "prefix".into()],
+            }
+        );
+
+        // Known command with a flag which is not modeled.
+        let command = round_trip("select-pane -Z -t 1");
+        assert_eq!(command.name(), "select-pane");
+        assert!(matches!(command, TmuxCommand::Other { .. }));
+    }
+
+    #[test]
+    fn round_trip_all_commands() {
+        for line in [
+            "new-window -a -d -k -P -F '#{window_id}' -c ~/src -n code -t main:3 vim",
+            "send-keys -H -R -X -N 3 -t %1 C-c Enter",
+            "unbind-key -a -q -T copy-mode",
+            "unbind -n M-x",
+            "select-window -l -n -p -T -t :1",
+            "select-layout -E -n -p -t @1 tiled",
+            "rename-session -t main work",
+            "rename-window -t @1 'my window'",
+            "kill-session -a -t main",
+            "kill-window -t @2",
+            "kill-pane -a -t %3",
+            "source-file -F -n -q -v ~/.tmux.conf '/etc/tmux/*.conf'",
+            "display-message -p -c /dev/pts/1 -d 500 -t %1 'Hello #{session_name}'",
+            "set-environment -g -h -r -u -F -t main EDITOR vim",
+            "run-shell -b -C -t %1 'display-message hi'",
+            "set-option -s -u -a -o -q -F -t main escape-time 0",
+            "set -p -t %1 @custom ''",
+        ] {
+            let command = round_trip(line);
+            assert!(
+                !matches!(command, TmuxCommand::Other { .. }),
+                "not modeled: {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        assert!(TmuxCommand::from_str("").is_err());
+        assert!(TmuxCommand::from_str("new-session -x wide").is_err());
+        assert!(TmuxCommand::from_str("new-session -e NOVALUE").is_err());
+        assert!(TmuxCommand::from_str("split-window -t").is_err());
+        assert!(TmuxCommand::from_str("split-window -l 30x").is_err());
+        assert!(TmuxCommand::from_str("rename-window a b").is_err());
+        assert!(TmuxCommand::from_str("set-option").is_err());
+        assert!(TmuxCommand::from_str("bind-key").is_err());
+        assert!(TmuxCommand::from_str("kill-pane ; kill-window").is_err());
+        assert!(TmuxCommand::from_str("display-message 'unterminated").is_err());
+        assert!(TmuxCommand::from_args(Vec::<String>::new()).is_err());
+    }
+
+    #[test]
+    fn parse_several_commands() {
+        let commands = parse_line("kill-pane -t %1; kill-window -t @1 # comment").unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[1].to_string(), "kill-window -t @1");
+    }
+
+    #[test]
+    fn split_nested_commands() {
+        let args: Vec<String> = ["a", "b;", "c\\;", ";", "d"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            split_commands(&args),
+            [vec!["a", "b"], vec!["c;"], vec!["d"]]
+        );
+    }
+
+    #[test]
+    fn quote_config_arguments() {
+        assert_eq!(quote("select-pane"), "select-pane");
+        assert_eq!(quote("%3"), "%3");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("a b"), "\"a b\"");
+        assert_eq!(quote("#{pane_id}"), "\"#{pane_id}\"");
+        assert_eq!(quote("~/src"), "\"~/src\"");
+        assert_eq!(
+            quote("say \"hi\" $HOME\\"),
+            "\"say \\\"hi\\\" \\$HOME\\\\\""
+        );
+        assert_eq!(quote("a\nb;"), "\"a\\nb;\"");
+        assert_eq!(quote("%H:%M"), "\"%H:%M\"");
+        assert_eq!(quote("%endif"), "\"%endif\"");
+        assert_eq!(quote("50%"), "50%");
+    }
+
+    #[test]
+    fn display_round_trips_percent_arguments() {
+        for line in [
+            "set -g status-right '%H:%M'",
+            "rename-window -t @1 '%endif'",
+            "send-keys -t %3 '%hidden' Enter",
+        ] {
+            let command = TmuxCommand::from_str(line).unwrap();
+            let rendered = command.to_string();
+            assert_eq!(
+                TmuxCommand::from_str(&rendered).unwrap(),
+                command,
+                "{rendered}"
+            );
+        }
+    }
+}
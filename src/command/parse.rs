@@ -0,0 +1,448 @@
+//! Lexer of tmux config files, following `cmd-parse.y` in tmux.
+//!
+//! Like the format parser, this scans the input char by char, and reports errors as nom errors.
+
+use std::{iter::Peekable, str::CharIndices};
+
+use nom::{
+    IResult,
+    error::{Error as NomError, ErrorKind},
+};
+
+use super::quote;
+
+fn failure(input: &str, kind: ErrorKind) -> nom::Err<NomError<&str>> {
+    nom::Err::Failure(NomError::new(input, kind))
+}
+
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+type LexError<'a> = nom::Err<NomError<&'a str>>;
+
+/// Token of a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// A word, unquoted and unescaped.
+    Word(String),
+    /// A directive such as `%if`.
+    Directive(String),
+    /// `;`, separating commands.
+    Separator,
+    /// End of a line, also separating commands.
+    Newline,
+    /// `{`, opening a block of commands.
+    Open,
+    /// `}`, closing a block of commands.
+    Close,
+}
+
+/// Chars ending an unquoted word.
+const WORD_END: [char; 6] = [' ', '\t', '\r', '\n', ';', '}'];
+
+/// Split a config file into tokens, tracking line numbers.
+pub(crate) struct Lexer<'a> {
+    input: &'a str,
+    chars: Chars<'a>,
+    line: usize,
+    peeked: Option<Option<(Token, usize)>>,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            chars: input.char_indices().peekable(),
+            line: 1,
+            peeked: None,
+        }
+    }
+
+    /// Line of the next token, or of the end of input.
+    pub(crate) fn line(&mut self) -> usize {
+        match &self.peeked {
+            Some(Some((_, line))) => *line,
+            _ => self.line,
+        }
+    }
+
+    /// Input which is not consumed yet, for reporting errors.
+    pub(crate) fn rest(&mut self) -> &'a str {
+        let offset = self
+            .chars
+            .peek()
+            .map_or(self.input.len(), |&(offset, _)| offset);
+        &self.input[offset..]
+    }
+
+    /// Return the next token and the line it starts on, without consuming it.
+    pub(crate) fn peek(&mut self) -> Result<Option<&(Token, usize)>, LexError<'a>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.scan()?);
+        }
+        Ok(self.peeked.as_ref().and_then(Option::as_ref))
+    }
+
+    /// Consume the next token, and return it with the line it starts on.
+    pub(crate) fn next_token(&mut self) -> Result<Option<(Token, usize)>, LexError<'a>> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.scan(),
+        }
+    }
+
+    fn scan(&mut self) -> Result<Option<(Token, usize)>, LexError<'a>> {
+        loop {
+            let Some(&(_, c)) = self.chars.peek() else {
+                return Ok(None);
+            };
+            let line = self.line;
+            let token = match c {
+                ' ' | '\t' | '\r' => {
+                    self.chars.next();
+                    continue;
+                }
+                '#' => {
+                    while self.chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                    continue;
+                }
+                // A backslash before a newline continues the line.
+                '\\' if self.lookahead(1) == Some('\n') => {
+                    self.chars.next();
+                    self.chars.next();
+                    self.line += 1;
+                    continue;
+                }
+                '\n' => {
+                    self.line += 1;
+                    Token::Newline
+                }
+                ';' => Token::Separator,
+                '{' => Token::Open,
+                '}' => Token::Close,
+                '%' => match self.directive() {
+                    Some(directive) => return Ok(Some((Token::Directive(directive), line))),
+                    None => return self.word().map(Some),
+                },
+                _ => return self.word().map(Some),
+            };
+            self.chars.next();
+            return Ok(Some((token, line)));
+        }
+    }
+
+    /// Char `n` positions after the next one.
+    fn lookahead(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n).map(|(_, c)| c)
+    }
+
+    /// Consume a directive: a word starting with `%`, unless it only has `%` and digits, like
+    /// pane ids.
+    fn directive(&mut self) -> Option<String> {
+        let word: String = self
+            .chars
+            .clone()
+            .map(|(_, c)| c)
+            .take_while(|c| !WORD_END.contains(c))
+            .collect();
+        if word.chars().all(|c| c == '%' || c.is_ascii_digit()) {
+            return None;
+        }
+        for _ in word.chars() {
+            self.chars.next();
+        }
+        Some(word)
+    }
+
+    fn word(&mut self) -> Result<(Token, usize), LexError<'a>> {
+        let line = self.line;
+        let mut word = String::new();
+
+        if self.chars.peek().is_some_and(|&(_, c)| c == '~')
+            && self
+                .lookahead(1)
+                .is_none_or(|next| next == '/' || WORD_END.contains(&next))
+        {
+            self.chars.next();
+            word = std::env::var("HOME").unwrap_or_default();
+        }
+
+        while let Some(&(offset, c)) = self.chars.peek() {
+            if WORD_END.contains(&c) {
+                break;
+            }
+            self.chars.next();
+            match c {
+                '\'' => loop {
+                    match self.chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, c)) => self.push(&mut word, c),
+                        None => return Err(failure(&self.input[offset..], ErrorKind::Char)),
+                    }
+                },
+                '"' => loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) if self.chars.next_if(|&(_, c)| c == '\n').is_some() => {
+                            self.line += 1;
+                        }
+                        Some((escape, '\\')) => {
+                            escaped(self.input, escape, &mut self.chars, &mut word)?;
+                        }
+                        Some((_, '$')) => variable(&mut self.chars, &mut word),
+                        Some((_, c)) => self.push(&mut word, c),
+                        None => return Err(failure(&self.input[offset..], ErrorKind::Char)),
+                    }
+                },
+                '\\' => escaped(self.input, offset, &mut self.chars, &mut word)?,
+                '$' => variable(&mut self.chars, &mut word),
+                c => word.push(c),
+            }
+        }
+
+        Ok((Token::Word(word), line))
+    }
+
+    /// Append a quoted char, counting lines.
+    fn push(&mut self, word: &mut String, c: char) {
+        if c == '\n' {
+            self.line += 1;
+        }
+        word.push(c);
+    }
+}
+
+/// Read the words of a command, up to the token ending it, which is returned.
+///
+/// A block of commands (`{ ... }`) is a single word holding its commands, like tmux passes it to
+/// commands such as `bind-key` or `if-shell`.
+pub(crate) fn words<'a>(
+    lexer: &mut Lexer<'a>,
+) -> Result<(Vec<String>, Option<Token>), LexError<'a>> {
+    let mut words = vec![];
+    loop {
+        match lexer.next_token()? {
+            None => return Ok((words, None)),
+            Some((Token::Word(word), _)) => words.push(word),
+            Some((Token::Open, _)) => words.push(block(lexer)?),
+            Some((Token::Directive(_), _)) => return Err(failure(lexer.rest(), ErrorKind::Tag)),
+            Some((end, _)) => return Ok((words, Some(end))),
+        }
+    }
+}
+
+/// Read the commands of a block up to its closing `}`, and render them as a single command
+/// string.
+fn block<'a>(lexer: &mut Lexer<'a>) -> Result<String, LexError<'a>> {
+    let mut commands = vec![];
+    loop {
+        let (words, end) = words(lexer)?;
+        if !words.is_empty() {
+            let words: Vec<String> = words
+                .iter()
+                .map(|word| {
+                    if word == ";" {
+                        "\\;".into()
+                    } else {
+                        quote(word)
+                    }
+                })
+                .collect();
+            commands.push(words.join(" "));
+        }
+        match end {
+            Some(Token::Close) => return Ok(commands.join(" ; ")),
+            None => return Err(failure(lexer.rest(), ErrorKind::Eof)),
+            Some(_) => {}
+        }
+    }
+}
+
+/// Split `input` into commands, each a list of words.
+///
+/// Commands are separated by `;` or newlines, and `#` starts a comment outside of words.
+pub(crate) fn commands(input: &str) -> IResult<&str, Vec<Vec<String>>> {
+    let mut lexer = Lexer::new(input);
+    let mut commands = vec![];
+    loop {
+        let (words, end) = words(&mut lexer)?;
+        if !words.is_empty() {
+            commands.push(words);
+        }
+        match end {
+            None => return Ok(("", commands)),
+            Some(Token::Close) => return Err(failure(lexer.rest(), ErrorKind::Char)),
+            Some(_) => {}
+        }
+    }
+}
+
+/// Append the char escaped by the backslash at `offset`, the escape sequence being consumed from
+/// `chars`.
+fn escaped<'a>(
+    input: &'a str,
+    offset: usize,
+    chars: &mut Chars<'a>,
+    word: &mut String,
+) -> Result<(), nom::Err<NomError<&'a str>>> {
+    let Some((_, c)) = chars.next() else {
+        return Err(failure(&input[offset..], ErrorKind::Escaped));
+    };
+    let unescaped = match c {
+        'a' => '\x07',
+        'b' => '\x08',
+        'e' => '\x1b',
+        'f' => '\x0c',
+        'n' => '\n',
+        'r' => '\r',
+        's' => ' ',
+        't' => '\t',
+        'v' => '\x0b',
+        'u' | 'U' => {
+            let digits = if c == 'u' { 4 } else { 8 };
+            let mut code = String::with_capacity(digits);
+            for _ in 0..digits {
+                match chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                    Some((_, digit)) => code.push(digit),
+                    None => return Err(failure(&input[offset..], ErrorKind::HexDigit)),
+                }
+            }
+            u32::from_str_radix(&code, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| failure(&input[offset..], ErrorKind::HexDigit))?
+        }
+        '0'..='7' => {
+            let mut code = c.to_digit(8).unwrap_or_default();
+            for _ in 0..2 {
+                match chars.next_if(|(_, c)| c.is_digit(8)) {
+                    Some((_, digit)) => code = code * 8 + digit.to_digit(8).unwrap_or_default(),
+                    None => return Err(failure(&input[offset..], ErrorKind::OctDigit)),
+                }
+            }
+            char::from_u32(code).ok_or_else(|| failure(&input[offset..], ErrorKind::OctDigit))?
+        }
+        c => c,
+    };
+    word.push(unescaped);
+    Ok(())
+}
+
+/// Append the value of the environment variable following a `$`, as `$NAME` or `${NAME}`, or a
+/// literal `$` if no name follows.
+fn variable(chars: &mut Chars<'_>, word: &mut String) {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut name = String::new();
+
+    if chars.next_if(|&(_, c)| c == '{').is_some() {
+        while let Some((_, c)) = chars.next_if(|&(_, c)| c != '}') {
+            name.push(c);
+        }
+        chars.next();
+    } else {
+        while let Some((_, c)) = chars.next_if(|&(_, c)| is_name(c)) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            word.push('$');
+            return;
+        }
+    }
+    word.push_str(&std::env::var(&name).unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<Vec<String>> {
+        commands(input).unwrap().1
+    }
+
+    #[test]
+    fn split_words_and_commands() {
+        assert_eq!(
+            words("  set -g  mouse on ; bind x kill-pane\nneww"),
+            [
+                vec!["set", "-g", "mouse", "on"],
+                vec!["bind", "x", "kill-pane"],
+                vec!["neww"],
+            ]
+        );
+        assert_eq!(words("kill-pane;"), [vec!["kill-pane"]]);
+        assert!(words("  # comment\n\n").is_empty());
+        assert_eq!(words("a#b # c"), [vec!["a#b"]]);
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(
+            words(r#"a 'b "c" \n' "d 'e' \"f\"""#)[0],
+            ["a", r#"b "c" \n"#, r#"d 'e' "f""#]
+        );
+        assert_eq!(words(r#""" ''"#)[0], ["", ""]);
+        assert_eq!(words(r#"x"a;b"y 'c#d'"#)[0], ["xa;by", "c#d"]);
+        assert_eq!(
+            words(r#""\t\eé\101\$" \; a\ b"#)[0],
+            ["\t\x1béA$", ";", "a b"]
+        );
+        assert_eq!(words("a \\\n b"), [vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn home_and_variables() {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let user = std::env::var("USER").unwrap_or_default();
+
+        assert_eq!(
+            words("~ ~/src a~ '~'")[0],
+            [home.clone(), format!("{home}/src"), "a~".into(), "~".into()]
+        );
+        assert_eq!(
+            words("$USER \"${USER}!\" '$USER' $ a$")[0],
+            [
+                user.clone(),
+                format!("{user}!"),
+                "$USER".into(),
+                "$".into(),
+                "a$".into()
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks_and_directives() {
+        assert_eq!(
+            words("bind x { a 'b c'\n d \\; } y")[0],
+            ["bind", "x", "a \"b c\" ; d \\;", "y"]
+        );
+        assert_eq!(words("selectp -t %1 ; a %%")[0], ["selectp", "-t", "%1"]);
+
+        let mut lexer = Lexer::new("%if 1\n  x}");
+        let mut tokens = vec![];
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push(token);
+        }
+        assert_eq!(
+            tokens,
+            [
+                (Token::Directive("%if".into()), 1),
+                (Token::Word("1".into()), 1),
+                (Token::Newline, 1),
+                (Token::Word("x".into()), 2),
+                (Token::Close, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_lines() {
+        assert!(commands("'unterminated").is_err());
+        assert!(commands("\"unterminated").is_err());
+        assert!(commands("trailing\\").is_err());
+        assert!(commands(r#""\u12""#).is_err());
+        assert!(commands(r#""\19""#).is_err());
+        assert!(commands("bind x { a").is_err());
+        assert!(commands("a }").is_err());
+        assert!(commands("%if 1").is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Parser of tmux config files, such as `~/.tmux.conf`.
+//!
+//! A [`Config`] keeps the structure of the file, for tools linting or migrating config files:
+//! commands, variable assignments, `%if` conditionals, and files included with `source-file`,
+//! each with its location.
+//!
+//! ```
+//! use std::str::FromStr;
+//! use tmux_lib::config::{Config, Item};
+//!
+//! let config = Config::from_str(
+//!     "set -g mouse on\n\
+//!      %if '#{==:#{host},laptop}'\n\
+//!      bind r { source-file ~/.tmux.conf; display-message reloaded }\n\
+//!      %endif\n",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(config.items.len(), 2);
+//! assert!(matches!(&config.items[1], Item::Condition { branches, .. } if branches[0].items.len() == 1));
+//! assert_eq!(config.commands(|_| false).len(), 1);
+//! ```
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{Result, command::TmuxCommand, error::Error};
+
+pub(crate) mod parse;
+
+/// Maximum nesting of files included with `source-file`, like tmux.
+const MAX_INCLUDE_DEPTH: usize = 50;
+
+/// Location of an item in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// Path of the file, if the config was loaded from a file.
+    pub path: Option<PathBuf>,
+    /// Line number, starting at 1. For items spanning several lines, this is the first line,
+    /// whereas tmux reports the last one.
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}:{}", path.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+/// Branch of a `%if` conditional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Location of the `%if` or `%elif` directive.
+    pub location: Location,
+    /// Condition, a format which is true if it expands to a non-empty string other than `0`.
+    pub condition: String,
+    /// Items of the branch.
+    pub items: Vec<Item>,
+}
+
+/// Item of a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    /// A command.
+    Command {
+        /// Location of the command.
+        location: Location,
+        /// The command.
+        command: TmuxCommand,
+    },
+    /// A `source-file` command, with the files it includes, when the config is loaded with
+    /// [`Config::load`]. Otherwise, `source-file` commands are kept as [`Item::Command`].
+    Include {
+        /// Location of the command.
+        location: Location,
+        /// The `source-file` command.
+        command: TmuxCommand,
+        /// Included files, in order.
+        files: Vec<Config>,
+    },
+    /// An environment variable assignment, `NAME=value`, or `%hidden NAME=value` for a variable
+    /// which is not passed to processes started by tmux.
+    Assignment {
+        /// Location of the assignment.
+        location: Location,
+        /// Name of the variable.
+        name: String,
+        /// Value of the variable.
+        value: String,
+        /// Assigned with `%hidden`.
+        hidden: bool,
+    },
+    /// A `%if` conditional, with its `%elif` branches and `%else` items.
+    Condition {
+        /// Location of the `%if` directive.
+        location: Location,
+        /// The `%if` branch followed by the `%elif` branches.
+        branches: Vec<Branch>,
+        /// Items of the `%else` branch, if any.
+        otherwise: Option<Vec<Item>>,
+    },
+}
+
+impl Item {
+    /// Location of the item, the `%if` directive for conditionals.
+    #[must_use]
+    pub fn location(&self) -> &Location {
+        match self {
+            Self::Command { location, .. }
+            | Self::Include { location, .. }
+            | Self::Assignment { location, .. }
+            | Self::Condition { location, .. } => location,
+        }
+    }
+}
+
+/// A parsed tmux config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Path of the file, if the config was loaded from a file.
+    pub path: Option<PathBuf>,
+    /// Items of the file, in order.
+    pub items: Vec<Item>,
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parse a config, without following `source-file` includes.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        parse::config(input, None, None)
+    }
+}
+
+impl Config {
+    /// Load the config file at `path`, and the files it includes with `source-file`.
+    ///
+    /// Like tmux, included paths may contain glob patterns, and missing files are an error unless
+    /// `source-file -q` is used. Relative paths are resolved from the directory of the including
+    /// file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Config` with the location of the error if a file is invalid or cannot be
+    /// read, or an `Error::Io` if `path` cannot be read.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        load(path.as_ref(), 0)
+    }
+
+    /// Commands run by tmux when sourcing this config, with their location.
+    ///
+    /// The branches of `%if` conditionals are selected by `is_true`, called with the condition of
+    /// each branch until one is true. Included files are replaced by their commands if they were
+    /// loaded.
+    pub fn commands(
+        &self,
+        mut is_true: impl FnMut(&str) -> bool,
+    ) -> Vec<(&Location, &TmuxCommand)> {
+        let mut commands = vec![];
+        collect_commands(&self.items, &mut is_true, &mut commands);
+        commands
+    }
+}
+
+fn load(path: &Path, depth: usize) -> Result<Config> {
+    let input = fs::read_to_string(path)?;
+    parse::config(&input, Some(path), Some(depth))
+}
+
+fn collect_commands<'a>(
+    items: &'a [Item],
+    is_true: &mut impl FnMut(&str) -> bool,
+    commands: &mut Vec<(&'a Location, &'a TmuxCommand)>,
+) {
+    for item in items {
+        match item {
+            Item::Command { location, command } => commands.push((location, command)),
+            Item::Include { files, .. } => {
+                for file in files {
+                    collect_commands(&file.items, is_true, commands);
+                }
+            }
+            Item::Assignment { .. } => {}
+            Item::Condition {
+                branches,
+                otherwise,
+                ..
+            } => {
+                let selected = branches
+                    .iter()
+                    .find(|branch| is_true(&branch.condition))
+                    .map(|branch| &branch.items)
+                    .or(otherwise.as_ref());
+                if let Some(items) = selected {
+                    collect_commands(items, is_true, commands);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::OptionScope;
+
+    fn location(line: usize) -> Location {
+        Location { path: None, line }
+    }
+
+    fn command(line: &str) -> TmuxCommand {
+        TmuxCommand::from_str(line).unwrap()
+    }
+
+    #[test]
+    fn parse_commands_with_locations() {
+        let config = Config::from_str(
+            "# comment\n\
+             set -g mouse on ; setw -g mode-keys vi\n\
+             \n\
+             bind x \\\n  kill-pane\n\
+             set -g status-left '#S \n #I'\n\
+             neww",
+        )
+        .unwrap();
+
+        let items: Vec<_> = config
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Command { location, command } => (location.line, command.clone()),
+                item => panic!("unexpected item: {item:?}"),
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (2, command("set -g mouse on")),
+                (2, command("set-option -w -g mode-keys vi")),
+                (4, command("bind x kill-pane")),
+                (6, command("set -g status-left \"#S \\n #I\"")),
+                (8, command("new-window")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_blocks() {
+        let config = Config::from_str(
+            "bind r {\n\
+               source-file ~/.tmux.conf\n\
+               display-message 'reloaded; ok'\n\
+             }\n\
+             if-shell true { set -g @a 1 } { set -g @a 2 }\n\
+             kill-pane",
+        )
+        .unwrap();
+
+        let TmuxCommand::BindKey { commands, .. } = config.commands(|_| true)[0].1 else {
+            panic!("unexpected items: {:?}", config.items);
+        };
+        assert_eq!(
+            commands[..],
+            [
+                command("source-file ~/.tmux.conf"),
+                command("display-message 'reloaded; ok'"),
+            ]
+        );
+        assert_eq!(
+            config.items[1],
+            Item::Command {
+                location: location(5),
+                command: TmuxCommand::Other {
+                    name: "if-shell".into(),
+                    args: vec!["true".into(), "set -g @a 1".into(), "set -g @a 2".into()],
+                }
+            }
+        );
+        assert_eq!(config.items[2].location().line, 6);
+    }
+
+    #[test]
+    fn parse_conditions() {
+        let config = Config::from_str(
+            "%if '#{==:#{host},a}'\n\
+               set -g @host a\n\
+             %elif '#{==:#{host},b}'\n\
+               %if 1\n\
+                 set -g @host b1\n\
+               %endif\n\
+             %else\n\
+               set -g @host other\n\
+             %endif\n\
+             set -g @done 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.items.len(), 2);
+        let Item::Condition {
+            location: if_location,
+            branches,
+            otherwise,
+        } = &config.items[0]
+        else {
+            panic!("unexpected items: {:?}", config.items);
+        };
+        assert_eq!(*if_location, location(1));
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].condition, "#{==:#{host},a}");
+        assert_eq!(branches[1].location, location(3));
+        assert!(matches!(
+            &branches[1].items[0],
+            Item::Condition { branches, otherwise: None, .. } if branches[0].location == location(4)
+        ));
+        assert_eq!(otherwise.as_ref().map(Vec::len), Some(1));
+        assert_eq!(config.items[1].location().line, 10);
+        assert_eq!(branches[1].items[0].location(), &location(4));
+
+        // Built without branches, a conditional still has a location.
+        let condition = Item::Condition {
+            location: location(7),
+            branches: vec![],
+            otherwise: None,
+        };
+        assert_eq!(condition.location(), &location(7));
+
+        let values = |host: &str| -> Vec<String> {
+            config
+                .commands(|condition| {
+                    condition == "1" || condition == format!("#{{==:#{{host}},{host}}}")
+                })
+                .iter()
+                .map(|(_, command)| command.to_string())
+                .collect()
+        };
+        assert_eq!(
+            values("a"),
+            ["set-option -g @host a", "set-option -g @done 1"]
+        );
+        assert_eq!(
+            values("b"),
+            ["set-option -g @host b1", "set-option -g @done 1"]
+        );
+        assert_eq!(
+            values("c"),
+            ["set-option -g @host other", "set-option -g @done 1"]
+        );
+    }
+
+    #[test]
+    fn parse_assignments() {
+        let config = Config::from_str("EDITOR=vim\n%hidden SECRET=\"a b\"\nA=1 B=2").unwrap();
+
+        assert_eq!(
+            config.items[..2],
+            [
+                Item::Assignment {
+                    location: location(1),
+                    name: "EDITOR".into(),
+                    value: "vim".into(),
+                    hidden: false,
+                },
+                Item::Assignment {
+                    location: location(2),
+                    name: "SECRET".into(),
+                    value: "a b".into(),
+                    hidden: true,
+                },
+            ]
+        );
+        // Several words are a command, not an assignment.
+        assert!(
+            matches!(&config.items[2], Item::Command { command, .. } if command.name() == "A=1")
+        );
+    }
+
+    #[test]
+    fn report_error_locations() {
+        for (input, line) in [
+            ("set -g mouse on\n%if 1\nkill-pane\n", 4),
+            ("kill-pane\n%else\n", 2),
+            ("%if 1\n%else\n%elif 1\n%endif", 3),
+            ("%if\nkill-pane\n%endif", 1),
+            ("%endif extra", 1),
+            ("\n%unknown", 2),
+            ("%hidden NOT AN ASSIGNMENT", 1),
+            ("bind x {\nkill-pane\n", 3),
+            ("kill-pane }", 1),
+            ("\nset -g status-left 'unterminated", 2),
+            ("\n\nnew-session -x wide", 3),
+        ] {
+            let error = Config::from_str(input).unwrap_err();
+            let Error::Config { location, .. } = &error else {
+                panic!("unexpected error for {input:?}: {error:?}");
+            };
+            assert_eq!(location.line, line, "for {input:?}: {error}");
+        }
+    }
+
+    #[test]
+    fn load_includes() {
+        let dir = std::env::temp_dir().join(format!("tmux-lib-config-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(
+            dir.join("tmux.conf"),
+            "set -g @main 1\n\
+             source-file conf.d/*.conf\n\
+             source-file -q missing.conf\n",
+        )
+        .unwrap();
+        fs::write(dir.join("conf.d/b.conf"), "set -g @b 1\n").unwrap();
+        fs::write(dir.join("conf.d/a.conf"), "\nset -wg @a 1\n").unwrap();
+        fs::write(dir.join("missing-include.conf"), "source-file nope.conf\n").unwrap();
+        fs::write(dir.join("cycle.conf"), "source-file cycle.conf\n").unwrap();
+        fs::write(
+            dir.join("invalid.conf"),
+            "source-file conf.d/a.conf invalid-b.conf",
+        )
+        .unwrap();
+        fs::write(dir.join("invalid-b.conf"), "\n%endif\n").unwrap();
+
+        let config = Config::load(dir.join("tmux.conf")).unwrap();
+        let commands: Vec<_> = config
+            .commands(|_| true)
+            .into_iter()
+            .map(|(location, command)| (location.to_string(), command.clone()))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                (
+                    format!("{}:1", dir.join("tmux.conf").display()),
+                    command("set -g @main 1")
+                ),
+                (
+                    format!("{}:2", dir.join("conf.d/a.conf").display()),
+                    TmuxCommand::SetOption {
+                        scope: Some(OptionScope::Window),
+                        global: true,
+                        unset: false,
+                        append: false,
+                        only_if_unset: false,
+                        quiet: false,
+                        expand: false,
+                        target: None,
+                        option: "@a".into(),
+                        value: Some("1".into()),
+                    }
+                ),
+                (
+                    format!("{}:1", dir.join("conf.d/b.conf").display()),
+                    command("set -g @b 1")
+                ),
+            ]
+        );
+        assert!(matches!(&config.items[2], Item::Include { files, .. } if files.is_empty()));
+
+        let error = Config::load(dir.join("missing-include.conf")).unwrap_err();
+        assert!(matches!(error, Error::Config { location, .. } if location.line == 1));
+        let error = Config::load(dir.join("cycle.conf")).unwrap_err();
+        assert!(matches!(error, Error::Config { .. }));
+        let error = Config::load(dir.join("invalid.conf")).unwrap_err();
+        assert!(
+            matches!(&error, Error::Config { location, .. } if location.path.as_deref() == Some(&dir.join("invalid-b.conf")) && location.line == 2),
+            "{error:?}"
+        );
+        assert!(matches!(
+            Config::load(dir.join("nope.conf")),
+            Err(Error::Io { .. })
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! Parser of tmux config files into items, following the grammar of `cmd-parse.y` in tmux.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use nom::error::{Error as NomError, ErrorKind};
+
+use super::{Branch, Config, Item, Location, MAX_INCLUDE_DEPTH};
+use crate::{
+    Result,
+    command::{
+        TmuxCommand, map_parse_error,
+        parse::{Lexer, Token, words},
+    },
+    error::{Error, map_add_intent},
+};
+
+/// Parse `input`, read from `path` if any. Includes are followed if `depth` is set, which is the
+/// nesting of the file.
+pub(crate) fn config(input: &str, path: Option<&Path>, depth: Option<usize>) -> Result<Config> {
+    let mut parser = Parser {
+        lexer: Lexer::new(input),
+        path,
+        depth,
+    };
+    let (items, _) = parser.items(false)?;
+
+    Ok(Config {
+        path: path.map(Path::to_path_buf),
+        items,
+    })
+}
+
+/// Split an assignment such as `NAME=value` into the name and value.
+fn assignment(word: &str) -> Option<(String, String)> {
+    let (name, value) = word.split_once('=')?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| (name.to_string(), value.to_string()))
+}
+
+/// A directive ending a branch of a conditional, with its line.
+type BranchEnd = Option<(String, usize)>;
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    path: Option<&'a Path>,
+    depth: Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn location(&self, line: usize) -> Location {
+        Location {
+            path: self.path.map(Path::to_path_buf),
+            line,
+        }
+    }
+
+    fn error(&self, line: usize, source: Error) -> Error {
+        Error::Config {
+            location: self.location(line),
+            source: Box::new(source),
+        }
+    }
+
+    /// Error at the current position of the lexer.
+    fn syntax_error(&mut self, kind: ErrorKind) -> Error {
+        let line = self.lexer.line();
+        let err = nom::Err::Failure(NomError::new(self.lexer.rest(), kind));
+        self.error(line, map_add_intent("Config", "tmux config", err))
+    }
+
+    fn lex_error(&mut self, err: nom::Err<NomError<&'a str>>) -> Error {
+        let line = self.lexer.line();
+        self.error(line, map_add_intent("Config", "tmux config", err))
+    }
+
+    /// Words of the rest of the statement, which must not be closed by `}`.
+    fn words(&mut self) -> Result<Vec<String>> {
+        match words(&mut self.lexer) {
+            Ok((_, Some(Token::Close))) => Err(self.syntax_error(ErrorKind::Char)),
+            Ok((words, _)) => Ok(words),
+            Err(err) => Err(self.lex_error(err)),
+        }
+    }
+
+    /// Parse items up to the end of input, or up to a `%elif`, `%else` or `%endif` directive
+    /// which is returned with its line if `in_condition`.
+    fn items(&mut self, in_condition: bool) -> Result<(Vec<Item>, BranchEnd)> {
+        let mut items = vec![];
+        loop {
+            let token = match self.lexer.peek() {
+                Ok(token) => token.cloned(),
+                Err(err) => return Err(self.lex_error(err)),
+            };
+            let Some((token, line)) = token else {
+                if in_condition {
+                    return Err(self.syntax_error(ErrorKind::Eof));
+                }
+                return Ok((items, None));
+            };
+
+            match token {
+                Token::Newline | Token::Separator => {
+                    let _ = self.lexer.next_token();
+                }
+                Token::Close => return Err(self.syntax_error(ErrorKind::Char)),
+                Token::Directive(directive) => {
+                    let _ = self.lexer.next_token();
+                    match directive.as_str() {
+                        "%if" => {
+                            let condition = self.condition(line)?;
+                            items.push(self.conditional(line, condition)?);
+                        }
+                        "%elif" | "%else" | "%endif" if in_condition => {
+                            return Ok((items, Some((directive, line))));
+                        }
+                        "%hidden" => match self.words()?.as_slice() {
+                            [word] if let Some((name, value)) = assignment(word) => {
+                                items.push(Item::Assignment {
+                                    location: self.location(line),
+                                    name,
+                                    value,
+                                    hidden: true,
+                                });
+                            }
+                            _ => return Err(self.syntax_error(ErrorKind::Verify)),
+                        },
+                        _ => return Err(self.error(line, self.directive_error(&directive))),
+                    }
+                }
+                Token::Word(_) | Token::Open => {
+                    let words = self.words()?;
+                    items.push(self.statement(line, words)?);
+                }
+            }
+        }
+    }
+
+    fn directive_error(&self, directive: &str) -> Error {
+        map_add_intent(
+            "Config",
+            "tmux config",
+            nom::Err::Failure(NomError::new(directive, ErrorKind::Tag)),
+        )
+    }
+
+    /// Condition of a `%if` or `%elif` directive, the only word of the rest of the line.
+    fn condition(&mut self, line: usize) -> Result<String> {
+        match <[String; 1]>::try_from(self.words()?) {
+            Ok([condition]) => Ok(condition),
+            Err(_) => Err(self.error(line, self.directive_error("%if"))),
+        }
+    }
+
+    /// Check that a `%else` or `%endif` directive is alone on its line.
+    fn end_of_directive(&mut self, line: usize, directive: &str) -> Result<()> {
+        if self.words()?.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(line, self.directive_error(directive)))
+        }
+    }
+
+    /// Parse a conditional after its `%if` directive, up to its `%endif` directive.
+    fn conditional(&mut self, line: usize, condition: String) -> Result<Item> {
+        let mut branches = vec![];
+        let start = self.location(line);
+        let (mut location, mut condition) = (start.clone(), condition);
+
+        loop {
+            let (items, end) = self.items(true)?;
+            branches.push(Branch {
+                location,
+                condition,
+                items,
+            });
+            let Some((directive, line)) = end else {
+                return Err(self.syntax_error(ErrorKind::Eof));
+            };
+
+            match directive.as_str() {
+                "%elif" => {
+                    condition = self.condition(line)?;
+                    location = self.location(line);
+                }
+                "%else" => {
+                    self.end_of_directive(line, &directive)?;
+                    let (items, end) = self.items(true)?;
+                    match end {
+                        Some((directive, line)) if directive == "%endif" => {
+                            self.end_of_directive(line, &directive)?;
+                        }
+                        Some((directive, line)) => {
+                            return Err(self.error(line, self.directive_error(&directive)));
+                        }
+                        None => return Err(self.syntax_error(ErrorKind::Eof)),
+                    }
+                    return Ok(Item::Condition {
+                        location: start,
+                        branches,
+                        otherwise: Some(items),
+                    });
+                }
+                _ => {
+                    self.end_of_directive(line, &directive)?;
+                    return Ok(Item::Condition {
+                        location: start,
+                        branches,
+                        otherwise: None,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Parse an assignment or a command, following includes.
+    fn statement(&mut self, line: usize, words: Vec<String>) -> Result<Item> {
+        if let [word] = words.as_slice()
+            && let Some((name, value)) = assignment(word)
+        {
+            return Ok(Item::Assignment {
+                location: self.location(line),
+                name,
+                value,
+                hidden: false,
+            });
+        }
+
+        let command = TmuxCommand::parse_args(&words)
+            .map_err(|err| self.error(line, map_parse_error(err)))?;
+
+        if let Some(depth) = self.depth
+            && let TmuxCommand::SourceFile { quiet, paths, .. } = &command
+        {
+            let files = self.include(line, paths, *quiet, depth)?;
+            return Ok(Item::Include {
+                location: self.location(line),
+                command,
+                files,
+            });
+        }
+
+        Ok(Item::Command {
+            location: self.location(line),
+            command,
+        })
+    }
+
+    /// Load the files matching `patterns`, relative to the directory of the current file.
+    fn include(
+        &self,
+        line: usize,
+        patterns: &[String],
+        quiet: bool,
+        depth: usize,
+    ) -> Result<Vec<Config>> {
+        if depth >= MAX_INCLUDE_DEPTH {
+            let err = io::Error::other("too many nested files");
+            return Err(self.error(line, err.into()));
+        }
+
+        let mut files = vec![];
+        for pattern in patterns {
+            let pattern = match self.path.and_then(Path::parent) {
+                Some(dir) if Path::new(pattern).is_relative() => dir.join(pattern),
+                _ => PathBuf::from(pattern),
+            };
+            let paths: Vec<PathBuf> = glob::glob(&pattern.to_string_lossy())
+                .map_err(|err| {
+                    let err = io::Error::new(io::ErrorKind::InvalidInput, err.to_string());
+                    self.error(line, err.into())
+                })?
+                .filter_map(std::result::Result::ok)
+                .collect();
+
+            if paths.is_empty() && !quiet {
+                let err = io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no such file: {}", pattern.display()),
+                );
+                return Err(self.error(line, err.into()));
+            }
+            for path in paths {
+                let file = super::load(&path, depth + 1).map_err(|err| match err {
+                    Error::Config { .. } => err,
+                    err => self.error(line, err),
+                })?;
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+}
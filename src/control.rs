@@ -0,0 +1,740 @@
+//! Tmux control mode: parse its notifications, and run a control mode client.
+//!
+//! A control mode client (`tmux -C`) receives the output of its panes and the changes of the
+//! server as notification lines starting with `%`, such as:
+//!
+//! ```text
+//! %output %3 \033[1mhello\033[0m\015\012
+//! %layout-change @1 b25d,80x24,0,0,3 b25d,80x24,0,0,3 *
+//! ```
+//!
+//! The output of panes is escaped by tmux: bytes below 32 and backslashes are written as `\ooo`
+//! octal sequences. The other bytes are written as is, so a line may not be valid utf-8 when a
+//! character is split across notifications: lines are parsed from bytes with
+//! [`Notification::from_bytes`].
+//!
+//! A [`Connection`] attaches a control mode client to a session, runs commands and receives the
+//! notifications, including the changes of the formats it subscribed to (tmux 3.2+):
+//!
+//! ```no_run
+//! # async fn watch(session_id: &tmux_lib::session_id::SessionId) -> tmux_lib::Result<()> {
+//! use tmux_lib::control::{Connection, Notification, SubscriptionTarget};
+//!
+//! let mut connection = Connection::attach(session_id).await?;
+//! connection
+//!     .subscribe("commands", SubscriptionTarget::AllPanes, "#{pane_current_command}")
+//!     .await?;
+//!
+//! while let Some(notification) = connection.next().await? {
+//!     if let Notification::SubscriptionChanged(change) = notification {
+//!         println!("{:?}: {}", change.pane_id, change.value);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map, map_res, opt, rest, value},
+    sequence::preceded,
+};
+
+use crate::{
+    Result,
+    command::quote,
+    error::{Error, map_add_intent},
+    layout::{self, WindowLayout},
+    pane_id::{PaneId, parse::pane_id},
+    runtime::{self, Command, Process},
+    session_id::{SessionId, parse::session_id},
+    target::Target,
+    version::{self, Capability},
+    window_id::{WindowId, parse::window_id},
+};
+
+/// A notification received by a control mode client.
+///
+/// ```
+/// use tmux_lib::control::Notification;
+///
+/// let notification = Notification::from_bytes(br"%output %3 hello\015\012").unwrap();
+/// match notification {
+///     Notification::Output { pane_id, data } => {
+///         assert_eq!(pane_id.as_str(), "%3");
+///         assert_eq!(data, b"hello\r\n");
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// Output of a pane (`%output`).
+    Output {
+        /// Pane which produced the output.
+        pane_id: PaneId,
+        /// Bytes written by the pane, unescaped.
+        data: Vec<u8>,
+    },
+    /// Output of a pane when flow control is enabled (`%extended-output`).
+    ExtendedOutput {
+        /// Pane which produced the output.
+        pane_id: PaneId,
+        /// Time the output was buffered by tmux before being sent, in milliseconds.
+        age: u64,
+        /// Bytes written by the pane, unescaped.
+        data: Vec<u8>,
+    },
+    /// The layout of a window changed (`%layout-change`), for instance after a split or a resize.
+    LayoutChange {
+        /// Window whose layout changed.
+        window_id: WindowId,
+        /// New layout of the window.
+        layout: WindowLayout,
+        /// New layout of the window, as visible when a pane is zoomed.
+        visible_layout: WindowLayout,
+        /// Flags of the window, such as `*` for the current window.
+        flags: String,
+    },
+    /// The value of a subscribed format changed (`%subscription-changed`).
+    SubscriptionChanged(SubscriptionChange),
+    /// Start of the output of a command (`%begin`).
+    Begin(Guard),
+    /// End of the output of a command which succeeded (`%end`).
+    End(Guard),
+    /// End of the output of a command which failed (`%error`).
+    Error(Guard),
+    /// The client is exiting (`%exit`).
+    Exit {
+        /// Reason given by tmux, if any.
+        reason: Option<String>,
+    },
+    /// Any other notification or line, as is.
+    Other(String),
+}
+
+/// Arguments of the `%begin`, `%end` and `%error` lines around the output of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guard {
+    /// Time the command ran, in seconds since the epoch.
+    pub time: u64,
+    /// Number of the command, the same in the `%begin` line and the line ending its output.
+    pub number: u64,
+    /// Flags, `1` if the command was sent by this client.
+    pub flags: u64,
+}
+
+/// New value of a subscribed format, see [`Connection::subscribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionChange {
+    /// Name of the subscription.
+    pub name: String,
+    /// Session of the client.
+    pub session_id: SessionId,
+    /// Window the value was expanded for, `None` for session subscriptions.
+    pub window_id: Option<WindowId>,
+    /// Index of the window, `None` for session subscriptions.
+    pub window_index: Option<u16>,
+    /// Pane the value was expanded for, `None` for session and window subscriptions.
+    pub pane_id: Option<PaneId>,
+    /// Expanded value of the format.
+    pub value: String,
+}
+
+/// What the format of a subscription is expanded for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionTarget<'a> {
+    /// The session of the client.
+    Session,
+    /// A window of the session of the client.
+    Window(&'a WindowId),
+    /// All the windows of the session of the client.
+    AllWindows,
+    /// A pane of the session of the client.
+    Pane(&'a PaneId),
+    /// All the panes of the session of the client.
+    AllPanes,
+}
+
+impl SubscriptionTarget<'_> {
+    /// Representation of the target in `refresh-client -B`.
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Session => "",
+            Self::Window(window_id) => window_id.as_str(),
+            Self::AllWindows => "@*",
+            Self::Pane(pane_id) => pane_id.as_str(),
+            Self::AllPanes => "%*",
+        }
+    }
+}
+
+impl Notification {
+    /// Parse a line received by a control mode client, without its line ending.
+    pub fn from_bytes(line: &[u8]) -> Result<Self> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        // The output is split from the header, as it may not be valid utf-8.
+        if line.starts_with(b"%output ") {
+            let (header, data) = split_at_nth_space(line, 2);
+            let desc = "Notification";
+            let intent = "%output %<pane> <data>";
+            let header = String::from_utf8_lossy(header);
+            let (_, pane_id) = all_consuming(preceded(tag("%output "), pane_id))
+                .parse(&header)
+                .map_err(|e| map_add_intent(desc, intent, e))?;
+            return Ok(Self::Output {
+                pane_id,
+                data: unescape(data),
+            });
+        }
+        if line.starts_with(b"%extended-output ") {
+            let desc = "Notification";
+            let intent = "%extended-output %<pane> <age> ... : <data>";
+            let (header, data) = match line.windows(3).position(|w| w == b" : ") {
+                Some(position) => (&line[..position], &line[position + 3..]),
+                None => (line, &b""[..]),
+            };
+            let header = String::from_utf8_lossy(header);
+            let (_, (pane_id, age)) = parse::extended_output_header(&header)
+                .map_err(|e| map_add_intent(desc, intent, e))?;
+            return Ok(Self::ExtendedOutput {
+                pane_id,
+                age,
+                data: unescape(data),
+            });
+        }
+
+        Self::from_str(&String::from_utf8_lossy(line))
+    }
+}
+
+impl FromStr for Notification {
+    type Err = Error;
+
+    /// Parse a line received by a control mode client. Prefer [`Notification::from_bytes`] for
+    /// lines read from tmux, as the output of panes may not be valid utf-8.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        if input.starts_with("%output ") || input.starts_with("%extended-output ") {
+            return Self::from_bytes(input.as_bytes());
+        }
+        if input.starts_with("%layout-change ") {
+            let desc = "Notification";
+            let intent = "%layout-change @<window> <layout> <visible-layout> <flags>";
+            let (_, (window_id, layout, visible_layout, flags)) =
+                parse::layout_change(input).map_err(|e| map_add_intent(desc, intent, e))?;
+            return Ok(Self::LayoutChange {
+                window_id,
+                layout: layout::parse_window_layout(layout)?,
+                visible_layout: layout::parse_window_layout(visible_layout)?,
+                flags: flags.to_string(),
+            });
+        }
+        if input.starts_with("%subscription-changed ") {
+            let desc = "Notification";
+            let intent =
+                "%subscription-changed <name> $<session> @<window> <index> %<pane> : <value>";
+            let (header, value) = input.split_once(" : ").unwrap_or((input, ""));
+            let (_, change) = parse::subscription_changed_header(header)
+                .map_err(|e| map_add_intent(desc, intent, e))?;
+            return Ok(Self::SubscriptionChanged(SubscriptionChange {
+                value: value.to_string(),
+                ..change
+            }));
+        }
+        for (prefix, variant) in [
+            ("%begin ", Self::Begin as fn(Guard) -> Self),
+            ("%end ", Self::End),
+            ("%error ", Self::Error),
+        ] {
+            if let Some(args) = input.strip_prefix(prefix) {
+                let desc = "Notification";
+                let intent = "%begin|%end|%error <time> <number> <flags>";
+                let (_, guard) = all_consuming(parse::guard)
+                    .parse(args)
+                    .map_err(|e| map_add_intent(desc, intent, e))?;
+                return Ok(variant(guard));
+            }
+        }
+        if input == "%exit" {
+            return Ok(Self::Exit { reason: None });
+        }
+        if let Some(reason) = input.strip_prefix("%exit ") {
+            return Ok(Self::Exit {
+                reason: Some(reason.to_string()),
+            });
+        }
+        Ok(Self::Other(input.to_string()))
+    }
+}
+
+/// A control mode client attached to a session, see the [module documentation](self).
+///
+/// The client is detached when the connection is dropped.
+pub struct Connection {
+    process: Process,
+    /// Notifications received while waiting for the output of a command.
+    pending: VecDeque<Notification>,
+}
+
+impl Connection {
+    /// Attach a control mode client (`tmux -C attach-session`) to the session of `target`.
+    ///
+    /// As with `attach-session`, a window or pane target is selected: it becomes the current
+    /// window of the session, and the active pane of its window, for all the attached clients.
+    /// Give a session to leave them unchanged.
+    pub async fn attach<'a>(target: impl Into<Target<'a>>) -> Result<Self> {
+        let target = target.into();
+        let mut command = Command::new("tmux");
+        command.args(["-C", "attach-session", "-t", target.as_str()]);
+
+        let mut connection = Self {
+            process: runtime::spawn(command)?,
+            pending: VecDeque::new(),
+        };
+        // tmux replies to the attach-session command like to any other command.
+        connection.read_output("attach-session").await?;
+        Ok(connection)
+    }
+
+    /// Run the command `args` (starting with the command name) through the client, and return
+    /// the lines of its output.
+    ///
+    /// Notifications received meanwhile are kept, and returned by [`Connection::next`].
+    pub async fn command<I, S>(&mut self, args: I) -> Result<Vec<String>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let line = args
+            .into_iter()
+            .map(|arg| quote(arg.as_ref()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.process
+            .write_all(format!("{line}\n").as_bytes())
+            .await?;
+        self.read_output(&line).await
+    }
+
+    /// Subscribe to the changes of `format`, expanded for `target` (`refresh-client -B`).
+    ///
+    /// tmux checks the subscriptions every second, and sends a
+    /// [`Notification::SubscriptionChanged`] with the new value when it changed, and right after
+    /// subscribing. Subscribing with the `name` of an existing subscription replaces it. The
+    /// `name` must not contain `:`.
+    ///
+    /// This requires tmux 3.2+.
+    pub async fn subscribe(
+        &mut self,
+        name: &str,
+        target: SubscriptionTarget<'_>,
+        format: &str,
+    ) -> Result<()> {
+        version::require(Capability::FormatSubscriptions).await?;
+
+        let subscription = format!("{name}:{}:{format}", target.as_str());
+        self.command(["refresh-client", "-B", &subscription])
+            .await
+            .map(|_| ())
+    }
+
+    /// Remove the subscription `name`.
+    pub async fn unsubscribe(&mut self, name: &str) -> Result<()> {
+        self.command(["refresh-client", "-B", name])
+            .await
+            .map(|_| ())
+    }
+
+    /// Wait for the next notification. Returns `None` once the client exited, after the
+    /// [`Notification::Exit`].
+    pub async fn next(&mut self) -> Result<Option<Notification>> {
+        if let Some(notification) = self.pending.pop_front() {
+            return Ok(Some(notification));
+        }
+        loop {
+            let Some(line) = self.read_line().await? else {
+                return Ok(None);
+            };
+            match Notification::from_bytes(&line)? {
+                // Output of a command sent by another client, or of a command whose output was
+                // abandoned.
+                Notification::Begin(guard) => {
+                    self.skip_output(guard).await?;
+                }
+                notification => return Ok(Some(notification)),
+            }
+        }
+    }
+
+    /// Read the output of the command `command`, which was just sent.
+    async fn read_output(&mut self, command: &str) -> Result<Vec<String>> {
+        // Notifications are never sent within the output of a command.
+        let guard = loop {
+            let line = self.read_line().await?.ok_or_else(|| closed(command))?;
+            match Notification::from_bytes(&line)? {
+                Notification::Begin(guard) => break guard,
+                notification => self.pending.push_back(notification),
+            }
+        };
+
+        let mut output = vec![];
+        loop {
+            let line = self.read_line().await?.ok_or_else(|| closed(command))?;
+            match end_of_output(&line, guard) {
+                Some(true) => return Ok(output),
+                Some(false) => {
+                    return Err(Error::ControlCommand {
+                        command: command.to_string(),
+                        message: output.join("\n"),
+                    });
+                }
+                None => output.push(String::from_utf8_lossy(&line).into_owned()),
+            }
+        }
+    }
+
+    /// Skip the output of the command which started with `guard`.
+    async fn skip_output(&mut self, guard: Guard) -> Result<()> {
+        while let Some(line) = self.read_line().await? {
+            if end_of_output(&line, guard).is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a line, without its line ending. Returns `None` at the end of the output.
+    async fn read_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut line = vec![];
+        if self.process.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+/// If `line` ends the output of the command started with `guard`, return `true` if the command
+/// succeeded.
+fn end_of_output(line: &[u8], guard: Guard) -> Option<bool> {
+    if !line.starts_with(b"%end ") && !line.starts_with(b"%error ") {
+        return None;
+    }
+    match Notification::from_bytes(line) {
+        Ok(Notification::End(end)) if end.number == guard.number => Some(true),
+        Ok(Notification::Error(end)) if end.number == guard.number => Some(false),
+        _ => None,
+    }
+}
+
+/// Error returned when the client exits while waiting for the output of `command`.
+fn closed(command: &str) -> Error {
+    Error::Io {
+        source: io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("control mode client exited while running `{command}`"),
+        ),
+    }
+}
+
+/// Split `line` after its `n`-th space, returning the part before the space and the rest.
+fn split_at_nth_space(line: &[u8], n: usize) -> (&[u8], &[u8]) {
+    match line
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b' ')
+        .nth(n - 1)
+    {
+        Some((position, _)) => (&line[..position], &line[position + 1..]),
+        None => (line, &[]),
+    }
+}
+
+/// Decode the `\ooo` octal escapes of the output of a pane.
+///
+/// ```
+/// use tmux_lib::control::unescape;
+///
+/// assert_eq!(unescape(br"a\134b\033[0m\015\012"), b"a\\b\x1b[0m\r\n");
+/// ```
+pub fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut index = 0;
+    while index < data.len() {
+        let octal = data.get(index + 1..index + 4).filter(|digits| {
+            data[index] == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d))
+        });
+        match octal {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u16, |value, digit| value * 8 + u16::from(digit - b'0'));
+                bytes.push(u8::try_from(value).unwrap_or(u8::MAX));
+                index += 4;
+            }
+            None => {
+                bytes.push(data[index]);
+                index += 1;
+            }
+        }
+    }
+    bytes
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    /// Parse `%extended-output %<pane> <age>`, ignoring the reserved arguments which follow.
+    pub(crate) fn extended_output_header(input: &str) -> IResult<&str, (PaneId, u64)> {
+        let (input, pane_id) = preceded(tag("%extended-output "), pane_id).parse(input)?;
+        let (input, age) = preceded(char(' '), map_res(digit1, str::parse)).parse(input)?;
+        let (input, _) = rest(input)?;
+        Ok((input, (pane_id, age)))
+    }
+
+    /// Parse `<time> <number> <flags>`, the arguments of `%begin`, `%end` and `%error`.
+    pub(crate) fn guard(input: &str) -> IResult<&str, Guard> {
+        let number = || map_res(digit1, str::parse::<u64>);
+        let (input, time) = number().parse(input)?;
+        let (input, number_) = preceded(char(' '), number()).parse(input)?;
+        let (input, flags) = preceded(char(' '), number()).parse(input)?;
+        Ok((
+            input,
+            Guard {
+                time,
+                number: number_,
+                flags,
+            },
+        ))
+    }
+
+    /// Parse `%subscription-changed <name> $<session> @<window> <index> %<pane>`, where the
+    /// window, index and pane are `-` when they do not apply. The value is left empty.
+    pub(crate) fn subscription_changed_header(input: &str) -> IResult<&str, SubscriptionChange> {
+        let absent = || tag("-");
+        let (input, name) =
+            preceded(tag("%subscription-changed "), take_till1(|c| c == ' ')).parse(input)?;
+        let (input, session_id) = preceded(char(' '), session_id).parse(input)?;
+        let (input, window_id) = preceded(
+            char(' '),
+            alt((map(window_id, Some), value(None, absent()))),
+        )
+        .parse(input)?;
+        let (input, window_index) = preceded(
+            char(' '),
+            alt((
+                map(map_res(digit1, str::parse), Some),
+                value(None, absent()),
+            )),
+        )
+        .parse(input)?;
+        let (input, pane_id) =
+            preceded(char(' '), alt((map(pane_id, Some), value(None, absent())))).parse(input)?;
+        // Arguments added by later versions of tmux are ignored.
+        let (input, _) = rest(input)?;
+        Ok((
+            input,
+            SubscriptionChange {
+                name: name.to_string(),
+                session_id,
+                window_id,
+                window_index,
+                pane_id,
+                value: String::new(),
+            },
+        ))
+    }
+
+    /// Parse `%layout-change @<window> <layout> <visible-layout> <flags>`.
+    pub(crate) fn layout_change(input: &str) -> IResult<&str, (WindowId, &str, &str, &str)> {
+        let word = || preceded(char(' '), take_till1(|c| c == ' '));
+        let (input, window_id) = preceded(tag("%layout-change "), window_id).parse(input)?;
+        let (input, layout) = word().parse(input)?;
+        let (input, visible_layout) = word().parse(input)?;
+        // The flags are empty for windows other than the current and last ones.
+        let (input, flags) = opt(preceded(char(' '), rest)).parse(input)?;
+        Ok((
+            input,
+            (window_id, layout, visible_layout, flags.unwrap_or("")),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let notification = Notification::from_bytes(b"%output %12 caf\xc3\r\n").unwrap();
+        assert_eq!(
+            notification,
+            Notification::Output {
+                pane_id: PaneId::from_str("%12").unwrap(),
+                data: b"caf\xc3".to_vec(),
+            }
+        );
+
+        let notification = Notification::from_bytes(b"%output %1 ").unwrap();
+        assert_eq!(
+            notification,
+            Notification::Output {
+                pane_id: PaneId::from_str("%1").unwrap(),
+                data: vec![],
+            }
+        );
+
+        assert!(Notification::from_bytes(b"%output 12 data").is_err());
+    }
+
+    #[test]
+    fn test_parse_extended_output() {
+        let notification =
+            Notification::from_bytes(br"%extended-output %4 1500 : one \: two\012").unwrap();
+        assert_eq!(
+            notification,
+            Notification::ExtendedOutput {
+                pane_id: PaneId::from_str("%4").unwrap(),
+                age: 1500,
+                data: br"one \: two".iter().copied().chain([b'\n']).collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_layout_change() {
+        let line = "%layout-change @2 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} *-";
+        let Notification::LayoutChange {
+            window_id,
+            layout,
+            visible_layout,
+            flags,
+        } = Notification::from_str(line).unwrap()
+        else {
+            panic!("expected a layout change");
+        };
+        assert_eq!(window_id, WindowId::from_str("@2").unwrap());
+        assert_eq!(layout.pane_ids(), vec![0, 1]);
+        assert_eq!(layout, visible_layout);
+        assert_eq!(flags, "*-");
+
+        let line = "%layout-change @3 b25d,80x24,0,0,0 b25d,80x24,0,0,0";
+        assert!(matches!(
+            Notification::from_str(line).unwrap(),
+            Notification::LayoutChange { flags, .. } if flags.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_parse_subscription_changed() {
+        let line = "%subscription-changed cmd $1 @2 0 %3 : vim README.md";
+        assert_eq!(
+            Notification::from_str(line).unwrap(),
+            Notification::SubscriptionChanged(SubscriptionChange {
+                name: "cmd".into(),
+                session_id: SessionId::from_str("$1").unwrap(),
+                window_id: Some(WindowId::from_str("@2").unwrap()),
+                window_index: Some(0),
+                pane_id: Some(PaneId::from_str("%3").unwrap()),
+                value: "vim README.md".into(),
+            })
+        );
+
+        let line = "%subscription-changed name $0 - - - : ";
+        let Notification::SubscriptionChanged(change) = Notification::from_str(line).unwrap()
+        else {
+            panic!("expected a subscription change");
+        };
+        assert_eq!(change.window_id, None);
+        assert_eq!(change.window_index, None);
+        assert_eq!(change.pane_id, None);
+        assert_eq!(change.value, "");
+
+        assert!(Notification::from_str("%subscription-changed name - - - - : x").is_err());
+    }
+
+    #[test]
+    fn test_parse_guards() {
+        let guard = Guard {
+            time: 1792341079,
+            number: 21004,
+            flags: 1,
+        };
+        assert_eq!(
+            Notification::from_str("%begin 1792341079 21004 1").unwrap(),
+            Notification::Begin(guard)
+        );
+        assert_eq!(
+            Notification::from_str("%end 1792341079 21004 1").unwrap(),
+            Notification::End(guard)
+        );
+        assert_eq!(
+            Notification::from_str("%error 1792341079 21004 1").unwrap(),
+            Notification::Error(guard)
+        );
+        assert!(Notification::from_str("%begin 1792341079").is_err());
+
+        assert_eq!(end_of_output(b"%end 1792341079 21004 1", guard), Some(true));
+        assert_eq!(
+            end_of_output(b"%error 1792341079 21004 1", guard),
+            Some(false)
+        );
+        assert_eq!(end_of_output(b"%end 1792341079 21003 1", guard), None);
+        assert_eq!(end_of_output(b"%end of output", guard), None);
+    }
+
+    #[test]
+    fn test_subscription_target() {
+        let window_id = WindowId::from_str("@4").unwrap();
+        assert_eq!(SubscriptionTarget::Session.as_str(), "");
+        assert_eq!(SubscriptionTarget::Window(&window_id).as_str(), "@4");
+        assert_eq!(SubscriptionTarget::AllWindows.as_str(), "@*");
+        assert_eq!(SubscriptionTarget::AllPanes.as_str(), "%*");
+    }
+
+    #[test]
+    fn test_parse_other_notifications() {
+        assert_eq!(
+            Notification::from_str("%exit").unwrap(),
+            Notification::Exit { reason: None }
+        );
+        assert_eq!(
+            Notification::from_str("%exit server exited").unwrap(),
+            Notification::Exit {
+                reason: Some("server exited".into())
+            }
+        );
+        assert_eq!(
+            Notification::from_str("%window-add @7").unwrap(),
+            Notification::Other("%window-add @7".into())
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(br"\134\134"), b"\\\\");
+        // Incomplete or invalid escapes are kept as is.
+        assert_eq!(unescape(br"\01"), br"\01");
+        assert_eq!(unescape(br"\08a"), br"\08a");
+        assert_eq!(unescape(b"\xe2\x82\xac"), "€".as_bytes());
+    }
+}
@@ -0,0 +1,300 @@
+//! Detect and describe the tmux environment enclosing the current process.
+//!
+//! Tmux tells the processes it starts where they run with two environment variables:
+//!
+//! - `$TMUX` holds the socket path and pid of the server, followed by the id of the session, e.g.
+//!   `/tmp/tmux-1000/default,2533,0`,
+//! - `$TMUX_PANE` holds the id of the pane, e.g. `%12`, and is only set for processes started in
+//!   a pane.
+//!
+//! [`detect`] parses them into an [`Environment`], whose [`Environment::server`] addresses the
+//! enclosing server even when other servers run, for instance with nested tmux.
+//! [`Environment::current`] then resolves the current session, window and pane.
+//!
+//! ```no_run
+//! # async fn describe() -> tmux_lib::Result<()> {
+//! use tmux_lib::environment;
+//!
+//! match environment::detect()? {
+//!     Some(environment) => {
+//!         let current = environment.current().await?;
+//!         println!("running in pane {} of {}", current.pane.id, current.session.name);
+//!     }
+//!     None => println!("not running inside tmux"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map, map_res},
+};
+
+use crate::{
+    Result,
+    error::map_add_intent,
+    pane::{self, Pane},
+    pane_id::PaneId,
+    server::Server,
+    session::{self, Session},
+    session_id::SessionId,
+    window::{self, Window},
+};
+
+/// Description of the tmux environment of a process, from `$TMUX` and `$TMUX_PANE`.
+///
+/// ```
+/// use tmux_lib::environment::Environment;
+///
+/// let environment = Environment::parse("/tmp/tmux-1000/default,2533,4", Some("%12")).unwrap();
+///
+/// assert_eq!(environment.socket_path.to_str(), Some("/tmp/tmux-1000/default"));
+/// assert_eq!(environment.server_pid, 2533);
+/// assert_eq!(environment.session_id.unwrap().as_str(), "$4");
+/// assert_eq!(environment.pane_id.unwrap().as_str(), "%12");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment {
+    /// Path of the socket of the server.
+    pub socket_path: PathBuf,
+    /// PID of the server.
+    pub server_pid: u32,
+    /// Session the process was started for, if any.
+    pub session_id: Option<SessionId>,
+    /// Pane the process runs in, if any. Processes started by `run-shell` or `if-shell` do not
+    /// run in a pane.
+    pub pane_id: Option<PaneId>,
+}
+
+/// Session, window and pane enclosing the current process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Current {
+    /// Current session.
+    pub session: Session,
+    /// Current window of the session.
+    pub window: Window,
+    /// Current pane of the window.
+    pub pane: Pane,
+}
+
+/// Return the tmux environment of the current process, or `None` if it does not run inside tmux.
+///
+/// # Errors
+///
+/// Returns an `Error::ParseError` if `$TMUX` or `$TMUX_PANE` are invalid.
+pub fn detect() -> Result<Option<Environment>> {
+    let Ok(tmux) = env::var("TMUX") else {
+        return Ok(None);
+    };
+    if tmux.is_empty() {
+        return Ok(None);
+    }
+    let tmux_pane = env::var("TMUX_PANE").ok();
+
+    Environment::parse(&tmux, tmux_pane.as_deref()).map(Some)
+}
+
+impl Environment {
+    /// Parse the values of `$TMUX` and `$TMUX_PANE`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::ParseError` if `tmux` or `tmux_pane` are invalid.
+    pub fn parse(tmux: &str, tmux_pane: Option<&str>) -> Result<Self> {
+        let desc = "Environment";
+        let intent = "<socket_path>,<server_pid>,<session_id>";
+
+        // The socket path may contain commas, the last two fields are the pid and the session.
+        let (socket_path, fields) = match tmux.rsplitn(3, ',').collect::<Vec<_>>()[..] {
+            [_, _, socket_path] => (socket_path, &tmux[socket_path.len() + 1..]),
+            _ => ("", tmux),
+        };
+        let (_, (server_pid, session_id)) = all_consuming(parse::pid_and_session)
+            .parse(fields)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+        if socket_path.is_empty() {
+            return Err(map_add_intent(
+                desc,
+                intent,
+                nom::Err::Error(nom::error::Error::new(
+                    tmux,
+                    nom::error::ErrorKind::NonEmpty,
+                )),
+            ));
+        }
+
+        // An empty `$TMUX_PANE` is left by tmux in processes which are not in a pane.
+        let pane_id = match tmux_pane {
+            Some(tmux_pane) if !tmux_pane.is_empty() => Some(PaneId::from_str(tmux_pane)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            socket_path: socket_path.into(),
+            server_pid,
+            session_id,
+            pane_id,
+        })
+    }
+
+    /// Return a handle on the enclosing server.
+    #[must_use]
+    pub fn server(&self) -> Server {
+        Server::new(&self.socket_path)
+    }
+
+    /// Resolve the current session, window and pane.
+    ///
+    /// In a pane, these are the pane and its window and session. Otherwise, as in `run-shell`
+    /// jobs, these are the session given by `$TMUX`, its current window and the active pane of
+    /// that window; this does not depend on a client being attached.
+    ///
+    /// A `$TMUX_PANE` inherited from another server, as with nested tmux, may not exist on the
+    /// enclosing server, or designate an unrelated pane there: a pane outside the session of
+    /// `$TMUX` is ignored.
+    pub async fn current(&self) -> Result<Current> {
+        let server = self.server();
+        let session_target = self.session_id.as_ref().map(SessionId::as_str);
+
+        let Some(pane_id) = self.pane_id.as_ref() else {
+            return resolve(&server, session_target).await;
+        };
+        let current = match resolve(&server, Some(pane_id.as_str())).await {
+            Ok(current) => current,
+            Err(_) if session_target.is_some() => return resolve(&server, session_target).await,
+            Err(err) => return Err(err),
+        };
+        let Some(session_id) = self.session_id.as_ref() else {
+            return Ok(current);
+        };
+        if current.session.id == *session_id {
+            return Ok(current);
+        }
+
+        // The pane resolved in another session: it is either in a window linked to several
+        // sessions, or not the pane of this process.
+        let pane_ids = server
+            .run([
+                "list-panes",
+                "-s",
+                "-t",
+                session_id.as_str(),
+                "-F",
+                "#{pane_id}",
+            ])
+            .await?;
+        if pane_ids.lines().any(|id| id == pane_id.as_str()) {
+            let target = format!(
+                "{}:{}.{}",
+                session_id.as_str(),
+                current.window.id.as_str(),
+                pane_id.as_str()
+            );
+            resolve(&server, Some(&target)).await
+        } else {
+            resolve(&server, session_target).await
+        }
+    }
+}
+
+/// Resolve the session, window and pane designated by `target`, or those tmux considers current
+/// without a target.
+async fn resolve(server: &Server, target: Option<&str>) -> Result<Current> {
+    let describe = async |format: &str| -> Result<String> {
+        let mut args = vec!["display-message", "-p"];
+        if let Some(target) = target {
+            args.extend(["-t", target]);
+        }
+        args.push(format);
+        let output = server.run(args).await?;
+        Ok(output.trim_end_matches('\n').to_string())
+    };
+
+    let session = Session::from_str(&describe(session::FORMAT).await?)?;
+    let window = Window::from_str(&describe(window::FORMAT).await?)?;
+    let pane = Pane::from_str(&describe(pane::FORMAT).await?)?;
+
+    Ok(Current {
+        session,
+        window,
+        pane,
+    })
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    /// Parse the pid of the server and the session id, `-1` meaning no session.
+    pub(crate) fn pid_and_session(input: &str) -> IResult<&str, (u32, Option<SessionId>)> {
+        let (input, (pid, _, session_id)) = (
+            map_res(digit1, str::parse),
+            char(','),
+            alt((
+                map(tag("-1"), |_| None),
+                map(digit1, |digits| {
+                    SessionId::from_str(&format!("${digits}")).ok()
+                }),
+            )),
+        )
+            .parse(input)?;
+
+        Ok((input, (pid, session_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_environment() {
+        let environment = Environment::parse("/tmp/tmux-1000/default,2533,0", Some("%3")).unwrap();
+        assert_eq!(
+            environment,
+            Environment {
+                socket_path: PathBuf::from("/tmp/tmux-1000/default"),
+                server_pid: 2533,
+                session_id: Some(SessionId::from_str("$0").unwrap()),
+                pane_id: Some(PaneId::from_str("%3").unwrap()),
+            }
+        );
+        assert_eq!(
+            environment.server().socket_path(),
+            PathBuf::from("/tmp/tmux-1000/default")
+        );
+    }
+
+    #[test]
+    fn test_parse_environment_without_pane_or_session() {
+        let environment = Environment::parse("/tmp/tmux-1000/default,2533,-1", None).unwrap();
+        assert_eq!(environment.session_id, None);
+        assert_eq!(environment.pane_id, None);
+
+        let environment = Environment::parse("/tmp/tmux-1000/default,2533,1", Some("")).unwrap();
+        assert_eq!(environment.pane_id, None);
+    }
+
+    #[test]
+    fn test_parse_environment_socket_with_commas() {
+        let environment = Environment::parse("/tmp/a,b/sock,12,7", None).unwrap();
+        assert_eq!(environment.socket_path, PathBuf::from("/tmp/a,b/sock"));
+        assert_eq!(environment.server_pid, 12);
+        assert_eq!(environment.session_id.unwrap().as_str(), "$7");
+    }
+
+    #[test]
+    fn test_parse_environment_fails() {
+        assert!(Environment::parse("/tmp/tmux-1000/default", None).is_err());
+        assert!(Environment::parse("/tmp/tmux-1000/default,pid,0", None).is_err());
+        assert!(Environment::parse(",2533,0", None).is_err());
+        assert!(Environment::parse("/tmp/tmux-1000/default,2533,0", Some("12")).is_err());
+    }
+}
@@ -18,6 +18,10 @@ pub enum Error {
     #[error("unexpected tmux config: `{0}`")]
     TmuxConfig(&'static str),
 
+    /// The options given to a builder cannot be used together.
+    #[error("invalid options: {0}")]
+    InvalidOptions(&'static str),
+
     /// Some parsing error.
     #[error("failed parsing: `{intent}`")]
     ParseError {
@@ -26,6 +30,15 @@ pub enum Error {
         err: nom::Err<nom::error::Error<String>>,
     },
 
+    /// Failed parsing or loading a tmux config file.
+    #[error("invalid tmux config at `{location}`: {source}")]
+    Config {
+        /// Location of the error.
+        location: crate::config::Location,
+        /// Source error.
+        source: Box<Error>,
+    },
+
     /// Failed parsing the output of a process invocation as utf-8.
     #[error("failed parsing utf-8 string: `{source}`")]
     Utf8 {
@@ -34,6 +47,24 @@ pub enum Error {
         source: std::string::FromUtf8Error,
     },
 
+    /// A command sent by a control mode client failed.
+    #[error("control mode command `{command}` failed: `{message}`")]
+    ControlCommand {
+        /// Command line sent to tmux.
+        command: String,
+        /// Error printed by tmux.
+        message: String,
+    },
+
+    /// The version of tmux does not support a feature required by an op.
+    #[error("tmux {version} does not support {capability}, which requires tmux {}", capability.min_version())]
+    Unsupported {
+        /// Feature required by the op.
+        capability: crate::version::Capability,
+        /// Version of tmux.
+        version: crate::version::Version,
+    },
+
     /// Some IO error.
     #[error("failed with io: `{source}`")]
     Io {
@@ -54,6 +85,8 @@ pub fn map_add_intent(
     intent: &'static str,
     nom_err: nom::Err<nom::error::Error<&str>>,
 ) -> Error {
+    #[cfg(feature = "tracing")]
+    crate::trace::parse_failure(desc, intent, &nom_err);
     Error::ParseError {
         desc,
         intent,
@@ -83,6 +116,20 @@ pub fn check_empty_process_output(
     Ok(())
 }
 
+/// Ensure that a `kill-*` command succeeded, see [`check_empty_process_output`].
+///
+/// Killing the last session of the server makes it exit: tmux may then fail with `server exited
+/// unexpectedly`, although the kill succeeded.
+pub(crate) fn check_kill_output(
+    output: &Output,
+    intent: &'static str,
+) -> std::result::Result<(), Error> {
+    if output.stdout.is_empty() && output.stderr.starts_with(b"server exited unexpectedly") {
+        return Ok(());
+    }
+    check_empty_process_output(output, intent)
+}
+
 /// Ensure that the tmux command succeeded (exit status 0) before parsing its output.
 ///
 /// This prevents confusing parse errors when tmux fails and returns empty or
@@ -107,6 +154,23 @@ pub fn check_process_success(
     Ok(())
 }
 
+/// Return `true` if tmux failed with `stderr` because no server is running on the socket.
+pub(crate) fn is_no_server(stderr: &str) -> bool {
+    stderr.starts_with("no server running") || stderr.starts_with("error connecting to")
+}
+
+impl Error {
+    /// Return `true` if this is the failure of a tmux invocation because no server is running.
+    pub(crate) fn is_no_server(&self) -> bool {
+        matches!(self, Error::UnexpectedTmuxOutput { stderr, .. } if is_no_server(stderr))
+    }
+
+    /// Return `true` if this is the failure of a tmux invocation because its pane does not exist.
+    pub(crate) fn is_missing_pane(&self) -> bool {
+        matches!(self, Error::UnexpectedTmuxOutput { stderr, .. } if stderr.starts_with("can't find pane"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,4 +310,38 @@ mod tests {
         assert!(msg.contains("unexpected tmux config"));
         assert!(msg.contains("missing default-shell"));
     }
+
+    #[test]
+    fn detect_missing_server() {
+        let missing = |stderr: &[u8]| {
+            check_process_success(&make_output(1, b"", stderr), "test-intent")
+                .unwrap_err()
+                .is_no_server()
+        };
+        assert!(missing(b"no server running on /tmp/tmux-1000/default\n"));
+        assert!(missing(
+            b"error connecting to /tmp/tmux-1000/work (No such file or directory)\n"
+        ));
+        assert!(!missing(b"can't find pane: %3\n"));
+        assert!(!missing(b"server exited unexpectedly\n"));
+    }
+
+    #[test]
+    fn detect_missing_pane() {
+        let error = |stderr: &[u8]| {
+            check_empty_process_output(&make_output(1, b"", stderr), "test-intent").unwrap_err()
+        };
+        assert!(error(b"can't find pane: %3\n").is_missing_pane());
+        assert!(!error(b"can't find window: @3\n").is_missing_pane());
+    }
+
+    #[test]
+    fn kill_of_last_session_succeeds() {
+        let exited = make_output(1, b"", b"server exited unexpectedly\n");
+        assert!(check_kill_output(&exited, "kill-session").is_ok());
+        assert!(check_empty_process_output(&exited, "kill-session").is_err());
+
+        let missing = make_output(1, b"", b"can't find session: $9\n");
+        assert!(check_kill_output(&missing, "kill-session").is_err());
+    }
 }
@@ -0,0 +1,265 @@
+//! In-process fake tmux server, for testing code built on this crate without running tmux.
+//!
+//! Once a [`FakeServer`] is installed on a thread, the ops awaited or run on that thread (async
+//! and `blocking`) are answered by the fake server instead of spawning tmux.
+//!
+//! The server is looked up on the thread polling the op: the ops of tasks spawned on other
+//! threads, as with the multi-threaded tokio runtime or `spawn_blocking`, spawn the real tmux.
+//! [`FakeServer::install_global`] answers the invocations of all the threads instead, for the
+//! test binaries which do not run tmux at all.
+//! The fake server keeps sessions, windows, panes, layouts and options in memory, and understands
+//! the commands, flags and formats used by the ops. Unsupported commands or flags fail like tmux
+//! does, with an error on stderr.
+//!
+//! This module requires the `fake` cargo feature, typically enabled only in the
+//! `[dev-dependencies]` of the crate under test.
+//!
+//! ```
+//! # async fn example() -> tmux_lib::Result<()> {
+//! use tmux_lib::{fake::FakeServer, session};
+//!
+//! let server = FakeServer::new();
+//! let _guard = server.install();
+//!
+//! session::NewSession::new().name("work").run().await?;
+//! let sessions = session::available_sessions().await?;
+//! assert_eq!(sessions[0].name, "work");
+//! assert_eq!(server.commands()[0][0], "new-session");
+//! # Ok(())
+//! # }
+//! ```
+
+mod layout;
+mod state;
+
+use std::{
+    cell::RefCell,
+    ffi::{OsStr, OsString},
+    marker::PhantomData,
+    os::unix::process::ExitStatusExt,
+    process::{ExitStatus, Output},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use state::State;
+
+thread_local! {
+    /// Fake server answering the tmux invocations of the current thread.
+    static INSTALLED: RefCell<Option<FakeServer>> = const { RefCell::new(None) };
+}
+
+/// Fake server answering the tmux invocations of the threads without their own.
+static GLOBAL: Mutex<Option<FakeServer>> = Mutex::new(None);
+
+/// An in-process fake tmux server.
+///
+/// Clones share the same state, so that a test can keep a handle on the server while it is
+/// installed.
+#[derive(Debug, Clone, Default)]
+pub struct FakeServer {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    /// Create a fake server without sessions, like a tmux server which is not running.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the tmux invocations of the current thread with this server, until the returned
+    /// guard is dropped.
+    ///
+    /// Installing a server while another one is installed replaces it, the previous one being
+    /// restored when the guard is dropped.
+    pub fn install(&self) -> FakeServerGuard {
+        let previous = INSTALLED.with(|installed| installed.replace(Some(self.clone())));
+        FakeServerGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Answer the tmux invocations of all the threads with this server, until the returned guard
+    /// is dropped, except on the threads where another server is installed with
+    /// [`FakeServer::install`].
+    ///
+    /// This covers the tasks of multi-threaded runtimes, but also the other tests of the same
+    /// binary, which no longer reach tmux.
+    pub fn install_global(&self) -> FakeServerGlobalGuard {
+        let previous = global().replace(self.clone());
+        FakeServerGlobalGuard { previous }
+    }
+
+    /// Run the invocation `tmux <args>` on this server, returning its output.
+    ///
+    /// The exit status is a failure if a command failed, its error being on stderr.
+    pub fn run<I, S>(&self, args: I) -> Output
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        self.run_with_stdin(&args, None)
+    }
+
+    /// Commands received by this server, each as its name followed by its arguments.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.state().commands.clone()
+    }
+
+    /// Messages shown with `display-message` (without `-p`) on this server, expanded.
+    pub fn messages(&self) -> Vec<String> {
+        self.state().messages.clone()
+    }
+
+    fn run_with_stdin(&self, args: &[OsString], stdin: Option<&[u8]>) -> Output {
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let (stdout, error) = self.state().run(&args, stdin);
+
+        let (code, stderr) = match error {
+            Some(error) => (1, format!("{error}\n")),
+            None => (0, String::new()),
+        };
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.into_bytes(),
+            stderr: stderr.into_bytes(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        // A panic in a test must not hide the state from the other tests.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keeps a [`FakeServer`] installed on the current thread, see [`FakeServer::install`].
+#[must_use = "the fake server is uninstalled when the guard is dropped"]
+#[derive(Debug)]
+pub struct FakeServerGuard {
+    previous: Option<FakeServer>,
+    // The server is installed on a thread, so the guard must be dropped on that thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for FakeServerGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        INSTALLED.with(|installed| *installed.borrow_mut() = previous);
+    }
+}
+
+/// Keeps a [`FakeServer`] installed on all the threads, see [`FakeServer::install_global`].
+#[must_use = "the fake server is uninstalled when the guard is dropped"]
+#[derive(Debug)]
+pub struct FakeServerGlobalGuard {
+    previous: Option<FakeServer>,
+}
+
+impl Drop for FakeServerGlobalGuard {
+    fn drop(&mut self) {
+        *global() = self.previous.take();
+    }
+}
+
+fn global() -> std::sync::MutexGuard<'static, Option<FakeServer>> {
+    GLOBAL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Fake server installed on the current thread, or else on all the threads.
+fn installed() -> Option<FakeServer> {
+    // Clone the server out of the thread-local, so that it is not borrowed while running.
+    INSTALLED
+        .with(|installed| installed.borrow().clone())
+        .or_else(|| global().clone())
+}
+
+/// Answer the invocation `tmux <args>` with the fake server installed for the current thread, if
+/// any.
+pub(crate) fn intercept(args: &[OsString], stdin: Option<&[u8]>) -> Option<Output> {
+    let server = installed()?;
+    Some(server.run_with_stdin(args, stdin))
+}
+
+/// Return `true` if a fake server is installed for the current thread.
+pub(crate) fn is_installed() -> bool {
+    installed().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_reports_errors() {
+        let server = FakeServer::new();
+        let output = server.run(["list-sessions"]);
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "no server running on /tmp/tmux-fake/default\n"
+        );
+
+        let output = server.run(["new-session", "-d", "-P", "-F", "#{session_id}"]);
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"$0\n");
+    }
+
+    #[test]
+    fn test_run_reports_version() {
+        let server = FakeServer::new();
+        let output = server.run(["-V"]);
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"tmux 3.4\n");
+        assert!(server.commands().is_empty());
+    }
+
+    #[test]
+    fn test_install_restores_previous_server() {
+        let outer = FakeServer::new();
+        let inner = FakeServer::new();
+        let args = [OsString::from("start-server")];
+
+        assert!(intercept(&args, None).is_none());
+        {
+            let _outer = outer.install();
+            {
+                let _inner = inner.install();
+                intercept(&args, None).unwrap();
+            }
+            intercept(&args, None).unwrap();
+        }
+        assert!(intercept(&args, None).is_none());
+
+        assert_eq!(outer.commands().len(), 1);
+        assert_eq!(inner.commands().len(), 1);
+    }
+
+    #[test]
+    fn test_install_global_answers_other_threads() {
+        let global = FakeServer::new();
+        let local = FakeServer::new();
+        let args = [OsString::from("start-server")];
+
+        {
+            let _global = global.install_global();
+            std::thread::spawn(move || intercept(&args, None).unwrap())
+                .join()
+                .unwrap();
+
+            let _local = local.install();
+            intercept(&[OsString::from("start-server")], None).unwrap();
+        }
+        let installed = std::thread::spawn(is_installed).join().unwrap();
+        assert!(!installed);
+
+        assert_eq!(global.commands().len(), 1);
+        assert_eq!(local.commands().len(), 1);
+    }
+}
@@ -0,0 +1,524 @@
+//! Layout operations of the fake server, on the containers of [`crate::layout`].
+//!
+//! Sizes follow tmux: splitting a pane keeps half of it, and the panes of even layouts share the
+//! space, the last one getting the remainder.
+
+use crate::layout::{Container, Coordinates, Dimensions, Element, Split};
+
+/// Orientation of a split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Orientation {
+    /// Panes side by side (`-h`, `{}` in layouts).
+    Horizontal,
+    /// Panes stacked (`-v`, `[]` in layouts).
+    Vertical,
+}
+
+/// Container of a single pane.
+pub(crate) fn pane(pane_id: u16, width: u16, height: u16) -> Container {
+    Container {
+        dimensions: Dimensions { width, height },
+        coordinates: Coordinates { x: 0, y: 0 },
+        element: Element::Pane { pane_id },
+    }
+}
+
+/// Pane containers of `container`, in order.
+pub(crate) fn panes(container: &Container) -> Vec<&Container> {
+    match &container.element {
+        Element::Pane { .. } => vec![container],
+        Element::Horizontal(split) | Element::Vertical(split) => {
+            split.elements.iter().flat_map(panes).collect()
+        }
+    }
+}
+
+/// Pane ids of `container`, in order.
+pub(crate) fn pane_ids(container: &Container) -> Vec<u16> {
+    panes(container)
+        .into_iter()
+        .filter_map(|pane| match pane.element {
+            Element::Pane { pane_id } => Some(pane_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split the pane `target` in two, the new pane `new_id` being placed after it, or before it if
+/// `before`. The new pane gets `size` cells along `orientation`, or half of the pane.
+///
+/// Returns `false` if `target` is not found or is too small.
+pub(crate) fn split(
+    root: &mut Container,
+    target: u16,
+    new_id: u16,
+    orientation: Orientation,
+    before: bool,
+    size: Option<u16>,
+) -> bool {
+    let done = split_in(root, target, new_id, orientation, before, size);
+    if done {
+        place(root, root.coordinates);
+    }
+    done
+}
+
+/// Split the whole window `root`, as with `split-window -f`.
+pub(crate) fn split_full(
+    root: &mut Container,
+    new_id: u16,
+    orientation: Orientation,
+    before: bool,
+    size: Option<u16>,
+) -> bool {
+    let total = length(root, orientation);
+    let Some((kept, added)) = halves(total, size) else {
+        return false;
+    };
+
+    let new_pane = sized(pane(new_id, 0, 0), root.dimensions, orientation, added);
+    let mut old = root.clone();
+    resize(&mut old, orientation, kept);
+
+    let mut elements = match (old.element, orientation) {
+        (Element::Horizontal(split), Orientation::Horizontal)
+        | (Element::Vertical(split), Orientation::Vertical) => split.elements,
+        (element, _) => vec![Container { element, ..old }],
+    };
+    if before {
+        elements.insert(0, new_pane);
+    } else {
+        elements.push(new_pane);
+    }
+    root.element = element(orientation, elements);
+    place(root, root.coordinates);
+    true
+}
+
+/// Remove the pane `pane_id`, giving its space to a neighbour.
+///
+/// Returns `false` if the pane is not found, or is the only pane of `root`.
+pub(crate) fn remove(root: &mut Container, pane_id: u16) -> bool {
+    let done = remove_in(root, pane_id);
+    if done {
+        place(root, root.coordinates);
+    }
+    done
+}
+
+/// Layout named `name` (such as `tiled`) for the panes `pane_ids`, or `None` if there is no
+/// such layout.
+pub(crate) fn named(name: &str, pane_ids: &[u16], dimensions: Dimensions) -> Option<Container> {
+    let Dimensions { width, height } = dimensions;
+    let mut root = match (name, pane_ids) {
+        (_, []) => return None,
+        ("even-horizontal", _) => even(pane_ids, Orientation::Horizontal, width, height),
+        ("even-vertical", _) => even(pane_ids, Orientation::Vertical, width, height),
+        ("main-horizontal", [first, others @ ..]) => {
+            main(*first, others, Orientation::Vertical, dimensions)
+        }
+        ("main-vertical", [first, others @ ..]) => {
+            main(*first, others, Orientation::Horizontal, dimensions)
+        }
+        ("tiled", _) => tiled(pane_ids, dimensions),
+        _ => return None,
+    };
+    place(&mut root, Coordinates { x: 0, y: 0 });
+    Some(root)
+}
+
+/// Size of `container` along `orientation`.
+fn length(container: &Container, orientation: Orientation) -> u16 {
+    match orientation {
+        Orientation::Horizontal => container.dimensions.width,
+        Orientation::Vertical => container.dimensions.height,
+    }
+}
+
+/// `container` with the `dimensions` of another container, except `length` along `orientation`.
+fn sized(
+    mut container: Container,
+    dimensions: Dimensions,
+    orientation: Orientation,
+    length: u16,
+) -> Container {
+    container.dimensions = dimensions;
+    match orientation {
+        Orientation::Horizontal => container.dimensions.width = length,
+        Orientation::Vertical => container.dimensions.height = length,
+    }
+    container
+}
+
+/// Element splitting `elements` along `orientation`.
+fn element(orientation: Orientation, elements: Vec<Container>) -> Element {
+    match orientation {
+        Orientation::Horizontal => Element::Horizontal(Split { elements }),
+        Orientation::Vertical => Element::Vertical(Split { elements }),
+    }
+}
+
+/// Sizes of the kept and added parts when splitting `total` cells, the added part getting
+/// `size` cells or half of them. Returns `None` if there is no space for both parts.
+fn halves(total: u16, size: Option<u16>) -> Option<(u16, u16)> {
+    if total < 3 {
+        return None;
+    }
+    let added = size.unwrap_or(total - total / 2 - 1).clamp(1, total - 2);
+    Some((total - added - 1, added))
+}
+
+/// Sizes of `count` parts sharing `total` cells, separated by borders, the last part getting the
+/// remainder.
+fn shares(total: u16, count: usize) -> Vec<u16> {
+    let count_u16 = u16::try_from(count).unwrap_or(u16::MAX).max(1);
+    let available = total.saturating_sub(count_u16 - 1);
+    let base = available / count_u16;
+    let mut sizes = vec![base; count];
+    if let Some(last) = sizes.last_mut() {
+        *last += available % count_u16;
+    }
+    sizes
+}
+
+fn split_in(
+    container: &mut Container,
+    target: u16,
+    new_id: u16,
+    orientation: Orientation,
+    before: bool,
+    size: Option<u16>,
+) -> bool {
+    let split_orientation = match &container.element {
+        Element::Pane { pane_id } if *pane_id == target => {
+            let Some(parts) = split_pane(container, new_id, orientation, before, size) else {
+                return false;
+            };
+            let dimensions = container.dimensions;
+            container.element = element(orientation, parts);
+            container.dimensions = dimensions;
+            return true;
+        }
+        Element::Pane { .. } => return false,
+        Element::Horizontal(_) => Orientation::Horizontal,
+        Element::Vertical(_) => Orientation::Vertical,
+    };
+    let (Element::Horizontal(split) | Element::Vertical(split)) = &mut container.element else {
+        unreachable!("panes are handled above");
+    };
+
+    // A pane split along the orientation of its parent gets a sibling in the parent.
+    if split_orientation == orientation {
+        let position = split.elements.iter().position(
+            |child| matches!(child.element, Element::Pane { pane_id } if pane_id == target),
+        );
+        if let Some(position) = position {
+            let Some(parts) =
+                split_pane(&split.elements[position], new_id, orientation, before, size)
+            else {
+                return false;
+            };
+            split.elements.splice(position..=position, parts);
+            return true;
+        }
+    }
+
+    split
+        .elements
+        .iter_mut()
+        .any(|child| split_in(child, target, new_id, orientation, before, size))
+}
+
+/// The pane `container` and the new pane `new_id`, in order, sharing the space of `container`.
+fn split_pane(
+    container: &Container,
+    new_id: u16,
+    orientation: Orientation,
+    before: bool,
+    size: Option<u16>,
+) -> Option<Vec<Container>> {
+    let (kept, added) = halves(length(container, orientation), size)?;
+    let old = sized(container.clone(), container.dimensions, orientation, kept);
+    let new_pane = sized(pane(new_id, 0, 0), container.dimensions, orientation, added);
+    Some(if before {
+        vec![new_pane, old]
+    } else {
+        vec![old, new_pane]
+    })
+}
+
+fn remove_in(container: &mut Container, pane_id: u16) -> bool {
+    let orientation = match &container.element {
+        Element::Pane { .. } => return false,
+        Element::Horizontal(_) => Orientation::Horizontal,
+        Element::Vertical(_) => Orientation::Vertical,
+    };
+    let (Element::Horizontal(split) | Element::Vertical(split)) = &mut container.element else {
+        unreachable!("panes are handled above");
+    };
+
+    let position = split
+        .elements
+        .iter()
+        .position(|child| matches!(child.element, Element::Pane { pane_id: id } if id == pane_id));
+    let Some(position) = position else {
+        return split
+            .elements
+            .iter_mut()
+            .any(|child| remove_in(child, pane_id));
+    };
+
+    let removed = split.elements.remove(position);
+    let neighbour = &mut split.elements[position.saturating_sub(1)];
+    let freed = length(&removed, orientation) + 1;
+    resize(
+        neighbour,
+        orientation,
+        length(neighbour, orientation) + freed,
+    );
+
+    if split.elements.len() == 1 {
+        let only = split.elements.remove(0);
+        container.element = only.element;
+    }
+    true
+}
+
+/// Resize `container` to `size` along `orientation`, scaling its children.
+fn resize(container: &mut Container, orientation: Orientation, size: u16) {
+    let old = length(container, orientation);
+    *container = sized(container.clone(), container.dimensions, orientation, size);
+
+    let split_orientation = match &container.element {
+        Element::Pane { .. } => return,
+        Element::Horizontal(_) => Orientation::Horizontal,
+        Element::Vertical(_) => Orientation::Vertical,
+    };
+    let (Element::Horizontal(split) | Element::Vertical(split)) = &mut container.element else {
+        unreachable!("panes are handled above");
+    };
+
+    if split_orientation != orientation {
+        for child in &mut split.elements {
+            resize(child, orientation, size);
+        }
+        return;
+    }
+
+    // Scale the children along the split like tmux, the last one getting the remainder.
+    let count = split.elements.len();
+    let borders = u16::try_from(count - 1).unwrap_or(u16::MAX);
+    let available = size.saturating_sub(borders);
+    let mut used = 0;
+    for (index, child) in split.elements.iter_mut().enumerate() {
+        let child_size = if index + 1 == count {
+            available.saturating_sub(used)
+        } else {
+            let scaled =
+                u32::from(length(child, orientation)) * u32::from(size) / u32::from(old.max(1));
+            u16::try_from(scaled).unwrap_or(available)
+        };
+        used += child_size;
+        resize(child, orientation, child_size);
+    }
+}
+
+/// Set the coordinates of `container` and of its children, from their sizes.
+fn place(container: &mut Container, coordinates: Coordinates) {
+    container.coordinates = coordinates;
+    let Coordinates { mut x, mut y } = coordinates;
+    match &mut container.element {
+        Element::Pane { .. } => {}
+        Element::Horizontal(split) => {
+            for child in &mut split.elements {
+                place(child, Coordinates { x, y });
+                x += child.dimensions.width + 1;
+            }
+        }
+        Element::Vertical(split) => {
+            for child in &mut split.elements {
+                place(child, Coordinates { x, y });
+                y += child.dimensions.height + 1;
+            }
+        }
+    }
+}
+
+/// Panes `pane_ids` sharing `width` x `height` along `orientation`.
+fn even(pane_ids: &[u16], orientation: Orientation, width: u16, height: u16) -> Container {
+    let dimensions = Dimensions { width, height };
+    if let [pane_id] = pane_ids {
+        return pane(*pane_id, width, height);
+    }
+    let total = match orientation {
+        Orientation::Horizontal => width,
+        Orientation::Vertical => height,
+    };
+    let elements = pane_ids
+        .iter()
+        .zip(shares(total, pane_ids.len()))
+        .map(|(pane_id, size)| sized(pane(*pane_id, 0, 0), dimensions, orientation, size))
+        .collect();
+    Container {
+        dimensions,
+        coordinates: Coordinates { x: 0, y: 0 },
+        element: element(orientation, elements),
+    }
+}
+
+/// The `main` pane with half of the window, next to the `others` along `orientation`.
+fn main(main: u16, others: &[u16], orientation: Orientation, dimensions: Dimensions) -> Container {
+    let Dimensions { width, height } = dimensions;
+    if others.is_empty() {
+        return pane(main, width, height);
+    }
+    let total = match orientation {
+        Orientation::Horizontal => width,
+        Orientation::Vertical => height,
+    };
+    let (rest, main_size) = halves(total, None).unwrap_or((total, 0));
+    let main_pane = sized(pane(main, 0, 0), dimensions, orientation, main_size);
+    let others = match orientation {
+        Orientation::Horizontal => even(others, Orientation::Vertical, rest, height),
+        Orientation::Vertical => even(others, Orientation::Horizontal, width, rest),
+    };
+    Container {
+        dimensions,
+        coordinates: Coordinates { x: 0, y: 0 },
+        element: element(orientation, vec![main_pane, others]),
+    }
+}
+
+/// Panes `pane_ids` in a grid, row by row.
+fn tiled(pane_ids: &[u16], dimensions: Dimensions) -> Container {
+    let Dimensions { width, height } = dimensions;
+    let (mut rows, mut columns) = (1, 1);
+    while rows * columns < pane_ids.len() {
+        rows += 1;
+        if rows * columns < pane_ids.len() {
+            columns += 1;
+        }
+    }
+
+    let heights = shares(height, rows);
+    let row_elements: Vec<Container> = pane_ids
+        .chunks(columns)
+        .zip(&heights)
+        .map(|(row, row_height)| even(row, Orientation::Horizontal, width, *row_height))
+        .collect();
+    if let [row] = row_elements.as_slice() {
+        return row.clone();
+    }
+    Container {
+        dimensions,
+        coordinates: Coordinates { x: 0, y: 0 },
+        element: element(Orientation::Vertical, row_elements),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::WindowLayout;
+
+    fn render(container: &Container) -> String {
+        WindowLayout::new(container.clone()).to_string()
+    }
+
+    #[test]
+    fn test_split_like_tmux() {
+        let mut root = pane(0, 80, 24);
+
+        assert!(split(&mut root, 0, 1, Orientation::Horizontal, false, None));
+        assert_eq!(render(&root), "8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1}");
+
+        assert!(split(&mut root, 1, 2, Orientation::Vertical, false, None));
+        assert_eq!(
+            render(&root),
+            "d67e,80x24,0,0{40x24,0,0,0,39x24,41,0[39x12,41,0,1,39x11,41,13,2]}"
+        );
+
+        assert!(split_full(&mut root, 3, Orientation::Vertical, false, None));
+        assert_eq!(
+            render(&root),
+            "7fc8,80x24,0,0[80x12,0,0{40x12,0,0,0,39x12,41,0[39x6,41,0,1,39x5,41,7,2]},80x11,0,13,3]"
+        );
+        assert_eq!(pane_ids(&root), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_split_sibling_and_before() {
+        let mut root = pane(0, 80, 24);
+        assert!(split(
+            &mut root,
+            0,
+            1,
+            Orientation::Horizontal,
+            false,
+            Some(10)
+        ));
+        assert!(split(&mut root, 0, 2, Orientation::Horizontal, true, None));
+
+        assert_eq!(
+            render(&root).split_once(',').unwrap().1,
+            "80x24,0,0{34x24,0,0,2,34x24,35,0,0,10x24,70,0,1}"
+        );
+    }
+
+    #[test]
+    fn test_split_too_small() {
+        let mut root = pane(0, 2, 24);
+        assert!(!split(
+            &mut root,
+            0,
+            1,
+            Orientation::Horizontal,
+            false,
+            None
+        ));
+        assert!(!split(&mut root, 7, 1, Orientation::Vertical, false, None));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut root = pane(0, 80, 24);
+        split(&mut root, 0, 1, Orientation::Horizontal, false, None);
+        split(&mut root, 1, 2, Orientation::Vertical, false, None);
+
+        assert!(remove(&mut root, 0));
+        assert_eq!(
+            render(&root).split_once(',').unwrap().1,
+            "80x24,0,0[80x12,0,0,1,80x11,0,13,2]"
+        );
+
+        assert!(remove(&mut root, 2));
+        assert_eq!(render(&root).split_once(',').unwrap().1, "80x24,0,0,1");
+
+        assert!(!remove(&mut root, 1));
+    }
+
+    #[test]
+    fn test_named_like_tmux() {
+        let dimensions = Dimensions {
+            width: 80,
+            height: 24,
+        };
+
+        let layout = named("even-horizontal", &[0, 1, 2, 3], dimensions).unwrap();
+        assert_eq!(
+            render(&layout),
+            "764c,80x24,0,0{19x24,0,0,0,19x24,20,0,1,19x24,40,0,2,20x24,60,0,3}"
+        );
+
+        let layout = named("tiled", &[0, 1, 2, 3], dimensions).unwrap();
+        assert_eq!(
+            render(&layout),
+            "30d6,80x24,0,0[80x11,0,0{39x11,0,0,0,40x11,40,0,1},80x12,0,12{39x12,0,12,2,40x12,40,12,3}]"
+        );
+
+        let layout = named("main-vertical", &[0, 1, 2], dimensions).unwrap();
+        assert_eq!(pane_ids(&layout), vec![0, 1, 2]);
+
+        assert!(named("spiral", &[0], dimensions).is_none());
+    }
+}
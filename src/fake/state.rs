@@ -0,0 +1,1776 @@
+//! State of the fake server, and the tmux commands it runs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use crate::{
+    command::{Arguments, parse},
+    format::Format,
+    layout::{self, Container, Dimensions, Element},
+};
+
+use super::layout::{self as fake_layout, Orientation};
+
+/// Path of the socket reported by the fake server.
+const SOCKET_PATH: &str = "/tmp/tmux-fake/default";
+
+/// Version of tmux reported by the fake server.
+const VERSION: &str = "3.4";
+
+/// Start directory of panes created without `-c`.
+const DEFAULT_PATH: &str = "/";
+
+/// Time of the first event, in seconds since the epoch. Each event advances the clock by one
+/// second, so that timestamps are deterministic.
+const EPOCH: u64 = 1_700_000_000;
+
+/// Full names, aliases and supported flags of the commands understood by the fake server.
+///
+/// Flags follow tmux (and getopt): a letter followed by `:` takes a value.
+const COMMANDS: [(&str, &str, &str); 22] = [
+    ("new-session", "new", "AdPF:c:e:n:s:t:x:y:"),
+    ("new-window", "neww", "abdkPF:c:e:n:t:"),
+    ("split-window", "splitw", "bdfhvPF:c:e:l:t:"),
+    ("list-sessions", "ls", "F:f:"),
+    ("list-windows", "lsw", "aF:f:t:"),
+    ("list-panes", "lsp", "asF:f:t:"),
+    ("list-clients", "lsc", "F:f:t:"),
+    ("detach-client", "detach", "as:t:"),
+    ("select-layout", "selectl", "t:"),
+    ("select-pane", "selectp", "lT:t:"),
+    ("select-window", "selectw", "lt:"),
+    ("kill-session", "kill-session", "at:"),
+    ("kill-window", "killw", "at:"),
+    ("kill-pane", "killp", "at:"),
+    ("kill-server", "kill-server", ""),
+    ("has-session", "has", "t:"),
+    ("rename-session", "rename", "t:"),
+    ("rename-window", "renamew", "t:"),
+    ("show-options", "show", "gqsvwt:"),
+    ("set-option", "set", "gsuwt:"),
+    ("display-message", "display", "pF:c:d:t:"),
+    ("source-file", "source", "q"),
+];
+
+/// A session, with its windows by index.
+#[derive(Debug)]
+struct Session {
+    id: u32,
+    name: String,
+    path: String,
+    created: u64,
+    activity: u64,
+    group: Option<String>,
+    winlinks: BTreeMap<u32, u32>,
+    current_window: u32,
+    last_window: Option<u32>,
+    options: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+struct Window {
+    id: u32,
+    name: String,
+    layout: Container,
+    active_pane: u16,
+    last_pane: Option<u16>,
+    options: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+struct Pane {
+    id: u16,
+    title: String,
+    current_command: String,
+    start_command: String,
+    path: String,
+}
+
+/// A session, one of its windows and one of the panes of that window, which is the context of
+/// formats and the result of resolving targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Context {
+    session: u32,
+    window: u32,
+    pane: u16,
+}
+
+/// Kind of a target, for error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Session,
+    Window,
+    Pane,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::Window => "window",
+            Self::Pane => "pane",
+        }
+    }
+}
+
+/// Scope of an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Server,
+    Session,
+    Window,
+}
+
+/// Sessions, windows, panes and options of the fake server.
+#[derive(Debug)]
+pub(crate) struct State {
+    sessions: Vec<Session>,
+    windows: BTreeMap<u32, Window>,
+    panes: BTreeMap<u16, Pane>,
+    current_session: Option<u32>,
+    next_session_id: u32,
+    next_window_id: u32,
+    next_pane_id: u16,
+    clock: u64,
+    server_options: BTreeMap<String, String>,
+    session_options: BTreeMap<String, String>,
+    window_options: BTreeMap<String, String>,
+    /// Commands received, each as its name and arguments.
+    pub(crate) commands: Vec<Vec<String>>,
+    /// Messages shown with `display-message`, expanded.
+    pub(crate) messages: Vec<String>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let options = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        Self {
+            sessions: vec![],
+            windows: BTreeMap::new(),
+            panes: BTreeMap::new(),
+            current_session: None,
+            next_session_id: 0,
+            next_window_id: 0,
+            next_pane_id: 0,
+            clock: EPOCH,
+            server_options: options(&[
+                ("buffer-limit", "50"),
+                ("escape-time", "500"),
+                ("exit-empty", "on"),
+            ]),
+            session_options: options(&[
+                ("base-index", "0"),
+                ("default-command", ""),
+                ("default-shell", "/bin/sh"),
+                ("default-size", "80x24"),
+                ("history-limit", "2000"),
+                ("renumber-windows", "off"),
+                ("status", "on"),
+            ]),
+            window_options: options(&[
+                ("automatic-rename", "on"),
+                ("pane-base-index", "0"),
+                ("remain-on-exit", "off"),
+            ]),
+            commands: vec![],
+            messages: vec![],
+        }
+    }
+}
+
+impl State {
+    /// Run the invocation `tmux <args>`, `stdin` being read by `source-file -`.
+    ///
+    /// Returns the stdout of the commands which ran, and the error which stopped them, if any.
+    pub(crate) fn run(
+        &mut self,
+        args: &[String],
+        stdin: Option<&[u8]>,
+    ) -> (String, Option<String>) {
+        if args == ["-V"] {
+            return (format!("tmux {VERSION}\n"), None);
+        }
+        let commands = split_commands(args);
+
+        // Like tmux, only commands which start the server can run without one.
+        let starts_server = commands.iter().any(|command| {
+            matches!(
+                full_name(command.first().map_or("", String::as_str)),
+                Some("new-session" | "start-server")
+            )
+        });
+        if self.sessions.is_empty() && !starts_server {
+            self.commands.extend(commands);
+            return (
+                String::new(),
+                Some(format!("no server running on {SOCKET_PATH}")),
+            );
+        }
+
+        let mut stdout = String::new();
+        for command in commands {
+            match self.execute(&command, stdin) {
+                Ok(output) => stdout.push_str(&output),
+                Err(error) => return (stdout, Some(error)),
+            }
+        }
+        (stdout, None)
+    }
+
+    /// Run a single command.
+    fn execute(&mut self, command: &[String], stdin: Option<&[u8]>) -> Result<String, String> {
+        let Some((name, args)) = command.split_first() else {
+            return Ok(String::new());
+        };
+        self.commands.push(command.to_vec());
+        self.clock += 1;
+
+        if name == "start-server" {
+            return Ok(String::new());
+        }
+        let Some((full_name, _, template)) = COMMANDS
+            .iter()
+            .find(|(full_name, alias, _)| name == full_name || name == alias)
+        else {
+            return Err(format!("unknown command: {name}"));
+        };
+        let args = match Arguments::parse(args, template) {
+            Ok(Some(args)) => args,
+            Ok(None) => return Err(format!("{full_name}: unsupported flag")),
+            Err(_) => return Err(format!("{full_name}: invalid arguments")),
+        };
+
+        match *full_name {
+            "new-session" => self.new_session(&args),
+            "new-window" => self.new_window(&args),
+            "split-window" => self.split_window(&args),
+            "list-sessions" => self.list_sessions(&args),
+            "list-windows" => self.list_windows(&args),
+            "list-panes" => self.list_panes(&args),
+            "list-clients" => self.list_clients(&args),
+            "detach-client" => self.detach_client(&args),
+            "select-layout" => self.select_layout(&args),
+            "select-pane" => self.select_pane(&args),
+            "select-window" => self.select_window(&args),
+            "kill-session" => self.kill_session(&args),
+            "kill-window" => self.kill_window(&args),
+            "kill-pane" => self.kill_pane(&args),
+            "kill-server" => {
+                *self = Self {
+                    commands: std::mem::take(&mut self.commands),
+                    messages: std::mem::take(&mut self.messages),
+                    ..Self::default()
+                };
+                Ok(String::new())
+            }
+            "has-session" => self
+                .resolve(args.value('t').as_deref(), Kind::Session)
+                .map(|_| String::new()),
+            "rename-session" => self.rename_session(&args),
+            "rename-window" => self.rename_window(&args),
+            "show-options" => self.show_options(&args),
+            "set-option" => self.set_option(&args),
+            "display-message" => self.display_message(&args),
+            "source-file" => self.source_file(&args, stdin),
+            _ => Err(format!("unknown command: {name}")),
+        }
+    }
+
+    // ------------------------------
+    // Commands
+    // ------------------------------
+
+    fn new_session(&mut self, args: &Arguments) -> Result<String, String> {
+        let name = args.value('s').map(|name| name.replace([':', '.'], "_"));
+        if let Some(existing) = name
+            .as_deref()
+            .and_then(|name| self.sessions.iter().find(|s| s.name == name))
+        {
+            if args.has('A') {
+                self.current_session = Some(existing.id);
+                return Ok(String::new());
+            }
+            return Err(format!("duplicate session: {}", existing.name));
+        }
+
+        let id = self.next_session_id;
+        self.next_session_id += 1;
+        let name = name.unwrap_or_else(|| {
+            (id..)
+                .map(|n| n.to_string())
+                .find(|n| self.sessions.iter().all(|s| &s.name != n))
+                .unwrap_or_default()
+        });
+        let path = args.value('c').unwrap_or_else(|| DEFAULT_PATH.into());
+
+        let mut session = Session {
+            id,
+            name,
+            path: path.clone(),
+            created: self.clock,
+            activity: self.clock,
+            group: None,
+            winlinks: BTreeMap::new(),
+            current_window: 0,
+            last_window: None,
+            options: BTreeMap::new(),
+        };
+
+        if let Some(target) = args.value('t') {
+            // A grouped session shares the windows of the target session.
+            let context = self.resolve(Some(&target), Kind::Session)?;
+            let group_session = self.session_mut(context.session);
+            let group = group_session
+                .group
+                .get_or_insert_with(|| group_session.name.clone())
+                .clone();
+            session.winlinks = group_session.winlinks.clone();
+            session.current_window = group_session.current_window;
+            session.group = Some(group);
+        } else {
+            let dimensions = self.new_session_dimensions(args)?;
+            let window_id = self.create_window(args.value('n'), command_of(args), path, dimensions);
+            session
+                .winlinks
+                .insert(self.option_number("base-index"), window_id);
+            session.current_window = window_id;
+        }
+
+        let window = session.current_window;
+        self.sessions.push(session);
+        self.current_session = Some(id);
+
+        let context = Context {
+            session: id,
+            window,
+            pane: self.windows[&window].active_pane,
+        };
+        Ok(self.printed(args, context, "#{session_name}:"))
+    }
+
+    fn new_window(&mut self, args: &Arguments) -> Result<String, String> {
+        let target = args.value('t');
+        let (session_id, index) = if args.has('a') || args.has('b') {
+            let context = self.resolve(target.as_deref(), Kind::Window)?;
+            let index = self.window_index(context.session, context.window);
+            let index = if args.has('a') { index + 1 } else { index };
+            self.shift_windows(context.session, index);
+            (context.session, index)
+        } else {
+            let (session_target, index) = match target.as_deref().map(|t| t.split_once(':')) {
+                Some(Some((session, index))) => (Some(session), Some(index)),
+                Some(None) => (target.as_deref(), None),
+                None => (None, None),
+            };
+            let session_id = match session_target.filter(|s| !s.is_empty()) {
+                Some(session) => self.resolve(Some(session), Kind::Session)?.session,
+                None => self.current()?.session,
+            };
+            let index = match index.filter(|i| !i.is_empty()) {
+                Some(index) => {
+                    let index: u32 = index
+                        .parse()
+                        .map_err(|_| format!("can't find window: {index}"))?;
+                    if let Some(&existing) = self.session(session_id).winlinks.get(&index) {
+                        if !args.has('k') {
+                            return Err(format!("index in use: {index}"));
+                        }
+                        self.unlink_window(session_id, existing);
+                    }
+                    index
+                }
+                None => self.free_index(session_id),
+            };
+            (session_id, index)
+        };
+
+        let dimensions = self.window_dimensions(self.session(session_id).current_window);
+        let path = args
+            .value('c')
+            .unwrap_or_else(|| self.session(session_id).path.clone());
+        let window_id = self.create_window(args.value('n'), command_of(args), path, dimensions);
+
+        for member in self.group_members(session_id) {
+            let session = self.session_mut(member);
+            session.winlinks.insert(index, window_id);
+            if member == session_id && !args.has('d') {
+                session.last_window = Some(session.current_window);
+                session.current_window = window_id;
+            }
+        }
+
+        let context = Context {
+            session: session_id,
+            window: window_id,
+            pane: self.windows[&window_id].active_pane,
+        };
+        Ok(self.printed(
+            args,
+            context,
+            "#{session_name}:#{window_index}.#{pane_index}",
+        ))
+    }
+
+    fn split_window(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Pane)?;
+        let orientation = if args.has('h') {
+            Orientation::Horizontal
+        } else {
+            Orientation::Vertical
+        };
+
+        let window = &self.windows[&context.window];
+        let reference = if args.has('f') {
+            &window.layout
+        } else {
+            fake_layout::panes(&window.layout)
+                .into_iter()
+                .find(|pane| matches!(pane.element, Element::Pane { pane_id } if pane_id == context.pane))
+                .ok_or_else(|| format!("can't find pane: %{}", context.pane))?
+        };
+        let total = match orientation {
+            Orientation::Horizontal => reference.dimensions.width,
+            Orientation::Vertical => reference.dimensions.height,
+        };
+        let size = args
+            .value('l')
+            .map(|size| parse_size(&size, total))
+            .transpose()?;
+
+        let pane_id = self.next_pane_id;
+        let window = self
+            .windows
+            .get_mut(&context.window)
+            .expect("resolved window exists");
+        let done = if args.has('f') {
+            fake_layout::split_full(
+                &mut window.layout,
+                pane_id,
+                orientation,
+                args.has('b'),
+                size,
+            )
+        } else {
+            fake_layout::split(
+                &mut window.layout,
+                context.pane,
+                pane_id,
+                orientation,
+                args.has('b'),
+                size,
+            )
+        };
+        if !done {
+            return Err("no space for new pane".into());
+        }
+        if !args.has('d') {
+            window.last_pane = Some(window.active_pane);
+            window.active_pane = pane_id;
+        }
+
+        let path = args
+            .value('c')
+            .unwrap_or_else(|| self.panes[&context.pane].path.clone());
+        self.create_pane(command_of(args), path);
+
+        let context = Context {
+            pane: pane_id,
+            ..context
+        };
+        Ok(self.printed(
+            args,
+            context,
+            "#{session_name}:#{window_index}.#{pane_index}",
+        ))
+    }
+
+    fn list_sessions(&self, args: &Arguments) -> Result<String, String> {
+        let contexts = self
+            .sorted_sessions()
+            .into_iter()
+            .map(|session| self.session_context(session.id))
+            .collect();
+        Ok(self.listed(
+            args,
+            contexts,
+            "#{session_name}: #{session_windows} windows (created #{session_created})",
+        ))
+    }
+
+    fn list_windows(&self, args: &Arguments) -> Result<String, String> {
+        let sessions = if args.has('a') {
+            self.sorted_sessions().into_iter().map(|s| s.id).collect()
+        } else {
+            vec![
+                self.resolve(args.value('t').as_deref(), Kind::Session)?
+                    .session,
+            ]
+        };
+        let contexts = sessions
+            .into_iter()
+            .flat_map(|session| self.window_contexts(session))
+            .collect();
+        let default = if args.has('a') {
+            "#{session_name}:#{window_index}: #{window_name}#{?window_active,*,} (#{window_panes} panes) [#{window_width}x#{window_height}]"
+        } else {
+            "#{window_index}: #{window_name}#{?window_active,*,} (#{window_panes} panes) [#{window_width}x#{window_height}]"
+        };
+        Ok(self.listed(args, contexts, default))
+    }
+
+    fn list_panes(&self, args: &Arguments) -> Result<String, String> {
+        let windows: Vec<Context> = if args.has('a') {
+            self.sorted_sessions()
+                .into_iter()
+                .flat_map(|session| self.window_contexts(session.id))
+                .collect()
+        } else if args.has('s') {
+            let context = self.resolve(args.value('t').as_deref(), Kind::Session)?;
+            self.window_contexts(context.session)
+        } else {
+            vec![self.resolve(args.value('t').as_deref(), Kind::Window)?]
+        };
+        let contexts = windows
+            .into_iter()
+            .flat_map(|context| {
+                fake_layout::pane_ids(&self.windows[&context.window.clone()].layout)
+                    .into_iter()
+                    .map(move |pane| Context { pane, ..context })
+            })
+            .collect();
+        Ok(self.listed(
+            args,
+            contexts,
+            "#{pane_index}: [#{pane_width}x#{pane_height}] #{pane_id}#{?pane_active, (active),}",
+        ))
+    }
+
+    fn list_clients(&self, args: &Arguments) -> Result<String, String> {
+        // The fake server has no clients.
+        if let Some(target) = args.value('t') {
+            self.resolve(Some(&target), Kind::Session)?;
+        }
+        Ok(String::new())
+    }
+
+    fn detach_client(&self, args: &Arguments) -> Result<String, String> {
+        if let Some(target) = args.value('s') {
+            self.resolve(Some(&target), Kind::Session)?;
+        }
+        // As tmux without clients, the fake server cannot resolve the client to detach.
+        match args.value('t') {
+            Some(client_name) => Err(format!("can't find client: {client_name}")),
+            None => Err("no current client".into()),
+        }
+    }
+
+    fn select_layout(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+        let Some(name) = args.positional.first() else {
+            return Ok(String::new());
+        };
+
+        let window = &self.windows[&context.window];
+        let pane_ids = fake_layout::pane_ids(&window.layout);
+        let container = match fake_layout::named(name, &pane_ids, window.layout.dimensions) {
+            Some(container) => container,
+            None => {
+                let layout = layout::parse_window_layout(name)
+                    .map_err(|_| format!("invalid layout: {name}"))?;
+                let mut expected = pane_ids.clone();
+                let mut actual = fake_layout::pane_ids(&layout.container);
+                expected.sort_unstable();
+                actual.sort_unstable();
+                if actual != expected {
+                    return Err(format!("invalid layout: {name}"));
+                }
+                layout.container
+            }
+        };
+
+        self.windows
+            .get_mut(&context.window)
+            .expect("resolved window exists")
+            .layout = container;
+        Ok(String::new())
+    }
+
+    fn select_pane(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Pane)?;
+
+        if let Some(title) = args.value('T') {
+            self.panes
+                .get_mut(&context.pane)
+                .expect("resolved pane exists")
+                .title = title;
+            return Ok(String::new());
+        }
+
+        let window = self
+            .windows
+            .get_mut(&context.window)
+            .expect("resolved window exists");
+        let pane = if args.has('l') {
+            window.last_pane.ok_or("no last pane")?
+        } else {
+            context.pane
+        };
+        if pane != window.active_pane {
+            window.last_pane = Some(window.active_pane);
+            window.active_pane = pane;
+        }
+        Ok(String::new())
+    }
+
+    fn select_window(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+        let session = self.session_mut(context.session);
+        let window = if args.has('l') {
+            session.last_window.ok_or("no last window")?
+        } else {
+            context.window
+        };
+        if window != session.current_window {
+            session.last_window = Some(session.current_window);
+            session.current_window = window;
+        }
+        Ok(String::new())
+    }
+
+    fn kill_session(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Session)?;
+        let killed: Vec<u32> = if args.has('a') {
+            self.sessions
+                .iter()
+                .map(|s| s.id)
+                .filter(|id| *id != context.session)
+                .collect()
+        } else {
+            vec![context.session]
+        };
+        for session in killed {
+            self.destroy_session(session);
+        }
+        Ok(String::new())
+    }
+
+    fn kill_window(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+        let killed: Vec<u32> = if args.has('a') {
+            self.session(context.session)
+                .winlinks
+                .values()
+                .copied()
+                .filter(|id| *id != context.window)
+                .collect()
+        } else {
+            vec![context.window]
+        };
+        for window in killed {
+            self.destroy_window(window);
+        }
+        Ok(String::new())
+    }
+
+    fn kill_pane(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Pane)?;
+        let killed: Vec<u16> = if args.has('a') {
+            fake_layout::pane_ids(&self.windows[&context.window].layout)
+                .into_iter()
+                .filter(|id| *id != context.pane)
+                .collect()
+        } else {
+            vec![context.pane]
+        };
+        for pane in killed {
+            self.destroy_pane(context.window, pane);
+        }
+        Ok(String::new())
+    }
+
+    fn rename_session(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Session)?;
+        let [name] = args.positional.as_slice() else {
+            return Err("rename-session: invalid arguments".into());
+        };
+        let name = name.replace([':', '.'], "_");
+        if self
+            .sessions
+            .iter()
+            .any(|s| s.name == name && s.id != context.session)
+        {
+            return Err(format!("duplicate session: {name}"));
+        }
+        self.session_mut(context.session).name = name;
+        Ok(String::new())
+    }
+
+    fn rename_window(&mut self, args: &Arguments) -> Result<String, String> {
+        let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+        let [name] = args.positional.as_slice() else {
+            return Err("rename-window: invalid arguments".into());
+        };
+        let window = self
+            .windows
+            .get_mut(&context.window)
+            .expect("resolved window exists");
+        window.name = name.clone();
+        window
+            .options
+            .insert("automatic-rename".into(), "off".into());
+        Ok(String::new())
+    }
+
+    fn show_options(&self, args: &Arguments) -> Result<String, String> {
+        let name = args.positional.first();
+        let options = self.options(args, self.scope(args, name.map(String::as_str)))?;
+        let line = |name: &str, value: &str| {
+            if args.has('v') {
+                format!("{}\n", quote_option(value))
+            } else {
+                format!("{name} {}\n", quote_option(value))
+            }
+        };
+
+        match name {
+            Some(name) => match options.get(name) {
+                Some(value) => Ok(line(name, value)),
+                None if args.has('q') => Ok(String::new()),
+                None if name.starts_with('@') => Err(format!("invalid option: {name}")),
+                None if self.is_known_option(name) => Ok(String::new()),
+                None => Err(format!("invalid option: {name}")),
+            },
+            None => Ok(options
+                .iter()
+                .map(|(name, value)| line(name, value))
+                .collect()),
+        }
+    }
+
+    fn set_option(&mut self, args: &Arguments) -> Result<String, String> {
+        let (name, value) = match args.positional.as_slice() {
+            [name] if args.has('u') => (name.clone(), None),
+            [name, value] => (name.clone(), Some(value.clone())),
+            _ => return Err("set-option: invalid arguments".into()),
+        };
+        if !name.starts_with('@') && !self.is_known_option(&name) {
+            return Err(format!("invalid option: {name}"));
+        }
+
+        let options = match (self.scope(args, Some(&name)), args.has('g')) {
+            (Scope::Server, _) => &mut self.server_options,
+            (Scope::Session, true) => &mut self.session_options,
+            (Scope::Window, true) => &mut self.window_options,
+            (Scope::Session, false) => {
+                let context = self.resolve(args.value('t').as_deref(), Kind::Session)?;
+                &mut self.session_mut(context.session).options
+            }
+            (Scope::Window, false) => {
+                let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+                &mut self
+                    .windows
+                    .get_mut(&context.window)
+                    .expect("resolved window exists")
+                    .options
+            }
+        };
+        match value {
+            Some(value) => options.insert(name, value),
+            None => options.remove(&name),
+        };
+        Ok(String::new())
+    }
+
+    fn display_message(&mut self, args: &Arguments) -> Result<String, String> {
+        if let Some(client) = args.value('c') {
+            return Err(format!("can't find client: {client}"));
+        }
+        let context = self.resolve(args.value('t').as_deref(), Kind::Pane)?;
+        let format = args
+            .value('F')
+            .or_else(|| args.positional.first().cloned())
+            .unwrap_or_default();
+        let message = self.expand(&format, context);
+
+        if args.has('p') {
+            Ok(format!("{message}\n"))
+        } else {
+            self.messages.push(message);
+            Ok(String::new())
+        }
+    }
+
+    fn source_file(&mut self, args: &Arguments, stdin: Option<&[u8]>) -> Result<String, String> {
+        let mut stdout = String::new();
+        for path in &args.positional {
+            let config = if path == "-" {
+                String::from_utf8_lossy(stdin.unwrap_or_default()).into_owned()
+            } else {
+                match std::fs::read_to_string(path) {
+                    Ok(config) => config,
+                    Err(_) if args.has('q') => continue,
+                    Err(error) => return Err(format!("{path}: {error}")),
+                }
+            };
+            let (_, commands) =
+                parse::commands(&config).map_err(|_| format!("{path}: syntax error"))?;
+            // Like tmux, the whole file is parsed before running any command.
+            if let Some(unknown) = commands.iter().find(|c| full_name(&c[0]).is_none()) {
+                return Err(format!("unknown command: {}", unknown[0]));
+            }
+            for command in commands {
+                stdout.push_str(&self.execute(&command, None)?);
+            }
+        }
+        Ok(stdout)
+    }
+
+    // ------------------------------
+    // Creation and destruction
+    // ------------------------------
+
+    /// Create a window with a single pane, and return its id.
+    fn create_window(
+        &mut self,
+        name: Option<String>,
+        command: Option<String>,
+        path: String,
+        dimensions: Dimensions,
+    ) -> u32 {
+        let pane_id = self.create_pane(command, path);
+        let id = self.next_window_id;
+        self.next_window_id += 1;
+
+        let name = name.unwrap_or_else(|| self.panes[&pane_id].current_command.clone());
+        let mut options = BTreeMap::new();
+        if !name.is_empty() {
+            options.insert("automatic-rename".into(), "off".into());
+        }
+        self.windows.insert(
+            id,
+            Window {
+                id,
+                name,
+                layout: fake_layout::pane(pane_id, dimensions.width, dimensions.height),
+                active_pane: pane_id,
+                last_pane: None,
+                options,
+            },
+        );
+        id
+    }
+
+    /// Create a pane running `command`, or the default command, and return its id.
+    fn create_pane(&mut self, command: Option<String>, path: String) -> u16 {
+        let id = self.next_pane_id;
+        self.next_pane_id += 1;
+
+        let start_command = command.unwrap_or_default();
+        let run = if start_command.is_empty() {
+            let default_command = &self.session_options["default-command"];
+            if default_command.is_empty() {
+                &self.session_options["default-shell"]
+            } else {
+                default_command
+            }
+        } else {
+            &start_command
+        };
+        let current_command = run
+            .split_whitespace()
+            .next()
+            .and_then(|program| program.rsplit('/').next())
+            .unwrap_or_default()
+            .to_string();
+
+        self.panes.insert(
+            id,
+            Pane {
+                id,
+                title: "fake".into(),
+                current_command,
+                start_command,
+                path,
+            },
+        );
+        id
+    }
+
+    /// Destroy the session `session_id`, and its windows which are not linked elsewhere.
+    fn destroy_session(&mut self, session_id: u32) {
+        let Some(position) = self.sessions.iter().position(|s| s.id == session_id) else {
+            return;
+        };
+        let session = self.sessions.remove(position);
+
+        for window in session.winlinks.into_values() {
+            if self.window_sessions(window).is_empty() {
+                self.remove_window(window);
+            }
+        }
+
+        // A group of a single session is no longer a group.
+        if let Some(group) = session.group {
+            let members: Vec<usize> = (0..self.sessions.len())
+                .filter(|i| self.sessions[*i].group.as_ref() == Some(&group))
+                .collect();
+            if let [only] = members.as_slice() {
+                self.sessions[*only].group = None;
+            }
+        }
+
+        if self.current_session == Some(session_id) {
+            self.current_session = self
+                .sessions
+                .iter()
+                .max_by_key(|s| s.activity)
+                .map(|s| s.id);
+        }
+    }
+
+    /// Destroy the window `window_id`, unlinking it from all sessions.
+    fn destroy_window(&mut self, window_id: u32) {
+        for session in self.window_sessions(window_id) {
+            self.unlink_window(session, window_id);
+        }
+        self.remove_window(window_id);
+    }
+
+    /// Unlink the window `window_id` from the session `session_id`, destroying the session if it
+    /// has no window left.
+    fn unlink_window(&mut self, session_id: u32, window_id: u32) {
+        let session = self.session_mut(session_id);
+        session.winlinks.retain(|_, window| *window != window_id);
+        if session.last_window == Some(window_id) {
+            session.last_window = None;
+        }
+        if session.current_window == window_id {
+            match session
+                .last_window
+                .take()
+                .or_else(|| session.winlinks.values().next().copied())
+            {
+                Some(window) => session.current_window = window,
+                None => self.destroy_session(session_id),
+            }
+        }
+    }
+
+    /// Remove the window `window_id` and its panes.
+    fn remove_window(&mut self, window_id: u32) {
+        if let Some(window) = self.windows.remove(&window_id) {
+            for pane in fake_layout::pane_ids(&window.layout) {
+                self.panes.remove(&pane);
+            }
+        }
+    }
+
+    /// Destroy the pane `pane_id` of the window `window_id`, and the window if it was its only
+    /// pane.
+    fn destroy_pane(&mut self, window_id: u32, pane_id: u16) {
+        let window = self
+            .windows
+            .get_mut(&window_id)
+            .expect("window of pane exists");
+        if !fake_layout::remove(&mut window.layout, pane_id) {
+            self.destroy_window(window_id);
+            return;
+        }
+        self.panes.remove(&pane_id);
+
+        if window.last_pane == Some(pane_id) {
+            window.last_pane = None;
+        }
+        if window.active_pane == pane_id {
+            window.active_pane = window
+                .last_pane
+                .take()
+                .unwrap_or_else(|| fake_layout::pane_ids(&window.layout)[0]);
+        }
+    }
+
+    /// Shift the windows of the session `session_id` (and its group) from `index`, to make
+    /// room for a new window at `index`.
+    fn shift_windows(&mut self, session_id: u32, index: u32) {
+        for member in self.group_members(session_id) {
+            let session = self.session_mut(member);
+            if session.winlinks.contains_key(&index) {
+                let shifted = session.winlinks.split_off(&index);
+                session
+                    .winlinks
+                    .extend(shifted.into_iter().map(|(i, window)| (i + 1, window)));
+            }
+        }
+    }
+
+    // ------------------------------
+    // Targets
+    // ------------------------------
+
+    /// Context of the current session, its current window and the active pane of that window.
+    fn current(&self) -> Result<Context, String> {
+        let session = self
+            .current_session
+            .or_else(|| self.sessions.first().map(|s| s.id))
+            .ok_or("no current session")?;
+        Ok(self.session_context(session))
+    }
+
+    /// Resolve `target` like tmux, defaulting to the current session, window and pane.
+    fn resolve(&self, target: Option<&str>, kind: Kind) -> Result<Context, String> {
+        let Some(target) = target else {
+            return self.current();
+        };
+        let not_found = || format!("can't find {}: {target}", kind.name());
+
+        if let Some(id) = target.strip_prefix('%') {
+            let pane: u16 = id.parse().map_err(|_| not_found())?;
+            let window = self
+                .windows
+                .values()
+                .find(|w| fake_layout::pane_ids(&w.layout).contains(&pane))
+                .ok_or_else(not_found)?;
+            let session = self.preferred_session(window.id).ok_or_else(not_found)?;
+            return Ok(Context {
+                session,
+                window: window.id,
+                pane,
+            });
+        }
+        if let Some(id) = target.strip_prefix('@') {
+            let window: u32 = id.parse().map_err(|_| not_found())?;
+            let session = self.preferred_session(window).ok_or_else(not_found)?;
+            return Ok(self.window_context(session, window));
+        }
+
+        let (session_part, window_part) = match target.split_once(':') {
+            Some((session, window)) => (session, Some(window)),
+            None if kind == Kind::Session => (target, None),
+            None => match self.find_session(target) {
+                Some(_) => (target, None),
+                None => ("", Some(target)),
+            },
+        };
+
+        let session = if session_part.is_empty() {
+            self.current()?.session
+        } else {
+            self.find_session(session_part).ok_or_else(not_found)?
+        };
+        let Some(window_part) = window_part.filter(|w| !w.is_empty()) else {
+            return Ok(self.session_context(session));
+        };
+
+        let (window_part, pane_part) = match window_part.rsplit_once('.') {
+            Some((window, pane)) => (window, Some(pane)),
+            None => (window_part, None),
+        };
+        let winlinks = &self.session(session).winlinks;
+        let window = if window_part.is_empty() {
+            self.session(session).current_window
+        } else if let Some(id) = window_part.strip_prefix('@') {
+            let id: u32 = id.parse().map_err(|_| not_found())?;
+            *winlinks
+                .values()
+                .find(|window| **window == id)
+                .ok_or_else(not_found)?
+        } else if let Ok(index) = window_part.parse::<u32>() {
+            *winlinks.get(&index).ok_or_else(not_found)?
+        } else {
+            let name = window_part.strip_prefix('=').unwrap_or(window_part);
+            *winlinks
+                .values()
+                .find(|window| self.windows[window].name == name)
+                .ok_or_else(not_found)?
+        };
+        let mut context = self.window_context(session, window);
+
+        if let Some(pane_part) = pane_part.filter(|p| !p.is_empty()) {
+            let pane_ids = fake_layout::pane_ids(&self.windows[&window].layout);
+            context.pane = match pane_part.strip_prefix('%') {
+                Some(id) => id.parse().ok().filter(|id| pane_ids.contains(id)),
+                None => pane_part
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| pane_ids.get(index).copied()),
+            }
+            .ok_or_else(not_found)?;
+        }
+        Ok(context)
+    }
+
+    /// Find a session by id (`$1`), exact name (`=name`), name, or unique name prefix.
+    fn find_session(&self, target: &str) -> Option<u32> {
+        if let Some(id) = target.strip_prefix('$') {
+            let id: u32 = id.parse().ok()?;
+            return self.sessions.iter().find(|s| s.id == id).map(|s| s.id);
+        }
+        if let Some(name) = target.strip_prefix('=') {
+            return self.sessions.iter().find(|s| s.name == name).map(|s| s.id);
+        }
+        if let Some(session) = self.sessions.iter().find(|s| s.name == target) {
+            return Some(session.id);
+        }
+        match self
+            .sessions
+            .iter()
+            .filter(|s| s.name.starts_with(target))
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [session] => Some(session.id),
+            _ => None,
+        }
+    }
+
+    /// The current session if it links `window_id`, or else the first session linking it.
+    fn preferred_session(&self, window_id: u32) -> Option<u32> {
+        let sessions = self.window_sessions(window_id);
+        self.current_session
+            .filter(|current| sessions.contains(current))
+            .or_else(|| sessions.first().copied())
+    }
+
+    fn session_context(&self, session_id: u32) -> Context {
+        self.window_context(session_id, self.session(session_id).current_window)
+    }
+
+    fn window_context(&self, session_id: u32, window_id: u32) -> Context {
+        Context {
+            session: session_id,
+            window: window_id,
+            pane: self.windows[&window_id].active_pane,
+        }
+    }
+
+    /// Contexts of the windows of the session `session_id`, by index.
+    fn window_contexts(&self, session_id: u32) -> Vec<Context> {
+        self.session(session_id)
+            .winlinks
+            .values()
+            .map(|window| self.window_context(session_id, *window))
+            .collect()
+    }
+
+    // ------------------------------
+    // Lookups
+    // ------------------------------
+
+    fn session(&self, session_id: u32) -> &Session {
+        self.sessions
+            .iter()
+            .find(|s| s.id == session_id)
+            .expect("session exists")
+    }
+
+    fn session_mut(&mut self, session_id: u32) -> &mut Session {
+        self.sessions
+            .iter_mut()
+            .find(|s| s.id == session_id)
+            .expect("session exists")
+    }
+
+    /// Sessions sorted by name, as tmux lists them.
+    fn sorted_sessions(&self) -> Vec<&Session> {
+        let mut sessions: Vec<&Session> = self.sessions.iter().collect();
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+        sessions
+    }
+
+    /// Sessions linking the window `window_id`, sorted by name.
+    fn window_sessions(&self, window_id: u32) -> Vec<u32> {
+        self.sorted_sessions()
+            .into_iter()
+            .filter(|s| s.winlinks.values().any(|w| *w == window_id))
+            .map(|s| s.id)
+            .collect()
+    }
+
+    /// The session `session_id` and the other sessions of its group.
+    fn group_members(&self, session_id: u32) -> Vec<u32> {
+        match &self.session(session_id).group {
+            Some(group) => self
+                .sessions
+                .iter()
+                .filter(|s| s.group.as_ref() == Some(group))
+                .map(|s| s.id)
+                .collect(),
+            None => vec![session_id],
+        }
+    }
+
+    /// Index of the window `window_id` in the session `session_id`.
+    fn window_index(&self, session_id: u32, window_id: u32) -> u32 {
+        self.session(session_id)
+            .winlinks
+            .iter()
+            .find(|(_, window)| **window == window_id)
+            .map_or(0, |(index, _)| *index)
+    }
+
+    /// First free window index of the session `session_id`, from `base-index`.
+    fn free_index(&self, session_id: u32) -> u32 {
+        let winlinks = &self.session(session_id).winlinks;
+        (self.option_number("base-index")..)
+            .find(|index| !winlinks.contains_key(index))
+            .unwrap_or_default()
+    }
+
+    fn window_dimensions(&self, window_id: u32) -> Dimensions {
+        self.windows[&window_id].layout.dimensions
+    }
+
+    /// Dimensions of a new session, from `-x` and `-y` or `default-size`.
+    fn new_session_dimensions(&self, args: &Arguments) -> Result<Dimensions, String> {
+        let (width, height) = self.session_options["default-size"]
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .unwrap_or((80, 24));
+        let number = |flag: char, default: u16| match args.value(flag) {
+            Some(value) => value.parse().map_err(|_| format!("invalid size: {value}")),
+            None => Ok(default),
+        };
+        Ok(Dimensions {
+            width: number('x', width)?,
+            height: number('y', height)?,
+        })
+    }
+
+    /// Value of the global session option `name`, as a number.
+    fn option_number(&self, name: &str) -> u32 {
+        self.session_options
+            .get(name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    fn is_known_option(&self, name: &str) -> bool {
+        self.server_options.contains_key(name)
+            || self.session_options.contains_key(name)
+            || self.window_options.contains_key(name)
+    }
+
+    /// Scope of the option `name`: like tmux, the scope of known options is the scope where they
+    /// are defined, and the scope of user options (`@name`) is given by the flags.
+    fn scope(&self, args: &Arguments, name: Option<&str>) -> Scope {
+        match name {
+            Some(name) if self.server_options.contains_key(name) => Scope::Server,
+            Some(name) if self.session_options.contains_key(name) => Scope::Session,
+            Some(name) if self.window_options.contains_key(name) => Scope::Window,
+            _ if args.has('s') => Scope::Server,
+            _ if args.has('w') => Scope::Window,
+            _ => Scope::Session,
+        }
+    }
+
+    /// Options of `scope`, global with `-g`, or else of the target of `args`.
+    fn options(&self, args: &Arguments, scope: Scope) -> Result<BTreeMap<String, String>, String> {
+        match (scope, args.has('g')) {
+            (Scope::Server, _) => Ok(self.server_options.clone()),
+            (Scope::Session, true) => Ok(self.session_options.clone()),
+            (Scope::Window, true) => Ok(self.window_options.clone()),
+            (Scope::Session, false) => {
+                let context = self.resolve(args.value('t').as_deref(), Kind::Session)?;
+                Ok(self.session(context.session).options.clone())
+            }
+            (Scope::Window, false) => {
+                let context = self.resolve(args.value('t').as_deref(), Kind::Window)?;
+                Ok(self.windows[&context.window].options.clone())
+            }
+        }
+    }
+
+    // ------------------------------
+    // Formats
+    // ------------------------------
+
+    /// Output of a command creating `context` with `-P`, in the format of `-F` or `default`.
+    fn printed(&self, args: &Arguments, context: Context, default: &str) -> String {
+        if !args.has('P') {
+            return String::new();
+        }
+        let format = args.value('F').unwrap_or_else(|| default.into());
+        format!("{}\n", self.expand(&format, context))
+    }
+
+    /// Output of a list command, a line per context matching the filter (`-f`), in the format of
+    /// `-F` or `default`.
+    fn listed(&self, args: &Arguments, contexts: Vec<Context>, default: &str) -> String {
+        let format = args.value('F').unwrap_or_else(|| default.into());
+        let filter = args.value('f');
+        contexts
+            .into_iter()
+            .filter(|context| {
+                filter.as_ref().is_none_or(|filter| {
+                    let value = self.expand(filter, *context);
+                    !value.is_empty() && value != "0"
+                })
+            })
+            .map(|context| format!("{}\n", self.expand(&format, context)))
+            .collect()
+    }
+
+    /// Expand `format` in `context`. Invalid formats are kept as is.
+    fn expand(&self, format: &str, context: Context) -> String {
+        match Format::from_str(format) {
+            Ok(format) => format.evaluate(&self.variables(context)),
+            Err(_) => format.to_string(),
+        }
+    }
+
+    /// Format variables of `context`.
+    fn variables(&self, context: Context) -> HashMap<String, String> {
+        let session = self.session(context.session);
+        let window = &self.windows[&context.window];
+        let pane = &self.panes[&context.pane];
+        let flag = |value: bool| if value { "1" } else { "0" }.to_string();
+
+        let linked_sessions: Vec<String> = self
+            .window_sessions(window.id)
+            .into_iter()
+            .map(|id| self.session(id).name.clone())
+            .collect();
+        let pane_ids = fake_layout::pane_ids(&window.layout);
+        let pane_container = fake_layout::panes(&window.layout)
+            .into_iter()
+            .find(|c| matches!(c.element, Element::Pane { pane_id } if pane_id == pane.id))
+            .expect("pane is in the layout of its window");
+        let layout = layout::WindowLayout::new(window.layout.clone()).to_string();
+
+        let variables = [
+            ("host", "fake".to_string()),
+            ("socket_path", SOCKET_PATH.to_string()),
+            ("version", VERSION.to_string()),
+            // Session.
+            ("session_id", format!("${}", session.id)),
+            ("session_name", session.name.clone()),
+            ("session_path", session.path.clone()),
+            ("session_attached", "0".to_string()),
+            ("session_many_attached", "0".to_string()),
+            ("session_created", session.created.to_string()),
+            ("session_activity", session.activity.to_string()),
+            ("session_last_attached", String::new()),
+            ("session_windows", session.winlinks.len().to_string()),
+            ("session_group", session.group.clone().unwrap_or_default()),
+            ("session_grouped", flag(session.group.is_some())),
+            // Window.
+            ("window_id", format!("@{}", window.id)),
+            (
+                "window_index",
+                self.window_index(session.id, window.id).to_string(),
+            ),
+            ("window_name", window.name.clone()),
+            ("window_active", flag(session.current_window == window.id)),
+            (
+                "window_last_flag",
+                flag(session.last_window == Some(window.id)),
+            ),
+            ("window_layout", layout.clone()),
+            ("window_visible_layout", layout),
+            ("window_panes", pane_ids.len().to_string()),
+            ("window_width", window.layout.dimensions.width.to_string()),
+            ("window_height", window.layout.dimensions.height.to_string()),
+            ("window_linked", flag(linked_sessions.len() > 1)),
+            ("window_linked_sessions", linked_sessions.len().to_string()),
+            ("window_linked_sessions_list", linked_sessions.join(",")),
+            // Pane.
+            ("pane_id", format!("%{}", pane.id)),
+            (
+                "pane_index",
+                pane_ids
+                    .iter()
+                    .position(|id| *id == pane.id)
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            ("pane_active", flag(window.active_pane == pane.id)),
+            ("pane_last", flag(window.last_pane == Some(pane.id))),
+            ("pane_title", pane.title.clone()),
+            ("pane_current_command", pane.current_command.clone()),
+            ("pane_current_path", pane.path.clone()),
+            ("pane_start_command", pane.start_command.clone()),
+            ("pane_pid", (10_000 + u32::from(pane.id)).to_string()),
+            ("pane_tty", format!("/dev/pts/{}", pane.id)),
+            ("pane_dead", "0".to_string()),
+            ("pane_dead_status", String::new()),
+            ("pane_dead_signal", String::new()),
+            ("pane_in_mode", "0".to_string()),
+            ("pane_width", pane_container.dimensions.width.to_string()),
+            ("pane_height", pane_container.dimensions.height.to_string()),
+            ("pane_left", pane_container.coordinates.x.to_string()),
+            ("pane_top", pane_container.coordinates.y.to_string()),
+            ("history_size", "0".to_string()),
+            (
+                "history_limit",
+                self.session_options["history-limit"].clone(),
+            ),
+        ];
+        variables
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+}
+
+/// Split the arguments of a tmux invocation into commands, separated by `;` like tmux does.
+fn split_commands(args: &[String]) -> Vec<Vec<String>> {
+    let mut commands = vec![vec![]];
+    for arg in args {
+        let current = commands.last_mut().expect("there is a current command");
+        if arg == ";" {
+            commands.push(vec![]);
+        } else if let Some(head) = arg.strip_suffix("\\;") {
+            current.push(format!("{head};"));
+        } else if let Some(head) = arg.strip_suffix(';') {
+            current.push(head.to_string());
+            commands.push(vec![]);
+        } else {
+            current.push(arg.clone());
+        }
+    }
+    commands.retain(|command| !command.is_empty());
+    commands
+}
+
+/// Full name of the command `name`, which can be an alias.
+fn full_name(name: &str) -> Option<&'static str> {
+    if name == "start-server" {
+        return Some("start-server");
+    }
+    COMMANDS
+        .iter()
+        .find(|(full_name, alias, _)| name == *full_name || name == *alias)
+        .map(|(full_name, _, _)| *full_name)
+}
+
+/// Shell command of a `new-*` or `split-window` command, from its positional arguments.
+fn command_of(args: &Arguments) -> Option<String> {
+    (!args.positional.is_empty()).then(|| args.positional.join(" "))
+}
+
+/// Parse the size of `-l`, in cells or as a percentage of `total`.
+fn parse_size(size: &str, total: u16) -> Result<u16, String> {
+    let invalid = || format!("invalid size: {size}");
+    match size.strip_suffix('%') {
+        Some(percent) => {
+            let percent: u32 = percent.parse().map_err(|_| invalid())?;
+            u16::try_from(u32::from(total) * percent / 100).map_err(|_| invalid())
+        }
+        None => size.parse().map_err(|_| invalid()),
+    }
+}
+
+/// Quote an option value like `show-options` does.
+fn quote_option(value: &str) -> String {
+    if value.is_empty() {
+        return "''".into();
+    }
+    if value.contains([' ', '"', '\'', '#', ';', '$', '\\', '{', '}', '~']) {
+        return format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(state: &mut State, args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        match state.run(&args, None) {
+            (stdout, None) => Ok(stdout),
+            (_, Some(error)) => Err(error),
+        }
+    }
+
+    #[test]
+    fn test_no_server() {
+        let mut state = State::default();
+        assert_eq!(
+            run(&mut state, &["list-sessions"]),
+            Err(format!("no server running on {SOCKET_PATH}"))
+        );
+        assert!(run(&mut state, &["has-session", "-t", "=main"]).is_err());
+    }
+
+    #[test]
+    fn test_sessions_windows_panes() {
+        let mut state = State::default();
+        let ids = run(
+            &mut state,
+            &[
+                "new-session",
+                "-d",
+                "-s",
+                "main",
+                "-n",
+                "edit",
+                "-P",
+                "-F",
+                "#{session_id}:#{window_id}:#{pane_id}",
+            ],
+        );
+        assert_eq!(ids.unwrap(), "$0:@0:%0\n");
+
+        let ids = run(
+            &mut state,
+            &[
+                "new-window",
+                "-t",
+                "$0",
+                "-n",
+                "logs",
+                "-P",
+                "-F",
+                "#{window_id}:#{pane_id}",
+                "tail -f log",
+            ],
+        );
+        assert_eq!(ids.unwrap(), "@1:%1\n");
+
+        let id = run(
+            &mut state,
+            &["split-window", "-h", "-t", "%1", "-P", "-F", "#{pane_id}"],
+        );
+        assert_eq!(id.unwrap(), "%2\n");
+
+        let windows = run(
+            &mut state,
+            &[
+                "list-windows",
+                "-a",
+                "-F",
+                "#{window_id}:#{window_index}:#{window_active}:#{window_name}:#{window_layout}",
+            ],
+        );
+        assert_eq!(
+            windows.unwrap(),
+            "@0:0:0:edit:b25d,80x24,0,0,0\n@1:1:1:logs:020a,80x24,0,0{40x24,0,0,1,39x24,41,0,2}\n"
+        );
+
+        let panes = run(
+            &mut state,
+            &[
+                "list-panes",
+                "-s",
+                "-t",
+                "main",
+                "-F",
+                "#{pane_id}:#{pane_index}:#{pane_active}:#{pane_current_command}:#{pane_start_command}",
+            ],
+        );
+        assert_eq!(
+            panes.unwrap(),
+            "%0:0:1:sh:\n%1:0:0:tail:tail -f log\n%2:1:1:sh:\n"
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d", "-s", "main"]).unwrap();
+        run(&mut state, &["new-window", "-n", "logs"]).unwrap();
+        run(&mut state, &["split-window"]).unwrap();
+
+        for (target, expected) in [
+            ("%1", "$0 @1 %1"),
+            ("@0", "$0 @0 %0"),
+            ("main", "$0 @1 %2"),
+            ("=main:0", "$0 @0 %0"),
+            (":logs.0", "$0 @1 %1"),
+            ("$0:1.%2", "$0 @1 %2"),
+        ] {
+            let output = run(
+                &mut state,
+                &[
+                    "display-message",
+                    "-p",
+                    "-t",
+                    target,
+                    "#{session_id} #{window_id} #{pane_id}",
+                ],
+            );
+            assert_eq!(output.unwrap(), format!("{expected}\n"), "target {target}");
+        }
+
+        assert_eq!(
+            run(&mut state, &["select-pane", "-t", "%7"]),
+            Err("can't find pane: %7".into())
+        );
+        assert_eq!(
+            run(&mut state, &["kill-session", "-t", "=other"]),
+            Err("can't find session: =other".into())
+        );
+    }
+
+    #[test]
+    fn test_kill() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d", "-s", "main"]).unwrap();
+        run(&mut state, &["split-window", "-h"]).unwrap();
+        run(&mut state, &["new-window"]).unwrap();
+
+        run(&mut state, &["kill-pane", "-t", "%0"]).unwrap();
+        let layout = run(
+            &mut state,
+            &["display-message", "-p", "-t", "@0", "#{window_layout}"],
+        );
+        assert_eq!(layout.unwrap(), "b25e,80x24,0,0,1\n");
+
+        run(&mut state, &["kill-pane", "-t", "%1"]).unwrap();
+        assert_eq!(
+            run(&mut state, &["list-windows", "-F", "#{window_id}"]).unwrap(),
+            "@1\n"
+        );
+
+        run(&mut state, &["kill-window", "-t", "@1"]).unwrap();
+        assert!(run(&mut state, &["list-sessions"]).is_err());
+    }
+
+    #[test]
+    fn test_grouped_sessions() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d", "-s", "main"]).unwrap();
+        run(
+            &mut state,
+            &["new-session", "-d", "-s", "peer", "-t", "main"],
+        )
+        .unwrap();
+        run(&mut state, &["new-window", "-t", "main"]).unwrap();
+
+        let sessions = run(
+            &mut state,
+            &[
+                "list-sessions",
+                "-F",
+                "#{session_name}:#{session_group}:#{session_windows}",
+            ],
+        );
+        assert_eq!(sessions.unwrap(), "main:main:2\npeer:main:2\n");
+
+        run(&mut state, &["kill-session", "-t", "main"]).unwrap();
+        let sessions = run(
+            &mut state,
+            &["list-sessions", "-F", "#{session_name}:#{session_group}"],
+        );
+        assert_eq!(sessions.unwrap(), "peer:\n");
+    }
+
+    #[test]
+    fn test_layouts() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d", "-x", "80", "-y", "24"]).unwrap();
+        run(&mut state, &["split-window", "-h"]).unwrap();
+        run(&mut state, &["split-window", "-h"]).unwrap();
+        run(&mut state, &["split-window", "-h"]).unwrap();
+
+        run(&mut state, &["select-layout", "tiled"]).unwrap();
+        let layout = run(&mut state, &["display-message", "-p", "#{window_layout}"]);
+        assert_eq!(
+            layout.unwrap(),
+            "30d6,80x24,0,0[80x11,0,0{39x11,0,0,0,40x11,40,0,1},80x12,0,12{39x12,0,12,2,40x12,40,12,3}]\n"
+        );
+
+        let custom = "764c,80x24,0,0{19x24,0,0,0,19x24,20,0,1,19x24,40,0,2,20x24,60,0,3}";
+        run(&mut state, &["select-layout", custom]).unwrap();
+        let layout = run(&mut state, &["display-message", "-p", "#{window_layout}"]);
+        assert_eq!(layout.unwrap(), format!("{custom}\n"));
+
+        assert_eq!(
+            run(&mut state, &["select-layout", "abcd,80x24,0,0,0"]),
+            Err("invalid layout: abcd,80x24,0,0,0".into())
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d"]).unwrap();
+
+        let options = run(&mut state, &["show-options", "-g"]).unwrap();
+        assert!(options.contains("default-command ''\ndefault-shell /bin/sh\n"));
+
+        run(
+            &mut state,
+            &["set-option", "-g", "default-shell", "/bin/zsh"],
+        )
+        .unwrap();
+        run(
+            &mut state,
+            &["set-option", "-w", "-g", "@theme", "dark blue"],
+        )
+        .unwrap();
+        assert_eq!(
+            run(&mut state, &["show-options", "-g", "-v", "default-shell"]).unwrap(),
+            "/bin/zsh\n"
+        );
+        assert_eq!(
+            run(&mut state, &["show-options", "-w", "-q", "-g", "@theme"]).unwrap(),
+            "@theme \"dark blue\"\n"
+        );
+        assert_eq!(
+            run(&mut state, &["show-options", "-w", "-q", "-g", "@missing"]).unwrap(),
+            ""
+        );
+        assert!(run(&mut state, &["show-options", "-g", "bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_command_sequences() {
+        let mut state = State::default();
+        let output = run(
+            &mut state,
+            &[
+                "new-session",
+                "-d",
+                "-s",
+                "a",
+                ";",
+                "display-message",
+                "-p",
+                "#{session_name}",
+                ";",
+                "rename-session",
+                "b;",
+            ],
+        );
+        assert_eq!(output.unwrap(), "a\n");
+
+        let output = run(&mut state, &["display-message", "-p", "x\\;"]);
+        assert_eq!(output.unwrap(), "x;\n");
+
+        let (stdout, error) = state.run(
+            &["source-file".into(), "-".into()],
+            Some(b"display-message -p one\nbogus-command\ndisplay-message -p two\n"),
+        );
+        assert_eq!(stdout, "");
+        assert_eq!(error.unwrap(), "unknown command: bogus-command");
+
+        let (stdout, error) = state.run(
+            &["source-file".into(), "-".into()],
+            Some(b"display-message -p one\ndisplay-message -p two\n"),
+        );
+        assert_eq!(stdout, "one\ntwo\n");
+        assert!(error.is_none());
+
+        assert_eq!(
+            run(&mut state, &["split-window", "-Z"]),
+            Err("split-window: unsupported flag".into())
+        );
+    }
+
+    #[test]
+    fn test_display_message_records_messages() {
+        let mut state = State::default();
+        run(&mut state, &["new-session", "-d", "-s", "main"]).unwrap();
+        run(&mut state, &["display-message", "hello #{session_name}"]).unwrap();
+
+        assert_eq!(state.messages, vec!["hello main".to_string()]);
+        assert_eq!(state.commands.len(), 2);
+    }
+}
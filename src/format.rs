@@ -0,0 +1,403 @@
+//! Tmux format strings, such as `#{pane_current_path}` or `#{s/foo/bar/:window_name}`.
+//!
+//! Formats can be evaluated by tmux in the context of a session, window or pane with
+//! [`expand`], or parsed into a [`Format`] and evaluated offline against a map of variables,
+//! which is useful for validating formats and previewing status lines without a server.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nom::{Parser, combinator::all_consuming};
+
+use crate::{
+    Result,
+    client::DisplayMessage,
+    error::{Error, map_add_intent},
+    target::Target,
+};
+
+mod eval;
+pub(crate) mod parse;
+
+/// A parsed tmux format.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::str::FromStr;
+/// use tmux_lib::format::Format;
+///
+/// let format = Format::from_str("#{?pane_active,*,} #{=5:pane_title}").unwrap();
+///
+/// let variables = HashMap::from([
+///     (String::from("pane_active"), String::from("1")),
+///     (String::from("pane_title"), String::from("build logs")),
+/// ]);
+/// assert_eq!(format.evaluate(&variables), "* build");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Format {
+    /// Successive parts of the format.
+    pub nodes: Vec<Node>,
+}
+
+/// Part of a [`Format`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Literal text, with escapes (`##`, `#,` and `#}`) already replaced.
+    Text(String),
+    /// An expression between `#{` and `}`, or an alias such as `#S`.
+    Expression {
+        /// Modifiers applied to the value of the operand, e.g. `=10` in `#{=10:pane_title}`.
+        modifiers: Vec<Modifier>,
+        /// Value of the expression, before modifiers.
+        operand: Operand,
+    },
+    /// A shell command, e.g. `#(uptime)`, which cannot be evaluated offline.
+    Shell(String),
+}
+
+/// Value of an expression, before modifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// A variable, e.g. `pane_id`.
+    Variable(String),
+    /// A nested format, e.g. `#{pane_id}` in `#{E:#{pane_id}}`.
+    Format(Format),
+    /// Literal text, from the `l:` modifier.
+    Literal(String),
+    /// A conditional, e.g. `#{?pane_active,a,b}` or `#{?c1,a,c2,b,c}`.
+    Conditional {
+        /// Conditions, either a variable or a format, and the value when true.
+        branches: Vec<(Operand, Format)>,
+        /// Value when all conditions are false.
+        otherwise: Format,
+    },
+    /// A comparison, e.g. `#{==:#{pane_index},1}`, which evaluates to `1` or `0`.
+    Comparison {
+        /// Comparison operator.
+        operator: Operator,
+        /// Left-hand side.
+        left: Format,
+        /// Right-hand side.
+        right: Format,
+    },
+}
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`, comparing strings.
+    Less,
+    /// `>`, comparing strings.
+    Greater,
+    /// `<=`, comparing strings.
+    LessOrEqual,
+    /// `>=`, comparing strings.
+    GreaterOrEqual,
+    /// `||`, true if any side is true.
+    Or,
+    /// `&&`, true if both sides are true.
+    And,
+    /// `m`, true if the left-hand side pattern matches the right-hand side.
+    Match {
+        /// The pattern is a regular expression (`m/r`) instead of a glob pattern.
+        regex: bool,
+        /// Ignore case (`m/i`).
+        ignore_case: bool,
+    },
+}
+
+/// Modifier of an expression value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Modifier {
+    /// `=N` keeps the first `N` chars, or the last ones if `N` is negative. `=/N/marker/`
+    /// adds `marker` where the value was truncated.
+    Truncate {
+        /// Number of chars to keep.
+        length: i32,
+        /// Marker shown if the value was truncated.
+        marker: Option<String>,
+    },
+    /// `pN` pads the value with spaces to `N` chars, on the left if `N` is negative.
+    Pad(i32),
+    /// `s/pattern/replacement/` replaces matches of a regular expression; `\1` in the
+    /// replacement refers to the first capture group.
+    Substitute {
+        /// Regular expression.
+        pattern: String,
+        /// Replacement of each match.
+        replacement: String,
+        /// Ignore case (`s/pattern/replacement/i`).
+        ignore_case: bool,
+    },
+    /// `t` formats a Unix timestamp like `ctime`, or with a `strftime` format (`t/f/%H:%M`).
+    /// Times are formatted in UTC.
+    Time(Option<String>),
+    /// `E` evaluates the value as a format.
+    Expand,
+    /// `b` keeps the last component of a path.
+    Basename,
+    /// `d` removes the last component of a path.
+    Dirname,
+    /// `n` replaces the value with its length.
+    Length,
+    /// `q` escapes shell special chars.
+    Quote,
+    /// Any other modifier, which tmux knows but which cannot be evaluated offline, such as the
+    /// `S:` loop over sessions. It leaves the value unchanged.
+    Other {
+        /// Name of the modifier, e.g. `S`.
+        name: String,
+        /// Arguments of the modifier.
+        arguments: Vec<String>,
+    },
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    /// Parse a tmux format into a new `Format`.
+    ///
+    /// This fails on unbalanced braces or parentheses, incomplete conditionals or comparisons,
+    /// and invalid regular expressions.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let desc = "Format";
+        let intent = "tmux format";
+
+        let (_, format) = all_consuming(parse::format)
+            .parse(input)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(format)
+    }
+}
+
+impl Format {
+    /// Evaluate the format, looking up values in `variables`. Missing variables are empty, and
+    /// shell commands evaluate to an empty string.
+    #[must_use]
+    pub fn evaluate(&self, variables: &HashMap<String, String>) -> String {
+        eval::format(self, variables, 0)
+    }
+}
+
+/// Escape `format`, so that tmux displays it as is instead of expanding it.
+///
+/// ```
+/// assert_eq!(tmux_lib::format::escape("#{pane_id}"), "##{pane_id}");
+/// ```
+#[must_use]
+pub fn escape(format: &str) -> String {
+    format.replace('#', "##")
+}
+
+/// Length of `format` once escaped by [`escape`].
+pub(crate) const fn escaped_len(format: &str) -> usize {
+    let bytes = format.as_bytes();
+    let mut len = bytes.len();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'#' {
+            len += 1;
+        }
+        index += 1;
+    }
+    len
+}
+
+/// Bytes of `format` once escaped by [`escape`], in a const context.
+pub(crate) const fn escaped_bytes<const N: usize>(format: &str) -> [u8; N] {
+    let bytes = format.as_bytes();
+    let mut escaped = [0; N];
+    let (mut index, mut offset) = (0, 0);
+    while index < bytes.len() {
+        escaped[offset] = bytes[index];
+        offset += 1;
+        if bytes[index] == b'#' {
+            escaped[offset] = b'#';
+            offset += 1;
+        }
+        index += 1;
+    }
+    escaped
+}
+
+/// Escape a constant format at compile time, producing the `&'static str` intent of parse
+/// errors.
+///
+/// This keeps the intent of `FromStr` impls in sync with the format actually passed to tmux.
+macro_rules! intent {
+    ($format:expr) => {{
+        const LEN: usize = $crate::format::escaped_len($format);
+        const BYTES: [u8; LEN] = $crate::format::escaped_bytes::<LEN>($format);
+        const INTENT: &str = match std::str::from_utf8(&BYTES) {
+            Ok(intent) => intent,
+            Err(_) => panic!("escaping a format produced invalid utf-8"),
+        };
+        INTENT
+    }};
+}
+pub(crate) use intent;
+
+/// Counter making each delimiter unique, even within the same nanosecond.
+static DELIMITER_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Return a delimiter which is very unlikely to appear in any expanded format.
+///
+/// It is made of ASCII unit separators around a nonce, and contains no character with a meaning
+/// in tmux formats (`#`, `{`, `}`, `,`, `:`).
+pub(crate) fn unique_delimiter() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or_default();
+    let count = DELIMITER_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("\x1f{:x}-{nanos:x}-{count:x}\x1f", std::process::id())
+}
+
+/// Join `formats` into a single format, separated by `delimiter`.
+fn join_formats<S: AsRef<str>>(formats: &[S], delimiter: &str) -> String {
+    formats
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join(delimiter)
+}
+
+/// Split the expansion of formats joined with [`join_formats`], checking that there is exactly
+/// one value per format.
+fn split_expansion(expansion: &str, delimiter: &str, count: usize) -> Result<Vec<String>> {
+    let values: Vec<String> = expansion.split(delimiter).map(String::from).collect();
+    if values.len() != count {
+        return Err(Error::UnexpectedTmuxOutput {
+            intent: "display-message",
+            stdout: expansion.to_string(),
+            stderr: format!("expected {count} values, got {}", values.len()),
+        });
+    }
+    Ok(values)
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the expansion of `format` in the context of `target`.
+///
+/// ```no_run
+/// # async fn cwd(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+/// let path = tmux_lib::format::expand(pane_id, "#{pane_current_path}").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn expand<'a>(target: impl Into<Target<'a>>, format: &str) -> Result<String> {
+    DisplayMessage::new(format).target(target).print().await
+}
+
+/// Return the expansions of all `formats` in the context of `target`, in order, with a single
+/// tmux invocation.
+///
+/// The formats are joined with a unique delimiter, which makes this safe even if expanded values
+/// contain newlines. An unbalanced format (such as `#{pane_id`) swallowing a delimiter is
+/// reported as an error.
+pub async fn expand_many<'a, S: AsRef<str>>(
+    target: impl Into<Target<'a>>,
+    formats: &[S],
+) -> Result<Vec<String>> {
+    if formats.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let delimiter = unique_delimiter();
+    let expansion = expand(target, &join_formats(formats, &delimiter)).await?;
+
+    split_expansion(&expansion, &delimiter, formats.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client, client_info, pane, pane_info, session, session_info, window};
+
+    /// Formats passed to tmux by this crate.
+    const CRATE_FORMATS: [&str; 9] = [
+        client::FORMAT,
+        client_info::FORMAT,
+        pane::FORMAT,
+        pane_info::FORMAT,
+        session::FORMAT,
+        session::NEW_SESSION_FORMAT,
+        session_info::FORMAT,
+        window::FORMAT,
+        window::NEW_WINDOW_FORMAT,
+    ];
+
+    #[test]
+    fn crate_formats_are_valid() {
+        for format in CRATE_FORMATS {
+            assert!(Format::from_str(format).is_ok(), "invalid format: {format}");
+        }
+    }
+
+    #[test]
+    fn intent_is_escaped_format() {
+        assert_eq!(intent!("#{pane_id}"), "##{pane_id}");
+        assert_eq!(intent!(pane::FORMAT), escape(pane::FORMAT));
+        assert_eq!(intent!("no format"), "no format");
+    }
+
+    #[test]
+    fn evaluated_format_is_parsed_back() {
+        let variables = HashMap::from(
+            [
+                ("pane_id", "%3"),
+                ("pane_index", "1"),
+                ("pane_active", "1"),
+                ("pane_title", "logs"),
+                ("pane_current_command", "tail"),
+                ("pane_current_path", "/var/log"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        let line = Format::from_str(pane::FORMAT).unwrap().evaluate(&variables);
+
+        assert_eq!(line, "%3:1:true:'logs':'tail':/var/log");
+        let parsed = pane::Pane::from_str(&line).unwrap();
+        assert!(parsed.is_active);
+        assert_eq!(parsed.command, "tail");
+    }
+
+    #[test]
+    fn delimiters_are_unique() {
+        let first = unique_delimiter();
+        let second = unique_delimiter();
+
+        assert_ne!(first, second);
+        assert!(first.starts_with('\x1f') && first.ends_with('\x1f'));
+        assert!(!first.contains(['#', '{', '}', ',', ':']));
+    }
+
+    #[test]
+    fn join_then_split_roundtrip() {
+        let delimiter = "\x1fd\x1f";
+        let joined = join_formats(&["#{pane_id}", "", "#{?pane_dead,a,b}"], delimiter);
+        assert_eq!(joined, "#{pane_id}\x1fd\x1f\x1fd\x1f#{?pane_dead,a,b}");
+
+        // Emulate the expansion by tmux, with values containing newlines.
+        let expansion = "%1\x1fd\x1f\x1fd\x1fline 1\nline 2";
+        let values = split_expansion(expansion, delimiter, 3).unwrap();
+        assert_eq!(values, ["%1", "", "line 1\nline 2"]);
+    }
+
+    #[test]
+    fn split_fails_on_swallowed_delimiter() {
+        let delimiter = "\x1fd\x1f";
+        assert!(split_expansion("%1", delimiter, 2).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! Offline evaluation of parsed tmux formats.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use regex::RegexBuilder;
+
+use super::{Format, Modifier, Node, Operand, Operator};
+
+/// Maximum nesting of `E:` expansions, protecting against variables expanding to themselves.
+const MAX_DEPTH: usize = 10;
+
+/// Shell special chars escaped by the `q:` modifier.
+const SHELL_SPECIAL_CHARS: &str = "|&;<>()$`\\\"'*?[# =%";
+
+pub(super) fn format(format: &Format, variables: &HashMap<String, String>, depth: usize) -> String {
+    format
+        .nodes
+        .iter()
+        .map(|node| match node {
+            Node::Text(text) => text.clone(),
+            Node::Expression { modifiers, operand } => {
+                expression(modifiers, operand, variables, depth)
+            }
+            Node::Shell(_) => String::new(),
+        })
+        .collect()
+}
+
+/// Values are true if they are neither empty nor `0`.
+fn is_true(value: &str) -> bool {
+    !value.is_empty() && value != "0"
+}
+
+fn operand(operand: &Operand, variables: &HashMap<String, String>, depth: usize) -> String {
+    match operand {
+        Operand::Variable(name) => variables.get(name).cloned().unwrap_or_default(),
+        Operand::Format(nested) => format(nested, variables, depth),
+        Operand::Literal(text) => text.clone(),
+        Operand::Conditional {
+            branches,
+            otherwise,
+        } => branches
+            .iter()
+            .find(|(condition, _)| is_true(&self::operand(condition, variables, depth)))
+            .map_or_else(
+                || format(otherwise, variables, depth),
+                |(_, value)| format(value, variables, depth),
+            ),
+        Operand::Comparison {
+            operator,
+            left,
+            right,
+        } => {
+            let left = format(left, variables, depth);
+            let right = format(right, variables, depth);
+            let result = match operator {
+                Operator::Equal => left == right,
+                Operator::NotEqual => left != right,
+                Operator::Less => left < right,
+                Operator::Greater => left > right,
+                Operator::LessOrEqual => left <= right,
+                Operator::GreaterOrEqual => left >= right,
+                Operator::Or => is_true(&left) || is_true(&right),
+                Operator::And => is_true(&left) && is_true(&right),
+                Operator::Match {
+                    regex: true,
+                    ignore_case,
+                } => RegexBuilder::new(&left)
+                    .case_insensitive(*ignore_case)
+                    .build()
+                    .is_ok_and(|regex| regex.is_match(&right)),
+                Operator::Match {
+                    regex: false,
+                    ignore_case: true,
+                } => glob_match(&left.to_lowercase(), &right.to_lowercase()),
+                Operator::Match { .. } => glob_match(&left, &right),
+            };
+            String::from(if result { "1" } else { "0" })
+        }
+    }
+}
+
+/// Apply modifiers in the same order as tmux, regardless of their order in the format.
+fn expression(
+    modifiers: &[Modifier],
+    operand: &Operand,
+    variables: &HashMap<String, String>,
+    depth: usize,
+) -> String {
+    let mut value = self::operand(operand, variables, depth);
+
+    for modifier in modifiers {
+        match modifier {
+            Modifier::Time(time_format) => value = time(&value, time_format.as_deref()),
+            Modifier::Basename => value = basename(&value).to_string(),
+            Modifier::Dirname => value = dirname(&value).to_string(),
+            Modifier::Quote => value = quote(&value),
+            _ => {}
+        }
+    }
+    if modifiers.contains(&Modifier::Expand) {
+        value = if depth < MAX_DEPTH {
+            Format::from_str(&value)
+                .map(|expanded| format(&expanded, variables, depth + 1))
+                .unwrap_or(value)
+        } else {
+            String::new()
+        };
+    }
+    for modifier in modifiers {
+        if let Modifier::Substitute {
+            pattern,
+            replacement,
+            ignore_case,
+        } = modifier
+            && let Ok(regex) = RegexBuilder::new(pattern)
+                .case_insensitive(*ignore_case)
+                .build()
+        {
+            value = regex
+                .replace_all(&value, replacement_template(replacement))
+                .into_owned();
+        }
+    }
+    for modifier in modifiers {
+        match modifier {
+            Modifier::Truncate { length, marker } => {
+                value = truncate(&value, *length, marker.as_deref());
+            }
+            Modifier::Pad(width) => value = pad(&value, *width),
+            _ => {}
+        }
+    }
+    if modifiers.contains(&Modifier::Length) {
+        value = value.chars().count().to_string();
+    }
+
+    value
+}
+
+/// Convert a tmux replacement, using `\1` for captures, into a `regex` one, using `${1}`.
+fn replacement_template(replacement: &str) -> String {
+    let mut template = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => template.push_str("$$"),
+            '\\' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let digit = chars.next().unwrap();
+                template.push_str(&format!("${{{digit}}}"));
+            }
+            c => template.push(c),
+        }
+    }
+    template
+}
+
+fn truncate(value: &str, length: i32, marker: Option<&str>) -> String {
+    let count = value.chars().count();
+    let keep = length.unsigned_abs() as usize;
+    if keep >= count {
+        return value.to_string();
+    }
+    let marker = marker.unwrap_or_default();
+    if length >= 0 {
+        let kept: String = value.chars().take(keep).collect();
+        format!("{kept}{marker}")
+    } else {
+        let kept: String = value.chars().skip(count - keep).collect();
+        format!("{marker}{kept}")
+    }
+}
+
+fn pad(value: &str, width: i32) -> String {
+    let count = value.chars().count();
+    let padding = " ".repeat((width.unsigned_abs() as usize).saturating_sub(count));
+    if width >= 0 {
+        format!("{value}{padding}")
+    } else {
+        format!("{padding}{value}")
+    }
+}
+
+fn basename(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { "." } else { "/" };
+    }
+    trimmed.rsplit('/').next().unwrap_or(trimmed)
+}
+
+fn dirname(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        None if trimmed.is_empty() && !path.is_empty() => "/",
+        None => ".",
+        Some(0) => "/",
+        Some(offset) => trimmed[..offset].trim_end_matches('/'),
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    for c in value.chars() {
+        if SHELL_SPECIAL_CHARS.contains(c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// Match `text` against a glob `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format the Unix timestamp `value` in UTC, like `ctime` by default. Values which are not
+/// timestamps evaluate to an empty string, like in tmux.
+fn time(value: &str, time_format: Option<&str>) -> String {
+    let Ok(timestamp) = value.parse::<i64>() else {
+        return String::new();
+    };
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+    let month_name = MONTHS[(month - 1) as usize];
+
+    let mut formatted = String::new();
+    let mut chars = time_format.unwrap_or("%a %b %e %H:%M:%S %Y").chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => formatted.push_str(&year.to_string()),
+            Some('y') => formatted.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => formatted.push_str(&format!("{month:02}")),
+            Some('d') => formatted.push_str(&format!("{day:02}")),
+            Some('e') => formatted.push_str(&format!("{day:2}")),
+            Some('H') => formatted.push_str(&format!("{hour:02}")),
+            Some('M') => formatted.push_str(&format!("{minute:02}")),
+            Some('S') => formatted.push_str(&format!("{second:02}")),
+            Some('a') => formatted.push_str(weekday),
+            Some('b') => formatted.push_str(month_name),
+            Some('s') => formatted.push_str(value),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                formatted.push('%');
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+    formatted
+}
+
+/// Convert a number of days since the Unix epoch into a (year, month, day) date, with the
+/// algorithm from <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(input: &str, variables: &[(&str, &str)]) -> String {
+        let variables = variables
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Format::from_str(input).unwrap().evaluate(&variables)
+    }
+
+    #[test]
+    fn evaluate_variables_and_escapes() {
+        let variables = [("pane_id", "%3"), ("session_name", "work")];
+
+        assert_eq!(evaluate("#{pane_id}", &variables), "%3");
+        assert_eq!(evaluate("#S:#{missing}##", &variables), "work:#");
+        assert_eq!(evaluate("#(uptime)", &variables), "");
+    }
+
+    #[test]
+    fn evaluate_conditionals() {
+        let variables = [("active", "1"), ("dead", "0"), ("name", "vim")];
+
+        assert_eq!(evaluate("#{?active,yes,no}", &variables), "yes");
+        assert_eq!(evaluate("#{?dead,yes,no}", &variables), "no");
+        assert_eq!(evaluate("#{?missing,yes}", &variables), "");
+        assert_eq!(evaluate("#{?dead,a,active,b,c}", &variables), "b");
+        assert_eq!(evaluate("#{?#{name},[#{name}#,],-}", &variables), "[vim,]");
+        assert_eq!(
+            evaluate("#{?#{==:#{name},vim},editor,other}", &variables),
+            "editor"
+        );
+    }
+
+    #[test]
+    fn evaluate_comparisons() {
+        let variables = [("a", "1"), ("b", "2"), ("title", "Build Logs")];
+
+        assert_eq!(evaluate("#{==:#{a},1}", &variables), "1");
+        assert_eq!(evaluate("#{!=:#{a},#{b}}", &variables), "1");
+        assert_eq!(evaluate("#{<:#{a},#{b}}", &variables), "1");
+        assert_eq!(evaluate("#{>=:#{a},#{b}}", &variables), "0");
+        assert_eq!(evaluate("#{||:#{missing},#{a}}", &variables), "1");
+        assert_eq!(evaluate("#{&&:#{missing},#{a}}", &variables), "0");
+        assert_eq!(evaluate("#{m:*Logs,#{title}}", &variables), "1");
+        assert_eq!(evaluate("#{m/i:*logs,#{title}}", &variables), "1");
+        assert_eq!(evaluate("#{m/r:^B.*s$,#{title}}", &variables), "1");
+        assert_eq!(evaluate("#{m:B?ild,#{title}}", &variables), "0");
+    }
+
+    #[test]
+    fn evaluate_modifiers() {
+        let variables = [
+            ("title", "build logs"),
+            ("path", "/home/user/src/"),
+            ("status", "#{?active,on,off}"),
+            ("active", "1"),
+            ("loop", "#{E:loop}"),
+        ];
+
+        assert_eq!(evaluate("#{=5:title}", &variables), "build");
+        assert_eq!(evaluate("#{=-4:title}", &variables), "logs");
+        assert_eq!(evaluate("#{=/5/...:title}", &variables), "build...");
+        assert_eq!(evaluate("#{=/20/...:title}", &variables), "build logs");
+        assert_eq!(evaluate("[#{p12:title}]", &variables), "[build logs  ]");
+        assert_eq!(evaluate("[#{p-12:title}]", &variables), "[  build logs]");
+        assert_eq!(
+            evaluate("#{s/(\\w+) (\\w+)/\\2 \\1/:title}", &variables),
+            "logs build"
+        );
+        assert_eq!(
+            evaluate("#{s/B/b/i;s/l/L/:title}", &variables),
+            "buiLd Logs"
+        );
+        assert_eq!(evaluate("#{b:path}", &variables), "src");
+        assert_eq!(evaluate("#{d:path}", &variables), "/home/user");
+        assert_eq!(evaluate("#{n:title}", &variables), "10");
+        assert_eq!(evaluate("#{q:title}", &variables), "build\\ logs");
+        assert_eq!(evaluate("#{status}", &variables), "#{?active,on,off}");
+        assert_eq!(evaluate("#{E:status}", &variables), "on");
+        assert_eq!(evaluate("#{E:loop}", &variables), "");
+        assert_eq!(evaluate("#{l:#{title}}", &variables), "#{title}");
+        assert_eq!(evaluate("#{S:#{session_name}}", &variables), "");
+    }
+
+    #[test]
+    fn evaluate_times() {
+        let variables = [("created", "1792326350"), ("epoch", "0"), ("none", "")];
+
+        assert_eq!(
+            evaluate("#{t:created}", &variables),
+            "Sun Oct 18 12:25:50 2026"
+        );
+        assert_eq!(
+            evaluate("#{t/f/%Y-%m-%d %H#:%M:epoch}", &variables),
+            "1970-01-01 00:00"
+        );
+        assert_eq!(evaluate("#{t:none}", &variables), "");
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*c", "abbbc"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a*d", "abc"));
+        assert!(glob_match("*.rs", "main.rs"));
+    }
+}
@@ -0,0 +1,463 @@
+//! Parser of tmux format strings, following the behavior of `format.c` in tmux.
+//!
+//! Formats are mostly free text, hence this parser scans the input char by char rather than
+//! combining nom parsers, but it reports errors as nom errors, like all parsers of this crate.
+
+use nom::{
+    IResult,
+    error::{Error as NomError, ErrorKind},
+};
+use regex::Regex;
+
+use super::{Format, Modifier, Node, Operand, Operator};
+
+/// Aliases such as `#S`, standing for `#{session_name}`.
+const ALIASES: [(char, &str); 9] = [
+    ('D', "pane_id"),
+    ('F', "window_flags"),
+    ('H', "host"),
+    ('I', "window_index"),
+    ('P', "pane_index"),
+    ('S', "session_name"),
+    ('T', "pane_title"),
+    ('W', "window_name"),
+    ('h', "host_short"),
+];
+
+fn failure(input: &str, kind: ErrorKind) -> nom::Err<NomError<&str>> {
+    nom::Err::Failure(NomError::new(input, kind))
+}
+
+/// Return the byte offset of the first char of `input` in `stops`, outside of any `#{...}` and
+/// escape sequence, or `None` if there is none.
+fn skip(input: &str, stops: &[char]) -> Option<usize> {
+    let mut depth: usize = 0;
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        if c == '#'
+            && let Some(&(_, next)) = chars.peek()
+        {
+            if next == '{' {
+                depth += 1;
+            }
+            if [',', '#', '{', '}', ':'].contains(&next) {
+                chars.next();
+                continue;
+            }
+        }
+        if c == '}' {
+            // An unbalanced closing brace ends the enclosing expression.
+            if depth == 0 && stops.contains(&c) {
+                return Some(offset);
+            }
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if depth == 0 && stops.contains(&c) {
+            return Some(offset);
+        }
+    }
+    None
+}
+
+/// Remove the `#` escaping chars such as `,` or `:` in modifier arguments.
+fn unescape(input: &str) -> String {
+    let mut unescaped = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '#' && chars.peek().is_some_and(|next| ",#{}:".contains(*next)) {
+            continue;
+        }
+        unescaped.push(c);
+    }
+    unescaped
+}
+
+/// Split `input` at each top-level `,`.
+fn split_arguments(input: &str) -> Vec<&str> {
+    let mut arguments = vec![];
+    let mut rest = input;
+    while let Some(offset) = skip(rest, &[',']) {
+        arguments.push(&rest[..offset]);
+        rest = &rest[offset + 1..];
+    }
+    arguments.push(rest);
+    arguments
+}
+
+/// Parse a whole format.
+pub(crate) fn format(input: &str) -> IResult<&str, Format> {
+    let mut nodes = vec![];
+    let mut text = String::new();
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        if c != '#' {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let after = &rest[1..];
+        match after.chars().next() {
+            Some(escaped @ ('#' | ',' | '}')) => {
+                text.push(escaped);
+                rest = &after[1..];
+            }
+            Some('{') => {
+                let body = &after[1..];
+                let end = skip(body, &['}']).ok_or_else(|| failure(rest, ErrorKind::Char))?;
+                flush(&mut text, &mut nodes);
+                nodes.push(expression(&body[..end])?);
+                rest = &body[end + 1..];
+            }
+            Some('(') => {
+                let body = &after[1..];
+                let end = shell_end(body).ok_or_else(|| failure(rest, ErrorKind::Char))?;
+                flush(&mut text, &mut nodes);
+                nodes.push(Node::Shell(body[..end].to_string()));
+                rest = &body[end + 1..];
+            }
+            Some(alias) if ALIASES.iter().any(|&(a, _)| a == alias) => {
+                let (_, name) = ALIASES.iter().find(|&&(a, _)| a == alias).unwrap();
+                flush(&mut text, &mut nodes);
+                nodes.push(Node::Expression {
+                    modifiers: vec![],
+                    operand: Operand::Variable(name.to_string()),
+                });
+                rest = &after[1..];
+            }
+            // Like tmux, keep any other `#` as is.
+            _ => {
+                text.push('#');
+                rest = after;
+            }
+        }
+    }
+    flush(&mut text, &mut nodes);
+
+    Ok((rest, Format { nodes }))
+}
+
+fn flush(text: &mut String, nodes: &mut Vec<Node>) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(std::mem::take(text)));
+    }
+}
+
+/// Return the offset of the `)` closing a shell command, allowing nested parentheses.
+fn shell_end(input: &str) -> Option<usize> {
+    let mut depth: usize = 0;
+    for (offset, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(offset),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse a complete nested format, which must consume all of `input`.
+fn nested(input: &str) -> Result<Format, nom::Err<NomError<&str>>> {
+    let (_, format) = format(input)?;
+    Ok(format)
+}
+
+/// Parse a variable name, or a nested format if it starts with `#`.
+fn operand(input: &str) -> Result<Operand, nom::Err<NomError<&str>>> {
+    if input.starts_with('#') {
+        Ok(Operand::Format(nested(input)?))
+    } else {
+        Ok(Operand::Variable(input.to_string()))
+    }
+}
+
+/// Parse the body of `#{...}`, without the enclosing braces.
+fn expression(input: &str) -> Result<Node, nom::Err<NomError<&str>>> {
+    if let Some(body) = input.strip_prefix('?') {
+        return conditional(body);
+    }
+
+    let Some((raw_modifiers, body)) = modifiers(input) else {
+        return Ok(Node::Expression {
+            modifiers: vec![],
+            operand: operand(input)?,
+        });
+    };
+
+    let mut modifiers = vec![];
+    let mut literal = false;
+    let mut operator = None;
+
+    for (name, arguments) in raw_modifiers {
+        match (name, arguments.as_slice()) {
+            ("l", []) => literal = true,
+            ("==", []) => operator = Some(Operator::Equal),
+            ("!=", []) => operator = Some(Operator::NotEqual),
+            ("<", []) => operator = Some(Operator::Less),
+            (">", []) => operator = Some(Operator::Greater),
+            ("<=", []) => operator = Some(Operator::LessOrEqual),
+            (">=", []) => operator = Some(Operator::GreaterOrEqual),
+            ("||", []) => operator = Some(Operator::Or),
+            ("&&", []) => operator = Some(Operator::And),
+            ("m", flags) => {
+                let flags = flags.first().copied().unwrap_or_default();
+                operator = Some(Operator::Match {
+                    regex: flags.contains('r'),
+                    ignore_case: flags.contains('i'),
+                });
+            }
+            ("E", []) => modifiers.push(Modifier::Expand),
+            ("b", []) => modifiers.push(Modifier::Basename),
+            ("d", []) => modifiers.push(Modifier::Dirname),
+            ("n", []) => modifiers.push(Modifier::Length),
+            ("q", []) => modifiers.push(Modifier::Quote),
+            ("t", []) => modifiers.push(Modifier::Time(None)),
+            ("t", [flags, time_format, ..]) if flags.contains('f') => {
+                modifiers.push(Modifier::Time(Some(unescape(time_format))));
+            }
+            ("t", [_]) => modifiers.push(Modifier::Time(None)),
+            ("=", [length]) | ("=", [length, _]) => {
+                let length = length
+                    .parse()
+                    .map_err(|_| failure(input, ErrorKind::Digit))?;
+                let marker = arguments.get(1).map(|marker| marker.to_string());
+                modifiers.push(Modifier::Truncate { length, marker });
+            }
+            ("p", [width]) => {
+                let width = width
+                    .parse()
+                    .map_err(|_| failure(input, ErrorKind::Digit))?;
+                modifiers.push(Modifier::Pad(width));
+            }
+            ("s", [pattern, replacement]) | ("s", [pattern, replacement, _]) => {
+                let ignore_case = arguments.get(2).is_some_and(|flags| flags.contains('i'));
+                Regex::new(pattern).map_err(|_| failure(input, ErrorKind::Verify))?;
+                modifiers.push(Modifier::Substitute {
+                    pattern: pattern.to_string(),
+                    replacement: replacement.to_string(),
+                    ignore_case,
+                });
+            }
+            (name, arguments) => modifiers.push(Modifier::Other {
+                name: name.to_string(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect(),
+            }),
+        }
+    }
+
+    let operand = if literal {
+        Operand::Literal(body.to_string())
+    } else if let Some(operator) = operator {
+        let arguments = split_arguments(body);
+        let [left, right] = arguments[..] else {
+            return Err(failure(body, ErrorKind::Count));
+        };
+        Operand::Comparison {
+            operator,
+            left: nested(left)?,
+            right: nested(right)?,
+        }
+    } else {
+        operand(body)?
+    };
+
+    Ok(Node::Expression { modifiers, operand })
+}
+
+/// Parse the body of `#{?cond1,value1,cond2,value2,...,otherwise}`, without the `?`.
+fn conditional(input: &str) -> Result<Node, nom::Err<NomError<&str>>> {
+    let arguments = split_arguments(input);
+    if arguments.len() < 2 {
+        return Err(failure(input, ErrorKind::Count));
+    }
+
+    let mut branches = vec![];
+    let mut pairs = arguments.chunks_exact(2);
+    for pair in pairs.by_ref() {
+        branches.push((operand(pair[0])?, nested(pair[1])?));
+    }
+    let otherwise = match pairs.remainder() {
+        [otherwise] => nested(otherwise)?,
+        _ => Format::default(),
+    };
+
+    Ok(Node::Expression {
+        modifiers: vec![],
+        operand: Operand::Conditional {
+            branches,
+            otherwise,
+        },
+    })
+}
+
+/// Modifier name and its arguments, e.g. `("s", ["foo", "bar"])` for `s/foo/bar/`.
+type RawModifier<'a> = (&'a str, Vec<&'a str>);
+
+/// Split the modifiers, such as `=10;s/a/b/:`, from the rest of the expression.
+///
+/// This returns `None` if `input` has no modifiers, which is a port of
+/// `format_build_modifiers` in tmux.
+fn modifiers(input: &str) -> Option<(Vec<RawModifier<'_>>, &str)> {
+    let is_end = |c: Option<char>| matches!(c, Some(';' | ':'));
+    let mut modifiers = vec![];
+    let mut rest = input;
+
+    loop {
+        if let Some(after) = rest.strip_prefix(';') {
+            rest = after;
+        }
+        let mut chars = rest.chars();
+        let first = chars.next()?;
+        if first == ':' {
+            return Some((modifiers, &rest[1..]));
+        }
+        let second = chars.next();
+
+        // Single char modifiers without arguments.
+        if "labcdnwETSWPL<>".contains(first) && is_end(second) {
+            modifiers.push((&rest[..1], vec![]));
+            rest = &rest[1..];
+            continue;
+        }
+
+        // Double char modifiers without arguments.
+        if ["||", "&&", "!=", "==", "<=", ">="]
+            .iter()
+            .any(|operator| rest.starts_with(operator))
+            && is_end(chars.next())
+        {
+            modifiers.push((&rest[..2], vec![]));
+            rest = &rest[2..];
+            continue;
+        }
+
+        // Single char modifiers with arguments.
+        if !"mCNst=peq".contains(first) {
+            return None;
+        }
+        let name = &rest[..1];
+        rest = &rest[1..];
+        let second = second?;
+        if is_end(Some(second)) {
+            modifiers.push((name, vec![]));
+            continue;
+        }
+
+        if !second.is_ascii_punctuation() || second == '-' {
+            // A single argument, without delimiter, e.g. `=10`.
+            let end = skip(rest, &[':', ';'])?;
+            modifiers.push((name, vec![&rest[..end]]));
+            rest = &rest[end..];
+        } else {
+            // Arguments delimited by `second`, e.g. `s/foo/bar/` or `m/ri`.
+            let mut arguments = vec![];
+            loop {
+                let mut chars = rest.chars();
+                if chars.next() == Some(second) && is_end(chars.next()) {
+                    rest = &rest[1..];
+                    break;
+                }
+                let end = skip(&rest[1..], &[second, ';', ':'])? + 1;
+                arguments.push(&rest[1..end]);
+                rest = &rest[end..];
+                if is_end(rest.chars().next()) {
+                    break;
+                }
+            }
+            modifiers.push((name, arguments));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip() {
+        assert_eq!(skip("abc,def", &[',']), Some(3));
+        assert_eq!(skip("#{a,b},c", &[',']), Some(6));
+        assert_eq!(skip("a#,b,c", &[',']), Some(4));
+        assert_eq!(skip("#{a}}", &['}']), Some(4));
+        assert_eq!(skip("abc", &[',']), None);
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        assert_eq!(split_arguments("a,#{b,c},d"), ["a", "#{b,c}", "d"]);
+        assert_eq!(split_arguments(""), [""]);
+    }
+
+    #[test]
+    fn test_modifiers() {
+        assert_eq!(modifiers("pane_id"), None);
+        assert_eq!(
+            modifiers("t:window_activity"),
+            Some((vec![("t", vec![])], "window_activity"))
+        );
+        assert_eq!(
+            modifiers("=10;s/a/b/:pane_title"),
+            Some((vec![("=", vec!["10"]), ("s", vec!["a", "b"])], "pane_title"))
+        );
+        assert_eq!(
+            modifiers("=/-5/...:pane_title"),
+            Some((vec![("=", vec!["-5", "..."])], "pane_title"))
+        );
+        assert_eq!(
+            modifiers("==:#{a},b"),
+            Some((vec![("==", vec![])], "#{a},b"))
+        );
+        assert_eq!(
+            modifiers("s/x/y/i:a"),
+            Some((vec![("s", vec!["x", "y", "i"])], "a"))
+        );
+        assert_eq!(
+            modifiers("m/ri:^a,b"),
+            Some((vec![("m", vec!["ri"])], "^a,b"))
+        );
+    }
+
+    #[test]
+    fn parse_text_and_escapes() {
+        let (rest, parsed) = format("a##b#,c#}d#x").unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(parsed.nodes, [Node::Text(String::from("a#b,c}d#x"))]);
+    }
+
+    #[test]
+    fn parse_aliases_and_shell() {
+        let (_, parsed) = format("#S:#(echo (a))").unwrap();
+
+        assert_eq!(
+            parsed.nodes,
+            [
+                Node::Expression {
+                    modifiers: vec![],
+                    operand: Operand::Variable(String::from("session_name")),
+                },
+                Node::Text(String::from(":")),
+                Node::Shell(String::from("echo (a)")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_fails_on_unbalanced_braces() {
+        assert!(format("#{pane_id").is_err());
+        assert!(format("#{?pane_active,a}").is_ok());
+        assert!(format("#{?pane_active}").is_err());
+    }
+
+    #[test]
+    fn parse_fails_on_invalid_regex() {
+        assert!(format("#{s/(/x/:pane_title}").is_err());
+    }
+}
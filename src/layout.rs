@@ -9,7 +9,10 @@
 //! "41e9,279x71,0,0[279x40,0,0,71,279x30,0,41{147x30,0,41,72,131x30,148,41,73}]"
 //! ```
 //!
-//! The parser in this module returns the corresponding [`WindowLayout`].
+//! The parser in this module returns the corresponding [`WindowLayout`], which renders back to
+//! the same string with `Display`.
+
+use std::fmt;
 
 use nom::{
     IResult, Parser,
@@ -20,18 +23,25 @@ use nom::{
     sequence::delimited,
 };
 
-use crate::{Result, error::map_add_intent};
+use crate::{Result, error::map_add_intent, pane_id::PaneId};
 
 /// Represent a parsed window layout.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WindowLayout {
     /// 4-char hex id, such as `9f58`.
     id: u16,
     /// Container.
-    container: Container,
+    pub(crate) container: Container,
 }
 
 impl WindowLayout {
+    /// Return the layout of `container`, with its checksum as id.
+    #[cfg(any(test, feature = "fake"))]
+    pub(crate) fn new(container: Container) -> Self {
+        let id = checksum(&container.to_string());
+        Self { id, container }
+    }
+
     /// Return a flat list of pane ids.
     #[must_use]
     pub fn pane_ids(&self) -> Vec<u16> {
@@ -40,20 +50,29 @@ impl WindowLayout {
         acc
     }
 
+    /// Return the width and height of the pane `pane_id`, if it is in this layout.
+    #[must_use]
+    pub fn pane_size(&self, pane_id: &PaneId) -> Option<(u16, u16)> {
+        self.container.find(pane_id).map(|c| {
+            let Dimensions { width, height } = c.dimensions;
+            (width, height)
+        })
+    }
+
     /// Walk the structure, searching for pane ids.
     fn walk(&self, acc: &mut Vec<u16>) {
         self.container.walk(acc);
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Container {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Container {
     /// Dimensions of the container.
-    dimensions: Dimensions,
+    pub(crate) dimensions: Dimensions,
     /// Offset of the top left corner of the container.
-    coordinates: Coordinates,
+    pub(crate) coordinates: Coordinates,
     /// Either a pane, or a horizontal or vertical split.
-    element: Element,
+    pub(crate) element: Element,
 }
 
 impl Container {
@@ -61,27 +80,37 @@ impl Container {
     fn walk(&self, acc: &mut Vec<u16>) {
         self.element.walk(acc);
     }
+
+    /// Return the container of the pane `pane_id`.
+    fn find(&self, pane_id: &PaneId) -> Option<&Self> {
+        match &self.element {
+            Element::Pane { pane_id: id } => (PaneId::from(id) == *pane_id).then_some(self),
+            Element::Horizontal(split) | Element::Vertical(split) => {
+                split.elements.iter().find_map(|c| c.find(pane_id))
+            }
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Dimensions {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Dimensions {
     /// Width (of the window or pane).
-    width: u16,
+    pub(crate) width: u16,
     /// Height (of the window or pane).
-    height: u16,
+    pub(crate) height: u16,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Coordinates {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Coordinates {
     /// Horizontal offset of the top left corner (of the window or pane).
-    x: u16,
+    pub(crate) x: u16,
     /// Vertical offset of the top left corner (of the window or pane).
-    y: u16,
+    pub(crate) y: u16,
 }
 
 /// Element in a container.
-#[derive(Debug, PartialEq, Eq)]
-enum Element {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Element {
     /// A pane.
     Pane { pane_id: u16 },
     /// A horizontal split.
@@ -102,10 +131,10 @@ impl Element {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Split {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Split {
     /// Embedded containers.
-    elements: Vec<Container>,
+    pub(crate) elements: Vec<Container>,
 }
 
 impl Split {
@@ -117,6 +146,42 @@ impl Split {
     }
 }
 
+impl fmt::Display for WindowLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x},{}", self.id, self.container)
+    }
+}
+
+impl fmt::Display for Container {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Dimensions { width, height } = self.dimensions;
+        let Coordinates { x, y } = self.coordinates;
+        write!(f, "{width}x{height},{x},{y}")?;
+
+        let (open, split, close) = match &self.element {
+            Element::Pane { pane_id } => return write!(f, ",{pane_id}"),
+            Element::Horizontal(split) => ('{', split, '}'),
+            Element::Vertical(split) => ('[', split, ']'),
+        };
+        write!(f, "{open}")?;
+        for (index, container) in split.elements.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{container}")?;
+        }
+        write!(f, "{close}")
+    }
+}
+
+/// Checksum of a layout, which tmux prints as the layout id.
+#[cfg(any(test, feature = "fake"))]
+pub(crate) fn checksum(layout: &str) -> u16 {
+    layout.bytes().fold(0, |csum: u16, byte| {
+        csum.rotate_right(1).wrapping_add(u16::from(byte))
+    })
+}
+
 /// Parse the Tmux layout string description and return the pane-ids.
 ///
 /// ```
@@ -203,6 +268,7 @@ mod tests {
         Container, Coordinates, Dimensions, Element, Split, WindowLayout, coordinates, dimensions,
         layout_id, single_pane, vert_split, window_layout,
     };
+    use crate::pane_id::PaneId;
 
     #[test]
     fn test_parse_layout_id() {
@@ -358,4 +424,30 @@ mod tests {
         let expected = vec![71, 72, 73];
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_pane_size() {
+        let input = "41e9,279x71,0,0[279x40,0,0,71,279x30,0,41{147x30,0,41,72,131x30,148,41,73}]";
+        let (_, layout) = window_layout(input).unwrap();
+
+        assert_eq!(layout.pane_size(&PaneId::from(&72)), Some((147, 30)));
+        assert_eq!(layout.pane_size(&PaneId::from(&71)), Some((279, 40)));
+        assert_eq!(layout.pane_size(&PaneId::from(&1)), None);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let input = "41e9,279x71,0,0[279x40,0,0,71,279x30,0,41{147x30,0,41,72,131x30,148,41,73}]";
+        let (_, layout) = window_layout(input).unwrap();
+
+        assert_eq!(layout.to_string(), input);
+    }
+
+    #[test]
+    fn test_new_computes_checksum() {
+        let (_, layout) = window_layout("0000,80x24,0,0{40x24,0,0,0,39x24,41,0,1}").unwrap();
+
+        let actual = WindowLayout::new(layout.container).to_string();
+        assert_eq!(actual, "8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1}");
+    }
 }
@@ -5,16 +5,42 @@
 
 pub mod error;
 
+pub mod asciicast;
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
+pub mod client_info;
+pub mod command;
+pub mod config;
+pub mod control;
+pub mod environment;
+#[cfg(feature = "fake")]
+pub mod fake;
+#[allow(deprecated)]
 pub use client::display_message;
+pub mod format;
 pub mod layout;
 pub mod pane;
 pub mod pane_id;
+pub mod pane_info;
+pub mod pane_log;
 pub(crate) mod parse;
+pub mod policy;
+pub(crate) mod runtime;
 pub mod server;
 pub mod session;
 pub mod session_id;
+pub mod session_info;
+pub mod target;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+#[cfg(feature = "tracing")]
+pub(crate) mod trace;
+pub mod ui;
 pub mod utils;
+pub mod version;
+pub mod watch;
 pub mod window;
 pub mod window_id;
 
@@ -3,7 +3,9 @@
 //! The main use cases are running Tmux commands & parsing Tmux panes
 //! information.
 
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::process::Output;
 use std::str::FromStr;
 
 use nom::{
@@ -12,16 +14,22 @@ use nom::{
     combinator::{all_consuming, map_res},
 };
 use serde::{Deserialize, Serialize};
-use smol::process::Command;
 
 use crate::{
     Result,
     error::{Error, check_empty_process_output, check_process_success, map_add_intent},
+    format::intent,
     pane_id::{PaneId, parse::pane_id},
     parse::{boolean, quoted_nonempty_string, quoted_string},
+    policy::Retry,
+    runtime::Command,
+    version::{self, Capability},
     window_id::WindowId,
 };
 
+/// Format of the panes listed by tmux, parsed by `Pane::from_str`.
+pub(crate) const FORMAT: &str = "#{pane_id}:#{pane_index}:#{?pane_active,true,false}:'#{pane_title}':'#{pane_current_command}':#{pane_current_path}";
+
 /// A Tmux pane.
 ///
 /// ```
@@ -78,7 +86,7 @@ impl FromStr for Pane {
     /// definitions.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "Pane";
-        let intent = "##{pane_id}:##{pane_index}:##{?pane_active,true,false}:'##{pane_title}':'##{pane_current_command}':##{pane_current_path}";
+        let intent = intent!(FORMAT);
 
         let (_, pane) = all_consuming(parse::pane)
             .parse(input)
@@ -97,7 +105,18 @@ impl Pane {
     /// processed by the function `tmux_lib::utils::cleanup_captured_buffer`.
     ///
     pub async fn capture(&self) -> Result<Vec<u8>> {
-        let args = vec![
+        let output = Command::new("tmux")
+            .args(self.capture_args())
+            .retry(Retry::Lookups)
+            .output()
+            .await?;
+
+        Ok(output.stdout)
+    }
+
+    /// Arguments of `capture-pane` for [`Pane::capture`].
+    pub(crate) fn capture_args(&self) -> Vec<&str> {
+        vec![
             "capture-pane",
             "-t",
             self.id.as_str(),
@@ -108,11 +127,7 @@ impl Pane {
             "-",  // start of history
             "-E", // ending line number
             "-",  // end of history
-        ];
-
-        let output = Command::new("tmux").args(&args).output().await?;
-
-        Ok(output.stdout)
+        ]
     }
 }
 
@@ -155,21 +170,16 @@ pub(crate) mod parse {
 
 /// Return a list of all `Pane` from all sessions.
 pub async fn available_panes() -> Result<Vec<Pane>> {
-    let args = vec![
-        "list-panes",
-        "-a",
-        "-F",
-        "#{pane_id}\
-        :#{pane_index}\
-        :#{?pane_active,true,false}\
-        :'#{pane_title}'\
-        :'#{pane_current_command}'\
-        :#{pane_current_path}",
-    ];
+    let args = vec!["list-panes", "-a", "-F", FORMAT];
 
     let output = Command::new("tmux").args(&args).output().await?;
     let buffer = String::from_utf8(output.stdout)?;
 
+    parse_panes(&buffer)
+}
+
+/// Parse the output of `list-panes` with [`FORMAT`].
+pub(crate) fn parse_panes(buffer: &str) -> Result<Vec<Pane>> {
     // Each call to `Pane::parse` returns a `Result<Pane, _>`. All results
     // are collected into a Result<Vec<Pane>, _>, thanks to `collect()`.
     let result: Result<Vec<Pane>> = buffer
@@ -183,36 +193,250 @@ pub async fn available_panes() -> Result<Vec<Pane>> {
 
 /// Create a new pane (horizontal split) in the window with `window_id`, and return the new
 /// pane id.
+///
+/// See [`SplitPane`] for more options.
 pub async fn new_pane(
     reference_pane: &Pane,
     pane_command: Option<&str>,
     window_id: &WindowId,
 ) -> Result<PaneId> {
-    let mut args = vec![
-        "split-window",
-        "-h",
-        "-c",
-        reference_pane.dirpath.to_str().unwrap(),
-        "-t",
-        window_id.as_str(),
-        "-P",
-        "-F",
-        "#{pane_id}",
-    ];
+    let mut split_pane = SplitPane::new()
+        .target_window(window_id)
+        .direction(SplitDirection::Horizontal)
+        .start_directory(&reference_pane.dirpath)
+        .detached(false);
     if let Some(pane_command) = pane_command {
-        args.push(pane_command);
+        split_pane = split_pane.command(pane_command);
     }
 
-    let output = Command::new("tmux").args(&args).output().await?;
+    split_pane.run().await
+}
 
-    // Check exit status before parsing to avoid confusing parse errors
-    // when tmux fails and returns empty/garbage stdout.
-    check_process_success(&output, "split-window")?;
+/// Direction in which [`SplitPane`] splits the target pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// The new pane is placed beside the target pane (`-h`).
+    Horizontal,
+    /// The new pane is placed below the target pane (`-v`), which is the tmux default.
+    Vertical,
+}
 
-    let buffer = String::from_utf8(output.stdout)?;
+/// Size of a new pane, along the split direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneSize {
+    /// Number of lines or columns.
+    Cells(u16),
+    /// Percentage of the available space.
+    Percent(u8),
+}
+
+/// Options for creating a Tmux pane by splitting an existing one.
+///
+/// By default, the active pane of the current window is split vertically, and the new pane is
+/// not selected.
+///
+/// ```no_run
+/// # async fn split(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+/// use tmux_lib::pane::{PaneSize, SplitDirection, SplitPane};
+///
+/// let new_pane_id = SplitPane::new()
+///     .target_pane(pane_id)
+///     .direction(SplitDirection::Horizontal)
+///     .size(PaneSize::Percent(30))
+///     .start_directory("/tmp")
+///     .command("htop")
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPane {
+    target: Option<String>,
+    direction: SplitDirection,
+    before: bool,
+    full: bool,
+    size: Option<PaneSize>,
+    start_directory: Option<PathBuf>,
+    environment: Vec<(String, String)>,
+    detached: bool,
+    command: Option<String>,
+}
 
-    let new_id = PaneId::from_str(buffer.trim_end())?;
-    Ok(new_id)
+impl Default for SplitPane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SplitPane {
+    /// Prepare splitting a pane, without selecting the new pane.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            target: None,
+            direction: SplitDirection::Vertical,
+            before: false,
+            full: false,
+            size: None,
+            start_directory: None,
+            environment: vec![],
+            detached: true,
+            command: None,
+        }
+    }
+
+    /// Split the pane with `pane_id`.
+    #[must_use]
+    pub fn target_pane(mut self, pane_id: &PaneId) -> Self {
+        self.target = Some(pane_id.as_str().to_string());
+        self
+    }
+
+    /// Split the active pane of the window with `window_id`.
+    #[must_use]
+    pub fn target_window(mut self, window_id: &WindowId) -> Self {
+        self.target = Some(window_id.as_str().to_string());
+        self
+    }
+
+    /// Set the split direction.
+    #[must_use]
+    pub fn direction(mut self, direction: SplitDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Place the new pane left of or above the target pane (`-b`).
+    #[must_use]
+    pub fn before(mut self, before: bool) -> Self {
+        self.before = before;
+        self
+    }
+
+    /// Split the full window height or width instead of the target pane (`-f`).
+    #[must_use]
+    pub fn full(mut self, full: bool) -> Self {
+        self.full = full;
+        self
+    }
+
+    /// Set the size of the new pane (`-l`).
+    #[must_use]
+    pub fn size(mut self, size: PaneSize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Set the working directory of the new pane (`-c`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the new pane (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Create the pane without selecting it (`-d`), which is the default.
+    #[must_use]
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.detached = detached;
+        self
+    }
+
+    /// Set the shell command run in the new pane.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        self.args_with(true)
+    }
+
+    /// Return `true` if the options need a version of tmux with [`Capability::PercentSizes`].
+    pub(crate) fn uses_percent_size(&self) -> bool {
+        matches!(self.size, Some(PaneSize::Percent(_)))
+    }
+
+    /// Arguments passed to tmux, giving a percentage size with `-p` unless `percent_sizes`, as
+    /// tmux before 3.1 requires.
+    pub(crate) fn args_with(&self, percent_sizes: bool) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["split-window".into()];
+        args.push(match self.direction {
+            SplitDirection::Horizontal => "-h".into(),
+            SplitDirection::Vertical => "-v".into(),
+        });
+        if self.before {
+            args.push("-b".into());
+        }
+        if self.full {
+            args.push("-f".into());
+        }
+        if self.detached {
+            args.push("-d".into());
+        }
+        match self.size {
+            Some(PaneSize::Cells(cells)) => {
+                args.push("-l".into());
+                args.push(cells.to_string().into());
+            }
+            Some(PaneSize::Percent(percent)) if percent_sizes => {
+                args.push("-l".into());
+                args.push(format!("{percent}%").into());
+            }
+            Some(PaneSize::Percent(percent)) => {
+                args.push("-p".into());
+                args.push(percent.to_string().into());
+            }
+            None => {}
+        }
+        if let Some(dirpath) = &self.start_directory {
+            args.push("-c".into());
+            args.push(dirpath.into());
+        }
+        for (name, value) in &self.environment {
+            args.push("-e".into());
+            args.push(format!("{name}={value}").into());
+        }
+        if let Some(target) = &self.target {
+            args.push("-t".into());
+            args.push(target.into());
+        }
+        args.push("-P".into());
+        args.push("-F".into());
+        args.push("#{pane_id}".into());
+        if let Some(command) = &self.command {
+            args.push(command.into());
+        }
+        args
+    }
+
+    /// Split the pane, and return the id of the new pane.
+    pub async fn run(&self) -> Result<PaneId> {
+        let percent_sizes =
+            !self.uses_percent_size() || version::supports(Capability::PercentSizes).await?;
+        let output = Command::new("tmux")
+            .args(self.args_with(percent_sizes))
+            .output()
+            .await?;
+
+        // Check exit status before parsing to avoid confusing parse errors
+        // when tmux fails and returns empty/garbage stdout.
+        check_process_success(&output, "split-window")?;
+
+        let buffer = String::from_utf8(output.stdout)?;
+
+        let new_id = PaneId::from_str(buffer.trim_end())?;
+        Ok(new_id)
+    }
 }
 
 /// Select (make active) the pane with `pane_id`.
@@ -223,11 +447,251 @@ pub async fn select_pane(pane_id: &PaneId) -> Result<()> {
     check_empty_process_output(&output, "select-pane")
 }
 
+/// Restart the process running in the pane with `pane_id`, in place.
+///
+/// Unlike [`new_pane`], this keeps the `PaneId` and the position of the pane in the window
+/// layout. This is typically used for restarting dead panes preserved with `remain-on-exit`.
+///
+/// ```no_run
+/// # async fn restart(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+/// use tmux_lib::pane::RespawnPane;
+///
+/// RespawnPane::new(pane_id)
+///     .kill(true)
+///     .start_directory("/tmp")
+///     .env("RUST_LOG", "debug")
+///     .command("cargo run")
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RespawnPane {
+    pane_id: PaneId,
+    options: RespawnOptions,
+}
+
+impl RespawnPane {
+    /// Prepare respawning the pane with `pane_id`.
+    #[must_use]
+    pub fn new(pane_id: &PaneId) -> Self {
+        Self {
+            pane_id: pane_id.clone(),
+            options: RespawnOptions::default(),
+        }
+    }
+
+    /// Kill the running process, if any (`-k`). Without it, tmux refuses to respawn a pane
+    /// which is not dead.
+    #[must_use]
+    pub fn kill(mut self, kill: bool) -> Self {
+        self.options.kill = kill;
+        self
+    }
+
+    /// Set the working directory of the new process (`-c`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.options.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the new process (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the shell command to run. Without it, tmux reuses the command the pane was started
+    /// with.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.options.command = Some(command.into());
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        self.options.args("respawn-pane", self.pane_id.as_str())
+    }
+
+    /// Respawn the pane.
+    pub async fn run(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "respawn-pane")
+    }
+}
+
+/// Options shared by [`RespawnPane`] and [`RespawnWindow`].
+///
+/// [`RespawnWindow`]: crate::window::RespawnWindow
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RespawnOptions {
+    pub(crate) kill: bool,
+    pub(crate) start_directory: Option<PathBuf>,
+    pub(crate) environment: Vec<(String, String)>,
+    pub(crate) command: Option<String>,
+}
+
+impl RespawnOptions {
+    /// Arguments passed to tmux for `respawn_command`, `respawn-pane` or `respawn-window`, on
+    /// `target`.
+    pub(crate) fn args(&self, respawn_command: &str, target: &str) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![respawn_command.into()];
+        if self.kill {
+            args.push("-k".into());
+        }
+        if let Some(dirpath) = &self.start_directory {
+            args.push("-c".into());
+            args.push(dirpath.into());
+        }
+        for (name, value) in &self.environment {
+            args.push("-e".into());
+            args.push(format!("{name}={value}").into());
+        }
+        args.push("-t".into());
+        args.push(target.into());
+        if let Some(command) = &self.command {
+            args.push(command.into());
+        }
+        args
+    }
+}
+
+/// Direction of the streams connected by [`PipePane`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeDirection {
+    /// The output of the pane is written to the command (`-O`), which is the tmux default.
+    Output,
+    /// The output of the command is written to the pane, as if typed (`-I`).
+    Input,
+    /// Both the output of the pane and the input of the command are connected (`-I -O`).
+    Both,
+}
+
+/// Options for piping a Tmux pane to a shell command.
+///
+/// Only one pipe can be open per pane: piping a pane which is already piped closes the previous
+/// pipe first, unless [`PipePane::toggle`] is set. See [`stop_pipe_pane`] for closing the pipe.
+///
+/// ```no_run
+/// # async fn log(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+/// use tmux_lib::pane::{PipeDirection, PipePane};
+///
+/// PipePane::new(pane_id, "cat >> /tmp/pane.log")
+///     .direction(PipeDirection::Output)
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipePane {
+    pane_id: PaneId,
+    direction: PipeDirection,
+    toggle: bool,
+    command: String,
+}
+
+impl PipePane {
+    /// Prepare piping the output of the pane with `pane_id` to the shell `command`.
+    ///
+    /// The command is expanded as a format by tmux, `#` characters must be doubled.
+    #[must_use]
+    pub fn new(pane_id: &PaneId, command: impl Into<String>) -> Self {
+        Self {
+            pane_id: pane_id.clone(),
+            direction: PipeDirection::Output,
+            toggle: false,
+            command: command.into(),
+        }
+    }
+
+    /// Set the direction of the pipe.
+    #[must_use]
+    pub fn direction(mut self, direction: PipeDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Only open the pipe if the pane is not piped already, and otherwise close the existing
+    /// pipe (`-o`).
+    #[must_use]
+    pub fn toggle(mut self, toggle: bool) -> Self {
+        self.toggle = toggle;
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<&str> {
+        let mut args = vec!["pipe-pane"];
+        match self.direction {
+            PipeDirection::Output => args.push("-O"),
+            PipeDirection::Input => args.push("-I"),
+            PipeDirection::Both => args.extend(["-I", "-O"]),
+        }
+        if self.toggle {
+            args.push("-o");
+        }
+        args.extend(["-t", self.pane_id.as_str(), self.command.as_str()]);
+        args
+    }
+
+    /// Pipe the pane.
+    pub async fn run(&self) -> Result<()> {
+        version::require(Capability::PipeDirections).await?;
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "pipe-pane")
+    }
+}
+
+/// Close the pipe of the pane with `pane_id`, if any.
+pub async fn stop_pipe_pane(pane_id: &PaneId) -> Result<()> {
+    let args = vec!["pipe-pane", "-t", pane_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "pipe-pane")
+}
+
+/// Return `true` if the pane with `pane_id` is piped to a command (`pane_pipe`).
+pub async fn is_pane_piped(pane_id: &PaneId) -> Result<bool> {
+    let args = vec![
+        "display-message",
+        "-p",
+        "-t",
+        pane_id.as_str(),
+        "#{pane_pipe}",
+    ];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    parse_pane_pipe(&output)
+}
+
+/// Parse the value of `pane_pipe` printed by `display-message`.
+pub(crate) fn parse_pane_pipe(output: &Output) -> Result<bool> {
+    check_process_success(output, "display-message")?;
+
+    match output.stdout.as_slice() {
+        b"1\n" => Ok(true),
+        b"0\n" => Ok(false),
+        _ => Err(Error::UnexpectedTmuxOutput {
+            intent: "display-message",
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Pane;
     use super::PaneId;
+    use super::RespawnPane;
+    use super::{PaneSize, SplitDirection, SplitPane};
+    use super::{PipeDirection, PipePane, parse_pane_pipe};
     use crate::Result;
+    use crate::window_id::WindowId;
     use std::path::PathBuf;
     use std::str::FromStr;
 
@@ -366,4 +830,185 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn respawn_pane_default_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let args = RespawnPane::new(&pane_id).args();
+
+        assert_eq!(args, ["respawn-pane", "-t", "%3"]);
+    }
+
+    #[test]
+    fn respawn_pane_all_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let args = RespawnPane::new(&pane_id)
+            .kill(true)
+            .start_directory("/home/user/My Projects")
+            .env("FOO", "bar baz")
+            .env("EMPTY", "")
+            .command("htop -d 10")
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "respawn-pane",
+                "-k",
+                "-c",
+                "/home/user/My Projects",
+                "-e",
+                "FOO=bar baz",
+                "-e",
+                "EMPTY=",
+                "-t",
+                "%3",
+                "htop -d 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_pane_default_args() {
+        let args = SplitPane::new().args();
+
+        assert_eq!(args, ["split-window", "-v", "-d", "-P", "-F", "#{pane_id}"]);
+    }
+
+    #[test]
+    fn split_pane_all_args() {
+        let pane_id = PaneId::from_str("%8").unwrap();
+        let args = SplitPane::new()
+            .target_pane(&pane_id)
+            .direction(SplitDirection::Horizontal)
+            .before(true)
+            .full(true)
+            .size(PaneSize::Percent(30))
+            .start_directory("/tmp")
+            .env("A", "1")
+            .detached(false)
+            .command("htop")
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "split-window",
+                "-h",
+                "-b",
+                "-f",
+                "-l",
+                "30%",
+                "-c",
+                "/tmp",
+                "-e",
+                "A=1",
+                "-t",
+                "%8",
+                "-P",
+                "-F",
+                "#{pane_id}",
+                "htop",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_pane_percent_size_before_tmux_3_1() {
+        let split_pane = SplitPane::new().size(PaneSize::Percent(30));
+        assert!(split_pane.uses_percent_size());
+
+        assert_eq!(
+            split_pane.args_with(false),
+            [
+                "split-window",
+                "-v",
+                "-d",
+                "-p",
+                "30",
+                "-P",
+                "-F",
+                "#{pane_id}"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_pane_target_window_with_cells() {
+        let window_id = WindowId::from_str("@2").unwrap();
+        let args = SplitPane::new()
+            .target_window(&window_id)
+            .size(PaneSize::Cells(12))
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "split-window",
+                "-v",
+                "-d",
+                "-l",
+                "12",
+                "-t",
+                "@2",
+                "-P",
+                "-F",
+                "#{pane_id}"
+            ]
+        );
+    }
+
+    #[test]
+    fn pipe_pane_default_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let pipe_pane = PipePane::new(&pane_id, "cat >> /tmp/pane.log");
+        let args = pipe_pane.args();
+
+        assert_eq!(
+            args,
+            ["pipe-pane", "-O", "-t", "%3", "cat >> /tmp/pane.log"]
+        );
+    }
+
+    #[test]
+    fn pipe_pane_all_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let pipe_pane = PipePane::new(&pane_id, "tee /tmp/pane.log")
+            .direction(PipeDirection::Both)
+            .toggle(true);
+        let args = pipe_pane.args();
+
+        assert_eq!(
+            args,
+            [
+                "pipe-pane",
+                "-I",
+                "-O",
+                "-o",
+                "-t",
+                "%3",
+                "tee /tmp/pane.log"
+            ]
+        );
+
+        let pipe_pane = PipePane::new(&pane_id, "echo ls").direction(PipeDirection::Input);
+        assert_eq!(pipe_pane.args(), ["pipe-pane", "-I", "-t", "%3", "echo ls"]);
+    }
+
+    #[test]
+    fn parse_pane_pipe_values() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{ExitStatus, Output};
+
+        let output = |status: i32, stdout: &[u8]| Output {
+            status: ExitStatus::from_raw(status << 8),
+            stdout: stdout.to_vec(),
+            stderr: vec![],
+        };
+
+        assert!(parse_pane_pipe(&output(0, b"1\n")).unwrap());
+        assert!(!parse_pane_pipe(&output(0, b"0\n")).unwrap());
+        assert!(parse_pane_pipe(&output(0, b"\n")).is_err());
+        assert!(parse_pane_pipe(&output(1, b"")).is_err());
+    }
 }
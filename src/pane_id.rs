@@ -11,7 +11,10 @@ use nom::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, map_add_intent};
+use crate::{
+    error::{Error, map_add_intent},
+    format::intent,
+};
 
 /// The id of a Tmux pane.
 ///
@@ -37,7 +40,7 @@ impl FromStr for PaneId {
     /// Parse into `PaneId`. The `&str` must start with '%' followed by a `u32`.
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let desc = "PaneId";
-        let intent = "##{pane_id}";
+        let intent = intent!("#{pane_id}");
 
         let (_, pane_id) = all_consuming(parse::pane_id)
             .parse(input)
@@ -0,0 +1,427 @@
+//! This module provides an extended description of Tmux panes, focused on the process running
+//! inside each pane.
+//!
+//! The main use case is supervising panes: knowing which process runs in a pane, whether it has
+//! exited (for panes kept around with `remain-on-exit`), and with which status.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    bytes::complete::is_not,
+    character::complete::{char, digit1, not_line_ending},
+    combinator::{all_consuming, map_res},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Result,
+    error::{Error, check_process_success, map_add_intent},
+    format::intent,
+    pane_id::{PaneId, parse::pane_id},
+    parse::{boolean, optional_number},
+    runtime::Command,
+};
+
+/// Format of the pane descriptions printed by tmux, parsed by `PaneInfo::from_str`.
+pub(crate) const FORMAT: &str = "#{pane_id}:#{pane_pid}:#{pane_tty}:#{?pane_dead,true,false}:#{pane_dead_status}:#{pane_dead_signal}:#{?pane_in_mode,true,false}:#{pane_width}:#{pane_height}:#{history_size}:#{?pane_last,true,false}:#{pane_start_command}";
+
+/// Process-level description of a Tmux pane.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::pane_info::PaneInfo;
+///
+/// let line = r#"%20:2626:/dev/pts/2:true:3::false:100:14:1:false:"sh -c \"exit 3\"""#;
+/// let info = PaneInfo::from_str(line).unwrap();
+///
+/// assert_eq!(info.id.as_str(), "%20");
+/// assert_eq!(info.pid, 2626);
+/// assert!(info.is_dead);
+/// assert_eq!(info.dead_status, Some(3));
+/// assert_eq!(info.dead_signal, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneInfo {
+    /// Pane identifier, e.g. `%37`.
+    pub id: PaneId,
+    /// PID of the first process started in the pane (usually the shell).
+    pub pid: u32,
+    /// Pseudo terminal of the pane, e.g. `/dev/pts/2`.
+    pub tty: PathBuf,
+    /// Describes if the pane process has exited (requires `remain-on-exit`).
+    pub is_dead: bool,
+    /// Exit status of the pane process, if it has exited normally.
+    pub dead_status: Option<i32>,
+    /// Signal which terminated the pane process, if any.
+    pub dead_signal: Option<i32>,
+    /// Describes if the pane is in a mode (copy-mode, view-mode, ...).
+    pub is_in_mode: bool,
+    /// Width of the pane, in cells.
+    pub width: u16,
+    /// Height of the pane, in cells.
+    pub height: u16,
+    /// Number of lines in the pane history.
+    pub history_size: u32,
+    /// Describes if the pane is the last active pane of its window.
+    pub is_last: bool,
+    /// Command the pane was started with, as quoted by tmux. Empty if the pane runs the default
+    /// command.
+    pub start_command: String,
+}
+
+impl FromStr for PaneInfo {
+    type Err = Error;
+
+    /// Parse a string containing the tmux pane process status into a new `PaneInfo`.
+    ///
+    /// This returns a `Result<PaneInfo, Error>` as this call can obviously
+    /// fail if provided an invalid format.
+    ///
+    /// The expected format of the tmux status is
+    ///
+    /// ```text
+    /// %19:2620:/dev/pts/1:false:::false:100:15:0:false:
+    /// %20:2626:/dev/pts/2:true:3::false:100:14:1:false:"sh -c \"exit 3\""
+    /// ```
+    ///
+    /// This status line is obtained with
+    ///
+    /// ```text
+    /// tmux list-panes -F "#{pane_id}:#{pane_pid}:#{pane_tty}:#{?pane_dead,true,false}:#{pane_dead_status}:#{pane_dead_signal}:#{?pane_in_mode,true,false}:#{pane_width}:#{pane_height}:#{history_size}:#{?pane_last,true,false}:#{pane_start_command}"
+    /// ```
+    ///
+    /// For definitions, look at `PaneInfo` type and the tmux man page for
+    /// definitions.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let desc = "PaneInfo";
+        let intent = intent!(FORMAT);
+
+        let (_, info) = all_consuming(parse::pane_info)
+            .parse(input)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(info)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PaneInfo {
+    /// Return the processes running under this pane, read from `/proc`.
+    ///
+    /// The first element is the pane process itself, followed by all its descendants in
+    /// depth-first order. If the pane process has exited, the returned list is empty.
+    pub fn process_tree(&self) -> Result<Vec<Process>> {
+        if self.is_dead {
+            return Ok(vec![]);
+        }
+        Ok(procfs::process_tree(self.pid)?)
+    }
+}
+
+/// A process running in a pane, as described by `/proc/<pid>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Process {
+    /// Process id.
+    pub pid: u32,
+    /// Parent process id.
+    pub ppid: u32,
+    /// Executable name, truncated by the kernel to 15 bytes.
+    pub name: String,
+    /// Full command line, empty for zombies and kernel threads.
+    pub cmdline: Vec<String>,
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    pub(crate) fn pane_info(input: &str) -> IResult<&str, PaneInfo> {
+        let (input, (id, _, pid, _, tty, _, is_dead, _, dead_status, _, dead_signal, _)) = (
+            pane_id,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            is_not(":"),
+            char(':'),
+            boolean,
+            char(':'),
+            optional_number,
+            char(':'),
+            optional_number,
+            char(':'),
+        )
+            .parse(input)?;
+
+        let (
+            input,
+            (is_in_mode, _, width, _, height, _, history_size, _, is_last, _, start_command),
+        ) = (
+            boolean,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            boolean,
+            char(':'),
+            not_line_ending,
+        )
+            .parse(input)?;
+
+        Ok((
+            input,
+            PaneInfo {
+                id,
+                pid,
+                tty: tty.into(),
+                is_dead,
+                dead_status,
+                dead_signal,
+                is_in_mode,
+                width,
+                height,
+                history_size,
+                is_last,
+                start_command: start_command.into(),
+            },
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod procfs {
+    use std::{collections::HashMap, fs, io};
+
+    use super::Process;
+
+    /// Parse the content of `/proc/<pid>/stat` into `(pid, name, ppid)`.
+    ///
+    /// The executable name is enclosed in parentheses and may itself contain spaces and
+    /// parentheses, hence the search for the last `)`.
+    pub(super) fn parse_stat(stat: &str) -> Option<(u32, String, u32)> {
+        let open = stat.find('(')?;
+        let close = stat.rfind(')')?;
+        let pid = stat[..open].trim().parse().ok()?;
+        let name = stat.get(open + 1..close)?.to_string();
+        let mut fields = stat[close + 1..].split_ascii_whitespace();
+        let _state = fields.next()?;
+        let ppid = fields.next()?.parse().ok()?;
+        Some((pid, name, ppid))
+    }
+
+    fn read_cmdline(pid: u32) -> Vec<String> {
+        fs::read(format!("/proc/{pid}/cmdline"))
+            .map(|buf| {
+                buf.split(|&b| b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Return the process `root` followed by all its descendants, depth-first.
+    ///
+    /// Processes which exit while `/proc` is being scanned are silently skipped.
+    pub(super) fn process_tree(root: u32) -> io::Result<Vec<Process>> {
+        let mut processes: HashMap<u32, (String, u32)> = HashMap::new();
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for entry in fs::read_dir("/proc")? {
+            let entry = entry?;
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|s| s.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+                continue;
+            };
+            if let Some((pid, name, ppid)) = parse_stat(&stat).filter(|(p, _, _)| *p == pid) {
+                processes.insert(pid, (name, ppid));
+                children.entry(ppid).or_default().push(pid);
+            }
+        }
+
+        let mut tree = vec![];
+        let mut stack = vec![root];
+        while let Some(pid) = stack.pop() {
+            let Some((name, ppid)) = processes.remove(&pid) else {
+                continue;
+            };
+            tree.push(Process {
+                pid,
+                ppid,
+                name,
+                cmdline: read_cmdline(pid),
+            });
+            if let Some(kids) = children.get_mut(&pid) {
+                kids.sort_unstable();
+                stack.extend(kids.iter().rev());
+            }
+        }
+
+        Ok(tree)
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the process description of all panes from all sessions.
+pub async fn available_pane_infos() -> Result<Vec<PaneInfo>> {
+    let args = vec!["list-panes", "-a", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    // Each call to `PaneInfo::from_str` returns a `Result<PaneInfo, _>`. All results
+    // are collected into a Result<Vec<PaneInfo>, _>, thanks to `collect()`.
+    let result: Result<Vec<PaneInfo>> = buffer
+        .trim_end() // trim last '\n' as it would create an empty line
+        .split('\n')
+        .map(PaneInfo::from_str)
+        .collect();
+
+    result
+}
+
+/// Return the process description of the pane with `pane_id`.
+pub async fn pane_info(pane_id: &PaneId) -> Result<PaneInfo> {
+    let args = vec!["display-message", "-p", "-t", pane_id.as_str(), FORMAT];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_process_success(&output, "display-message")?;
+
+    let buffer = String::from_utf8(output.stdout)?;
+
+    PaneInfo::from_str(buffer.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaneInfo;
+    use crate::pane_id::PaneId;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_list_pane_infos() {
+        let output = [
+            "%19:2620:/dev/pts/1:false:::false:100:15:0:false:",
+            r#"%20:2626:/dev/pts/2:true:3::false:100:14:1:false:"sh -c \"exit 3\"""#,
+        ];
+        let infos: Vec<PaneInfo> = output
+            .iter()
+            .map(|&line| PaneInfo::from_str(line))
+            .collect::<crate::Result<_>>()
+            .expect("Could not parse tmux pane infos");
+
+        let expected = vec![
+            PaneInfo {
+                id: PaneId::from_str("%19").unwrap(),
+                pid: 2620,
+                tty: PathBuf::from("/dev/pts/1"),
+                is_dead: false,
+                dead_status: None,
+                dead_signal: None,
+                is_in_mode: false,
+                width: 100,
+                height: 15,
+                history_size: 0,
+                is_last: false,
+                start_command: String::new(),
+            },
+            PaneInfo {
+                id: PaneId::from_str("%20").unwrap(),
+                pid: 2626,
+                tty: PathBuf::from("/dev/pts/2"),
+                is_dead: true,
+                dead_status: Some(3),
+                dead_signal: None,
+                is_in_mode: false,
+                width: 100,
+                height: 14,
+                history_size: 1,
+                is_last: false,
+                start_command: String::from(r#""sh -c \"exit 3\"""#),
+            },
+        ];
+
+        assert_eq!(infos, expected);
+    }
+
+    #[test]
+    fn parse_pane_info_killed_by_signal() {
+        let line = "%3:410:/dev/pts/4:true::9:false:80:24:2000:true:vim";
+        let info = PaneInfo::from_str(line).expect("Should parse pane killed by a signal");
+
+        assert!(info.is_dead);
+        assert_eq!(info.dead_status, None);
+        assert_eq!(info.dead_signal, Some(9));
+        assert_eq!(info.history_size, 2000);
+        assert!(info.is_last);
+        assert_eq!(info.start_command, "vim");
+    }
+
+    #[test]
+    fn parse_pane_info_start_command_with_colons() {
+        let line = "%3:410:/dev/pts/4:false:::true:80:24:0:false:ssh host -L 8080:localhost:80";
+        let info = PaneInfo::from_str(line).expect("Should parse start command with colons");
+
+        assert!(info.is_in_mode);
+        assert_eq!(info.start_command, "ssh host -L 8080:localhost:80");
+    }
+
+    #[test]
+    fn parse_pane_info_fails_on_missing_pid() {
+        let line = "%3::/dev/pts/4:false:::false:80:24:0:false:";
+        assert!(PaneInfo::from_str(line).is_err());
+    }
+
+    #[test]
+    fn parse_pane_info_fails_on_invalid_boolean() {
+        let line = "%3:410:/dev/pts/4:0:::false:80:24:0:false:";
+        assert!(PaneInfo::from_str(line).is_err());
+    }
+
+    #[test]
+    fn parse_pane_info_fails_on_truncated_input() {
+        let line = "%3:410:/dev/pts/4:false:::false:80:24";
+        assert!(PaneInfo::from_str(line).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_proc_stat() {
+        let stat = "1234 (tmux: server) S 1 1234 1234 0 -1 4194560";
+        assert_eq!(
+            super::procfs::parse_stat(stat),
+            Some((1234, String::from("tmux: server"), 1))
+        );
+
+        let stat = "42 (weird) name)) R 7 42";
+        assert_eq!(
+            super::procfs::parse_stat(stat),
+            Some((42, String::from("weird) name)"), 7))
+        );
+
+        assert_eq!(super::procfs::parse_stat("garbage"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn process_tree_contains_own_process() {
+        let pid = std::process::id();
+        let tree = super::procfs::process_tree(pid).expect("Should read /proc");
+
+        assert_eq!(tree.first().map(|p| p.pid), Some(pid));
+        assert!(!tree[0].cmdline.is_empty());
+    }
+}
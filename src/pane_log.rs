@@ -0,0 +1,398 @@
+//! Log the output of panes to rotating files, with `pipe-pane`.
+//!
+//! A [`PaneLogger`] pipes the output of each logged pane to a `cat` process appending to the log
+//! file of the pane, `pane-<n>.log` in the log directory. Once a log file reaches the maximum
+//! size, [`PaneLogger::rotate`] renames it to `pane-<n>.log.1`, shifting the previous files
+//! (`.1` to `.2`, and so on, deleting the oldest), and pipes the pane to a fresh file.
+//!
+//! With [`PaneLogger::strip_ansi`], a log file is rewritten as plain text with
+//! [`cleanup_logged_buffer`] when it is rotated or when logging stops. The current log file keeps
+//! the raw output, escape sequences included, until then. As the `cat` process of a closed pipe
+//! may still append the last output of the pane, each pipe creates a marker file next to the log
+//! file, `pane-<n>.log.writer-<k>`, removed once `cat` exits: a log file is only rewritten after
+//! that.
+//!
+//! ```no_run
+//! # async fn log(pane_id: &tmux_lib::pane_id::PaneId) -> tmux_lib::Result<()> {
+//! use std::time::Duration;
+//! use tmux_lib::pane_log::PaneLogger;
+//!
+//! let mut logger = PaneLogger::new("/tmp/tmux-logs")
+//!     .max_size(1024 * 1024)
+//!     .max_files(3)
+//!     .strip_ansi(true);
+//! logger.start(pane_id).await?;
+//!
+//! // Rotate the log files every second, until the logged panes are closed.
+//! logger.run(Duration::from_secs(1)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{
+    Result,
+    pane::{PipeDirection, PipePane, available_panes, stop_pipe_pane},
+    pane_id::PaneId,
+    runtime::sleep,
+    utils::cleanup_logged_buffer,
+};
+
+/// Default maximum size of a log file, in bytes.
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated log files kept per pane.
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// Maximum time to wait for the `cat` process of a closed pipe to exit.
+const WRITER_EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delay between checks whether the `cat` process of a closed pipe has exited.
+const WRITER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Logs the output of panes to rotating files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneLogger {
+    directory: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    strip_ansi: bool,
+    /// Panes currently logged.
+    panes: Vec<PaneId>,
+    /// Marker file of the current pipe of each logged pane, removed when its `cat` exits.
+    writers: HashMap<PaneId, PathBuf>,
+    /// Number of pipes opened so far, numbering the marker files.
+    pipe_count: u64,
+}
+
+impl PaneLogger {
+    /// Prepare logging panes to files in `directory`, rotated at 10 MiB and keeping 5 rotated
+    /// files per pane.
+    #[must_use]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_size: DEFAULT_MAX_SIZE,
+            max_files: DEFAULT_MAX_FILES,
+            strip_ansi: false,
+            panes: vec![],
+            writers: HashMap::new(),
+            pipe_count: 0,
+        }
+    }
+
+    /// Set the size in bytes from which a log file is rotated.
+    #[must_use]
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set the number of rotated log files kept per pane, besides the current one. With 0, a log
+    /// file is deleted when rotated.
+    #[must_use]
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Rewrite the log files as plain text, without escape sequences, once they are complete.
+    #[must_use]
+    pub fn strip_ansi(mut self, strip_ansi: bool) -> Self {
+        self.strip_ansi = strip_ansi;
+        self
+    }
+
+    /// Panes currently logged.
+    #[must_use]
+    pub fn panes(&self) -> &[PaneId] {
+        &self.panes
+    }
+
+    /// Path of the current log file of the pane with `pane_id`, e.g. `pane-3.log`.
+    #[must_use]
+    pub fn log_path(&self, pane_id: &PaneId) -> PathBuf {
+        let number = pane_id.as_str().trim_start_matches('%');
+        self.directory.join(format!("pane-{number}.log"))
+    }
+
+    /// Path of the rotated log file `index` of the pane with `pane_id`, e.g. `pane-3.log.1`.
+    fn rotated_path(&self, pane_id: &PaneId, index: usize) -> PathBuf {
+        let mut path = self.log_path(pane_id).into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    /// Start logging the pane with `pane_id`, appending to its current log file.
+    ///
+    /// This replaces any pipe already open on the pane.
+    pub async fn start(&mut self, pane_id: &PaneId) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        self.pipe(pane_id).await?;
+        if !self.panes.contains(pane_id) {
+            self.panes.push(pane_id.clone());
+        }
+        Ok(())
+    }
+
+    /// Stop logging the pane with `pane_id`.
+    ///
+    /// The pane is no longer logged even if this fails. Stopping a pane which was closed, or whose
+    /// server has exited, is not an error.
+    pub async fn stop(&mut self, pane_id: &PaneId) -> Result<()> {
+        self.panes.retain(|id| id != pane_id);
+        let writer = self.writers.remove(pane_id);
+        let stopped = match stop_pipe_pane(pane_id).await {
+            Err(err) if err.is_missing_pane() || err.is_no_server() => Ok(()),
+            stopped => stopped,
+        };
+        self.finish(&self.log_path(pane_id), writer).await?;
+        stopped
+    }
+
+    /// Stop logging all the panes.
+    pub async fn stop_all(&mut self) -> Result<()> {
+        while let Some(pane_id) = self.panes.first().cloned() {
+            self.stop(&pane_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Rotate the log files which reached the maximum size, and return the ids of their panes.
+    ///
+    /// Panes which were closed since the last call are no longer logged, and their log file is
+    /// kept without being rotated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the panes fails, unless the server has exited along with all
+    /// its panes.
+    pub async fn rotate(&mut self) -> Result<Vec<PaneId>> {
+        let live_pane_ids: Vec<PaneId> = match available_panes().await {
+            Ok(panes) => panes.into_iter().map(|pane| pane.id).collect(),
+            Err(err) if err.is_no_server() => vec![],
+            Err(err) => return Err(err),
+        };
+        let (live, closed): (Vec<PaneId>, Vec<PaneId>) = self
+            .panes
+            .drain(..)
+            .partition(|pane_id| live_pane_ids.contains(pane_id));
+        self.panes = live;
+        for pane_id in closed {
+            let writer = self.writers.remove(&pane_id);
+            self.finish(&self.log_path(&pane_id), writer).await?;
+        }
+
+        let mut rotated = vec![];
+        for pane_id in self.panes.clone() {
+            let size = match fs::metadata(self.log_path(&pane_id)) {
+                Ok(metadata) => metadata.len(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+                Err(err) => return Err(err.into()),
+            };
+            if size < self.max_size {
+                continue;
+            }
+
+            let first = self.shift(&pane_id)?;
+            // The previous `cat` keeps writing to the renamed file until the pane is piped again.
+            let writer = self.writers.remove(&pane_id);
+            self.pipe(&pane_id).await?;
+            match first {
+                Some(path) => self.finish(&path, writer).await?,
+                None => remove_if_exists(&self.log_path(&pane_id).with_extension("log.old"))?,
+            }
+            rotated.push(pane_id);
+        }
+
+        Ok(rotated)
+    }
+
+    /// Rotate the log files every `interval`, until none of the logged panes remains.
+    pub async fn run(&mut self, interval: Duration) -> Result<()> {
+        while !self.panes.is_empty() {
+            sleep(interval).await;
+            self.rotate().await?;
+        }
+        Ok(())
+    }
+
+    /// Pipe the output of the pane with `pane_id` to its current log file, with a marker file
+    /// removed once the `cat` process exits.
+    async fn pipe(&mut self, pane_id: &PaneId) -> Result<()> {
+        self.pipe_count += 1;
+        let mut writer = self.log_path(pane_id).into_os_string();
+        writer.push(format!(".writer-{}", self.pipe_count));
+        let writer = PathBuf::from(writer);
+        fs::write(&writer, "")?;
+
+        let command = format!(
+            "cat >> {}; rm -f {}",
+            shell_quote(&self.log_path(pane_id)),
+            shell_quote(&writer)
+        );
+        let result = PipePane::new(pane_id, command)
+            .direction(PipeDirection::Output)
+            .run()
+            .await;
+        if let Err(err) = result {
+            remove_if_exists(&writer)?;
+            return Err(err);
+        }
+        // A pipe replaced by this one also leaves its `cat` exiting on its own.
+        self.writers.insert(pane_id.clone(), writer);
+        Ok(())
+    }
+
+    /// Shift the log files of the pane with `pane_id`, deleting the oldest one, and return the
+    /// path the current log file was moved to.
+    ///
+    /// Without rotated files to keep, the current log file is moved to `pane-<n>.log.old`, to be
+    /// deleted once the pane is piped to a new file.
+    fn shift(&self, pane_id: &PaneId) -> io::Result<Option<PathBuf>> {
+        let current = self.log_path(pane_id);
+        if self.max_files == 0 {
+            fs::rename(&current, current.with_extension("log.old"))?;
+            return Ok(None);
+        }
+
+        remove_if_exists(&self.rotated_path(pane_id, self.max_files))?;
+        for index in (1..self.max_files).rev() {
+            let path = self.rotated_path(pane_id, index);
+            if path.exists() {
+                fs::rename(&path, self.rotated_path(pane_id, index + 1))?;
+            }
+        }
+        let first = self.rotated_path(pane_id, 1);
+        fs::rename(&current, &first)?;
+        Ok(Some(first))
+    }
+
+    /// Rewrite the complete log file at `path` as plain text, if requested, once the `cat`
+    /// process of its closed pipe, with the marker file `writer`, has exited.
+    async fn finish(&self, path: &Path, writer: Option<PathBuf>) -> io::Result<()> {
+        if !self.strip_ansi {
+            return Ok(());
+        }
+        if let Some(writer) = writer {
+            let deadline = Instant::now() + WRITER_EXIT_TIMEOUT;
+            while writer.exists() && Instant::now() < deadline {
+                sleep(WRITER_POLL_INTERVAL).await;
+            }
+            // The marker is left behind if `cat` was killed.
+            remove_if_exists(&writer)?;
+        }
+        self.strip(path)
+    }
+
+    /// Rewrite the log file at `path` as plain text, through a new file renamed over it.
+    fn strip(&self, path: &Path) -> io::Result<()> {
+        let buffer = match fs::read(path) {
+            Ok(buffer) => buffer,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let stripped = path.with_extension("log.stripped");
+        fs::write(&stripped, cleanup_logged_buffer(&buffer))?;
+        fs::rename(&stripped, path)
+    }
+}
+
+/// Remove the file at `path`, if it exists.
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Quote `path` for the shell, doubling `#` which tmux expands in `pipe-pane` commands.
+fn shell_quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    format!("'{}'", path.replace('\'', r"'\''").replace('#', "##"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("tmux-lib-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_log_paths() {
+        let logger = PaneLogger::new("/var/log/tmux");
+        let pane_id = PaneId::from_str("%12").unwrap();
+
+        assert_eq!(
+            logger.log_path(&pane_id),
+            PathBuf::from("/var/log/tmux/pane-12.log")
+        );
+        assert_eq!(
+            logger.rotated_path(&pane_id, 2),
+            PathBuf::from("/var/log/tmux/pane-12.log.2")
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote(Path::new("/tmp/a b/x.log")), "'/tmp/a b/x.log'");
+        assert_eq!(
+            shell_quote(Path::new("/tmp/it's #1.log")),
+            r"'/tmp/it'\''s ##1.log'"
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let directory = temp_directory("shift");
+        let logger = PaneLogger::new(&directory).max_files(2);
+        let pane_id = PaneId::from_str("%1").unwrap();
+        let read = |index: usize| fs::read_to_string(logger.rotated_path(&pane_id, index)).ok();
+
+        for content in ["first", "second", "third"] {
+            fs::write(logger.log_path(&pane_id), content).unwrap();
+            let first = logger.shift(&pane_id).unwrap();
+            assert_eq!(first, Some(logger.rotated_path(&pane_id, 1)));
+        }
+
+        assert!(!logger.log_path(&pane_id).exists());
+        assert_eq!(read(1).as_deref(), Some("third"));
+        assert_eq!(read(2).as_deref(), Some("second"));
+        assert_eq!(read(3), None);
+
+        let logger = logger.max_files(0);
+        fs::write(logger.log_path(&pane_id), "fourth").unwrap();
+        assert_eq!(logger.shift(&pane_id).unwrap(), None);
+        assert!(!logger.log_path(&pane_id).exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_strip() {
+        let directory = temp_directory("strip");
+        let path = directory.join("pane-1.log");
+        fs::write(&path, "\x1b[1mbold\x1b[0m  \r\n\r\n").unwrap();
+
+        let logger = PaneLogger::new(&directory).strip_ansi(true);
+        logger.strip(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bold\n");
+        assert!(!path.with_extension("log.stripped").exists());
+        logger.strip(&directory.join("missing.log")).unwrap();
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
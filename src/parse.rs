@@ -2,8 +2,8 @@ use nom::{
     IResult, Parser,
     branch::alt,
     bytes::complete::{escaped, tag},
-    character::complete::none_of,
-    combinator::value,
+    character::complete::{digit1, none_of},
+    combinator::{map_res, opt, value},
     sequence::delimited,
 };
 
@@ -32,6 +32,12 @@ pub(crate) fn boolean(input: &str) -> IResult<&str, bool> {
     alt((parse_true, parse_false)).parse(input)
 }
 
+/// Return an optional number: the input may be empty, as tmux expands unset numeric variables
+/// to an empty string.
+pub(crate) fn optional_number<T: std::str::FromStr>(input: &str) -> IResult<&str, Option<T>> {
+    opt(map_res(digit1, str::parse)).parse(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,4 +142,15 @@ mod tests {
     fn test_boolean_fails_on_empty() {
         assert!(boolean("").is_err());
     }
+
+    #[test]
+    fn test_optional_number() {
+        let (input, res) = optional_number::<i32>("42:next").unwrap();
+        assert_eq!(input, ":next");
+        assert_eq!(res, Some(42));
+
+        let (input, res) = optional_number::<i32>(":next").unwrap();
+        assert_eq!(input, ":next");
+        assert_eq!(res, None);
+    }
 }
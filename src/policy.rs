@@ -0,0 +1,319 @@
+//! Timeouts and retries of the tmux invocations.
+//!
+//! By default, the ops wait for tmux as long as it takes, and do not retry. A [`Policy`] bounds
+//! the duration of each tmux invocation, and retries the invocations failing with a transient
+//! error, waiting longer before each retry:
+//!
+//! - any invocation is retried when its client could not reach the server, with `error
+//!   connecting to` (e.g. a full listen queue), or when the server crashed, with `lost server`,
+//!   taking the command with it,
+//! - idempotent queries, like [`Pane::capture`](crate::pane::Pane::capture) or
+//!   [`format::expand`](crate::format::expand), are also retried when their target is not found,
+//!   as this may be a lookup racing with the creation of the target.
+//!
+//! [`CommandBatch`](crate::batch::CommandBatch) and `source-file` invocations, which may have
+//! partly run, and [`has_session`](crate::session::has_session), whose failure is its answer, are
+//! never retried.
+//!
+//! `server exited unexpectedly` is not transient: tmux prints it after the command ran whenever
+//! the command made the server exit, such as when killing its last session.
+//!
+//! A policy applies to all the ops once installed with [`set_global`], or to the ops awaited
+//! within [`Policy::scope`] (or run within [`Policy::scope_blocking`] with the `blocking`
+//! feature), taking precedence over the global policy:
+//!
+//! ```no_run
+//! # async fn restore() -> tmux_lib::Result<()> {
+//! use std::time::Duration;
+//! use tmux_lib::{policy::{self, Policy}, session};
+//!
+//! policy::set_global(Policy::new().timeout(Duration::from_secs(5)));
+//!
+//! let patient = Policy::new()
+//!     .timeout(Duration::from_secs(30))
+//!     .retries(3)
+//!     .backoff(Duration::from_millis(100));
+//! let sessions = patient.scope(session::available_sessions()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! An invocation which times out is killed, and fails with an `std::io::ErrorKind::TimedOut`
+//! error. Likewise, dropping the future of an op before it completes kills the tmux process it
+//! spawned.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{PoisonError, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::server::{SERVER_READY_POLL_INTERVAL, SERVER_READY_TIMEOUT};
+
+/// Messages printed by tmux when the client could not reach the server, or lost it.
+const UNSENT_ERRORS: [&str; 2] = ["error connecting to", "lost server"];
+
+/// Messages printed by tmux when a target is not found, possibly not yet created.
+const LOOKUP_ERRORS: [&str; 3] = ["can't find session", "can't find window", "can't find pane"];
+
+/// Failures after which an invocation can be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Retry {
+    /// None, for invocations which may have partly run, or whose failure is an answer.
+    Never,
+    /// Failures to reach the server, the default.
+    Unsent,
+    /// Also failures to find the target, for idempotent queries.
+    Lookups,
+}
+
+/// Timeouts and retries of the tmux invocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+    server_ready_timeout: Duration,
+    server_ready_poll_interval: Duration,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy {
+    /// Prepare a policy without timeout nor retries.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            timeout: None,
+            retries: 0,
+            backoff: Duration::from_millis(50),
+            server_ready_timeout: SERVER_READY_TIMEOUT,
+            server_ready_poll_interval: SERVER_READY_POLL_INTERVAL,
+        }
+    }
+
+    /// Kill each tmux invocation which does not complete within `timeout`.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry the invocations failing with a transient error up to `retries` times.
+    #[must_use]
+    pub const fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Wait `backoff` before the first retry, then twice as long before each next retry. This
+    /// defaults to 50ms.
+    #[must_use]
+    pub const fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Wait at most `timeout` for a server started by [`crate::server::start`] to accept
+    /// commands. This defaults to 5s.
+    #[must_use]
+    pub const fn server_ready_timeout(mut self, timeout: Duration) -> Self {
+        self.server_ready_timeout = timeout;
+        self
+    }
+
+    /// Check every `interval` whether a server started by [`crate::server::start`] accepts
+    /// commands. This defaults to 50ms.
+    #[must_use]
+    pub const fn server_ready_poll_interval(mut self, interval: Duration) -> Self {
+        self.server_ready_poll_interval = interval;
+        self
+    }
+
+    /// Run the ops awaited by `future` with this policy.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        Scoped {
+            policy: *self,
+            future: Box::pin(future),
+        }
+        .await
+    }
+
+    /// Run the blocking ops called by `f` with this policy.
+    #[cfg(feature = "blocking")]
+    pub fn scope_blocking<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = SCOPED.with(|scoped| scoped.replace(Some(*self)));
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// Maximum duration of an invocation, if any.
+    pub(crate) fn invocation_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Return the delay before the retry `attempt` (starting at 1) of an invocation which failed
+    /// with `stderr`, or `None` if it should not be retried.
+    pub(crate) fn retry_delay(
+        &self,
+        retry: Retry,
+        attempt: u32,
+        stderr: &[u8],
+    ) -> Option<Duration> {
+        if attempt > self.retries || !is_transient(retry, stderr) {
+            return None;
+        }
+        Some(
+            self.backoff
+                .saturating_mul(2u32.saturating_pow(attempt - 1)),
+        )
+    }
+
+    /// Maximum time to wait for a server to become ready, and delay between readiness checks.
+    pub(crate) fn server_ready(&self) -> (Duration, Duration) {
+        (self.server_ready_timeout, self.server_ready_poll_interval)
+    }
+}
+
+/// Error of an invocation killed after `timeout`.
+pub(crate) fn timed_out(timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("tmux did not complete within {timeout:?}"),
+    )
+}
+
+/// Policy of all the ops, unless overridden by [`Policy::scope`].
+static GLOBAL: RwLock<Policy> = RwLock::new(Policy::new());
+
+thread_local! {
+    /// Policy of the scope being polled or run on this thread, if any.
+    static SCOPED: RefCell<Option<Policy>> = const { RefCell::new(None) };
+}
+
+/// Set the policy of all the ops, except those within a [`Policy::scope`].
+pub fn set_global(policy: Policy) {
+    *GLOBAL.write().unwrap_or_else(PoisonError::into_inner) = policy;
+}
+
+/// Return the policy of all the ops, except those within a [`Policy::scope`].
+pub fn global() -> Policy {
+    *GLOBAL.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Return the policy of the ops run now on this thread.
+pub(crate) fn current() -> Policy {
+    SCOPED
+        .with(|scoped| *scoped.borrow())
+        .unwrap_or_else(global)
+}
+
+/// Return `true` if `stderr` reports a failure which may not happen again, and can be retried.
+fn is_transient(retry: Retry, stderr: &[u8]) -> bool {
+    let messages: &[&str] = match retry {
+        Retry::Never => return false,
+        Retry::Unsent => &UNSENT_ERRORS,
+        Retry::Lookups => &[UNSENT_ERRORS.as_slice(), LOOKUP_ERRORS.as_slice()].concat(),
+    };
+    let stderr = String::from_utf8_lossy(stderr);
+    messages.iter().any(|message| stderr.contains(message))
+}
+
+/// Future installing a policy on the thread each time it is polled.
+struct Scoped<F: Future> {
+    policy: Policy,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let previous = SCOPED.with(|scoped| scoped.replace(Some(self.policy)));
+        let _restore = Restore(previous);
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Restores the previous scoped policy of the thread on drop, even on panic.
+struct Restore(Option<Policy>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        SCOPED.with(|scoped| *scoped.borrow_mut() = self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays() {
+        let policy = Policy::new().retries(3).backoff(Duration::from_millis(10));
+        let transient = b"lost server\n";
+
+        let delays: Vec<_> = (1..=4)
+            .map(|attempt| policy.retry_delay(Retry::Unsent, attempt, transient))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(10)),
+                Some(Duration::from_millis(20)),
+                Some(Duration::from_millis(40)),
+                None
+            ]
+        );
+        assert_eq!(
+            policy.retry_delay(Retry::Unsent, 1, b"unknown command: foo\n"),
+            None
+        );
+        assert_eq!(Policy::new().retry_delay(Retry::Unsent, 1, transient), None);
+    }
+
+    #[test]
+    fn test_retried_failures() {
+        let policy = Policy::new().retries(1).backoff(Duration::from_millis(10));
+        let retried = |retry, stderr: &[u8]| policy.retry_delay(retry, 1, stderr).is_some();
+
+        assert!(retried(Retry::Unsent, b"lost server\n"));
+        assert!(!retried(Retry::Unsent, b"can't find pane: %3\n"));
+        assert!(retried(Retry::Lookups, b"can't find pane: %3\n"));
+        assert!(retried(Retry::Lookups, b"lost server\n"));
+        assert!(retried(
+            Retry::Unsent,
+            b"error connecting to /tmp/tmux-0/default (Resource temporarily unavailable)\n"
+        ));
+        // Printed after a command made the server exit, e.g. killing its last session.
+        assert!(!retried(Retry::Unsent, b"server exited unexpectedly\n"));
+        assert!(!retried(Retry::Never, b"lost server\n"));
+        assert!(!retried(Retry::Never, b"can't find session: =work\n"));
+    }
+
+    #[test]
+    fn test_scoped_policy() {
+        let scoped = Policy::new().retries(2);
+        assert_eq!(current(), global());
+
+        let inner = poll_once(scoped.scope(async { current() }));
+        assert_eq!(inner, scoped);
+        assert_eq!(current(), global());
+    }
+
+    /// Poll a future which completes without waiting.
+    fn poll_once<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let mut context = Context::from_waker(std::task::Waker::noop());
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future did not complete"),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Async runtime backend, selected with cargo features.
+//!
+//! Processes are spawned with the `Command` of the selected runtime, so that the async ops run
+//! natively on that runtime, without a second reactor. The features are, by order of precedence:
+//!
+//! - `tokio`: uses `tokio::process`, ops must be awaited inside a tokio runtime with both the IO
+//!   and time drivers enabled (`enable_io` and `enable_time`, or `enable_all`): child processes
+//!   are reaped with the IO driver, timeouts and retries use the time driver,
+//! - `async-std`: uses `async_std::process`,
+//! - `smol` (default): uses `smol::process`, ops can be awaited on any executor.
+//!
+//! Because `smol` is a default feature, disable the default features when selecting another
+//! runtime, in order to avoid compiling smol.
+//!
+//! The invocations follow the current [`Policy`](crate::policy::Policy): they are killed once
+//! they exceed its timeout, and retried when they fail with a transient error. The tmux process
+//! is also killed when the future of an invocation is dropped before completing.
+
+#[cfg(not(any(feature = "smol", feature = "tokio", feature = "async-std")))]
+compile_error!("tmux-lib requires one of the `smol`, `tokio` or `async-std` features");
+
+use std::{
+    ffi::{OsStr, OsString},
+    future::Future,
+    io,
+    process::Output,
+};
+
+pub(crate) use imp::{sleep, timeout};
+
+use crate::policy::{self, Policy, Retry};
+
+/// A tmux invocation, spawned with the `Command` of the selected runtime.
+///
+/// With the `fake` feature, the invocation is answered by the [`FakeServer`] installed for the
+/// current thread, if any.
+///
+/// [`FakeServer`]: crate::fake::FakeServer
+pub(crate) struct Command {
+    inner: imp::Command,
+    retry: Retry,
+    #[cfg(any(feature = "fake", feature = "tracing"))]
+    args: Vec<OsString>,
+}
+
+impl Command {
+    pub(crate) fn new(program: &str) -> Self {
+        let mut inner = imp::Command::new(program);
+        inner.kill_on_drop(true);
+        Self {
+            inner,
+            retry: Retry::Unsent,
+            #[cfg(any(feature = "fake", feature = "tracing"))]
+            args: vec![],
+        }
+    }
+
+    pub(crate) fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        self.inner.args(&args);
+        #[cfg(any(feature = "fake", feature = "tracing"))]
+        self.args.extend(args);
+        self
+    }
+
+    /// Set the failures after which the command is retried, see [`crate::policy`].
+    pub(crate) fn retry(&mut self, retry: Retry) -> &mut Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run the command and collect its output.
+    pub(crate) async fn output(&mut self) -> io::Result<Output> {
+        #[cfg(feature = "fake")]
+        if let Some(output) = crate::fake::intercept(&self.args, None) {
+            return Ok(output);
+        }
+        self.run(None).await
+    }
+
+    /// Run the command, writing `input` to its stdin if any, following the current policy.
+    async fn run(&mut self, input: Option<&[u8]>) -> io::Result<Output> {
+        let policy = policy::current();
+        let mut attempt = 0;
+        loop {
+            #[cfg(feature = "tracing")]
+            let invocation = crate::trace::Invocation::start(&self.args);
+            let output = match input {
+                Some(input) => {
+                    bounded(&policy, imp::output_with_stdin(&mut self.inner, input)).await
+                }
+                None => bounded(&policy, self.inner.output()).await,
+            };
+            #[cfg(feature = "tracing")]
+            invocation.finish(&output);
+            let output = output?;
+            if output.status.success() {
+                return Ok(output);
+            }
+            attempt += 1;
+            match policy.retry_delay(self.retry, attempt, &output.stderr) {
+                Some(delay) => sleep(delay).await,
+                None => return Ok(output),
+            }
+        }
+    }
+}
+
+/// Await the invocation `future` for at most the timeout of `policy`.
+async fn bounded(
+    policy: &Policy,
+    future: impl Future<Output = io::Result<Output>>,
+) -> io::Result<Output> {
+    match policy.invocation_timeout() {
+        Some(duration) => timeout(duration, future)
+            .await
+            .unwrap_or_else(|| Err(policy::timed_out(duration))),
+        None => future.await,
+    }
+}
+
+/// Spawn `command`, write `input` to its stdin, close it and collect the output.
+pub(crate) async fn output_with_stdin(mut command: Command, input: &[u8]) -> io::Result<Output> {
+    #[cfg(feature = "fake")]
+    if let Some(output) = crate::fake::intercept(&command.args, Some(input)) {
+        return Ok(output);
+    }
+    command.run(Some(input)).await
+}
+
+/// Spawn `command` with piped stdin and stdout, in order to exchange lines with it.
+///
+/// The process is killed when dropped. With the `fake` feature, this fails if a [`FakeServer`]
+/// is installed for the current thread, as it does not support long-running clients.
+///
+/// [`FakeServer`]: crate::fake::FakeServer
+pub(crate) fn spawn(command: Command) -> io::Result<Process> {
+    #[cfg(feature = "fake")]
+    if crate::fake::is_installed() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the fake server does not support control mode",
+        ));
+    }
+    Process::spawn(command.inner)
+}
+
+pub(crate) use imp::Process;
+
+#[cfg(feature = "tokio")]
+mod imp {
+    use std::{future::Future, io, process::Output, time::Duration};
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    pub(crate) use tokio::process::Command;
+    use tokio::process::{Child, ChildStdin, ChildStdout};
+
+    /// Wait for `duration`.
+    pub(crate) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    /// Await `future` for at most `duration`, returning `None` if it did not complete in time.
+    pub(crate) async fn timeout<T>(
+        duration: Duration,
+        future: impl Future<Output = T>,
+    ) -> Option<T> {
+        tokio::time::timeout(duration, future).await.ok()
+    }
+
+    /// Spawn `command`, write `input` to its stdin, close it and collect the output.
+    pub(crate) async fn output_with_stdin(
+        command: &mut Command,
+        input: &[u8],
+    ) -> io::Result<Output> {
+        let mut child = super::piped(command).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input).await?;
+            stdin.shutdown().await?;
+        }
+        child.wait_with_output().await
+    }
+
+    /// A process with piped stdin and stdout.
+    pub(crate) struct Process {
+        _child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+    }
+
+    impl Process {
+        pub(super) fn spawn(mut command: Command) -> io::Result<Self> {
+            let mut child = super::piped_lines(&mut command).spawn()?;
+            let (stdin, stdout) = super::take_pipes(child.stdin.take(), child.stdout.take())?;
+            Ok(Self {
+                _child: child,
+                stdin,
+                stdout: BufReader::new(stdout),
+            })
+        }
+
+        /// Write `bytes` to the stdin of the process, and flush it.
+        pub(crate) async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.stdin.write_all(bytes).await?;
+            self.stdin.flush().await
+        }
+
+        /// Read a line from the stdout of the process, including its newline, into `buf`.
+        /// Returns 0 at the end of the output.
+        pub(crate) async fn read_line(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+            self.stdout.read_until(b'\n', buf).await
+        }
+    }
+}
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+mod imp {
+    use std::{future::Future, io, process::Output, time::Duration};
+
+    use async_std::io::{BufReader, WriteExt, prelude::BufReadExt};
+    pub(crate) use async_std::process::Command;
+    use async_std::process::{Child, ChildStdin, ChildStdout};
+
+    /// Wait for `duration`.
+    pub(crate) async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await;
+    }
+
+    /// Await `future` for at most `duration`, returning `None` if it did not complete in time.
+    pub(crate) async fn timeout<T>(
+        duration: Duration,
+        future: impl Future<Output = T>,
+    ) -> Option<T> {
+        async_std::future::timeout(duration, future).await.ok()
+    }
+
+    /// Spawn `command`, write `input` to its stdin, close it and collect the output.
+    pub(crate) async fn output_with_stdin(
+        command: &mut Command,
+        input: &[u8],
+    ) -> io::Result<Output> {
+        let mut child = super::piped(command).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input).await?;
+            // Dropping the stdin closes it.
+            stdin.flush().await?;
+        }
+        child.output().await
+    }
+
+    /// A process with piped stdin and stdout.
+    pub(crate) struct Process {
+        _child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+    }
+
+    impl Process {
+        pub(super) fn spawn(mut command: Command) -> io::Result<Self> {
+            let mut child = super::piped_lines(&mut command).spawn()?;
+            let (stdin, stdout) = super::take_pipes(child.stdin.take(), child.stdout.take())?;
+            Ok(Self {
+                _child: child,
+                stdin,
+                stdout: BufReader::new(stdout),
+            })
+        }
+
+        /// Write `bytes` to the stdin of the process, and flush it.
+        pub(crate) async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.stdin.write_all(bytes).await?;
+            self.stdin.flush().await
+        }
+
+        /// Read a line from the stdout of the process, including its newline, into `buf`.
+        /// Returns 0 at the end of the output.
+        pub(crate) async fn read_line(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+            self.stdout.read_until(b'\n', buf).await
+        }
+    }
+}
+
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+mod imp {
+    use std::{future::Future, io, process::Output, time::Duration};
+
+    pub(crate) use smol::process::Command;
+    use smol::{
+        Timer, future,
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        process::{Child, ChildStdin, ChildStdout},
+    };
+
+    /// Wait for `duration`.
+    pub(crate) async fn sleep(duration: Duration) {
+        Timer::after(duration).await;
+    }
+
+    /// Await `future` for at most `duration`, returning `None` if it did not complete in time.
+    pub(crate) async fn timeout<T>(
+        duration: Duration,
+        future: impl Future<Output = T>,
+    ) -> Option<T> {
+        let expired = async {
+            Timer::after(duration).await;
+            None
+        };
+        future::or(async { Some(future.await) }, expired).await
+    }
+
+    /// Spawn `command`, write `input` to its stdin, close it and collect the output.
+    pub(crate) async fn output_with_stdin(
+        command: &mut Command,
+        input: &[u8],
+    ) -> io::Result<Output> {
+        let mut child = super::piped(command).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input).await?;
+            stdin.close().await?;
+        }
+        child.output().await
+    }
+
+    /// A process with piped stdin and stdout.
+    pub(crate) struct Process {
+        _child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+    }
+
+    impl Process {
+        pub(super) fn spawn(mut command: Command) -> io::Result<Self> {
+            let mut child = super::piped_lines(&mut command).spawn()?;
+            let (stdin, stdout) = super::take_pipes(child.stdin.take(), child.stdout.take())?;
+            Ok(Self {
+                _child: child,
+                stdin,
+                stdout: BufReader::new(stdout),
+            })
+        }
+
+        /// Write `bytes` to the stdin of the process, and flush it.
+        pub(crate) async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.stdin.write_all(bytes).await?;
+            self.stdin.flush().await
+        }
+
+        /// Read a line from the stdout of the process, including its newline, into `buf`.
+        /// Returns 0 at the end of the output.
+        pub(crate) async fn read_line(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+            self.stdout.read_until(b'\n', buf).await
+        }
+    }
+}
+
+/// Pipe the stdin, stdout and stderr of `command`.
+fn piped(command: &mut imp::Command) -> &mut imp::Command {
+    use std::process::Stdio;
+
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+}
+
+/// Pipe the stdin and stdout of `command`, discarding its stderr.
+fn piped_lines(command: &mut imp::Command) -> &mut imp::Command {
+    use std::process::Stdio;
+
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+}
+
+/// Return the stdin and stdout taken from a spawned child, which were piped.
+fn take_pipes<I, O>(stdin: Option<I>, stdout: Option<O>) -> io::Result<(I, O)> {
+    match (stdin, stdout) {
+        (Some(stdin), Some(stdout)) => Ok((stdin, stdout)),
+        _ => Err(io::Error::other(
+            "the stdin or stdout of the process is not piped",
+        )),
+    }
+}
@@ -1,19 +1,324 @@
 //! Server management.
+//!
+//! Besides the ops on the server of the default socket, [`discover`] lists the sockets of all the
+//! servers of the current user, and a [`Server`] handle queries, starts or kills the server of a
+//! given socket:
+//!
+//! ```no_run
+//! # async fn list() -> tmux_lib::Result<()> {
+//! use tmux_lib::server;
+//!
+//! for server in server::discover(&server::socket_directory()?)? {
+//!     if server.is_alive() {
+//!         let info = server.info().await?;
+//!         println!("{}: {} sessions", info.socket_path.display(), info.session_count);
+//!     } else {
+//!         server.remove_if_stale()?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsStr,
+    fs, io,
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt},
+        net::UnixStream,
+    },
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use std::{collections::HashMap, time::Duration};
-
-use smol::{Timer, future, process::Command};
+use nom::{
+    IResult, Parser,
+    character::complete::{char, digit1, not_line_ending},
+    combinator::{all_consuming, map_res},
+};
 
 use crate::{
     Result,
-    error::{Error, check_empty_process_output},
+    error::{
+        Error, check_empty_process_output, check_kill_output, check_process_success, map_add_intent,
+    },
+    format::intent,
+    policy,
+    runtime::{Command, sleep, timeout},
+    version::Version,
 };
 
-/// Maximum time to wait for the server to become ready.
-const SERVER_READY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default maximum time to wait for the server to become ready.
+pub(crate) const SERVER_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default delay between readiness checks.
+pub(crate) const SERVER_READY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Format of the server description printed by tmux, parsed by `parse_server_info`.
+pub(crate) const INFO_FORMAT: &str = "#{pid}:#{start_time}:#{version}";
+
+/// Handle on the Tmux server listening on a given socket.
+///
+/// The ops of this crate address the server of the default socket, or the one given by `$TMUX`
+/// when running inside tmux. A `Server` runs commands on a specific server instead (`tmux -S`),
+/// such as the one enclosing the current process, see [`crate::environment`].
+///
+/// ```no_run
+/// # async fn list() -> tmux_lib::Result<()> {
+/// use tmux_lib::server::Server;
+///
+/// let server = Server::new("/tmp/tmux-1000/work");
+/// let names = server.run(["list-sessions", "-F", "#{session_name}"]).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Server {
+    socket_path: PathBuf,
+}
+
+impl Server {
+    /// Return a handle on the server listening on the socket at `socket_path`.
+    #[must_use]
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    /// Return a handle on the server of the socket named `name` in the [`socket_directory`],
+    /// like `tmux -L`.
+    pub fn from_name(name: &str) -> io::Result<Self> {
+        Ok(Self::new(socket_directory()?.join(name)))
+    }
+
+    /// Path of the socket of the server.
+    #[must_use]
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Return `true` if a server accepts connections on the socket.
+    ///
+    /// The socket of a server which crashed or was killed may be left behind: it is then stale.
+    /// This also returns `false` when the server is too busy to accept a connection, or the socket
+    /// cannot be accessed, use [`Server::remove_if_stale`] to tell these cases apart.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        UnixStream::connect(&self.socket_path).is_ok()
+    }
+
+    /// Remove the socket if it is stale, and return `true` if it was removed.
+    ///
+    /// As with tmux, the socket is stale only if connecting to it is refused: other errors, such
+    /// as a busy server or a denied access, are returned and the socket is kept.
+    pub fn remove_if_stale(&self) -> io::Result<bool> {
+        match UnixStream::connect(&self.socket_path) {
+            Ok(_) => return Ok(false),
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        }
+        match fs::remove_file(&self.socket_path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Describe the server: its pid, version, start time, and number of sessions and clients.
+    pub async fn info(&self) -> Result<ServerInfo> {
+        let buffer = self.run(INFO_ARGS).await?;
+        let mut info = self.parse_info(&buffer)?;
+        // Without sessions, `list-clients` fails for lack of a target, and no client is attached.
+        if info.session_count > 0 {
+            info.client_count = self.run(CLIENTS_ARGS).await?.lines().count();
+        }
+
+        Ok(info)
+    }
+
+    /// Parse the output of [`INFO_ARGS`]: the description of the server, followed by one line per
+    /// session.
+    pub(crate) fn parse_info(&self, buffer: &str) -> Result<ServerInfo> {
+        let desc = "ServerInfo";
+        let intent = intent!(INFO_FORMAT);
+
+        let mut lines = buffer.lines();
+        let (_, (pid, start_time, version)) = all_consuming(parse::server_info)
+            .parse(lines.next().unwrap_or_default())
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(ServerInfo {
+            socket_path: self.socket_path.clone(),
+            pid,
+            version: Version::from_str(version)?,
+            start_time,
+            session_count: lines.count(),
+            client_count: 0,
+        })
+    }
+
+    /// Start the server, loading `config_file` instead of the default config files if given.
+    ///
+    /// The server exits right away unless the config creates a session or turns the
+    /// `exit-empty` option off.
+    pub async fn start(&self, config_file: Option<&Path>) -> Result<()> {
+        self.run(self.start_args(config_file)).await.map(|_| ())
+    }
+
+    /// Arguments of `start-server`, see [`Server::start`].
+    pub(crate) fn start_args<'a>(&self, config_file: Option<&'a Path>) -> Vec<&'a OsStr> {
+        let mut args = vec![];
+        if let Some(config_file) = config_file {
+            args.extend([OsStr::new("-f"), config_file.as_os_str()]);
+        }
+        args.push(OsStr::new("start-server"));
+        args
+    }
+
+    /// Kill the server, along with all its sessions and clients.
+    pub async fn kill(&self) -> Result<()> {
+        self.run(["kill-server"]).await.map(|_| ())
+    }
+
+    /// Run the tmux command `args` on this server, and return its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::UnexpectedTmuxOutput` if tmux fails, for instance if the server is not
+    /// running.
+    pub async fn run<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let output = Command::new("tmux")
+            .args(self.socket_args())
+            .args(args)
+            .output()
+            .await?;
+        check_process_success(&output, "server-command")?;
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Arguments selecting the socket of the server, passed before the command.
+    pub(crate) fn socket_args(&self) -> [&OsStr; 2] {
+        [OsStr::new("-S"), self.socket_path.as_os_str()]
+    }
+}
+
+/// Arguments describing a server, parsed by `Server::parse_info`.
+pub(crate) const INFO_ARGS: [&str; 7] = [
+    "display-message",
+    "-p",
+    INFO_FORMAT,
+    ";",
+    "list-sessions",
+    "-F",
+    "session",
+];
+
+/// Arguments listing the clients of a server, one per line.
+pub(crate) const CLIENTS_ARGS: [&str; 3] = ["list-clients", "-F", "client"];
+
+/// Description of a running Tmux server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// Path of the socket of the server.
+    pub socket_path: PathBuf,
+    /// PID of the server.
+    pub pid: u32,
+    /// Version of the server.
+    pub version: Version,
+    /// Start time of the server, in seconds since the Unix epoch.
+    pub start_time: u64,
+    /// Number of sessions.
+    pub session_count: usize,
+    /// Number of attached clients.
+    pub client_count: usize,
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    /// Parse the pid, start time and version of a server, printed with [`INFO_FORMAT`].
+    pub(crate) fn server_info(input: &str) -> IResult<&str, (u32, u64, &str)> {
+        let (input, (pid, _, start_time, _, version)) = (
+            map_res(digit1, str::parse),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            not_line_ending,
+        )
+            .parse(input)?;
+
+        Ok((input, (pid, start_time, version)))
+    }
+}
 
-/// Delay between readiness checks.
-const SERVER_READY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Return the directory of the sockets of the current user, `$TMUX_TMPDIR/tmux-<uid>`, or
+/// `/tmp/tmux-<uid>` if `$TMUX_TMPDIR` is not set.
+pub fn socket_directory() -> io::Result<PathBuf> {
+    let parent = env::var_os("TMUX_TMPDIR")
+        .filter(|dirpath| !dirpath.is_empty())
+        .map_or_else(|| PathBuf::from("/tmp"), PathBuf::from);
+
+    Ok(parent.join(format!("tmux-{}", current_uid()?)))
+}
+
+/// Return the uid of the current process.
+fn current_uid() -> io::Result<u32> {
+    // On Linux, `/proc/self` belongs to the user of the process.
+    if let Ok(metadata) = fs::metadata("/proc/self") {
+        return Ok(metadata.uid());
+    }
+
+    let output = std::process::Command::new("id").arg("-u").output()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::other("failed reading the uid with `id -u`"))
+}
+
+/// Return a handle on the server of each socket in `directory`, alive or stale, sorted by socket
+/// path.
+///
+/// A missing directory holds no socket.
+pub fn discover(directory: &Path) -> Result<Vec<Server>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut servers = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_socket() {
+            servers.push(Server::new(entry.path()));
+        }
+    }
+    servers.sort_by(|a, b| a.socket_path.cmp(&b.socket_path));
+
+    Ok(servers)
+}
+
+/// Remove the stale sockets in `directory`, and return their paths.
+pub fn remove_stale_sockets(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = vec![];
+    for server in discover(directory)? {
+        if server.remove_if_stale()? {
+            removed.push(server.socket_path);
+        }
+    }
+
+    Ok(removed)
+}
 
 // ------------------------------
 // Ops
@@ -38,8 +343,10 @@ pub async fn start(initial_session_name: &str) -> Result<()> {
 
 /// Wait for the tmux server to be ready to accept commands.
 ///
-/// This polls the server using `tmux list-sessions` until it succeeds or times out.
+/// This polls the server using `tmux list-sessions` until it succeeds or times out, as configured
+/// by the current [`Policy`](crate::policy::Policy).
 async fn wait_for_server_ready() -> Result<()> {
+    let (ready_timeout, poll_interval) = policy::current().server_ready();
     let poll = async {
         loop {
             let output = Command::new("tmux")
@@ -51,23 +358,17 @@ async fn wait_for_server_ready() -> Result<()> {
                 return Ok(());
             }
 
-            Timer::after(SERVER_READY_POLL_INTERVAL).await;
+            sleep(poll_interval).await;
         }
     };
 
-    let timeout = async {
-        Timer::after(SERVER_READY_TIMEOUT).await;
+    timeout(ready_timeout, poll).await.unwrap_or_else(|| {
         Err(Error::UnexpectedTmuxOutput {
             intent: "wait-for-server-ready",
             stdout: String::new(),
-            stderr: format!(
-                "server did not become ready within {:?}",
-                SERVER_READY_TIMEOUT
-            ),
+            stderr: format!("server did not become ready within {ready_timeout:?}"),
         })
-    };
-
-    future::or(poll, timeout).await
+    })
 }
 
 /// Remove the session named `"[placeholder]"` used to keep the server alive.
@@ -76,7 +377,7 @@ pub async fn kill_session(name: &str) -> Result<()> {
     let args = vec!["kill-session", "-t", &exact_name];
 
     let output = Command::new("tmux").args(&args).output().await?;
-    check_empty_process_output(&output, "kill-session")
+    check_kill_output(&output, "kill-session")
 }
 
 /// Return the value of a Tmux option. For instance, this can be used to get Tmux's default
@@ -90,12 +391,18 @@ pub async fn show_option(option_name: &str, global: bool) -> Result<Option<Strin
 
     let output = Command::new("tmux").args(&args).output().await?;
     let buffer = String::from_utf8(output.stdout)?;
+
+    Ok(parse_option(&buffer))
+}
+
+/// Parse the output of `tmux show-options -w -q <option>`, empty if the option is not set.
+pub(crate) fn parse_option(buffer: &str) -> Option<String> {
     let buffer = buffer.trim_end();
 
     if buffer.is_empty() {
-        return Ok(None);
+        return None;
     }
-    Ok(Some(buffer.to_string()))
+    Some(buffer.to_string())
 }
 
 /// Return all Tmux options as a `HashMap`.
@@ -116,7 +423,7 @@ pub async fn show_options(global: bool) -> Result<HashMap<String, String>> {
 ///
 /// Lines without a space (bare flags) are skipped. Values that are empty or
 /// equal to `''` are filtered out.
-fn parse_options(buffer: &str) -> HashMap<String, String> {
+pub(crate) fn parse_options(buffer: &str) -> HashMap<String, String> {
     buffer
         .trim_end()
         .split('\n')
@@ -133,6 +440,11 @@ fn parse_options(buffer: &str) -> HashMap<String, String> {
 pub async fn default_command() -> Result<String> {
     let all_options = show_options(true).await?;
 
+    select_default_command(&all_options)
+}
+
+/// Select the default command among the global options, see [`default_command`].
+pub(crate) fn select_default_command(all_options: &HashMap<String, String>) -> Result<String> {
     let default_shell = all_options
         .get("default-shell")
         .ok_or(Error::TmuxConfig("no default-shell"))
@@ -154,7 +466,8 @@ pub async fn default_command() -> Result<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_options;
+    use super::{Path, PathBuf, Server, discover, parse_options, remove_stale_sockets};
+    use std::{fs, os::unix::net::UnixListener};
 
     #[test]
     fn parse_options_typical_output() {
@@ -208,4 +521,73 @@ mod tests {
 
         assert_eq!(opts.get("key").unwrap(), "value-with-extra-spaces");
     }
+
+    #[test]
+    fn parse_server_info() {
+        let server = Server::new("/tmp/tmux-1000/default");
+        let info = server
+            .parse_info("2533:1700000000:3.3a\nsession\nsession\n")
+            .unwrap();
+
+        assert_eq!(info.socket_path, PathBuf::from("/tmp/tmux-1000/default"));
+        assert_eq!(info.pid, 2533);
+        assert_eq!(info.start_time, 1_700_000_000);
+        assert_eq!(info.version.to_string(), "3.3a");
+        assert_eq!(info.session_count, 2);
+        assert_eq!(info.client_count, 0);
+
+        assert!(server.parse_info("2533:3.3a\n").is_err());
+        assert!(server.parse_info("").is_err());
+    }
+
+    #[test]
+    fn start_server_args() {
+        let server = Server::new("/tmp/tmux-1000/work");
+        assert_eq!(server.start_args(None), ["start-server"]);
+        assert_eq!(
+            server.start_args(Some(Path::new("/etc/work.conf"))),
+            ["-f", "/etc/work.conf", "start-server"]
+        );
+    }
+
+    #[test]
+    fn discover_and_remove_stale_sockets() {
+        let directory =
+            std::env::temp_dir().join(format!("tmux-lib-sockets-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        assert!(discover(&directory).unwrap().is_empty());
+        fs::create_dir_all(&directory).unwrap();
+
+        let _alive = UnixListener::bind(directory.join("alive")).unwrap();
+        drop(UnixListener::bind(directory.join("stale")).unwrap());
+        fs::write(directory.join("file"), "not a socket").unwrap();
+
+        let servers = discover(&directory).unwrap();
+        assert_eq!(
+            servers,
+            [
+                Server::new(directory.join("alive")),
+                Server::new(directory.join("stale"))
+            ]
+        );
+        assert!(servers[0].is_alive());
+        assert!(!servers[1].is_alive());
+
+        assert_eq!(
+            remove_stale_sockets(&directory).unwrap(),
+            [directory.join("stale")]
+        );
+        assert_eq!(
+            discover(&directory).unwrap(),
+            [Server::new(directory.join("alive"))]
+        );
+
+        // A path too long for a socket address fails with another error than a refusal.
+        let long = directory.join("x".repeat(120));
+        fs::write(&long, "").unwrap();
+        assert!(Server::new(&long).remove_if_stale().is_err());
+        assert!(long.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
 }
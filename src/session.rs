@@ -3,7 +3,7 @@
 //! The main use cases are running Tmux commands & parsing Tmux session
 //! information.
 
-use std::{path::PathBuf, str::FromStr};
+use std::{ffi::OsString, path::PathBuf, process::Output, str::FromStr};
 
 use nom::{
     IResult, Parser,
@@ -11,19 +11,30 @@ use nom::{
     combinator::all_consuming,
 };
 use serde::{Deserialize, Serialize};
-use smol::process::Command;
 
 use crate::{
     Result,
-    error::{Error, check_process_success, map_add_intent},
+    error::{
+        Error, check_empty_process_output, check_kill_output, check_process_success, map_add_intent,
+    },
+    format::intent,
     pane::Pane,
     pane_id::{PaneId, parse::pane_id},
     parse::quoted_nonempty_string,
+    policy::Retry,
+    runtime::Command,
     session_id::{SessionId, parse::session_id},
+    version::{self, Capability},
     window::Window,
     window_id::{WindowId, parse::window_id},
 };
 
+/// Format of the sessions listed by tmux, parsed by `Session::from_str`.
+pub(crate) const FORMAT: &str = "#{session_id}:'#{session_name}':#{session_path}";
+
+/// Format of the ids printed by tmux when creating a session.
+pub(crate) const NEW_SESSION_FORMAT: &str = "#{session_id}:#{window_id}:#{pane_id}";
+
 /// A Tmux session.
 ///
 /// ```
@@ -73,7 +84,7 @@ impl FromStr for Session {
     /// definitions.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "Session";
-        let intent = "##{session_id}:'##{session_name}':##{session_path}";
+        let intent = intent!(FORMAT);
 
         let (_, sess) = all_consuming(parse::session)
             .parse(input)
@@ -113,15 +124,16 @@ pub(crate) mod parse {
 
 /// Return a list of all `Session` from the current tmux session.
 pub async fn available_sessions() -> Result<Vec<Session>> {
-    let args = vec![
-        "list-sessions",
-        "-F",
-        "#{session_id}:'#{session_name}':#{session_path}",
-    ];
+    let args = vec!["list-sessions", "-F", FORMAT];
 
     let output = Command::new("tmux").args(&args).output().await?;
     let buffer = String::from_utf8(output.stdout)?;
 
+    parse_sessions(&buffer)
+}
+
+/// Parse the output of `list-sessions` with [`FORMAT`].
+pub(crate) fn parse_sessions(buffer: &str) -> Result<Vec<Session>> {
     // Each call to `Session::parse` returns a `Result<Session, _>`. All results
     // are collected into a Result<Vec<Session>, _>, thanks to `collect()`.
     let result: Result<Vec<Session>> = buffer
@@ -140,53 +152,348 @@ pub async fn available_sessions() -> Result<Vec<Session>> {
 /// - the session name is taken from the passed `session`
 /// - the working directory is taken from the pane's working directory.
 ///
+/// See [`NewSession`] for more options.
 pub async fn new_session(
     session: &Session,
     window: &Window,
     pane: &Pane,
     pane_command: Option<&str>,
 ) -> Result<(SessionId, WindowId, PaneId)> {
-    let mut args = vec![
-        "new-session",
-        "-d",
-        "-c",
-        pane.dirpath.to_str().unwrap(),
-        "-s",
-        &session.name,
-        "-n",
-        &window.name,
-        "-P",
-        "-F",
-        "#{session_id}:#{window_id}:#{pane_id}",
-    ];
+    let mut new_session = NewSession::new()
+        .name(&session.name)
+        .window_name(&window.name)
+        .start_directory(&pane.dirpath);
     if let Some(pane_command) = pane_command {
-        args.push(pane_command);
+        new_session = new_session.command(pane_command);
+    }
+
+    new_session.run().await
+}
+
+/// Options for creating a Tmux session, and thus a window & pane.
+///
+/// By default, the session is created detached, in tmux's default directory, with an automatic
+/// name.
+///
+/// ```no_run
+/// # async fn create() -> tmux_lib::Result<()> {
+/// use tmux_lib::session::NewSession;
+///
+/// let (session_id, window_id, pane_id) = NewSession::new()
+///     .name("work")
+///     .window_name("editor")
+///     .start_directory("/home/user/code")
+///     .env("EDITOR", "nvim")
+///     .size(200, 50)
+///     .command("nvim")
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewSession {
+    name: Option<String>,
+    window_name: Option<String>,
+    start_directory: Option<PathBuf>,
+    environment: Vec<(String, String)>,
+    size: Option<(u16, u16)>,
+    detached: bool,
+    attach_if_exists: bool,
+    group_with: Option<SessionId>,
+    command: Option<String>,
+}
+
+impl Default for NewSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NewSession {
+    /// Prepare creating a detached session.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            window_name: None,
+            start_directory: None,
+            environment: vec![],
+            size: None,
+            detached: true,
+            attach_if_exists: false,
+            group_with: None,
+            command: None,
+        }
+    }
+
+    /// Set the session name (`-s`).
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the name of the initial window (`-n`).
+    #[must_use]
+    pub fn window_name(mut self, name: impl Into<String>) -> Self {
+        self.window_name = Some(name.into());
+        self
+    }
+
+    /// Set the working directory of the session and its initial pane (`-c`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the session (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the initial size of the session, in cells (`-x` and `-y`).
+    #[must_use]
+    pub fn size(mut self, width: u16, height: u16) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Create the session detached (`-d`), which is the default. Otherwise, tmux attaches the
+    /// session to the current terminal.
+    #[must_use]
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.detached = detached;
+        self
+    }
+
+    /// Attach to the session if a session with the same name already exists, instead of failing
+    /// (`-A`).
+    ///
+    /// In that case, tmux attaches the current terminal to the existing session, even if the
+    /// session is created detached: this fails if there is no terminal. This requires a
+    /// [`name`](NewSession::name): without it, tmux attaches to the current session instead.
+    #[must_use]
+    pub fn attach_if_exists(mut self, attach_if_exists: bool) -> Self {
+        self.attach_if_exists = attach_if_exists;
+        self
+    }
+
+    /// Put the new session in the same group as the session with `session_id` (`-t`): sessions
+    /// in a group share the same set of windows.
+    #[must_use]
+    pub fn group_with(mut self, session_id: &SessionId) -> Self {
+        self.group_with = Some(session_id.clone());
+        self
+    }
+
+    /// Set the shell command run in the initial pane.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["new-session".into()];
+        if self.detached {
+            args.push("-d".into());
+        }
+        if self.attach_if_exists {
+            args.push("-A".into());
+        }
+        if let Some(dirpath) = &self.start_directory {
+            args.push("-c".into());
+            args.push(dirpath.into());
+        }
+        for (name, value) in &self.environment {
+            args.push("-e".into());
+            args.push(format!("{name}={value}").into());
+        }
+        if let Some(name) = &self.name {
+            args.push("-s".into());
+            args.push(name.into());
+        }
+        if let Some(window_name) = &self.window_name {
+            args.push("-n".into());
+            args.push(window_name.into());
+        }
+        if let Some(session_id) = &self.group_with {
+            args.push("-t".into());
+            args.push(session_id.as_str().into());
+        }
+        if let Some((width, height)) = self.size {
+            args.push("-x".into());
+            args.push(width.to_string().into());
+            args.push("-y".into());
+            args.push(height.to_string().into());
+        }
+        args.push("-P".into());
+        args.push("-F".into());
+        args.push(NEW_SESSION_FORMAT.into());
+        if let Some(command) = &self.command {
+            args.push(command.into());
+        }
+        args
+    }
+
+    /// Capability of tmux required by the options, if any.
+    pub(crate) fn capability(&self) -> Option<Capability> {
+        (!self.environment.is_empty()).then_some(Capability::SessionEnvironment)
+    }
+
+    /// Ensure that the options can be used together.
+    pub(crate) fn check_options(&self) -> Result<()> {
+        if self.attach_if_exists && self.name.is_none() {
+            return Err(Error::InvalidOptions(
+                "`new-session -A` requires a session name",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Create the session, and return the ids of the session, its window and its pane.
+    ///
+    /// If the session already existed and [`NewSession::attach_if_exists`] was set, this returns
+    /// the ids of the existing session and of its current window & pane.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::InvalidOptions` if [`NewSession::attach_if_exists`] was set without a
+    /// name.
+    pub async fn run(&self) -> Result<(SessionId, WindowId, PaneId)> {
+        self.check_options()?;
+        if let Some(capability) = self.capability() {
+            version::require(capability).await?;
+        }
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+
+        // Check exit status before parsing to avoid confusing parse errors
+        // when tmux fails and returns empty/garbage stdout.
+        check_process_success(&output, "new-session")?;
+
+        let mut buffer = String::from_utf8(output.stdout)?;
+
+        // When attaching to an existing session, tmux does not print the new ids.
+        if let Some(args) = self.existing_session_args(&buffer) {
+            let output = Command::new("tmux").args(&args).output().await?;
+            check_process_success(&output, "display-message")?;
+            buffer = String::from_utf8(output.stdout)?;
+        }
+
+        Self::parse_ids(&buffer)
+    }
+
+    /// Arguments for looking up the ids of the existing session, if `buffer`, the output of
+    /// `new-session`, is empty because the session already existed.
+    pub(crate) fn existing_session_args(&self, buffer: &str) -> Option<Vec<String>> {
+        match &self.name {
+            Some(name) if self.attach_if_exists && buffer.is_empty() => Some(vec![
+                "display-message".into(),
+                "-p".into(),
+                "-t".into(),
+                format!("={name}:"),
+                NEW_SESSION_FORMAT.into(),
+            ]),
+            _ => None,
+        }
+    }
+
+    /// Parse the ids printed with [`NEW_SESSION_FORMAT`].
+    pub(crate) fn parse_ids(buffer: &str) -> Result<(SessionId, WindowId, PaneId)> {
+        let desc = "new-session";
+        let intent = intent!(NEW_SESSION_FORMAT);
+        let (_, (new_session_id, _, new_window_id, _, new_pane_id)) =
+            all_consuming((session_id, char(':'), window_id, char(':'), pane_id))
+                .parse(buffer.trim_end())
+                .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok((new_session_id, new_window_id, new_pane_id))
     }
+}
+
+/// Rename the session with `session_id`.
+pub async fn rename_session(session_id: &SessionId, name: &str) -> Result<()> {
+    let args = vec!["rename-session", "-t", session_id.as_str(), name];
 
     let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "rename-session")
+}
 
-    // Check exit status before parsing to avoid confusing parse errors
-    // when tmux fails and returns empty/garbage stdout.
-    check_process_success(&output, "new-session")?;
+/// Kill the session with `session_id`.
+///
+/// Windows which are linked to other sessions are only unlinked from this session. Killing the
+/// last session makes the server exit, which is not an error.
+pub async fn kill_session(session_id: &SessionId) -> Result<()> {
+    let args = vec!["kill-session", "-t", session_id.as_str()];
 
-    let buffer = String::from_utf8(output.stdout)?;
-    let buffer = buffer.trim_end();
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_kill_output(&output, "kill-session")
+}
 
-    let desc = "new-session";
-    let intent = "##{session_id}:##{window_id}:##{pane_id}";
-    let (_, (new_session_id, _, new_window_id, _, new_pane_id)) =
-        all_consuming((session_id, char(':'), window_id, char(':'), pane_id))
-            .parse(buffer)
-            .map_err(|e| map_add_intent(desc, intent, e))?;
+/// Return `true` if a session exactly named `session_name` exists.
+///
+/// This also returns `false` if no tmux server is running.
+pub async fn has_session(session_name: &str) -> Result<bool> {
+    let exact_session_name = format!("={session_name}");
+    let args = vec!["has-session", "-t", &exact_session_name];
+
+    // A missing session is the answer, not a lookup to retry.
+    let output = Command::new("tmux")
+        .args(&args)
+        .retry(Retry::Never)
+        .output()
+        .await?;
+    Ok(output.status.success())
+}
+
+/// Create a detached session named `session_name`, grouped with the session with `session_id`
+/// (`new-session -t`), and return its id.
+///
+/// Sessions in a group share the same set of windows, but each session has its own current
+/// window.
+pub async fn new_grouped_session(session_id: &SessionId, session_name: &str) -> Result<SessionId> {
+    let (new_session_id, _, _) = NewSession::new()
+        .name(session_name)
+        .group_with(session_id)
+        .run()
+        .await?;
+
+    Ok(new_session_id)
+}
+
+/// Detach all clients attached to the session with `session_id`.
+///
+/// This succeeds if no client is attached.
+pub async fn detach_clients(session_id: &SessionId) -> Result<()> {
+    let args = vec!["detach-client", "-s", session_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_detach_output(&output)
+}
 
-    Ok((new_session_id, new_window_id, new_pane_id))
+/// Ensure that `detach-client -s` succeeded.
+///
+/// tmux resolves a client even with `-s`, and fails with `no current client` when none is
+/// attached to the server: then no client is attached to the session either.
+pub(crate) fn check_detach_output(output: &Output) -> Result<()> {
+    if output.stderr.starts_with(b"no current client") {
+        return Ok(());
+    }
+    check_empty_process_output(output, "detach-client")
 }
 
 #[cfg(test)]
 mod tests {
+    use super::NewSession;
     use super::Session;
     use super::SessionId;
-    use crate::Result;
+    use crate::{Result, error::Error};
     use std::path::PathBuf;
     use std::str::FromStr;
 
@@ -298,4 +605,87 @@ mod tests {
 
         assert_eq!(session.dirpath, PathBuf::from("/path/with:colon/here"));
     }
+
+    #[test]
+    fn new_session_default_args() {
+        let args = NewSession::new().args();
+
+        assert_eq!(
+            args,
+            [
+                "new-session",
+                "-d",
+                "-P",
+                "-F",
+                "#{session_id}:#{window_id}:#{pane_id}"
+            ]
+        );
+    }
+
+    #[test]
+    fn new_session_all_args() {
+        let group = SessionId::from_str("$4").unwrap();
+        let args = NewSession::new()
+            .name("work")
+            .window_name("editor")
+            .start_directory("/home/user/My Projects")
+            .env("EDITOR", "nvim")
+            .size(200, 50)
+            .detached(false)
+            .attach_if_exists(true)
+            .group_with(&group)
+            .command("nvim .")
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "new-session",
+                "-A",
+                "-c",
+                "/home/user/My Projects",
+                "-e",
+                "EDITOR=nvim",
+                "-s",
+                "work",
+                "-n",
+                "editor",
+                "-t",
+                "$4",
+                "-x",
+                "200",
+                "-y",
+                "50",
+                "-P",
+                "-F",
+                "#{session_id}:#{window_id}:#{pane_id}",
+                "nvim .",
+            ]
+        );
+    }
+
+    #[test]
+    fn new_session_attach_requires_name() {
+        let new_session = NewSession::new().attach_if_exists(true);
+        assert!(matches!(
+            new_session.check_options(),
+            Err(Error::InvalidOptions(_))
+        ));
+
+        assert!(new_session.name("work").check_options().is_ok());
+        assert!(NewSession::new().check_options().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_session_non_utf8_start_directory() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dirpath = PathBuf::from(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        let args = NewSession::new().start_directory(&dirpath).args();
+
+        assert_eq!(args[2], "-c");
+        assert_eq!(args[3], dirpath.as_os_str());
+    }
 }
@@ -10,7 +10,10 @@ use nom::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, map_add_intent};
+use crate::{
+    error::{Error, map_add_intent},
+    format::intent,
+};
 
 /// The id of a Tmux session.
 ///
@@ -33,7 +36,7 @@ impl FromStr for SessionId {
     /// `u16`.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "SessionId";
-        let intent = "##{session_id}";
+        let intent = intent!("#{session_id}");
 
         let (_, sess_id) = all_consuming(parse::session_id)
             .parse(input)
@@ -0,0 +1,239 @@
+//! This module provides an extended description of Tmux sessions, focused on their attachment
+//! state and activity.
+//!
+//! The main use case is managing sessions: finding detached or idle sessions, or sessions
+//! sharing their windows in a group.
+
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map_res},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Result,
+    error::{Error, map_add_intent},
+    format::intent,
+    parse::{optional_number, quoted_nonempty_string, quoted_string},
+    runtime::Command,
+    session_id::{SessionId, parse::session_id},
+};
+
+/// Format of the sessions listed by tmux, parsed by `SessionInfo::from_str`.
+pub(crate) const FORMAT: &str = "#{session_id}:#{session_attached}:#{session_created}:#{session_activity}:#{session_last_attached}:#{session_windows}:'#{session_group}':'#{session_name}'";
+
+/// Attachment and activity description of a Tmux session.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::session_info::SessionInfo;
+///
+/// let line = "$3:1:1792325910:1792326000:1792325950:4:'work':'work-2'";
+/// let info = SessionInfo::from_str(line).unwrap();
+///
+/// assert_eq!(info.id.as_str(), "$3");
+/// assert_eq!(info.attached, 1);
+/// assert_eq!(info.group.as_deref(), Some("work"));
+/// assert_eq!(info.name, "work-2");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    /// Session identifier, e.g. `$3`.
+    pub id: SessionId,
+    /// Number of clients attached to the session.
+    pub attached: u16,
+    /// Creation time, in seconds since the Unix epoch.
+    pub created: u64,
+    /// Time of the last activity, in seconds since the Unix epoch.
+    pub activity: u64,
+    /// Time the session was last attached, in seconds since the Unix epoch. `None` if the
+    /// session was never attached.
+    pub last_attached: Option<u64>,
+    /// Number of windows in the session.
+    pub windows: u16,
+    /// Name of the session group, if the session shares its windows with other sessions.
+    pub group: Option<String>,
+    /// Name of the session.
+    pub name: String,
+}
+
+impl FromStr for SessionInfo {
+    type Err = Error;
+
+    /// Parse a string containing the tmux session attachment status into a new `SessionInfo`.
+    ///
+    /// This returns a `Result<SessionInfo, Error>` as this call can obviously
+    /// fail if provided an invalid format.
+    ///
+    /// The expected format of the tmux status is
+    ///
+    /// ```text
+    /// $1:0:1792325910:1792325910::1:'':'pytorch'
+    /// $3:1:1792325910:1792326000:1792325950:4:'work':'work-2'
+    /// ```
+    ///
+    /// This status line is obtained with
+    ///
+    /// ```text
+    /// tmux list-sessions -F "#{session_id}:#{session_attached}:#{session_created}:#{session_activity}:#{session_last_attached}:#{session_windows}:'#{session_group}':'#{session_name}'"
+    /// ```
+    ///
+    /// For definitions, look at `SessionInfo` type and the tmux man page for
+    /// definitions.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let desc = "SessionInfo";
+        let intent = intent!(FORMAT);
+
+        let (_, info) = all_consuming(parse::session_info)
+            .parse(input)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(info)
+    }
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    pub(crate) fn session_info(input: &str) -> IResult<&str, SessionInfo> {
+        let (
+            input,
+            (
+                id,
+                _,
+                attached,
+                _,
+                created,
+                _,
+                activity,
+                _,
+                last_attached,
+                _,
+                windows,
+                _,
+                group,
+                _,
+                name,
+            ),
+        ) = (
+            session_id,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            optional_number,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            quoted_string,
+            char(':'),
+            quoted_nonempty_string,
+        )
+            .parse(input)?;
+
+        Ok((
+            input,
+            SessionInfo {
+                id,
+                attached,
+                created,
+                activity,
+                last_attached,
+                windows,
+                group: (!group.is_empty()).then(|| group.to_string()),
+                name: name.to_string(),
+            },
+        ))
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the attachment description of all sessions.
+pub async fn available_session_infos() -> Result<Vec<SessionInfo>> {
+    let args = vec!["list-sessions", "-F", FORMAT];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    let buffer = String::from_utf8(output.stdout)?;
+
+    // Each call to `SessionInfo::from_str` returns a `Result<SessionInfo, _>`. All results
+    // are collected into a Result<Vec<SessionInfo>, _>, thanks to `collect()`.
+    let result: Result<Vec<SessionInfo>> = buffer
+        .trim_end() // trim last '\n' as it would create an empty line
+        .split('\n')
+        .map(SessionInfo::from_str)
+        .collect();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionInfo;
+    use crate::session_id::SessionId;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_list_session_infos() {
+        let output = [
+            "$1:0:1792325910:1792325910::1:'':'pytorch'",
+            "$3:2:1792325910:1792326000:1792325950:4:'work':'work: 2'",
+        ];
+        let infos: Vec<SessionInfo> = output
+            .iter()
+            .map(|&line| SessionInfo::from_str(line))
+            .collect::<crate::Result<_>>()
+            .expect("Could not parse tmux session infos");
+
+        let expected = vec![
+            SessionInfo {
+                id: SessionId::from_str("$1").unwrap(),
+                attached: 0,
+                created: 1792325910,
+                activity: 1792325910,
+                last_attached: None,
+                windows: 1,
+                group: None,
+                name: String::from("pytorch"),
+            },
+            SessionInfo {
+                id: SessionId::from_str("$3").unwrap(),
+                attached: 2,
+                created: 1792325910,
+                activity: 1792326000,
+                last_attached: Some(1792325950),
+                windows: 4,
+                group: Some(String::from("work")),
+                name: String::from("work: 2"),
+            },
+        ];
+
+        assert_eq!(infos, expected);
+    }
+
+    #[test]
+    fn parse_session_info_fails_on_missing_created() {
+        let line = "$1:0::1792325910::1:'':'pytorch'";
+        assert!(SessionInfo::from_str(line).is_err());
+    }
+
+    #[test]
+    fn parse_session_info_fails_on_empty_name() {
+        let line = "$1:0:1792325910:1792325910::1:'':''";
+        assert!(SessionInfo::from_str(line).is_err());
+    }
+
+    #[test]
+    fn parse_session_info_fails_on_unquoted_group() {
+        let line = "$1:0:1792325910:1792325910::1:work:'pytorch'";
+        assert!(SessionInfo::from_str(line).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Target of a tmux command.
+//!
+//! Most tmux commands accept a `-t` target, which can be a session, a window or a pane. Ops
+//! accepting any of them take an `impl Into<Target>`, so that any id can be passed directly.
+
+use crate::{pane_id::PaneId, session_id::SessionId, window_id::WindowId};
+
+/// A session, window or pane, designated by its id.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::{pane_id::PaneId, target::Target};
+///
+/// let pane_id = PaneId::from_str("%4").unwrap();
+/// let target = Target::from(&pane_id);
+/// assert_eq!(target.as_str(), "%4");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// A session, e.g. `$3`.
+    Session(&'a SessionId),
+    /// A window, e.g. `@12`.
+    Window(&'a WindowId),
+    /// A pane, e.g. `%37`.
+    Pane(&'a PaneId),
+}
+
+impl Target<'_> {
+    /// Extract a string slice containing the raw representation of the id.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Session(session_id) => session_id.as_str(),
+            Self::Window(window_id) => window_id.as_str(),
+            Self::Pane(pane_id) => pane_id.as_str(),
+        }
+    }
+}
+
+impl<'a> From<&'a SessionId> for Target<'a> {
+    fn from(value: &'a SessionId) -> Self {
+        Self::Session(value)
+    }
+}
+
+impl<'a> From<&'a WindowId> for Target<'a> {
+    fn from(value: &'a WindowId) -> Self {
+        Self::Window(value)
+    }
+}
+
+impl<'a> From<&'a PaneId> for Target<'a> {
+    fn from(value: &'a PaneId) -> Self {
+        Self::Pane(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn target_as_str() {
+        let session_id = SessionId::from_str("$1").unwrap();
+        let window_id = WindowId::from_str("@2").unwrap();
+        let pane_id = PaneId::from_str("%3").unwrap();
+
+        assert_eq!(Target::from(&session_id).as_str(), "$1");
+        assert_eq!(Target::from(&window_id).as_str(), "@2");
+        assert_eq!(Target::from(&pane_id).as_str(), "%3");
+    }
+}
@@ -0,0 +1,306 @@
+//! Headless terminal emulation of panes, from the output received in control mode.
+//!
+//! An [`Emulator`] feeds the `%output` notifications of each pane into a VT100/xterm screen
+//! model, which tracks the cursor, scroll regions, the alternate screen and the SGR attributes of
+//! each cell. The screens are resized from the `%layout-change` notifications, so that they keep
+//! the size of the panes.
+//!
+//! This mirrors the panes in real time, without polling [`Pane::capture`]. Panes which already
+//! have content can be seeded with the output of `capture-pane -e -p`, see [`Emulator::seed`].
+//!
+//! This module requires the `terminal` cargo feature.
+//!
+//! ```
+//! use tmux_lib::terminal::Emulator;
+//!
+//! let mut emulator = Emulator::new(24, 80);
+//! emulator
+//!     .process_line(br"%output %1 \033[1mhello\033[0m\015\012world")
+//!     .unwrap();
+//!
+//! let pane_id = "%1".parse().unwrap();
+//! let screen = emulator.screen(&pane_id).unwrap();
+//! assert_eq!(screen.contents(), "hello\nworld");
+//! assert!(screen.cell(0, 0).unwrap().bold());
+//! assert_eq!(screen.cursor_position(), (1, 5));
+//! ```
+//!
+//! [`Pane::capture`]: crate::pane::Pane::capture
+
+use std::{collections::HashMap, fmt};
+
+pub use vt100::{Cell, Color, Screen};
+
+use crate::{
+    Result,
+    control::Notification,
+    layout::{Container, Element},
+    pane_id::PaneId,
+};
+
+/// Screens of the panes, updated from the notifications of a control mode client.
+pub struct Emulator {
+    /// Screen of each pane.
+    parsers: HashMap<PaneId, vt100::Parser>,
+    /// Size of the screens of panes not found in a layout yet, as rows and columns.
+    default_size: (u16, u16),
+    /// Number of lines kept in the scrollback of each screen.
+    scrollback: usize,
+}
+
+impl Emulator {
+    /// Create an emulator, giving `rows` and `cols` to the screens of panes which have not been
+    /// seen in a layout yet.
+    #[must_use]
+    pub fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            parsers: HashMap::new(),
+            default_size: (rows, cols),
+            scrollback: 0,
+        }
+    }
+
+    /// Keep `rows` lines of scrollback for the screens created from now on.
+    #[must_use]
+    pub fn scrollback(mut self, rows: usize) -> Self {
+        self.scrollback = rows;
+        self
+    }
+
+    /// Parse a line received by the control mode client, and process it.
+    ///
+    /// Returns the panes whose screen changed.
+    pub fn process_line(&mut self, line: &[u8]) -> Result<Vec<PaneId>> {
+        let notification = Notification::from_bytes(line)?;
+        Ok(self.process(&notification))
+    }
+
+    /// Process a notification: feed the output of a pane into its screen, or resize the screens
+    /// of the panes of a window after a layout change. Other notifications are ignored.
+    ///
+    /// Screens are sized from the visible layout, so that a zoomed pane gets the size of the
+    /// whole window while the hidden panes keep their last size.
+    ///
+    /// Returns the panes whose screen changed.
+    pub fn process(&mut self, notification: &Notification) -> Vec<PaneId> {
+        match notification {
+            Notification::Output { pane_id, data }
+            | Notification::ExtendedOutput { pane_id, data, .. } => {
+                self.feed(pane_id, data);
+                vec![pane_id.clone()]
+            }
+            Notification::LayoutChange { visible_layout, .. } => {
+                let mut panes = vec![];
+                pane_sizes(&visible_layout.container, &mut panes);
+                panes
+                    .into_iter()
+                    .filter_map(|(pane_id, rows, cols)| {
+                        let pane_id = PaneId::from(&pane_id);
+                        let resized = self
+                            .screen(&pane_id)
+                            .is_none_or(|s| s.size() != (rows, cols));
+                        self.resize(&pane_id, rows, cols);
+                        resized.then_some(pane_id)
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Feed `bytes` written by the pane `pane_id` into its screen, creating it if needed.
+    ///
+    /// The bytes are processed as terminal output: a bare `\n` moves the cursor down without
+    /// returning to the first column. See [`Emulator::seed`] for captured content.
+    pub fn feed(&mut self, pane_id: &PaneId, bytes: &[u8]) {
+        self.parser(pane_id).process(bytes);
+    }
+
+    /// Seed the screen of the pane `pane_id` with its current content, as printed by
+    /// `capture-pane -e -p`, creating it if needed.
+    ///
+    /// The lines of `capture` are written from the cursor, which is left after the last line,
+    /// usually on a fresh screen.
+    pub fn seed(&mut self, pane_id: &PaneId, capture: &[u8]) {
+        let capture = capture.strip_suffix(b"\n").unwrap_or(capture);
+        let mut bytes = Vec::with_capacity(capture.len() * 2);
+        for line in capture.split(|&byte| byte == b'\n') {
+            if !bytes.is_empty() {
+                bytes.extend_from_slice(b"\r\n");
+            }
+            bytes.extend_from_slice(line);
+        }
+        self.feed(pane_id, &bytes);
+    }
+
+    /// Resize the screen of the pane `pane_id` to `rows` and `cols`, creating it if needed.
+    pub fn resize(&mut self, pane_id: &PaneId, rows: u16, cols: u16) {
+        self.parser(pane_id).screen_mut().set_size(rows, cols);
+    }
+
+    /// Screen of the pane `pane_id`, if it produced output or was seen in a layout.
+    pub fn screen(&self, pane_id: &PaneId) -> Option<&Screen> {
+        self.parsers.get(pane_id).map(vt100::Parser::screen)
+    }
+
+    /// Text displayed by the pane `pane_id`, without attributes, rows being separated by newlines.
+    pub fn contents(&self, pane_id: &PaneId) -> Option<String> {
+        self.screen(pane_id).map(Screen::contents)
+    }
+
+    /// Panes which have a screen.
+    pub fn pane_ids(&self) -> impl Iterator<Item = &PaneId> {
+        self.parsers.keys()
+    }
+
+    /// Forget the screen of the pane `pane_id`, for instance after it was killed.
+    ///
+    /// Returns `true` if the pane had a screen.
+    pub fn remove(&mut self, pane_id: &PaneId) -> bool {
+        self.parsers.remove(pane_id).is_some()
+    }
+
+    fn parser(&mut self, pane_id: &PaneId) -> &mut vt100::Parser {
+        let (rows, cols) = self.default_size;
+        let scrollback = self.scrollback;
+        self.parsers
+            .entry(pane_id.clone())
+            .or_insert_with(|| vt100::Parser::new(rows, cols, scrollback))
+    }
+}
+
+impl fmt::Debug for Emulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The screens do not implement `Debug`, so only their panes and sizes are shown.
+        let screens: HashMap<&PaneId, (u16, u16)> = self
+            .parsers
+            .iter()
+            .map(|(pane_id, parser)| (pane_id, parser.screen().size()))
+            .collect();
+        f.debug_struct("Emulator")
+            .field("screens", &screens)
+            .field("default_size", &self.default_size)
+            .field("scrollback", &self.scrollback)
+            .finish()
+    }
+}
+
+/// Collect the ids and sizes (rows and columns) of the panes of `container`.
+fn pane_sizes(container: &Container, acc: &mut Vec<(u16, u16, u16)>) {
+    match &container.element {
+        Element::Pane { pane_id } => acc.push((
+            *pane_id,
+            container.dimensions.height,
+            container.dimensions.width,
+        )),
+        Element::Horizontal(split) | Element::Vertical(split) => {
+            for element in &split.elements {
+                pane_sizes(element, acc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn pane(id: &str) -> PaneId {
+        PaneId::from_str(id).unwrap()
+    }
+
+    #[test]
+    fn test_output_per_pane() {
+        let mut emulator = Emulator::new(4, 20);
+        let changed = emulator.process_line(br"%output %1 one\015\012").unwrap();
+        assert_eq!(changed, vec![pane("%1")]);
+        emulator
+            .process_line(br"%extended-output %2 10 : two")
+            .unwrap();
+        assert!(emulator.process_line(b"%window-add @1").unwrap().is_empty());
+
+        assert_eq!(emulator.contents(&pane("%1")).unwrap(), "one");
+        assert_eq!(emulator.contents(&pane("%2")).unwrap(), "two");
+        assert!(emulator.screen(&pane("%3")).is_none());
+
+        assert!(emulator.remove(&pane("%2")));
+        assert_eq!(emulator.pane_ids().collect::<Vec<_>>(), vec![&pane("%1")]);
+    }
+
+    #[test]
+    fn test_seed_with_capture() {
+        let mut emulator = Emulator::new(3, 10);
+        let pane_id = pane("%1");
+
+        emulator.seed(&pane_id, b"ab\n\x1b[1mcd\x1b[0m\nef\n");
+        let screen = emulator.screen(&pane_id).unwrap();
+        assert_eq!(screen.contents(), "ab\ncd\nef");
+        assert!(screen.cell(1, 0).unwrap().bold());
+        // The last line does not scroll the screen.
+        assert_eq!(screen.cursor_position(), (2, 2));
+
+        // Fed as output, the lines make a staircase.
+        emulator.feed(&pane("%2"), b"ab\ncd\n");
+        assert_eq!(emulator.contents(&pane("%2")).unwrap(), "ab\n  cd");
+    }
+
+    #[test]
+    fn test_escape_sequences() {
+        let mut emulator = Emulator::new(4, 20);
+        let pane_id = pane("%1");
+
+        // Scroll region on the last 2 rows, then scroll it.
+        emulator.feed(&pane_id, b"top\x1b[3;4r\x1b[3;1Ha\r\nb\r\nc");
+        assert_eq!(emulator.contents(&pane_id).unwrap(), "top\n\nb\nc");
+
+        // The alternate screen hides the main screen until it is left.
+        emulator.feed(&pane_id, b"\x1b[?1049h\x1b[Hvim");
+        let screen = emulator.screen(&pane_id).unwrap();
+        assert!(screen.alternate_screen());
+        assert_eq!(screen.contents(), "vim");
+        emulator.feed(&pane_id, b"\x1b[?1049l");
+        assert_eq!(emulator.contents(&pane_id).unwrap(), "top\n\nb\nc");
+
+        emulator.feed(&pane_id, b"\x1b[H\x1b[2J\x1b[31;4mred\x1b[0m");
+        let screen = emulator.screen(&pane_id).unwrap();
+        let cell = screen.cell(0, 0).unwrap();
+        assert_eq!(cell.fgcolor(), Color::Idx(1));
+        assert!(cell.underline());
+        assert!(!screen.cell(0, 3).unwrap().underline());
+    }
+
+    #[test]
+    fn test_layout_change_resizes_screens() {
+        let mut emulator = Emulator::new(24, 80);
+        emulator.process_line(br"%output %0 left").unwrap();
+
+        let line = b"%layout-change @0 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} *";
+        let changed = emulator.process_line(line).unwrap();
+        assert_eq!(changed, vec![pane("%0"), pane("%1")]);
+        assert_eq!(emulator.screen(&pane("%0")).unwrap().size(), (24, 40));
+        assert_eq!(emulator.screen(&pane("%1")).unwrap().size(), (24, 39));
+        assert_eq!(emulator.contents(&pane("%0")).unwrap(), "left");
+
+        assert!(emulator.process_line(line).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_zoomed_pane_gets_window_size() {
+        let mut emulator = Emulator::new(24, 80);
+        let split = b"%layout-change @0 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} *";
+        emulator.process_line(split).unwrap();
+
+        let zoom =
+            b"%layout-change @0 8205,80x24,0,0{40x24,0,0,0,39x24,41,0,1} b25d,80x24,0,0,0 *Z";
+        let changed = emulator.process_line(zoom).unwrap();
+        assert_eq!(changed, vec![pane("%0")]);
+        assert_eq!(emulator.screen(&pane("%0")).unwrap().size(), (24, 80));
+        assert_eq!(emulator.screen(&pane("%1")).unwrap().size(), (24, 39));
+
+        let changed = emulator.process_line(split).unwrap();
+        assert_eq!(changed, vec![pane("%0")]);
+        assert_eq!(emulator.screen(&pane("%0")).unwrap().size(), (24, 40));
+    }
+}
@@ -0,0 +1,120 @@
+//! Tracing of the tmux invocations and parse failures, with the `tracing` cargo feature.
+//!
+//! Each tmux process is covered by a `tmux` span at the debug level, recording its argv, its
+//! target (the value of `-t`, if any), its duration, its exit status and the start of its stdout
+//! and stderr. The span ends with an event, so that subscribers which only print events show the
+//! invocation along with these fields. Control mode clients, which run until they are closed, are
+//! not covered.
+//!
+//! Parse failures are reported by a debug event carrying the description and intent of the
+//! parser, as in `Error::ParseError`. They are not warnings: the same parsers read values given
+//! by callers, such as pane ids, and some failures are expected and handled, such as a control
+//! mode line which does not end a command output.
+
+use std::{ffi::OsString, fmt::Display, io, process::Output, time::Instant};
+
+use tracing::{Level, Span, field};
+
+/// Maximum number of bytes of stdout and stderr recorded in the span of an invocation.
+const MAX_OUTPUT_LEN: usize = 512;
+
+/// The span of a tmux invocation, from the spawn of the process until it exits.
+pub(crate) struct Invocation {
+    span: Span,
+    start: Instant,
+}
+
+impl Invocation {
+    /// Open the span of the invocation of tmux with `args`.
+    pub(crate) fn start(args: &[OsString]) -> Self {
+        let argv = args
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let span = tracing::span!(
+            Level::DEBUG,
+            "tmux",
+            argv = %argv,
+            target = target(args).as_deref(),
+            duration_ms = field::Empty,
+            status = field::Empty,
+            stdout = field::Empty,
+            stderr = field::Empty,
+        );
+        Self {
+            span,
+            start: Instant::now(),
+        }
+    }
+
+    /// Record the outcome of the invocation, and close its span.
+    pub(crate) fn finish(self, result: &io::Result<Output>) {
+        let span = self.span;
+        span.record("duration_ms", self.start.elapsed().as_millis());
+        match result {
+            Ok(output) => {
+                match output.status.code() {
+                    Some(code) => span.record("status", code),
+                    None => span.record("status", field::display(output.status)),
+                };
+                span.record("stdout", truncate(&output.stdout));
+                span.record("stderr", truncate(&output.stderr));
+                span.in_scope(|| tracing::debug!(success = output.status.success(), "tmux exited"));
+            }
+            Err(err) => span.in_scope(|| tracing::debug!(error = %err, "tmux failed")),
+        }
+    }
+}
+
+/// Report the failure of the parser of `desc`.
+pub(crate) fn parse_failure(desc: &str, intent: &str, err: &impl Display) {
+    tracing::debug!(desc, intent, error = %err, "parse failure");
+}
+
+/// Return the target of the invocation with `args`, the value of its first `-t`.
+fn target(args: &[OsString]) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == "-t")
+        .and_then(|index| args.get(index + 1))
+        .map(|target| target.to_string_lossy().into_owned())
+}
+
+/// Return the start of `bytes` as text, with the number of bytes left out, if any.
+fn truncate(bytes: &[u8]) -> String {
+    if bytes.len() <= MAX_OUTPUT_LEN {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let text = String::from_utf8_lossy(&bytes[..MAX_OUTPUT_LEN]);
+    format!("{text}… ({} more bytes)", bytes.len() - MAX_OUTPUT_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            target(&args(&["send-keys", "-t", "%3", "ls", "Enter"])),
+            Some("%3".to_string())
+        );
+        assert_eq!(
+            target(&args(&["list-sessions", "-F", "#{session_id}"])),
+            None
+        );
+        assert_eq!(target(&args(&["kill-session", "-t"])), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate(b"$0:main\n"), "$0:main\n");
+
+        let long = vec![b'x'; MAX_OUTPUT_LEN + 10];
+        let truncated = truncate(&long);
+        assert!(truncated.starts_with(&"x".repeat(MAX_OUTPUT_LEN)));
+        assert!(truncated.ends_with("… (10 more bytes)"));
+    }
+}
@@ -0,0 +1,966 @@
+//! Interactive user interface elements: popups, menus and prompts.
+//!
+//! These are displayed on a client, hence tmux reports an error if there is no attached client
+//! to display them on.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::{
+    Result,
+    error::{Error, check_empty_process_output},
+    pane_id::PaneId,
+    runtime::Command,
+    version::{self, Capability},
+};
+
+/// Size of a popup, along one dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// Number of lines or columns.
+    Cells(u16),
+    /// Percentage of the client size.
+    Percent(u8),
+}
+
+impl Size {
+    fn to_arg(self) -> String {
+        match self {
+            Self::Cells(cells) => cells.to_string(),
+            Self::Percent(percent) => format!("{percent}%"),
+        }
+    }
+}
+
+/// Position of a popup or menu, along one dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Offset from the left or top of the client, in cells.
+    Cells(u16),
+    /// Centered in the client (`C`).
+    Center,
+    /// At the mouse position (`M`).
+    Mouse,
+    /// Aligned with the target pane (`P`).
+    Pane,
+}
+
+impl Position {
+    fn to_arg(self) -> String {
+        match self {
+            Self::Cells(cells) => cells.to_string(),
+            Self::Center => String::from("C"),
+            Self::Mouse => String::from("M"),
+            Self::Pane => String::from("P"),
+        }
+    }
+}
+
+/// Line drawing characters of a popup border (`-b`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderLines {
+    /// Single lines, which is the tmux default.
+    Single,
+    /// Single lines with rounded corners.
+    Rounded,
+    /// Double lines.
+    Double,
+    /// Heavy lines.
+    Heavy,
+    /// Simple ASCII characters.
+    Simple,
+    /// Spaces.
+    Padded,
+    /// No border at all (`-B`).
+    None,
+}
+
+impl BorderLines {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Single => "single",
+            Self::Rounded => "rounded",
+            Self::Double => "double",
+            Self::Heavy => "heavy",
+            Self::Simple => "simple",
+            Self::Padded => "padded",
+            Self::None => "none",
+        }
+    }
+}
+
+/// When a popup running a command is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupClose {
+    /// The popup stays open until closed with a key, which is the tmux default.
+    Manually,
+    /// The popup closes when the command exits (`-E`).
+    OnExit,
+    /// The popup closes when the command exits successfully (`-EE`).
+    OnSuccess,
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Options for displaying a popup, possibly running a shell command.
+///
+/// ```no_run
+/// # async fn show() -> tmux_lib::Result<()> {
+/// use tmux_lib::ui::{BorderLines, Popup, PopupClose, Size};
+///
+/// Popup::new()
+///     .command("htop")
+///     .size(Size::Percent(80), Size::Percent(60))
+///     .border_lines(BorderLines::Rounded)
+///     .title("Processes")
+///     .close(PopupClose::OnExit)
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Popup {
+    client: Option<String>,
+    target_pane: Option<PaneId>,
+    width: Option<Size>,
+    height: Option<Size>,
+    x: Option<Position>,
+    y: Option<Position>,
+    border_lines: Option<BorderLines>,
+    border_style: Option<String>,
+    style: Option<String>,
+    title: Option<String>,
+    close: PopupClose,
+    start_directory: Option<PathBuf>,
+    environment: Vec<(String, String)>,
+    command: Option<String>,
+}
+
+impl Default for Popup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Popup {
+    /// Prepare displaying a popup on the current client.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            target_pane: None,
+            width: None,
+            height: None,
+            x: None,
+            y: None,
+            border_lines: None,
+            border_style: None,
+            style: None,
+            title: None,
+            close: PopupClose::Manually,
+            start_directory: None,
+            environment: vec![],
+            command: None,
+        }
+    }
+
+    /// Display the popup on the client named `client_name` (`-c`).
+    #[must_use]
+    pub fn client(mut self, client_name: impl Into<String>) -> Self {
+        self.client = Some(client_name.into());
+        self
+    }
+
+    /// Expand formats and position the popup relative to the pane with `pane_id` (`-t`).
+    #[must_use]
+    pub fn target_pane(mut self, pane_id: &PaneId) -> Self {
+        self.target_pane = Some(pane_id.clone());
+        self
+    }
+
+    /// Set the size of the popup, including its border (`-w` and `-h`).
+    #[must_use]
+    pub fn size(mut self, width: Size, height: Size) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Set the position of the popup (`-x` and `-y`).
+    #[must_use]
+    pub fn position(mut self, x: Position, y: Position) -> Self {
+        self.x = Some(x);
+        self.y = Some(y);
+        self
+    }
+
+    /// Set the line drawing characters of the border.
+    #[must_use]
+    pub fn border_lines(mut self, border_lines: BorderLines) -> Self {
+        self.border_lines = Some(border_lines);
+        self
+    }
+
+    /// Set the style of the border, e.g. `fg=blue` (`-S`).
+    #[must_use]
+    pub fn border_style(mut self, style: impl Into<String>) -> Self {
+        self.border_style = Some(style.into());
+        self
+    }
+
+    /// Set the style of the popup content, e.g. `bg=black` (`-s`).
+    #[must_use]
+    pub fn style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Set the title shown in the border (`-T`).
+    #[must_use]
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set when the popup is closed.
+    #[must_use]
+    pub fn close(mut self, close: PopupClose) -> Self {
+        self.close = close;
+        self
+    }
+
+    /// Set the working directory of the command (`-d`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the command (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the shell command run in the popup.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["display-popup".into()];
+        match self.close {
+            PopupClose::Manually => {}
+            PopupClose::OnExit => args.push("-E".into()),
+            PopupClose::OnSuccess => args.push("-EE".into()),
+        }
+        match self.border_lines {
+            None => {}
+            Some(BorderLines::None) => args.push("-B".into()),
+            Some(border_lines) => {
+                args.push("-b".into());
+                args.push(border_lines.as_str().into());
+            }
+        }
+        if let Some(client) = &self.client {
+            args.push("-c".into());
+            args.push(client.into());
+        }
+        if let Some(dirpath) = &self.start_directory {
+            args.push("-d".into());
+            args.push(dirpath.into());
+        }
+        for (name, value) in &self.environment {
+            args.push("-e".into());
+            args.push(format!("{name}={value}").into());
+        }
+        if let Some(width) = self.width {
+            args.push("-w".into());
+            args.push(width.to_arg().into());
+        }
+        if let Some(height) = self.height {
+            args.push("-h".into());
+            args.push(height.to_arg().into());
+        }
+        if let Some(x) = self.x {
+            args.push("-x".into());
+            args.push(x.to_arg().into());
+        }
+        if let Some(y) = self.y {
+            args.push("-y".into());
+            args.push(y.to_arg().into());
+        }
+        if let Some(style) = &self.style {
+            args.push("-s".into());
+            args.push(style.into());
+        }
+        if let Some(style) = &self.border_style {
+            args.push("-S".into());
+            args.push(style.into());
+        }
+        if let Some(title) = &self.title {
+            args.push("-T".into());
+            args.push(title.into());
+        }
+        if let Some(pane_id) = &self.target_pane {
+            args.push("-t".into());
+            args.push(pane_id.as_str().into());
+        }
+        if let Some(command) = &self.command {
+            args.push(command.into());
+        }
+        args
+    }
+
+    /// Capability of tmux required by the options.
+    pub(crate) fn capability(&self) -> Capability {
+        let styled = self.border_lines.is_some()
+            || self.border_style.is_some()
+            || self.style.is_some()
+            || self.title.is_some();
+        if styled || !self.environment.is_empty() {
+            Capability::PopupOptions
+        } else {
+            Capability::Popups
+        }
+    }
+
+    /// Display the popup.
+    ///
+    /// This returns as soon as the popup is displayed, not when it is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Unsupported` before tmux 3.2, or before tmux 3.3 with a border, a
+    /// style, a title or environment variables.
+    pub async fn run(&self) -> Result<()> {
+        version::require(self.capability()).await?;
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "display-popup")
+    }
+}
+
+/// An item of a [`Menu`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuItem {
+    /// An entry running a tmux command when chosen.
+    Entry {
+        /// Name of the entry, which is a tmux format.
+        name: String,
+        /// Key shortcut choosing the entry, e.g. `q` or `C-r`.
+        key: Option<String>,
+        /// Tmux command run when the entry is chosen, e.g. `kill-pane`.
+        command: String,
+        /// Disabled entries are dimmed and cannot be chosen.
+        disabled: bool,
+    },
+    /// A separator line.
+    Separator,
+}
+
+impl MenuItem {
+    /// An entry running the tmux `command` when chosen.
+    #[must_use]
+    pub fn entry(name: impl Into<String>, key: Option<&str>, command: impl Into<String>) -> Self {
+        Self::Entry {
+            name: name.into(),
+            key: key.map(String::from),
+            command: command.into(),
+            disabled: false,
+        }
+    }
+}
+
+/// Options for displaying a menu.
+///
+/// ```no_run
+/// # async fn show() -> tmux_lib::Result<()> {
+/// use tmux_lib::ui::{Menu, MenuItem};
+///
+/// Menu::new()
+///     .title("Pane")
+///     .item(MenuItem::entry("Zoom", Some("z"), "resize-pane -Z"))
+///     .item(MenuItem::Separator)
+///     .item(MenuItem::entry("Kill", Some("x"), "kill-pane"))
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Menu {
+    client: Option<String>,
+    target_pane: Option<PaneId>,
+    title: Option<String>,
+    x: Option<Position>,
+    y: Option<Position>,
+    items: Vec<MenuItem>,
+}
+
+impl Menu {
+    /// Prepare displaying an empty menu on the current client.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Display the menu on the client named `client_name` (`-c`).
+    #[must_use]
+    pub fn client(mut self, client_name: impl Into<String>) -> Self {
+        self.client = Some(client_name.into());
+        self
+    }
+
+    /// Expand formats and position the menu relative to the pane with `pane_id` (`-t`).
+    #[must_use]
+    pub fn target_pane(mut self, pane_id: &PaneId) -> Self {
+        self.target_pane = Some(pane_id.clone());
+        self
+    }
+
+    /// Set the title of the menu (`-T`).
+    #[must_use]
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the position of the menu (`-x` and `-y`).
+    #[must_use]
+    pub fn position(mut self, x: Position, y: Position) -> Self {
+        self.x = Some(x);
+        self.y = Some(y);
+        self
+    }
+
+    /// Append `item` to the menu.
+    #[must_use]
+    pub fn item(mut self, item: MenuItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = vec![String::from("display-menu")];
+        if let Some(client) = &self.client {
+            args.push("-c".into());
+            args.push(client.clone());
+        }
+        if let Some(pane_id) = &self.target_pane {
+            args.push("-t".into());
+            args.push(pane_id.as_str().to_string());
+        }
+        if let Some(title) = &self.title {
+            args.push("-T".into());
+            args.push(title.clone());
+        }
+        if let Some(x) = self.x {
+            args.push("-x".into());
+            args.push(x.to_arg());
+        }
+        if let Some(y) = self.y {
+            args.push("-y".into());
+            args.push(y.to_arg());
+        }
+        for item in &self.items {
+            match item {
+                MenuItem::Entry {
+                    name,
+                    key,
+                    command,
+                    disabled,
+                } => {
+                    // A leading hyphen marks the entry as disabled.
+                    args.push(if *disabled {
+                        format!("-{name}")
+                    } else {
+                        name.clone()
+                    });
+                    args.push(key.clone().unwrap_or_default());
+                    args.push(command.clone());
+                }
+                // An empty name is a separator, without key nor command.
+                MenuItem::Separator => args.push(String::new()),
+            }
+        }
+        args
+    }
+
+    /// Display the menu.
+    ///
+    /// This returns as soon as the menu is displayed, the command of the chosen entry is run
+    /// later by tmux.
+    pub async fn run(&self) -> Result<()> {
+        version::require(Capability::Menus).await?;
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "display-menu")
+    }
+}
+
+/// Options for prompting for input, then running a tmux command with it.
+///
+/// In the command template, `%1`, `%2`... (or `%%`) are replaced with the answers to each
+/// prompt.
+///
+/// ```no_run
+/// # async fn prompt() -> tmux_lib::Result<()> {
+/// use tmux_lib::ui::CommandPrompt;
+///
+/// CommandPrompt::new("rename-window '%%'")
+///     .prompt("New name:")
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandPrompt {
+    template: String,
+    prompts: Vec<String>,
+    inputs: Vec<String>,
+    client: Option<String>,
+    single_key: bool,
+    numeric: bool,
+    background: bool,
+}
+
+impl CommandPrompt {
+    /// Prepare prompting for the arguments of the tmux command `template`.
+    #[must_use]
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            prompts: vec![],
+            inputs: vec![],
+            client: None,
+            single_key: false,
+            numeric: false,
+            background: false,
+        }
+    }
+
+    /// Append a prompt, with an empty initial input (`-p`).
+    ///
+    /// Tmux separates the prompts, and their inputs, with commas: neither can contain one.
+    #[must_use]
+    pub fn prompt(self, prompt: impl Into<String>) -> Self {
+        self.prompt_with_input(prompt, "")
+    }
+
+    /// Append a prompt, with `input` as initial input (`-p` and `-I`).
+    #[must_use]
+    pub fn prompt_with_input(
+        mut self,
+        prompt: impl Into<String>,
+        input: impl Into<String>,
+    ) -> Self {
+        self.prompts.push(prompt.into());
+        self.inputs.push(input.into());
+        self
+    }
+
+    /// Display the prompt on the client named `client_name` (`-t`).
+    #[must_use]
+    pub fn client(mut self, client_name: impl Into<String>) -> Self {
+        self.client = Some(client_name.into());
+        self
+    }
+
+    /// Accept a single key press as answer (`-1`).
+    #[must_use]
+    pub fn single_key(mut self, single_key: bool) -> Self {
+        self.single_key = single_key;
+        self
+    }
+
+    /// Accept only numeric key presses (`-N`).
+    #[must_use]
+    pub fn numeric(mut self, numeric: bool) -> Self {
+        self.numeric = numeric;
+        self
+    }
+
+    /// Run the command in the background, without blocking the client (`-b`).
+    #[must_use]
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = vec![String::from("command-prompt")];
+        if self.single_key {
+            args.push("-1".into());
+        }
+        if self.numeric {
+            args.push("-N".into());
+        }
+        if self.background {
+            args.push("-b".into());
+        }
+        if !self.prompts.is_empty() {
+            args.push("-p".into());
+            args.push(self.prompts.join(","));
+        }
+        if self.inputs.iter().any(|input| !input.is_empty()) {
+            args.push("-I".into());
+            args.push(self.inputs.join(","));
+        }
+        if let Some(client) = &self.client {
+            args.push("-t".into());
+            args.push(client.clone());
+        }
+        args.push(self.template.clone());
+        args
+    }
+
+    /// Capability of tmux required by the options, if any.
+    pub(crate) fn capability(&self) -> Option<Capability> {
+        self.background.then_some(Capability::BackgroundPrompts)
+    }
+
+    /// Ensure that the prompts and inputs can be passed to tmux.
+    pub(crate) fn check_options(&self) -> Result<()> {
+        if self
+            .prompts
+            .iter()
+            .chain(&self.inputs)
+            .any(|s| s.contains(','))
+        {
+            return Err(Error::InvalidOptions(
+                "`command-prompt` prompts and inputs cannot contain commas",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Display the prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::InvalidOptions` if a prompt or an input contains a comma, and an
+    /// `Error::Unsupported` before tmux 3.3 in the background.
+    pub async fn run(&self) -> Result<()> {
+        self.check_options()?;
+        if let Some(capability) = self.capability() {
+            version::require(capability).await?;
+        }
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "command-prompt")
+    }
+}
+
+/// Options for asking for confirmation (`y`) before running a tmux command.
+///
+/// ```no_run
+/// # async fn confirm() -> tmux_lib::Result<()> {
+/// use tmux_lib::ui::ConfirmBefore;
+///
+/// ConfirmBefore::new("kill-session")
+///     .prompt("Kill the session? (y/n)")
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmBefore {
+    command: String,
+    prompt: Option<String>,
+    client: Option<String>,
+    background: bool,
+}
+
+impl ConfirmBefore {
+    /// Prepare asking for confirmation before running the tmux `command`.
+    #[must_use]
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            prompt: None,
+            client: None,
+            background: false,
+        }
+    }
+
+    /// Set the prompt, which defaults to the command followed by `(y/n)` (`-p`).
+    #[must_use]
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Display the prompt on the client named `client_name` (`-t`).
+    #[must_use]
+    pub fn client(mut self, client_name: impl Into<String>) -> Self {
+        self.client = Some(client_name.into());
+        self
+    }
+
+    /// Run the command in the background, without blocking the client (`-b`).
+    #[must_use]
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = vec![String::from("confirm-before")];
+        if self.background {
+            args.push("-b".into());
+        }
+        if let Some(prompt) = &self.prompt {
+            args.push("-p".into());
+            args.push(prompt.clone());
+        }
+        if let Some(client) = &self.client {
+            args.push("-t".into());
+            args.push(client.clone());
+        }
+        args.push(self.command.clone());
+        args
+    }
+
+    /// Capability of tmux required by the options, if any.
+    pub(crate) fn capability(&self) -> Option<Capability> {
+        self.background.then_some(Capability::BackgroundPrompts)
+    }
+
+    /// Display the confirmation prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Unsupported` before tmux 3.3 in the background.
+    pub async fn run(&self) -> Result<()> {
+        if let Some(capability) = self.capability() {
+            version::require(capability).await?;
+        }
+
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "confirm-before")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn popup_default_args() {
+        assert_eq!(Popup::new().args(), ["display-popup"]);
+    }
+
+    #[test]
+    fn popup_args() {
+        let pane_id = PaneId::from_str("%3").unwrap();
+        let args = Popup::new()
+            .client("/dev/pts/3")
+            .target_pane(&pane_id)
+            .size(Size::Percent(80), Size::Cells(20))
+            .position(Position::Center, Position::Cells(2))
+            .border_lines(BorderLines::Rounded)
+            .border_style("fg=blue")
+            .style("bg=black")
+            .title("Logs")
+            .close(PopupClose::OnSuccess)
+            .start_directory("/tmp")
+            .env("LESS", "-R")
+            .command("less log.txt")
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "display-popup",
+                "-EE",
+                "-b",
+                "rounded",
+                "-c",
+                "/dev/pts/3",
+                "-d",
+                "/tmp",
+                "-e",
+                "LESS=-R",
+                "-w",
+                "80%",
+                "-h",
+                "20",
+                "-x",
+                "C",
+                "-y",
+                "2",
+                "-s",
+                "bg=black",
+                "-S",
+                "fg=blue",
+                "-T",
+                "Logs",
+                "-t",
+                "%3",
+                "less log.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn popup_without_border_args() {
+        let args = Popup::new()
+            .border_lines(BorderLines::None)
+            .close(PopupClose::OnExit)
+            .args();
+
+        assert_eq!(args, ["display-popup", "-E", "-B"]);
+    }
+
+    #[test]
+    fn popup_capability() {
+        let popup = Popup::new().size(Size::Cells(10), Size::Cells(5));
+        assert_eq!(popup.capability(), Capability::Popups);
+        assert_eq!(
+            popup.clone().command("top").capability(),
+            Capability::Popups
+        );
+
+        assert_eq!(
+            popup.clone().style("bg=black").capability(),
+            Capability::PopupOptions
+        );
+        assert_eq!(
+            popup.clone().border_lines(BorderLines::None).capability(),
+            Capability::PopupOptions
+        );
+        assert_eq!(
+            popup.clone().title("Logs").capability(),
+            Capability::PopupOptions
+        );
+        assert_eq!(
+            popup.env("LESS", "-R").capability(),
+            Capability::PopupOptions
+        );
+    }
+
+    #[test]
+    fn menu_args() {
+        let disabled = MenuItem::Entry {
+            name: String::from("Swap"),
+            key: None,
+            command: String::from("swap-pane -D"),
+            disabled: true,
+        };
+        let args = Menu::new()
+            .title("Pane")
+            .position(Position::Mouse, Position::Pane)
+            .item(MenuItem::entry("Zoom", Some("z"), "resize-pane -Z"))
+            .item(MenuItem::Separator)
+            .item(disabled)
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "display-menu",
+                "-T",
+                "Pane",
+                "-x",
+                "M",
+                "-y",
+                "P",
+                "Zoom",
+                "z",
+                "resize-pane -Z",
+                "",
+                "-Swap",
+                "",
+                "swap-pane -D",
+            ]
+        );
+    }
+
+    #[test]
+    fn command_prompt_args() {
+        let args = CommandPrompt::new("rename-session '%1' ; rename-window '%2'")
+            .prompt("Session:")
+            .prompt_with_input("Window:", "main")
+            .client("/dev/pts/3")
+            .background(true)
+            .args();
+
+        assert_eq!(
+            args,
+            [
+                "command-prompt",
+                "-b",
+                "-p",
+                "Session:,Window:",
+                "-I",
+                ",main",
+                "-t",
+                "/dev/pts/3",
+                "rename-session '%1' ; rename-window '%2'",
+            ]
+        );
+    }
+
+    #[test]
+    fn command_prompt_options() {
+        let prompt = CommandPrompt::new("rename-window '%%'").prompt("Name:");
+        assert!(prompt.check_options().is_ok());
+        assert_eq!(prompt.capability(), None);
+        assert_eq!(
+            prompt.clone().background(true).capability(),
+            Some(Capability::BackgroundPrompts)
+        );
+
+        assert!(matches!(
+            prompt.clone().prompt("Name, again:").check_options(),
+            Err(Error::InvalidOptions(_))
+        ));
+        assert!(matches!(
+            prompt.prompt_with_input("Other:", "a,b").check_options(),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn command_prompt_single_key_args() {
+        let args = CommandPrompt::new("select-window -t ':%%'")
+            .single_key(true)
+            .numeric(true)
+            .args();
+
+        assert_eq!(
+            args,
+            ["command-prompt", "-1", "-N", "select-window -t ':%%'"]
+        );
+    }
+
+    #[test]
+    fn confirm_before_args() {
+        let args = ConfirmBefore::new("kill-server")
+            .prompt("Really? (y/n)")
+            .background(true)
+            .args();
+
+        assert_eq!(
+            args,
+            ["confirm-before", "-b", "-p", "Really? (y/n)", "kill-server"]
+        );
+        assert_eq!(
+            ConfirmBefore::new("kill-server")
+                .background(true)
+                .capability(),
+            Some(Capability::BackgroundPrompts)
+        );
+        assert_eq!(ConfirmBefore::new("kill-server").capability(), None);
+    }
+}
@@ -57,9 +57,85 @@ pub fn cleanup_captured_buffer(buffer: &[u8], drop_n_last_lines: usize) -> Vec<u
     final_buffer
 }
 
+/// Remove the escape sequences, carriage returns and other control characters (but newlines and
+/// tabs) from a buffer.
+fn strip_escape_sequences(buffer: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(buffer.len());
+    let mut bytes = buffer.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            0x1b => match bytes.next() {
+                // Control sequence, ended by a byte in `@` to `~`.
+                Some(b'[') => {
+                    for byte in bytes.by_ref() {
+                        if (0x40..=0x7e).contains(&byte) {
+                            break;
+                        }
+                    }
+                }
+                // Operating system command or other string, ended by `BEL` or `ESC \`.
+                Some(b']' | b'P' | b'X' | b'^' | b'_') => {
+                    while let Some(byte) = bytes.next() {
+                        if byte == 0x07 {
+                            break;
+                        }
+                        if byte == 0x1b && bytes.next_if_eq(&b'\\').is_some() {
+                            break;
+                        }
+                    }
+                }
+                // Character set designation, followed by the character set.
+                Some(b'(' | b')' | b'*' | b'+') => {
+                    bytes.next();
+                }
+                // Two bytes sequence.
+                _ => {}
+            },
+            b'\n' | b'\t' => stripped.push(byte),
+            byte if byte < 0x20 || byte == 0x7f => {}
+            byte => stripped.push(byte),
+        }
+    }
+    stripped
+}
+
+/// Process a buffer of pane output, as written by `pipe-pane`.
+///
+/// - Escape sequences, carriage returns and other control characters are removed, leaving plain
+///   text.
+/// - Lines are trimmed and the last empty lines are dropped, as in [`cleanup_captured_buffer`].
+///
+/// ```
+/// use tmux_lib::utils::cleanup_logged_buffer;
+///
+/// let buffer = b"\x1b[32mgreen\x1b[0m  \r\n\x1b]0;title\x07line2\r\n\r\n";
+/// let result = cleanup_logged_buffer(buffer);
+///
+/// assert_eq!(result, b"green\nline2\n");
+/// ```
+pub fn cleanup_logged_buffer(buffer: &[u8]) -> Vec<u8> {
+    let stripped = strip_escape_sequences(buffer);
+    let trimmed_lines: Vec<&[u8]> = buf_trim_trailing(&stripped);
+    let lines: Vec<&[u8]> = drop_last_empty_lines(&trimmed_lines);
+    if lines.iter().all(|line| line.is_empty()) {
+        return vec![];
+    }
+
+    let mut final_buffer: Vec<u8> = Vec::with_capacity(stripped.len());
+    for line in lines {
+        final_buffer.extend_from_slice(line);
+        final_buffer.push(b'\n');
+    }
+
+    final_buffer
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{buf_trim_trailing, cleanup_captured_buffer, drop_last_empty_lines};
+    use super::{
+        buf_trim_trailing, cleanup_captured_buffer, cleanup_logged_buffer, drop_last_empty_lines,
+        strip_escape_sequences,
+    };
 
     #[test]
     fn trims_trailing_whitespaces() {
@@ -237,4 +313,26 @@ mod tests {
         assert_eq!(actual[0], "  indented".as_bytes());
         assert_eq!(actual[1], "\tnested".as_bytes());
     }
+
+    #[test]
+    fn test_strip_escape_sequences() {
+        let input = b"\x1b[1;31mred\x1b[m \x1b(Bplain\x1b=\x07\x08\ttab\r\n";
+        assert_eq!(strip_escape_sequences(input), b"red plain\ttab\n");
+
+        let input = b"\x1b]0;title\x1b\\a\x1bPdata\x07b\x1b]unterminated";
+        assert_eq!(strip_escape_sequences(input), b"ab");
+    }
+
+    #[test]
+    fn test_cleanup_logged_buffer() {
+        let input = b"$ ls   \r\n\x1b[34mdir\x1b[0m\r\n\r\n$ \r\n\r\n";
+        let result = cleanup_logged_buffer(input);
+        assert_eq!(result, b"$ ls\ndir\n\n$\n");
+    }
+
+    #[test]
+    fn test_cleanup_logged_buffer_empty() {
+        assert_eq!(cleanup_logged_buffer(b""), b"");
+        assert_eq!(cleanup_logged_buffer(b"\x1b[H\x1b[2J\r\n  \n"), b"");
+    }
 }
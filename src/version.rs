@@ -0,0 +1,480 @@
+//! Tmux version detection, and the features each version supports.
+//!
+//! [`version`] parses the output of `tmux -V` into a [`Version`], which compares as tmux
+//! releases do. A [`Capability`] names a feature used by the ops of this crate along with the
+//! first version supporting it, so that ops can fail early with [`require`] on older versions,
+//! instead of with an obscure tmux error, or fall back to older flags with [`supports`].
+//!
+//! ```no_run
+//! # async fn check() -> tmux_lib::Result<()> {
+//! use tmux_lib::version::{self, Capability, Version};
+//!
+//! let version = version::version().await?;
+//! if version >= Version::new(3, 3) {
+//!     println!("tmux {version} is recent");
+//! }
+//! version::require(Capability::Popups).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Unknown formats expand to an empty string on older versions, so formats such as
+//! `#{window_linked_sessions_list}` do not need a capability: the corresponding fields are empty.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, satisfy},
+    combinator::{all_consuming, map_res, opt},
+    sequence::preceded,
+};
+
+use crate::{
+    Result,
+    error::{Error, check_process_success, map_add_intent},
+    runtime::Command,
+};
+
+/// Version of tmux, as printed by `tmux -V`.
+///
+/// Development versions (`next-3.5`) come before the release candidates (`3.5-rc1`) and the
+/// release of the same version, and patch releases (`3.3a`) after it. Versions shipped with
+/// OpenBSD (`openbsd-7.4`) come right after the tmux release they are known to include (`3.3`),
+/// since tmux is developed in the OpenBSD tree, and before its next patch release.
+///
+/// ```
+/// use std::str::FromStr;
+/// use tmux_lib::version::Version;
+///
+/// let version = Version::from_str("3.3a").unwrap();
+/// assert!(version > Version::new(3, 3));
+/// assert!(version < Version::from_str("next-3.4").unwrap());
+/// assert_eq!(version.to_string(), "3.3a");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Version {
+    /// Major version, e.g. `3` in `3.3a`, or the OpenBSD major version.
+    pub major: u16,
+    /// Minor version, e.g. `3` in `3.3a`, or the OpenBSD minor version.
+    pub minor: u16,
+    /// Patch letter, e.g. `a` in `3.3a`.
+    pub patch: Option<char>,
+    /// Kind of build.
+    pub kind: VersionKind,
+}
+
+/// Kind of tmux build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VersionKind {
+    /// Release, e.g. `3.4`.
+    Release,
+    /// Release candidate, e.g. `3.4-rc2`.
+    Candidate(u16),
+    /// Development version leading to the version, e.g. `next-3.5`.
+    Next,
+    /// Version shipped with OpenBSD, numbered after the OpenBSD release, e.g. `openbsd-7.4`.
+    OpenBsd,
+}
+
+impl Version {
+    /// Return the release `major.minor`.
+    #[must_use]
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch: None,
+            kind: VersionKind::Release,
+        }
+    }
+
+    /// Return `true` if this version supports `capability`.
+    #[must_use]
+    pub fn supports(&self, capability: Capability) -> bool {
+        *self >= capability.min_version()
+    }
+
+    /// Key ordering versions, see [`Version`].
+    fn sort_key(&self) -> (ReleaseKey, Option<(u16, u16)>) {
+        if self.kind == VersionKind::OpenBsd {
+            let release = self.openbsd_release().release_key();
+            return (release, Some((self.major, self.minor)));
+        }
+        (self.release_key(), None)
+    }
+
+    /// Key ordering tmux versions, OpenBSD versions being ordered as releases.
+    fn release_key(&self) -> ReleaseKey {
+        let (stage, candidate) = match self.kind {
+            VersionKind::Next => (0, 0),
+            VersionKind::Candidate(candidate) => (1, candidate),
+            VersionKind::Release | VersionKind::OpenBsd => (2, 0),
+        };
+        (self.major, self.minor, stage, candidate, self.patch)
+    }
+
+    /// Tmux release included in the OpenBSD release of this version.
+    ///
+    /// OpenBSD releases newer than the known ones are assumed to have the features of the last
+    /// known tmux release, and older ones no features at all.
+    fn openbsd_release(&self) -> Version {
+        OPENBSD_RELEASES
+            .iter()
+            .rev()
+            .find(|(openbsd, _)| *openbsd <= (self.major, self.minor))
+            .map_or(Version::new(0, 0), |(_, release)| *release)
+    }
+}
+
+/// Major, minor, stage (development, candidate or release), candidate and patch of a version.
+type ReleaseKey = (u16, u16, u8, u16, Option<char>);
+
+/// OpenBSD releases, with the last tmux release published before each of them, ignoring patch
+/// releases.
+const OPENBSD_RELEASES: [((u16, u16), Version); 17] = [
+    ((6, 1), Version::new(2, 3)),
+    ((6, 2), Version::new(2, 6)),
+    ((6, 3), Version::new(2, 7)),
+    ((6, 4), Version::new(2, 8)),
+    ((6, 5), Version::new(2, 9)),
+    ((6, 6), Version::new(2, 9)),
+    ((6, 7), Version::new(3, 1)),
+    ((6, 8), Version::new(3, 1)),
+    ((6, 9), Version::new(3, 2)),
+    ((7, 0), Version::new(3, 2)),
+    ((7, 1), Version::new(3, 2)),
+    ((7, 2), Version::new(3, 3)),
+    ((7, 3), Version::new(3, 3)),
+    ((7, 4), Version::new(3, 3)),
+    ((7, 5), Version::new(3, 4)),
+    ((7, 6), Version::new(3, 5)),
+    ((7, 7), Version::new(3, 5)),
+];
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    /// Parse a tmux version, with or without the `tmux ` prefix printed by `tmux -V`.
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let desc = "Version";
+        let intent = "tmux -V";
+
+        let (_, version) = all_consuming(parse::version_output)
+            .parse(input.trim_end())
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok(version)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            VersionKind::Next => write!(f, "next-")?,
+            VersionKind::OpenBsd => write!(f, "openbsd-")?,
+            VersionKind::Release | VersionKind::Candidate(_) => {}
+        }
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, "{patch}")?;
+        }
+        if let VersionKind::Candidate(candidate) = self.kind {
+            write!(f, "-rc{candidate}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A tmux feature which is not available in all the versions supported by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Pipes reading from and writing to panes, with `pipe-pane -I` and `-O`.
+    PipeDirections,
+    /// Menus, with `display-menu`.
+    Menus,
+    /// Sizes of new panes given as a percentage with `split-window -l`, instead of `-p`.
+    PercentSizes,
+    /// Popups, with `display-popup`.
+    Popups,
+    /// Environment variables of new sessions, with `new-session -e`.
+    SessionEnvironment,
+    /// Format subscriptions of control mode clients, with `refresh-client -B`.
+    FormatSubscriptions,
+    /// Borders, styles, titles and environment variables of popups, with `display-popup -b`,
+    /// `-B`, `-s`, `-S`, `-T` and `-e`.
+    PopupOptions,
+    /// Prompts which do not block the client, with `command-prompt -b` and `confirm-before -b`.
+    BackgroundPrompts,
+}
+
+impl Capability {
+    /// All capabilities, by order of their minimum version.
+    pub const ALL: [Self; 8] = [
+        Self::PipeDirections,
+        Self::Menus,
+        Self::PercentSizes,
+        Self::Popups,
+        Self::SessionEnvironment,
+        Self::FormatSubscriptions,
+        Self::PopupOptions,
+        Self::BackgroundPrompts,
+    ];
+
+    /// First version supporting this capability.
+    #[must_use]
+    pub const fn min_version(self) -> Version {
+        match self {
+            Self::PipeDirections => Version::new(2, 7),
+            Self::Menus => Version::new(3, 0),
+            Self::PercentSizes => Version::new(3, 1),
+            Self::Popups | Self::SessionEnvironment | Self::FormatSubscriptions => {
+                Version::new(3, 2)
+            }
+            Self::PopupOptions | Self::BackgroundPrompts => Version::new(3, 3),
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Self::PipeDirections => "`pipe-pane -I` and `-O`",
+            Self::Menus => "`display-menu`",
+            Self::PercentSizes => "percentages in `split-window -l`",
+            Self::Popups => "`display-popup`",
+            Self::SessionEnvironment => "`new-session -e`",
+            Self::FormatSubscriptions => "`refresh-client -B`",
+            Self::PopupOptions => "`display-popup -b`, `-B`, `-s`, `-S`, `-T` and `-e`",
+            Self::BackgroundPrompts => "`command-prompt -b` and `confirm-before -b`",
+        };
+        f.write_str(description)
+    }
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    /// Parse the output of `tmux -V`, e.g. `tmux 3.3a`, or a bare version.
+    pub(crate) fn version_output(input: &str) -> IResult<&str, Version> {
+        preceded(opt(tag("tmux ")), version).parse(input)
+    }
+
+    pub(crate) fn version(input: &str) -> IResult<&str, Version> {
+        let (input, prefix) = opt(alt((tag("next-"), tag("openbsd-")))).parse(input)?;
+        let (input, (major, _, minor, patch)) = (
+            map_res(digit1, str::parse),
+            char('.'),
+            map_res(digit1, str::parse),
+            opt(satisfy(|c| c.is_ascii_lowercase())),
+        )
+            .parse(input)?;
+        let (input, candidate) =
+            opt(preceded(tag("-rc"), map_res(digit1, str::parse))).parse(input)?;
+
+        let kind = match (prefix, candidate) {
+            (Some("next-"), _) => VersionKind::Next,
+            (Some(_), _) => VersionKind::OpenBsd,
+            (None, Some(candidate)) => VersionKind::Candidate(candidate),
+            (None, None) => VersionKind::Release,
+        };
+        Ok((
+            input,
+            Version {
+                major,
+                minor,
+                patch,
+                kind,
+            },
+        ))
+    }
+}
+
+/// Version of tmux, queried once and kept for the lifetime of the process.
+static VERSION: OnceLock<Version> = OnceLock::new();
+
+/// Return the cached version, unless a fake server answers the tmux invocations.
+pub(crate) fn cached_version() -> Option<Version> {
+    #[cfg(feature = "fake")]
+    if crate::fake::is_installed() {
+        return None;
+    }
+    VERSION.get().copied()
+}
+
+/// Parse the output of `tmux -V`, and cache the version.
+pub(crate) fn parse_version(output: &std::process::Output) -> Result<Version> {
+    check_process_success(output, "tmux -V")?;
+    let version = Version::from_str(&String::from_utf8(output.stdout.clone())?)?;
+
+    #[cfg(feature = "fake")]
+    if crate::fake::is_installed() {
+        return Ok(version);
+    }
+    Ok(*VERSION.get_or_init(|| version))
+}
+
+/// Return an `Error::Unsupported` if `version` does not support `capability`.
+pub(crate) fn check_capability(version: Version, capability: Capability) -> Result<()> {
+    if version.supports(capability) {
+        Ok(())
+    } else {
+        Err(Error::Unsupported {
+            capability,
+            version,
+        })
+    }
+}
+
+// ------------------------------
+// Ops
+// ------------------------------
+
+/// Return the version of tmux (`tmux -V`).
+///
+/// The version is queried once, then kept for the lifetime of the process.
+pub async fn version() -> Result<Version> {
+    if let Some(version) = cached_version() {
+        return Ok(version);
+    }
+
+    let output = Command::new("tmux").args(["-V"]).output().await?;
+    parse_version(&output)
+}
+
+/// Return `true` if the version of tmux supports `capability`.
+pub async fn supports(capability: Capability) -> Result<bool> {
+    Ok(version().await?.supports(capability))
+}
+
+/// Ensure that the version of tmux supports `capability`.
+///
+/// # Errors
+///
+/// Returns an `Error::Unsupported` if the version of tmux is too old.
+pub async fn require(capability: Capability) -> Result<()> {
+    check_capability(version().await?, capability)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(input: &str) -> Version {
+        Version::from_str(input).unwrap()
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(version("tmux 3.4\n"), Version::new(3, 4));
+        assert_eq!(
+            version("tmux 3.3a"),
+            Version {
+                major: 3,
+                minor: 3,
+                patch: Some('a'),
+                kind: VersionKind::Release,
+            }
+        );
+        assert_eq!(
+            version("next-3.5"),
+            Version {
+                major: 3,
+                minor: 5,
+                patch: None,
+                kind: VersionKind::Next,
+            }
+        );
+        assert_eq!(version("3.2-rc3").kind, VersionKind::Candidate(3));
+        assert_eq!(
+            version("tmux openbsd-7.4"),
+            Version {
+                major: 7,
+                minor: 4,
+                patch: None,
+                kind: VersionKind::OpenBsd,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_versions() {
+        assert!(Version::from_str("tmux master").is_err());
+        assert!(Version::from_str("3").is_err());
+        assert!(Version::from_str("tmux 3.4 extra").is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for input in ["3.4", "3.3a", "next-3.5", "3.2-rc3", "openbsd-7.4", "2.9a"] {
+            assert_eq!(version(input).to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let ordered = [
+            "2.9a",
+            "3.0",
+            "3.1c",
+            "next-3.2",
+            "3.2-rc2",
+            "3.2-rc3",
+            "3.2",
+            "openbsd-6.9",
+            "openbsd-7.0",
+            "openbsd-7.1",
+            "3.2a",
+            "next-3.3",
+            "3.3",
+            "openbsd-7.4",
+            "3.3a",
+            "next-3.4",
+            "openbsd-7.7",
+            "openbsd-8.0",
+            "3.10",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn test_capabilities() {
+        assert!(version("3.1c").supports(Capability::PercentSizes));
+        assert!(!version("3.1c").supports(Capability::Popups));
+        assert!(!version("next-3.2").supports(Capability::Popups));
+        assert!(version("openbsd-6.9").supports(Capability::FormatSubscriptions));
+        assert!(!version("openbsd-6.8").supports(Capability::Popups));
+        assert!(version("openbsd-6.8").supports(Capability::PercentSizes));
+        assert!(!version("openbsd-5.9").supports(Capability::PipeDirections));
+        assert!(!version("3.2a").supports(Capability::PopupOptions));
+        assert!(version("openbsd-7.2").supports(Capability::BackgroundPrompts));
+
+        let ordered = Capability::ALL.map(Capability::min_version);
+        assert!(ordered.is_sorted());
+
+        let error = check_capability(version("3.0"), Capability::Popups).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "tmux 3.0 does not support `display-popup`, which requires tmux 3.2"
+        );
+    }
+}
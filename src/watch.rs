@@ -0,0 +1,667 @@
+//! Watch the changes of the server by polling, when attaching a control mode client is not
+//! desirable.
+//!
+//! A [`Watcher`] periodically lists all the panes of the server with a single `list-panes -a`
+//! query, along with their window and session, and compares each listing to the previous one.
+//! The differences are returned one by one as typed [`Event`]s.
+//!
+//! Changes happening between two polls are merged: a window created then closed before the next
+//! poll is not reported. With a debounce delay, a change is reported once the server stayed
+//! unchanged for that delay, so that a burst of changes (such as restoring a session) is
+//! reported against its final state.
+//!
+//! The events form an endless async stream, consumed by calling [`Watcher::next`] in a loop: a
+//! failed poll returns an error, after which the watcher can still be polled.
+//!
+//! ```no_run
+//! # async fn watch() -> tmux_lib::Result<()> {
+//! use std::time::Duration;
+//! use tmux_lib::watch::{Event, Watcher};
+//!
+//! let mut watcher = Watcher::new()
+//!     .interval(Duration::from_millis(500))
+//!     .debounce(Duration::from_millis(100))
+//!     .start()
+//!     .await?;
+//!
+//! loop {
+//!     match watcher.next().await? {
+//!         Event::PaneCwdChanged { pane_id, dirpath } => {
+//!             println!("{} moved to {}", pane_id.as_str(), dirpath.display());
+//!         }
+//!         event => println!("{event:?}"),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::process::Output;
+use std::time::Duration;
+
+use nom::{
+    IResult, Parser,
+    character::complete::{char, digit1, not_line_ending},
+    combinator::{all_consuming, map_res, recognize},
+};
+
+use crate::{
+    Result,
+    error::{check_process_success, map_add_intent},
+    format::intent,
+    layout::window_layout,
+    pane_id::{PaneId, parse::pane_id},
+    parse::{boolean, quoted_nonempty_string, quoted_string},
+    runtime::{Command, sleep},
+    session_id::{SessionId, parse::session_id},
+    window_id::{WindowId, parse::window_id},
+};
+
+/// Format of the panes listed by the watcher, with their window and session.
+pub(crate) const FORMAT: &str = "#{session_id}:'#{session_name}':#{window_id}:#{window_index}:'#{window_name}':#{window_layout}:#{pane_id}:#{?pane_active,true,false}:'#{pane_current_command}':#{pane_current_path}";
+
+/// Default delay between two polls.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change of the server, found by comparing two successive polls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A session was created.
+    SessionCreated {
+        /// Id of the new session.
+        session_id: SessionId,
+        /// Name of the new session.
+        name: String,
+    },
+    /// A session was renamed.
+    SessionRenamed {
+        /// Id of the session.
+        session_id: SessionId,
+        /// New name of the session.
+        name: String,
+    },
+    /// A session was closed.
+    SessionClosed {
+        /// Id of the closed session.
+        session_id: SessionId,
+    },
+    /// A window was added to a session, either created or linked.
+    WindowAdded {
+        /// Id of the window.
+        window_id: WindowId,
+        /// Id of the session the window was added to.
+        session_id: SessionId,
+        /// Index of the window in the session.
+        index: u16,
+    },
+    /// A window was removed from a session, either closed or unlinked.
+    WindowClosed {
+        /// Id of the window.
+        window_id: WindowId,
+        /// Id of the session the window was removed from.
+        session_id: SessionId,
+    },
+    /// A window changed index in a session.
+    WindowMoved {
+        /// Id of the window.
+        window_id: WindowId,
+        /// Id of the session.
+        session_id: SessionId,
+        /// New index of the window in the session.
+        index: u16,
+    },
+    /// A window was renamed.
+    WindowRenamed {
+        /// Id of the window.
+        window_id: WindowId,
+        /// New name of the window.
+        name: String,
+    },
+    /// The layout of a window changed, after a pane was split, resized or closed.
+    LayoutChanged {
+        /// Id of the window.
+        window_id: WindowId,
+        /// New layout of the window, e.g. `b25d,80x24,0,0,3`.
+        layout: String,
+    },
+    /// A pane was created.
+    PaneAdded {
+        /// Id of the new pane.
+        pane_id: PaneId,
+        /// Id of the window of the pane.
+        window_id: WindowId,
+    },
+    /// A pane was closed.
+    PaneClosed {
+        /// Id of the closed pane.
+        pane_id: PaneId,
+    },
+    /// The current directory of a pane changed.
+    PaneCwdChanged {
+        /// Id of the pane.
+        pane_id: PaneId,
+        /// New current directory of the pane.
+        dirpath: PathBuf,
+    },
+    /// The command running in the foreground of a pane changed.
+    PaneCommandChanged {
+        /// Id of the pane.
+        pane_id: PaneId,
+        /// New foreground command of the pane.
+        command: String,
+    },
+    /// A pane became, or stopped being, the active pane of its window.
+    PaneActiveChanged {
+        /// Id of the pane.
+        pane_id: PaneId,
+        /// Describes if the pane is now active.
+        is_active: bool,
+    },
+}
+
+/// Polls the server and reports its changes as [`Event`]s.
+#[derive(Debug)]
+pub struct Watcher {
+    /// Delay between two polls.
+    interval: Duration,
+    /// Delay the server must stay unchanged before a change is reported.
+    debounce: Duration,
+    /// State of the server at the last poll, once started.
+    state: Option<Snapshot>,
+    /// Events found by the last poll, not returned yet.
+    pending: VecDeque<Event>,
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            debounce: Duration::ZERO,
+            state: None,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl Watcher {
+    /// Create a watcher polling every second, without debouncing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Poll the server every `interval`.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Report a change only once the server stayed unchanged for `debounce`, polling it every
+    /// `debounce` meanwhile.
+    #[must_use]
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Poll the current state of the server, which later changes are compared to.
+    ///
+    /// Without this call, the state is polled by the first call to [`Watcher::next`], so that
+    /// the changes made before it are not reported.
+    pub async fn start(mut self) -> Result<Self> {
+        self.state = Some(Snapshot::poll().await?);
+        Ok(self)
+    }
+
+    /// Wait for the next change of the server.
+    ///
+    /// This is the equivalent of the `next` method of a stream which never ends, but may yield
+    /// errors: calling it again after an error resumes watching from the last successful poll,
+    /// so that the changes made meanwhile are still reported. The events of a poll are
+    /// returned one per call.
+    ///
+    /// A server which is not running has no sessions: when it exits, its sessions are reported
+    /// as closed. Other failures to list the panes are returned, and leave the state unchanged.
+    ///
+    /// Dropping the returned future, as in a `select!`, loses no event.
+    pub async fn next(&mut self) -> Result<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            if self.state.is_none() {
+                self.state = Some(Snapshot::poll().await?);
+            }
+
+            sleep(self.interval).await;
+            let mut current = Snapshot::poll().await?;
+            if self.state.as_ref() == Some(&current) {
+                continue;
+            }
+
+            if !self.debounce.is_zero() {
+                loop {
+                    sleep(self.debounce).await;
+                    let settled = Snapshot::poll().await?;
+                    if settled == current {
+                        break;
+                    }
+                    current = settled;
+                }
+            }
+
+            let previous = self.state.take().unwrap_or_default();
+            self.pending.extend(previous.diff(&current));
+            self.state = Some(current);
+        }
+    }
+}
+
+/// State of the server, as listed by a single poll.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Snapshot {
+    sessions: Vec<SessionState>,
+    /// Windows, once per session they are linked to.
+    windows: Vec<WindowState>,
+    panes: Vec<PaneState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SessionState {
+    id: SessionId,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WindowState {
+    id: WindowId,
+    session_id: SessionId,
+    index: u16,
+    name: String,
+    layout: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PaneState {
+    id: PaneId,
+    window_id: WindowId,
+    is_active: bool,
+    command: String,
+    dirpath: PathBuf,
+}
+
+impl Snapshot {
+    /// List the panes of all sessions.
+    async fn poll() -> Result<Self> {
+        let args = vec!["list-panes", "-a", "-F", FORMAT];
+
+        let output = Command::new("tmux").args(&args).output().await?;
+        Self::from_output(output)
+    }
+
+    /// Build a snapshot from the result of `list-panes -a`.
+    ///
+    /// A server which is not running has no sessions, other failures are returned.
+    fn from_output(output: Output) -> Result<Self> {
+        if let Err(err) = check_process_success(&output, "list-panes") {
+            return if err.is_no_server() {
+                Ok(Self::default())
+            } else {
+                Err(err)
+            };
+        }
+        let buffer = String::from_utf8(output.stdout)?;
+
+        Self::from_lines(&buffer)
+    }
+
+    /// Build a snapshot from the output of `list-panes -a` with [`FORMAT`].
+    fn from_lines(buffer: &str) -> Result<Self> {
+        let desc = "Snapshot";
+        let intent = intent!(FORMAT);
+
+        let mut snapshot = Self::default();
+        // Unlike `available_panes`, an empty listing is valid: the server may have no sessions.
+        for line in buffer.lines() {
+            let (_, (session, window, pane)) = all_consuming(parse::line)
+                .parse(line)
+                .map_err(|e| map_add_intent(desc, intent, e))?;
+
+            if !snapshot.sessions.iter().any(|s| s.id == session.id) {
+                snapshot.sessions.push(session);
+            }
+            if !snapshot
+                .windows
+                .iter()
+                .any(|w| w.id == window.id && w.session_id == window.session_id)
+            {
+                snapshot.windows.push(window);
+            }
+            // A window linked to several sessions lists its panes once per session.
+            if !snapshot.panes.iter().any(|p| p.id == pane.id) {
+                snapshot.panes.push(pane);
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Events turning `self` into `other`: additions and changes first, from sessions to panes,
+    /// then removals, from panes to sessions.
+    fn diff(&self, other: &Self) -> Vec<Event> {
+        let mut events = vec![];
+
+        for session in &other.sessions {
+            match self.sessions.iter().find(|s| s.id == session.id) {
+                None => events.push(Event::SessionCreated {
+                    session_id: session.id.clone(),
+                    name: session.name.clone(),
+                }),
+                Some(old) if old.name != session.name => events.push(Event::SessionRenamed {
+                    session_id: session.id.clone(),
+                    name: session.name.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for window in &other.windows {
+            let link = self
+                .windows
+                .iter()
+                .find(|w| w.id == window.id && w.session_id == window.session_id);
+            match link {
+                None => events.push(Event::WindowAdded {
+                    window_id: window.id.clone(),
+                    session_id: window.session_id.clone(),
+                    index: window.index,
+                }),
+                Some(old) if old.index != window.index => events.push(Event::WindowMoved {
+                    window_id: window.id.clone(),
+                    session_id: window.session_id.clone(),
+                    index: window.index,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        // The name and layout are shared by the links of a window: report them once.
+        for (position, window) in other.windows.iter().enumerate() {
+            if other.windows[..position].iter().any(|w| w.id == window.id) {
+                continue;
+            }
+            let Some(old) = self.windows.iter().find(|w| w.id == window.id) else {
+                continue;
+            };
+            if old.name != window.name {
+                events.push(Event::WindowRenamed {
+                    window_id: window.id.clone(),
+                    name: window.name.clone(),
+                });
+            }
+            if old.layout != window.layout {
+                events.push(Event::LayoutChanged {
+                    window_id: window.id.clone(),
+                    layout: window.layout.clone(),
+                });
+            }
+        }
+
+        for pane in &other.panes {
+            let Some(old) = self.panes.iter().find(|p| p.id == pane.id) else {
+                events.push(Event::PaneAdded {
+                    pane_id: pane.id.clone(),
+                    window_id: pane.window_id.clone(),
+                });
+                continue;
+            };
+            if old.dirpath != pane.dirpath {
+                events.push(Event::PaneCwdChanged {
+                    pane_id: pane.id.clone(),
+                    dirpath: pane.dirpath.clone(),
+                });
+            }
+            if old.command != pane.command {
+                events.push(Event::PaneCommandChanged {
+                    pane_id: pane.id.clone(),
+                    command: pane.command.clone(),
+                });
+            }
+            if old.is_active != pane.is_active {
+                events.push(Event::PaneActiveChanged {
+                    pane_id: pane.id.clone(),
+                    is_active: pane.is_active,
+                });
+            }
+        }
+
+        for pane in &self.panes {
+            if !other.panes.iter().any(|p| p.id == pane.id) {
+                events.push(Event::PaneClosed {
+                    pane_id: pane.id.clone(),
+                });
+            }
+        }
+
+        for window in &self.windows {
+            if !other
+                .windows
+                .iter()
+                .any(|w| w.id == window.id && w.session_id == window.session_id)
+            {
+                events.push(Event::WindowClosed {
+                    window_id: window.id.clone(),
+                    session_id: window.session_id.clone(),
+                });
+            }
+        }
+
+        for session in &self.sessions {
+            if !other.sessions.iter().any(|s| s.id == session.id) {
+                events.push(Event::SessionClosed {
+                    session_id: session.id.clone(),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+pub(crate) mod parse {
+    use super::*;
+
+    /// Parse a line of `list-panes -a` with [`FORMAT`] into the states of the session, window
+    /// and pane it describes.
+    pub(super) fn line(input: &str) -> IResult<&str, (SessionState, WindowState, PaneState)> {
+        let (input, (session_id, _, session_name, _)) =
+            (session_id, char(':'), quoted_nonempty_string, char(':')).parse(input)?;
+        let (input, (window_id, _, index, _, name, _, layout, _)) = (
+            window_id,
+            char(':'),
+            map_res(digit1, str::parse),
+            char(':'),
+            quoted_string,
+            char(':'),
+            recognize(window_layout),
+            char(':'),
+        )
+            .parse(input)?;
+        let (input, (pane_id, _, is_active, _, command, _, dirpath)) = (
+            pane_id,
+            char(':'),
+            boolean,
+            char(':'),
+            quoted_string,
+            char(':'),
+            not_line_ending,
+        )
+            .parse(input)?;
+
+        Ok((
+            input,
+            (
+                SessionState {
+                    id: session_id.clone(),
+                    name: session_name.to_string(),
+                },
+                WindowState {
+                    id: window_id.clone(),
+                    session_id,
+                    index,
+                    name: name.to_string(),
+                    layout: layout.to_string(),
+                },
+                PaneState {
+                    id: pane_id,
+                    window_id,
+                    is_active,
+                    command: command.to_string(),
+                    dirpath: PathBuf::from(dirpath),
+                },
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const LISTING: &str = "\
+$0:'work':@0:0:'edit':b25d,80x24,0,0,0:%0:true:'vim':/home/user/src
+$0:'work':@1:1:'logs':b25e,80x24,0,0,1:%1:true:'tail':/var/log
+$1:'notes':@1:0:'logs':b25e,80x24,0,0,1:%1:true:'tail':/var/log
+";
+
+    #[test]
+    fn test_parse_listing() {
+        let snapshot = Snapshot::from_lines(LISTING).unwrap();
+        assert_eq!(snapshot.sessions.len(), 2);
+        assert_eq!(snapshot.sessions[1].name, "notes");
+        // The window @1 is linked to both sessions, its pane is listed once.
+        assert_eq!(snapshot.windows.len(), 3);
+        assert_eq!(snapshot.panes.len(), 2);
+        assert_eq!(snapshot.panes[0].dirpath, PathBuf::from("/home/user/src"));
+        assert_eq!(snapshot.panes[1].command, "tail");
+
+        assert_eq!(Snapshot::from_lines("").unwrap(), Snapshot::default());
+        assert!(Snapshot::from_lines("$0:'work':@0\n").is_err());
+    }
+
+    #[test]
+    fn test_only_missing_server_is_empty() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        let output = |code: i32, stdout: &str, stderr: &str| Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+
+        let snapshot = Snapshot::from_output(output(0, LISTING, "")).unwrap();
+        assert_eq!(snapshot.panes.len(), 2);
+
+        let missing = output(1, "", "no server running on /tmp/tmux-0/default\n");
+        assert_eq!(Snapshot::from_output(missing).unwrap(), Snapshot::default());
+        let refused = output(
+            1,
+            "",
+            "error connecting to /tmp/tmux-0/default (Connection refused)\n",
+        );
+        assert_eq!(Snapshot::from_output(refused).unwrap(), Snapshot::default());
+
+        let lost = output(1, "", "lost server\n");
+        assert!(Snapshot::from_output(lost).is_err());
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let before = Snapshot::from_lines(LISTING).unwrap();
+        let after = Snapshot::from_lines(
+            "\
+$0:'main':@0:2:'edit':c3a1,80x24,0,0{40x24,0,0,0,39x24,41,0,2}:%0:false:'zsh':/home/user
+$0:'main':@0:2:'edit':c3a1,80x24,0,0{40x24,0,0,0,39x24,41,0,2}:%2:true:'zsh':/home/user
+$1:'notes':@1:0:'journal':b25e,80x24,0,0,1:%1:true:'tail':/var/log
+$2:'new':@3:0:'zsh':b25f,80x24,0,0,3:%3:true:'zsh':/tmp
+",
+        )
+        .unwrap();
+
+        let session = |id| SessionId::from_str(id).unwrap();
+        let window = |id| WindowId::from_str(id).unwrap();
+        let pane = |id| PaneId::from_str(id).unwrap();
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                Event::SessionRenamed {
+                    session_id: session("$0"),
+                    name: "main".into(),
+                },
+                Event::SessionCreated {
+                    session_id: session("$2"),
+                    name: "new".into(),
+                },
+                Event::WindowMoved {
+                    window_id: window("@0"),
+                    session_id: session("$0"),
+                    index: 2,
+                },
+                Event::WindowAdded {
+                    window_id: window("@3"),
+                    session_id: session("$2"),
+                    index: 0,
+                },
+                Event::LayoutChanged {
+                    window_id: window("@0"),
+                    layout: "c3a1,80x24,0,0{40x24,0,0,0,39x24,41,0,2}".into(),
+                },
+                Event::WindowRenamed {
+                    window_id: window("@1"),
+                    name: "journal".into(),
+                },
+                Event::PaneCwdChanged {
+                    pane_id: pane("%0"),
+                    dirpath: PathBuf::from("/home/user"),
+                },
+                Event::PaneCommandChanged {
+                    pane_id: pane("%0"),
+                    command: "zsh".into(),
+                },
+                Event::PaneActiveChanged {
+                    pane_id: pane("%0"),
+                    is_active: false,
+                },
+                Event::PaneAdded {
+                    pane_id: pane("%2"),
+                    window_id: window("@0"),
+                },
+                Event::PaneAdded {
+                    pane_id: pane("%3"),
+                    window_id: window("@3"),
+                },
+                Event::WindowClosed {
+                    window_id: window("@1"),
+                    session_id: session("$0"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_server_exit() {
+        let before = Snapshot::from_lines(LISTING).unwrap();
+        let events = before.diff(&Snapshot::default());
+        assert_eq!(events.len(), 2 + 3 + 2);
+        assert!(matches!(events[0], Event::PaneClosed { .. }));
+        assert!(matches!(events[6], Event::SessionClosed { .. }));
+
+        assert!(before.diff(&before).is_empty());
+    }
+}
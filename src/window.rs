@@ -2,10 +2,10 @@
 //!
 //! The main use cases are running Tmux commands & parsing Tmux window information.
 
+use std::ffi::OsString;
+use std::path::PathBuf;
 use std::str::FromStr;
 
-use smol::process::Command;
-
 use nom::{
     IResult, Parser,
     character::complete::{char, digit1},
@@ -15,15 +15,26 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     Result,
-    error::{Error, check_empty_process_output, check_process_success, map_add_intent},
+    error::{
+        Error, check_empty_process_output, check_kill_output, check_process_success, map_add_intent,
+    },
+    format::intent,
     layout::{self, window_layout},
-    pane::Pane,
+    pane::{Pane, RespawnOptions},
     pane_id::{PaneId, parse::pane_id},
     parse::{boolean, quoted_nonempty_string},
+    runtime::Command,
     session::Session,
+    session_id::SessionId,
     window_id::{WindowId, parse::window_id},
 };
 
+/// Format of the windows listed by tmux, parsed by `Window::from_str`.
+pub(crate) const FORMAT: &str = "#{window_id}:#{window_index}:#{?window_active,true,false}:#{window_layout}:'#{window_name}':'#{window_linked_sessions_list}'";
+
+/// Format of the ids printed by tmux when creating a window.
+pub(crate) const NEW_WINDOW_FORMAT: &str = "#{window_id}:#{pane_id}";
+
 /// A Tmux window.
 ///
 /// ```
@@ -88,7 +99,7 @@ impl FromStr for Window {
     /// definitions.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "Window";
-        let intent = "##{window_id}:##{window_index}:##{?window_active,true,false}:##{window_layout}:'##{window_name}':'##{window_linked_sessions_list}'";
+        let intent = intent!(FORMAT);
 
         let (_, window) = all_consuming(parse::window)
             .parse(input)
@@ -145,21 +156,16 @@ pub(crate) mod parse {
 
 /// Return a list of all `Window` from all sessions.
 pub async fn available_windows() -> Result<Vec<Window>> {
-    let args = vec![
-        "list-windows",
-        "-a",
-        "-F",
-        "#{window_id}\
-        :#{window_index}\
-        :#{?window_active,true,false}\
-        :#{window_layout}\
-        :'#{window_name}'\
-        :'#{window_linked_sessions_list}'",
-    ];
+    let args = vec!["list-windows", "-a", "-F", FORMAT];
 
     let output = Command::new("tmux").args(&args).output().await?;
     let buffer = String::from_utf8(output.stdout)?;
 
+    parse_windows(&buffer)
+}
+
+/// Parse the output of `list-windows` with [`FORMAT`].
+pub(crate) fn parse_windows(buffer: &str) -> Result<Vec<Window>> {
     // Note: each call to the `Window::from_str` returns a `Result<Window, _>`.
     // All results are then collected into a Result<Vec<Window>, _>, via
     // `collect()`.
@@ -180,6 +186,7 @@ pub async fn available_windows() -> Result<Vec<Window>> {
 /// - the window name is taken from the passed `window`
 /// - the working directory is the pane's working directory.
 ///
+/// See [`NewWindow`] for more options.
 pub async fn new_window(
     session: &Session,
     window: &Window,
@@ -189,42 +196,222 @@ pub async fn new_window(
     // Use session ID for targeting - it's unambiguous and immediately valid
     // after session creation, unlike names which may have parsing issues
     // (e.g., names containing colons) or brief lookup race conditions.
-    let target_session = session.id.as_str();
-
-    let mut args = vec![
-        "new-window",
-        "-d",
-        "-c",
-        pane.dirpath.to_str().unwrap(),
-        "-n",
-        &window.name,
-        "-t",
-        target_session,
-        "-P",
-        "-F",
-        "#{window_id}:#{pane_id}",
-    ];
+    let mut new_window = NewWindow::new()
+        .target_session(&session.id)
+        .name(&window.name)
+        .start_directory(&pane.dirpath);
     if let Some(pane_command) = pane_command {
-        args.push(pane_command);
+        new_window = new_window.command(pane_command);
     }
 
-    let output = Command::new("tmux").args(&args).output().await?;
+    new_window.run().await
+}
 
-    // Check exit status before parsing to avoid confusing parse errors
-    // when tmux fails and returns empty/garbage stdout.
-    check_process_success(&output, "new-window")?;
+/// Options for creating a Tmux window, and thus a pane.
+///
+/// By default, the window is created without being selected, in the current session, at the
+/// next free index.
+///
+/// ```no_run
+/// # async fn create(session_id: &tmux_lib::session_id::SessionId) -> tmux_lib::Result<()> {
+/// use tmux_lib::window::NewWindow;
+///
+/// let (window_id, pane_id) = NewWindow::new()
+///     .target_session(session_id)
+///     .index(3)
+///     .name("logs")
+///     .start_directory("/var/log")
+///     .command("tail -f syslog")
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewWindow {
+    target_session: Option<SessionId>,
+    index: Option<u16>,
+    relative_to: Option<(RelativePosition, WindowId)>,
+    name: Option<String>,
+    start_directory: Option<PathBuf>,
+    environment: Vec<(String, String)>,
+    detached: bool,
+    command: Option<String>,
+}
 
-    let buffer = String::from_utf8(output.stdout)?;
-    let buffer = buffer.trim_end();
+/// Position of a new window relative to an existing window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RelativePosition {
+    After,
+    Before,
+}
+
+impl Default for NewWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NewWindow {
+    /// Prepare creating a window which is not selected.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            target_session: None,
+            index: None,
+            relative_to: None,
+            name: None,
+            start_directory: None,
+            environment: vec![],
+            detached: true,
+            command: None,
+        }
+    }
+
+    /// Create the window in the session with `session_id`.
+    #[must_use]
+    pub fn target_session(mut self, session_id: &SessionId) -> Self {
+        self.target_session = Some(session_id.clone());
+        self
+    }
+
+    /// Create the window at `index` in the target session. This fails if a window already
+    /// exists at this index.
+    #[must_use]
+    pub fn index(mut self, index: u16) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Insert the window right after the window with `window_id`, in the same session (`-a`).
+    ///
+    /// This takes precedence over [`NewWindow::target_session`] and [`NewWindow::index`].
+    #[must_use]
+    pub fn after(mut self, window_id: &WindowId) -> Self {
+        self.relative_to = Some((RelativePosition::After, window_id.clone()));
+        self
+    }
+
+    /// Insert the window right before the window with `window_id`, in the same session (`-b`).
+    ///
+    /// This takes precedence over [`NewWindow::target_session`] and [`NewWindow::index`].
+    #[must_use]
+    pub fn before(mut self, window_id: &WindowId) -> Self {
+        self.relative_to = Some((RelativePosition::Before, window_id.clone()));
+        self
+    }
 
-    let desc = "new-window";
-    let intent = "##{window_id}:##{pane_id}";
+    /// Set the window name (`-n`).
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the working directory of the window pane (`-c`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the window pane (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Create the window without selecting it (`-d`), which is the default.
+    #[must_use]
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.detached = detached;
+        self
+    }
+
+    /// Set the shell command run in the window pane.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
 
-    let (_, (new_window_id, _, new_pane_id)) = all_consuming((window_id, char(':'), pane_id))
-        .parse(buffer)
-        .map_err(|e| map_add_intent(desc, intent, e))?;
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["new-window".into()];
+        if self.detached {
+            args.push("-d".into());
+        }
+        if let Some(dirpath) = &self.start_directory {
+            args.push("-c".into());
+            args.push(dirpath.into());
+        }
+        for (name, value) in &self.environment {
+            args.push("-e".into());
+            args.push(format!("{name}={value}").into());
+        }
+        if let Some(name) = &self.name {
+            args.push("-n".into());
+            args.push(name.into());
+        }
+        match (&self.relative_to, &self.target_session, self.index) {
+            (Some((position, window_id)), _, _) => {
+                args.push(match position {
+                    RelativePosition::After => "-a".into(),
+                    RelativePosition::Before => "-b".into(),
+                });
+                args.push("-t".into());
+                args.push(window_id.as_str().into());
+            }
+            (None, Some(session_id), Some(index)) => {
+                args.push("-t".into());
+                args.push(window_index_target(session_id, Some(index)).into());
+            }
+            (None, Some(session_id), None) => {
+                args.push("-t".into());
+                args.push(session_id.as_str().into());
+            }
+            (None, None, Some(index)) => {
+                args.push("-t".into());
+                args.push(format!(":{index}").into());
+            }
+            (None, None, None) => {}
+        }
+        args.push("-P".into());
+        args.push("-F".into());
+        args.push(NEW_WINDOW_FORMAT.into());
+        if let Some(command) = &self.command {
+            args.push(command.into());
+        }
+        args
+    }
+
+    /// Create the window, and return the ids of the window and its pane.
+    pub async fn run(&self) -> Result<(WindowId, PaneId)> {
+        let output = Command::new("tmux").args(self.args()).output().await?;
+
+        // Check exit status before parsing to avoid confusing parse errors
+        // when tmux fails and returns empty/garbage stdout.
+        check_process_success(&output, "new-window")?;
 
-    Ok((new_window_id, new_pane_id))
+        let buffer = String::from_utf8(output.stdout)?;
+
+        Self::parse_ids(&buffer)
+    }
+
+    /// Parse the ids printed with [`NEW_WINDOW_FORMAT`].
+    pub(crate) fn parse_ids(buffer: &str) -> Result<(WindowId, PaneId)> {
+        let buffer = buffer.trim_end();
+
+        let desc = "new-window";
+        let intent = intent!(NEW_WINDOW_FORMAT);
+
+        let (_, (new_window_id, _, new_pane_id)) = all_consuming((window_id, char(':'), pane_id))
+            .parse(buffer)
+            .map_err(|e| map_add_intent(desc, intent, e))?;
+
+        Ok((new_window_id, new_pane_id))
+    }
 }
 
 /// Apply the provided `layout` to the window with `window_id`.
@@ -243,8 +430,218 @@ pub async fn select_window(window_id: &WindowId) -> Result<()> {
     check_empty_process_output(&output, "select-window")
 }
 
+/// Rename the window with `window_id`.
+///
+/// This also disables the `automatic-rename` option on that window.
+pub async fn rename_window(window_id: &WindowId, name: &str) -> Result<()> {
+    let args = vec!["rename-window", "-t", window_id.as_str(), name];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "rename-window")
+}
+
+/// Kill the window with `window_id`, unlinking it from all the sessions it belongs to.
+///
+/// Killing the last window of the last session makes the server exit, which is not an error.
+pub async fn kill_window(window_id: &WindowId) -> Result<()> {
+    let args = vec!["kill-window", "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_kill_output(&output, "kill-window")
+}
+
+/// Kill all windows of the session except the window with `window_id` (`kill-window -a`).
+pub async fn kill_other_windows(window_id: &WindowId) -> Result<()> {
+    let args = vec!["kill-window", "-a", "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_kill_output(&output, "kill-window")
+}
+
+/// Return the tmux target of the window at `index` in the session with `session_id`, or the
+/// next free index if `index` is `None`.
+pub(crate) fn window_index_target(session_id: &SessionId, index: Option<u16>) -> String {
+    match index {
+        Some(index) => format!("{}:{index}", session_id.as_str()),
+        None => format!("{}:", session_id.as_str()),
+    }
+}
+
+/// Move the window with `window_id` to the session with `session_id`, at `index`, or at the next
+/// free index if `index` is `None`.
+///
+/// The moved window keeps its `WindowId`, and is not selected in the destination session.
+pub async fn move_window(
+    window_id: &WindowId,
+    session_id: &SessionId,
+    index: Option<u16>,
+) -> Result<()> {
+    let target = window_index_target(session_id, index);
+    let args = vec!["move-window", "-d", "-s", window_id.as_str(), "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "move-window")
+}
+
+/// Renumber the windows of the session with `session_id`, closing gaps in the sequence of indexes
+/// (`move-window -r`).
+pub async fn renumber_windows(session_id: &SessionId) -> Result<()> {
+    let args = vec!["move-window", "-r", "-t", session_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "move-window")
+}
+
+/// Swap the windows with `window_id` and `other_id`, without changing the active window.
+///
+/// The two windows exchange their indexes, and sessions if they belong to different sessions.
+pub async fn swap_windows(window_id: &WindowId, other_id: &WindowId) -> Result<()> {
+    let args = vec![
+        "swap-window",
+        "-d",
+        "-s",
+        window_id.as_str(),
+        "-t",
+        other_id.as_str(),
+    ];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "swap-window")
+}
+
+/// Link the window with `window_id` into the session with `session_id`, at `index`, or at the
+/// next free index if `index` is `None`.
+///
+/// The window is then shared by both sessions, see `Window::sessions`.
+pub async fn link_window(
+    window_id: &WindowId,
+    session_id: &SessionId,
+    index: Option<u16>,
+) -> Result<()> {
+    let target = window_index_target(session_id, index);
+    let args = vec!["link-window", "-d", "-s", window_id.as_str(), "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "link-window")
+}
+
+/// Unlink the window with `window_id` from the session with `session_id`.
+///
+/// This fails if the window is not linked to any other session: use [`kill_window`] instead.
+pub async fn unlink_window(window_id: &WindowId, session_id: &SessionId) -> Result<()> {
+    let target = format!("{}:{}", session_id.as_str(), window_id.as_str());
+    let args = vec!["unlink-window", "-t", &target];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "unlink-window")
+}
+
+/// Direction in which [`rotate_window`] moves the panes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Each pane takes the place of the previous one (`-U`).
+    Upward,
+    /// Each pane takes the place of the next one (`-D`).
+    Downward,
+}
+
+impl Rotation {
+    /// Flag of `rotate-window` for this rotation.
+    pub(crate) fn flag(self) -> &'static str {
+        match self {
+            Rotation::Upward => "-U",
+            Rotation::Downward => "-D",
+        }
+    }
+}
+
+/// Rotate the positions of the panes in the window with `window_id`.
+pub async fn rotate_window(window_id: &WindowId, rotation: Rotation) -> Result<()> {
+    let args = vec!["rotate-window", rotation.flag(), "-t", window_id.as_str()];
+
+    let output = Command::new("tmux").args(&args).output().await?;
+    check_empty_process_output(&output, "rotate-window")
+}
+
+/// Restart the window with `window_id`, in place.
+///
+/// Unlike [`new_window`], this keeps the `WindowId`. All panes but one are destroyed, and the
+/// remaining pane process is restarted, with the same `PaneId`.
+///
+/// ```no_run
+/// # async fn restart(window_id: &tmux_lib::window_id::WindowId) -> tmux_lib::Result<()> {
+/// use tmux_lib::window::RespawnWindow;
+///
+/// RespawnWindow::new(window_id)
+///     .kill(true)
+///     .command("htop")
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RespawnWindow {
+    window_id: WindowId,
+    options: RespawnOptions,
+}
+
+impl RespawnWindow {
+    /// Prepare respawning the window with `window_id`.
+    #[must_use]
+    pub fn new(window_id: &WindowId) -> Self {
+        Self {
+            window_id: window_id.clone(),
+            options: RespawnOptions::default(),
+        }
+    }
+
+    /// Kill the running processes, if any (`-k`). Without it, tmux refuses to respawn a window
+    /// which is not dead.
+    #[must_use]
+    pub fn kill(mut self, kill: bool) -> Self {
+        self.options.kill = kill;
+        self
+    }
+
+    /// Set the working directory of the new process (`-c`).
+    #[must_use]
+    pub fn start_directory(mut self, dirpath: impl Into<PathBuf>) -> Self {
+        self.options.start_directory = Some(dirpath.into());
+        self
+    }
+
+    /// Add an environment variable to the new process (`-e`).
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.environment.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the shell command to run. Without it, tmux reuses the command the window was started
+    /// with.
+    #[must_use]
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.options.command = Some(command.into());
+        self
+    }
+
+    /// Arguments passed to tmux.
+    pub(crate) fn args(&self) -> Vec<OsString> {
+        self.options.args("respawn-window", self.window_id.as_str())
+    }
+
+    /// Respawn the window.
+    pub async fn run(&self) -> Result<()> {
+        let output = Command::new("tmux").args(self.args()).output().await?;
+        check_empty_process_output(&output, "respawn-window")
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::NewWindow;
+    use super::RespawnWindow;
+    use super::SessionId;
     use super::Window;
     use super::WindowId;
     use crate::Result;
@@ -471,4 +868,97 @@ mod tests {
         assert_eq!(pane_ids[1], PaneId::from_str("%2").unwrap());
         assert_eq!(pane_ids[2], PaneId::from_str("%3").unwrap());
     }
+
+    #[test]
+    fn respawn_window_args() {
+        let window_id = WindowId::from_str("@4").unwrap();
+
+        let args = RespawnWindow::new(&window_id).args();
+        assert_eq!(args, ["respawn-window", "-t", "@4"]);
+
+        let args = RespawnWindow::new(&window_id)
+            .kill(true)
+            .start_directory("/tmp")
+            .env("TERM", "xterm-256color")
+            .command("top")
+            .args();
+        assert_eq!(
+            args,
+            [
+                "respawn-window",
+                "-k",
+                "-c",
+                "/tmp",
+                "-e",
+                "TERM=xterm-256color",
+                "-t",
+                "@4",
+                "top",
+            ]
+        );
+    }
+
+    #[test]
+    fn window_index_target_format() {
+        let session_id = SessionId::from_str("$2").unwrap();
+
+        assert_eq!(super::window_index_target(&session_id, Some(7)), "$2:7");
+        assert_eq!(super::window_index_target(&session_id, None), "$2:");
+    }
+
+    #[test]
+    fn new_window_args() {
+        let session_id = SessionId::from_str("$2").unwrap();
+        let window_id = WindowId::from_str("@7").unwrap();
+        let format = "#{window_id}:#{pane_id}";
+
+        let args = NewWindow::new().args();
+        assert_eq!(args, ["new-window", "-d", "-P", "-F", format]);
+
+        let args = NewWindow::new().target_session(&session_id).args();
+        assert_eq!(args, ["new-window", "-d", "-t", "$2", "-P", "-F", format]);
+
+        let args = NewWindow::new().target_session(&session_id).index(3).args();
+        assert_eq!(args, ["new-window", "-d", "-t", "$2:3", "-P", "-F", format]);
+
+        let args = NewWindow::new().index(3).args();
+        assert_eq!(args, ["new-window", "-d", "-t", ":3", "-P", "-F", format]);
+
+        let args = NewWindow::new()
+            .target_session(&session_id)
+            .after(&window_id)
+            .args();
+        assert_eq!(
+            args,
+            ["new-window", "-d", "-a", "-t", "@7", "-P", "-F", format]
+        );
+
+        let args = NewWindow::new()
+            .before(&window_id)
+            .detached(false)
+            .name("logs")
+            .start_directory("/var/log")
+            .env("LESS", "-R")
+            .command("tail -f syslog")
+            .args();
+        assert_eq!(
+            args,
+            [
+                "new-window",
+                "-c",
+                "/var/log",
+                "-e",
+                "LESS=-R",
+                "-n",
+                "logs",
+                "-b",
+                "-t",
+                "@7",
+                "-P",
+                "-F",
+                format,
+                "tail -f syslog",
+            ]
+        );
+    }
 }
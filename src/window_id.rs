@@ -10,7 +10,10 @@ use nom::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, map_add_intent};
+use crate::{
+    error::{Error, map_add_intent},
+    format::intent,
+};
 
 /// The id of a Tmux window.
 ///
@@ -33,7 +36,7 @@ impl FromStr for WindowId {
     /// `u16`.
     fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
         let desc = "WindowId";
-        let intent = "##{window_id}";
+        let intent = intent!("#{window_id}");
 
         let (_, window_id) = all_consuming(parse::window_id)
             .parse(input)
@@ -8,7 +8,9 @@ use std::str::FromStr;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use tmux_lib::{
-    pane, server, session,
+    pane,
+    pane_id::PaneId,
+    server, session,
     session::Session,
     session_id::SessionId,
     window::{self, Window},
@@ -58,16 +60,92 @@ impl Drop for SessionGuard {
     }
 }
 
+/// Kill the tmux server of a dedicated socket on drop.
+struct ServerGuard(String);
+
+impl Drop for ServerGuard {
+    fn drop(&mut self) {
+        let _ = Command::new("tmux")
+            .args(["-L", &self.0, "kill-server"])
+            .output();
+    }
+}
+
 /// Check if tmux is available.
 fn tmux_available() -> bool {
     Command::new("tmux").arg("-V").output().is_ok()
 }
 
-/// Helper to run async tests with smol.
+/// Split the first window of the session with a pane running `command`, keeping the pane
+/// around once `command` exits (`remain-on-exit`), and wait until the pane is dead with an exit status.
+async fn split_dead_pane(session_name: &str, command: &str) -> PaneId {
+    let target = format!("={}:", session_name);
+    Command::new("tmux")
+        .args(["set-option", "-w", "-t", &target, "remain-on-exit", "on"])
+        .output()
+        .unwrap();
+    let output = Command::new("tmux")
+        .args([
+            "split-window",
+            "-d",
+            "-t",
+            &target,
+            "-P",
+            "-F",
+            "#{pane_id}",
+            command,
+        ])
+        .output()
+        .unwrap();
+    let pane_id = PaneId::from_str(String::from_utf8_lossy(&output.stdout).trim_end())
+        .expect("Should create a pane");
+
+    // tmux reports the pane as dead as soon as its pty is closed, but collects the exit status
+    // slightly later. Occasionally, tmux does not collect the exit status at all.
+    for _ in 0..40 {
+        let info = tmux_lib::pane_info::pane_info(&pane_id).await.unwrap();
+        if info.dead_status.is_some() || info.dead_signal.is_some() {
+            break;
+        }
+        sleep(std::time::Duration::from_millis(50)).await;
+    }
+    pane_id
+}
+
+/// Helper to run async tests on the runtime selected by the cargo features.
+#[cfg(feature = "tokio")]
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Helper to run async tests on the runtime selected by the cargo features.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+    async_std::task::block_on(future)
+}
+
+/// Helper to run async tests on the runtime selected by the cargo features.
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
 fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
     smol::block_on(future)
 }
 
+/// Helper to wait within async tests, on the runtime selected by the cargo features.
+async fn sleep(duration: std::time::Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+    async_std::task::sleep(duration).await;
+
+    #[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+    smol::Timer::after(duration).await;
+}
+
 // ============================================================================
 // Server Tests
 // ============================================================================
@@ -106,6 +184,45 @@ mod server_tests {
         });
     }
 
+    #[test]
+    fn test_start_describe_and_kill_server() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let name = unique_session_name("server-handle");
+        let server = server::Server::from_name(&name).unwrap();
+        let config = std::env::temp_dir().join(format!("{name}.conf"));
+        std::fs::write(&config, "set-option -s exit-empty off\n").unwrap();
+
+        block_on(async {
+            server.start(Some(&config)).await.unwrap();
+            assert!(server.is_alive());
+
+            let info = server.info().await.unwrap();
+            assert_eq!(info.session_count, 0);
+            assert_eq!(info.client_count, 0);
+
+            server.run(["new-session", "-d"]).await.unwrap();
+            let info = server.info().await.unwrap();
+            assert_eq!(info.session_count, 1);
+            assert_eq!(info.version, tmux_lib::version::version().await.unwrap());
+
+            let directory = server::socket_directory().unwrap();
+            assert_eq!(server.socket_path(), directory.join(&name));
+            assert!(server::discover(&directory).unwrap().contains(&server));
+
+            server.kill().await.unwrap();
+            sleep(std::time::Duration::from_millis(100)).await;
+            assert!(!server.is_alive());
+            server.remove_if_stale().unwrap();
+            assert!(!server::discover(&directory).unwrap().contains(&server));
+        });
+
+        let _ = std::fs::remove_file(config);
+    }
+
     #[test]
     fn test_show_options_global() {
         if !tmux_available() {
@@ -606,3 +723,2075 @@ mod window_pane_ids_tests {
         });
     }
 }
+
+// ============================================================================
+// Pane Info Tests
+// ============================================================================
+
+mod pane_info_tests {
+    use super::*;
+    use tmux_lib::pane_info;
+
+    #[test]
+    fn test_dead_pane_info() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("paneinfo");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+
+            // Retry in the rare case tmux misses the exit status.
+            let mut pane_id = split_dead_pane(&session_name, "sleep 0.2; exit 3").await;
+            for _ in 0..3 {
+                if pane_info::pane_info(&pane_id)
+                    .await
+                    .unwrap()
+                    .dead_status
+                    .is_some()
+                {
+                    break;
+                }
+                pane_id = split_dead_pane(&session_name, "sleep 0.2; exit 3").await;
+            }
+            let info = pane_info::pane_info(&pane_id).await.unwrap();
+
+            assert_eq!(info.id, pane_id);
+            assert!(info.is_dead, "Pane should be dead");
+            assert_eq!(info.dead_status, Some(3), "{:?}", info);
+            assert_eq!(info.start_command, "\"sleep 0.2; exit 3\"");
+
+            let infos = pane_info::available_pane_infos().await.unwrap();
+            assert!(infos.iter().any(|i| i.id == pane_id && i.is_dead));
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pane_process_tree() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("proctree");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+
+            let windows = window::available_windows().await.unwrap();
+            let our_window = windows
+                .iter()
+                .find(|w| w.sessions.iter().any(|s| s == &session_name))
+                .expect("Session should have a window");
+            let pane_id = &our_window.pane_ids()[0];
+
+            let info = pane_info::pane_info(pane_id).await.unwrap();
+            assert!(!info.is_dead);
+            assert!(info.tty.starts_with("/dev"));
+
+            let tree = info.process_tree().unwrap();
+            assert_eq!(tree.first().map(|p| p.pid), Some(info.pid));
+        });
+    }
+}
+
+// ============================================================================
+// Respawn Tests
+// ============================================================================
+
+mod respawn_tests {
+    use super::*;
+    use tmux_lib::{pane::RespawnPane, pane_info, window::RespawnWindow};
+
+    #[test]
+    fn test_respawn_dead_pane() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("respawn");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+
+            let pane_id = split_dead_pane(&session_name, "sleep 0.2; exit 1").await;
+            assert!(pane_info::pane_info(&pane_id).await.unwrap().is_dead);
+
+            let result = RespawnPane::new(&pane_id)
+                .start_directory("/tmp")
+                .env("TMUX_LIB_TEST", "1")
+                .command("sleep 30")
+                .run()
+                .await;
+            assert!(result.is_ok(), "Failed to respawn pane: {:?}", result);
+
+            let info = pane_info::pane_info(&pane_id).await.unwrap();
+            assert_eq!(info.id, pane_id);
+            assert!(!info.is_dead, "Respawned pane should be alive");
+            assert_eq!(info.start_command, "\"sleep 30\"");
+        });
+    }
+
+    #[test]
+    fn test_respawn_live_pane_requires_kill() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("respawnk");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+
+            let windows = window::available_windows().await.unwrap();
+            let win = windows
+                .iter()
+                .find(|w| w.sessions.iter().any(|s| s == &session_name))
+                .expect("Session should have a window");
+            let pane_id = &win.pane_ids()[0];
+
+            let result = RespawnPane::new(pane_id).run().await;
+            assert!(result.is_err(), "Respawning a live pane should fail");
+
+            let result = RespawnPane::new(pane_id).kill(true).run().await;
+            assert!(result.is_ok(), "Failed to respawn pane: {:?}", result);
+
+            let result = RespawnWindow::new(&win.id).kill(true).run().await;
+            assert!(result.is_ok(), "Failed to respawn window: {:?}", result);
+
+            let windows = window::available_windows().await.unwrap();
+            assert!(windows.iter().any(|w| w.id == win.id));
+        });
+    }
+}
+
+// ============================================================================
+// Window Management Tests
+// ============================================================================
+
+mod window_management_tests {
+    use super::*;
+    use tmux_lib::window::Rotation;
+
+    /// Return the session named `name`.
+    async fn find_session(name: &str) -> Session {
+        session::available_sessions()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|s| s.name == name)
+            .expect("Session should exist")
+    }
+
+    /// Return the windows of the session named `name`, with their index.
+    async fn session_windows(name: &str) -> Vec<(u16, WindowId)> {
+        let output = Command::new("tmux")
+            .args([
+                "list-windows",
+                "-t",
+                &format!("={}", name),
+                "-F",
+                "#{window_index}:#{window_id}",
+            ])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let (index, id) = line.split_once(':').unwrap();
+                (index.parse().unwrap(), WindowId::from_str(id).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_window_management() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let name_a = unique_session_name("winmgmt-a");
+        let name_b = unique_session_name("winmgmt-b");
+        let mut guard = SessionGuard::new(&name_a);
+        guard.add(&name_b);
+
+        block_on(async {
+            let _ = server::start(&name_a).await;
+            let _ = server::start(&name_b).await;
+            let session_a = find_session(&name_a).await;
+            let session_b = find_session(&name_b).await;
+
+            let output = Command::new("tmux")
+                .args([
+                    "new-window",
+                    "-d",
+                    "-t",
+                    &format!("{}:", session_a.id.as_str()),
+                    "-P",
+                    "-F",
+                    "#{window_id}",
+                ])
+                .output()
+                .unwrap();
+            let window_id =
+                WindowId::from_str(String::from_utf8_lossy(&output.stdout).trim_end()).unwrap();
+
+            // Rename
+            window::rename_window(&window_id, "renamed").await.unwrap();
+            let windows = window::available_windows().await.unwrap();
+            let win = windows.iter().find(|w| w.id == window_id).unwrap();
+            assert_eq!(win.name, "renamed");
+
+            // Link & unlink
+            window::link_window(&window_id, &session_b.id, None)
+                .await
+                .unwrap();
+            let windows = window::available_windows().await.unwrap();
+            let win = windows.iter().find(|w| w.id == window_id).unwrap();
+            assert!(win.sessions[0].contains(&name_b), "{:?}", win.sessions);
+
+            window::unlink_window(&window_id, &session_b.id)
+                .await
+                .unwrap();
+            assert!(
+                !session_windows(&name_b)
+                    .await
+                    .iter()
+                    .any(|(_, id)| id == &window_id)
+            );
+
+            // Move & renumber
+            window::move_window(&window_id, &session_b.id, Some(5))
+                .await
+                .unwrap();
+            assert!(
+                session_windows(&name_b)
+                    .await
+                    .contains(&(5, window_id.clone()))
+            );
+
+            window::renumber_windows(&session_b.id).await.unwrap();
+            let windows_b = session_windows(&name_b).await;
+            assert_eq!(windows_b.len(), 2);
+            assert_eq!(windows_b[1].1, window_id);
+            assert_eq!(windows_b[1].0, windows_b[0].0 + 1);
+
+            // Swap
+            let other_id = windows_b[0].1.clone();
+            window::swap_windows(&window_id, &other_id).await.unwrap();
+            assert_eq!(session_windows(&name_b).await[0].1, window_id);
+
+            // Rotate
+            window::rotate_window(&window_id, Rotation::Downward)
+                .await
+                .unwrap();
+            window::rotate_window(&window_id, Rotation::Upward)
+                .await
+                .unwrap();
+
+            // Kill others, then kill
+            window::kill_other_windows(&window_id).await.unwrap();
+            assert_eq!(
+                session_windows(&name_b).await,
+                vec![(windows_b[0].0, window_id.clone())]
+            );
+
+            let other = session_windows(&name_a).await;
+            window::kill_window(&other[0].1).await.unwrap();
+            assert!(
+                !window::available_windows()
+                    .await
+                    .unwrap()
+                    .iter()
+                    .any(|w| w.id == other[0].1)
+            );
+        });
+    }
+}
+
+// ============================================================================
+// Session Management Tests
+// ============================================================================
+
+mod session_management_tests {
+    use super::*;
+    use tmux_lib::session_info;
+
+    #[test]
+    fn test_session_management() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("sessmgmt");
+        let renamed = unique_session_name("sessmgmt-renamed");
+        let grouped = unique_session_name("sessmgmt-grouped");
+        let mut guard = SessionGuard::new(&session_name);
+        guard.add(&renamed);
+        guard.add(&grouped);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            assert!(session::has_session(&session_name).await.unwrap());
+            assert!(!session::has_session(&renamed).await.unwrap());
+
+            let sessions = session::available_sessions().await.unwrap();
+            let sess = sessions.iter().find(|s| s.name == session_name).unwrap();
+
+            // Rename
+            session::rename_session(&sess.id, &renamed).await.unwrap();
+            assert!(!session::has_session(&session_name).await.unwrap());
+            assert!(session::has_session(&renamed).await.unwrap());
+
+            // Group
+            let grouped_id = session::new_grouped_session(&sess.id, &grouped)
+                .await
+                .unwrap();
+
+            let infos = session_info::available_session_infos().await.unwrap();
+            let info = infos.iter().find(|i| i.id == sess.id).unwrap();
+            assert_eq!(info.name, renamed);
+            assert_eq!(info.attached, 0);
+            assert_eq!(info.windows, 1);
+            assert!(info.created > 0);
+            assert_eq!(info.last_attached, None);
+            let grouped_info = infos.iter().find(|i| i.id == grouped_id).unwrap();
+            assert_eq!(grouped_info.name, grouped);
+            assert!(grouped_info.group.is_some());
+            assert_eq!(grouped_info.group, info.group);
+
+            // Detach without any attached client
+            session::detach_clients(&sess.id).await.unwrap();
+
+            // Detach a control mode client, which stays attached as long as its stdin is open.
+            let mut control = Command::new("tmux")
+                .args(["-C", "attach-session", "-t", sess.id.as_str()])
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap();
+            for _ in 0..40 {
+                let clients = tmux_lib::client_info::available_clients().await.unwrap();
+                if clients.iter().any(|c| c.pid == control.id()) {
+                    break;
+                }
+                sleep(std::time::Duration::from_millis(50)).await;
+            }
+            session::detach_clients(&sess.id).await.unwrap();
+            assert!(control.wait().unwrap().success());
+
+            // Kill
+            session::kill_session(&grouped_id).await.unwrap();
+            assert!(!session::has_session(&grouped).await.unwrap());
+            session::kill_session(&sess.id).await.unwrap();
+            assert!(!session::has_session(&renamed).await.unwrap());
+        });
+    }
+
+    /// Variable giving the session or window killed by `kill_on_dedicated_server`.
+    const KILL_TARGET: &str = "TMUX_LIB_TEST_KILL_TARGET";
+
+    /// Kill the session or window given by [`KILL_TARGET`], on the server given by `$TMUX`.
+    ///
+    /// The ops address the server of `$TMUX`, shared by all the tests of this process: this test
+    /// runs in a child process started by `test_kill_only_session`.
+    #[test]
+    #[ignore = "run in a child process by test_kill_only_session"]
+    fn kill_on_dedicated_server() {
+        let Ok(target) = std::env::var(KILL_TARGET) else {
+            return;
+        };
+
+        block_on(async {
+            if target.starts_with('$') {
+                let session_id = SessionId::from_str(&target).unwrap();
+                session::kill_session(&session_id).await.unwrap();
+            } else {
+                let window_id = WindowId::from_str(&target).unwrap();
+                window::kill_window(&window_id).await.unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn test_kill_only_session() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        // Killing the only session, or its only window, makes the server exit.
+        for target_format in ["#{session_id}", "#{window_id}"] {
+            let socket_name = unique_session_name("last-session");
+            let _guard = ServerGuard(socket_name.clone());
+            let output = Command::new("tmux")
+                .args(["-L", &socket_name, "-f", "/dev/null", "new-session", "-d"])
+                .args(["-P", "-F"])
+                .arg(format!(
+                    "#{{socket_path}},#{{pid}},#{{session_id}}:{target_format}"
+                ))
+                .output()
+                .unwrap();
+            let printed = String::from_utf8(output.stdout).unwrap();
+            let (tmux, target) = printed.trim_end().split_once(':').unwrap();
+            // `$TMUX` holds the session id without its `$`.
+            let tmux = tmux.replace(",$", ",");
+
+            let child = Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "session_management_tests::kill_on_dedicated_server",
+                ])
+                .args(["--ignored", "--nocapture"])
+                .env("TMUX", &tmux)
+                .env_remove("TMUX_PANE")
+                .env(KILL_TARGET, target)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&child.stdout);
+            assert!(child.status.success(), "{stdout}");
+            assert!(stdout.contains("1 passed"), "{stdout}");
+
+            let server = server::Server::from_name(&socket_name).unwrap();
+            for _ in 0..40 {
+                if !server.is_alive() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(25));
+            }
+            assert!(!server.is_alive());
+        }
+    }
+}
+
+// ============================================================================
+// Builder Tests
+// ============================================================================
+
+mod builder_tests {
+    use super::*;
+    use tmux_lib::{
+        pane::{PaneSize, SplitDirection, SplitPane},
+        pane_info,
+        session::NewSession,
+        window::NewWindow,
+    };
+
+    #[test]
+    fn test_builders() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("builders");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let result = NewSession::new()
+                .name(&session_name)
+                .window_name("first")
+                .start_directory("/tmp")
+                .env("TMUX_LIB_TEST", "builders")
+                .size(120, 40)
+                .run()
+                .await;
+            assert!(result.is_ok(), "Failed to create session: {:?}", result);
+            let (session_id, window_id, pane_id) = result.unwrap();
+
+            let info = pane_info::pane_info(&pane_id).await.unwrap();
+            assert_eq!((info.width, info.height), (120, 40));
+
+            let result = NewWindow::new()
+                .target_session(&session_id)
+                .index(7)
+                .name("seventh")
+                .run()
+                .await;
+            assert!(result.is_ok(), "Failed to create window: {:?}", result);
+            let (seventh_id, _) = result.unwrap();
+
+            let (between_id, _) = NewWindow::new()
+                .after(&window_id)
+                .name("between")
+                .run()
+                .await
+                .unwrap();
+
+            let windows = window::available_windows().await.unwrap();
+            let index_of = |id: &WindowId| windows.iter().find(|w| &w.id == id).unwrap().index;
+            assert_eq!(index_of(&seventh_id), 7);
+            assert_eq!(index_of(&between_id), index_of(&window_id) + 1);
+
+            let result = SplitPane::new()
+                .target_pane(&pane_id)
+                .direction(SplitDirection::Horizontal)
+                .size(PaneSize::Cells(30))
+                .run()
+                .await;
+            assert!(result.is_ok(), "Failed to split pane: {:?}", result);
+            let new_pane_id = result.unwrap();
+
+            let info = pane_info::pane_info(&new_pane_id).await.unwrap();
+            assert_eq!(info.width, 30);
+            assert_eq!(info.height, 40);
+        });
+    }
+}
+
+// ============================================================================
+// Client Tests
+// ============================================================================
+
+mod client_tests {
+    use super::*;
+    use std::process::Stdio;
+    use tmux_lib::{client, client_info, window::NewWindow};
+
+    #[test]
+    fn test_control_mode_client() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("client");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            let sessions = session::available_sessions().await.unwrap();
+            let sess = sessions.iter().find(|s| s.name == session_name).unwrap();
+            let (window_id, _) = NewWindow::new()
+                .target_session(&sess.id)
+                .name("second")
+                .run()
+                .await
+                .unwrap();
+
+            // A control mode client stays attached as long as its stdin is open.
+            let mut control = Command::new("tmux")
+                .args(["-C", "attach-session", "-t", &format!("={}", session_name)])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap();
+
+            let mut found = None;
+            for _ in 0..40 {
+                let clients = client_info::available_clients().await.unwrap();
+                found = clients.into_iter().find(|c| c.pid == control.id());
+                if found.is_some() {
+                    break;
+                }
+                sleep(std::time::Duration::from_millis(50)).await;
+            }
+            let found = found.expect("Control mode client not listed");
+            assert!(found.is_control_mode);
+            assert_eq!(found.session_id.as_ref(), Some(&sess.id));
+            assert_eq!(found.tty, None);
+
+            client::set_client_size(&found.name, 100, 30).await.unwrap();
+            client::refresh_client(&found.name).await.unwrap();
+            client::refresh_client_status(&found.name).await.unwrap();
+            client::DisplayMessage::new("hello")
+                .client(&found.name)
+                .duration(std::time::Duration::from_millis(100))
+                .run()
+                .await
+                .unwrap();
+            client::switch_client_to(&found.name, &window_id)
+                .await
+                .unwrap();
+
+            let clients = client_info::available_clients().await.unwrap();
+            let switched = clients.iter().find(|c| c.name == found.name).unwrap();
+            assert_eq!(switched.window_id.as_ref(), Some(&window_id));
+            assert_eq!(switched.width, Some(100));
+
+            client::detach_client(&found.name).await.unwrap();
+            assert!(control.wait().unwrap().success());
+
+            let clients = client_info::available_clients().await.unwrap();
+            assert!(clients.iter().all(|c| c.name != found.name));
+        });
+    }
+
+    #[test]
+    fn test_display_message_print() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("display");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            let sessions = session::available_sessions().await.unwrap();
+            let sess = sessions.iter().find(|s| s.name == session_name).unwrap();
+
+            let printed = client::DisplayMessage::new("#{session_name}:#{session_id}")
+                .target(&sess.id)
+                .print()
+                .await
+                .unwrap();
+            assert_eq!(printed, format!("{}:{}", session_name, sess.id.as_str()));
+        });
+    }
+}
+
+// ============================================================================
+// Format Tests
+// ============================================================================
+
+mod format_tests {
+    use super::*;
+    use tmux_lib::format;
+
+    #[test]
+    fn test_expand_formats() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("format");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            let sessions = session::available_sessions().await.unwrap();
+            let sess = sessions.iter().find(|s| s.name == session_name).unwrap();
+            let pane_id = format::expand(&sess.id, "#{pane_id}").await.unwrap();
+            let pane_id = PaneId::from_str(&pane_id).unwrap();
+
+            let name = format::expand(&sess.id, "#{s/test-/T-/:session_name}")
+                .await
+                .unwrap();
+            assert_eq!(name, session_name.replacen("test-", "T-", 1));
+
+            let values = format::expand_many(
+                &pane_id,
+                &[
+                    "#{pane_id}",
+                    "",
+                    "a\nb",
+                    "#{?pane_dead,dead,alive}",
+                    "#{session_name}",
+                ],
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                values,
+                [pane_id.as_str(), "", "a\nb", "alive", session_name.as_str()]
+            );
+
+            let result = format::expand_many(&pane_id, &["#{pane_id", "#{pane_id}"]).await;
+            assert!(
+                result.is_err(),
+                "Unbalanced format should fail: {:?}",
+                result
+            );
+
+            assert!(
+                format::expand_many::<&str>(&pane_id, &[])
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        });
+    }
+
+    #[test]
+    fn test_offline_evaluation_matches_tmux() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("offline-format");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            let sessions = session::available_sessions().await.unwrap();
+            let sess = sessions.iter().find(|s| s.name == session_name).unwrap();
+
+            let names = [
+                "session_name",
+                "pane_active",
+                "pane_index",
+                "pane_current_path",
+            ];
+            let values = format::expand_many(&sess.id, &names.map(|name| format!("#{{{name}}}")))
+                .await
+                .unwrap();
+            let variables = names
+                .iter()
+                .map(|name| name.to_string())
+                .zip(values)
+                .collect();
+
+            let formats = [
+                "#{=4:session_name}",
+                "#{=/-3/...:session_name}",
+                "#{?pane_active,active,inactive}",
+                "#{s/-([0-9]+)/_\\1/:session_name}",
+                "#{==:#{pane_index},0}",
+                "#{b:pane_current_path}",
+                "#{n:session_name}",
+                "##{l:#{pane_index}}",
+            ];
+            let expanded = format::expand_many(&sess.id, &formats).await.unwrap();
+            for (format, expected) in formats.iter().zip(expanded) {
+                let evaluated = format::Format::from_str(format)
+                    .unwrap()
+                    .evaluate(&variables);
+                assert_eq!(evaluated, expected, "format: {format}");
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Batch Tests
+// ============================================================================
+
+mod batch_tests {
+    use super::*;
+    use tmux_lib::{
+        batch::{BatchMode, CommandBatch},
+        format,
+        pane::SplitPane,
+        session::NewSession,
+        window::NewWindow,
+    };
+
+    async fn create_layout(session_name: &str, mode: BatchMode) {
+        let (session_id, window_id, pane_id) =
+            NewSession::new().name(session_name).run().await.unwrap();
+
+        let mut batch = CommandBatch::new().mode(mode);
+        for index in 0..50 {
+            batch.add_command(
+                &NewWindow::new()
+                    .target_session(&session_id)
+                    .name(format!("w{index};")),
+            );
+        }
+        batch
+            .add_command(&SplitPane::new().target_pane(&pane_id))
+            .add([
+                "rename-window",
+                "-t",
+                window_id.as_str(),
+                "say \"hi\"; done",
+            ]);
+        // Large enough to need several invocations in arguments mode.
+        let large = "x".repeat(10_000);
+        batch.add(["display-message", "-p", large.as_str()]).add([
+            "display-message",
+            "-p",
+            large.as_str(),
+        ]);
+
+        let outputs = batch.run().await.unwrap();
+        assert_eq!(outputs.len(), 54);
+        assert!(outputs[..51].iter().all(|output| output.ends_with('\n')));
+        assert!(outputs[51].is_empty());
+        assert_eq!(outputs[53], format!("{large}\n"));
+
+        let name = format::expand(&window_id, "#{window_name}").await.unwrap();
+        assert_eq!(name, "say \"hi\"; done");
+
+        let new_pane_id = PaneId::from_str(outputs[50].trim_end()).unwrap();
+        let panes = pane::available_panes().await.unwrap();
+        assert!(panes.iter().any(|p| p.id == new_pane_id));
+
+        let names = format::expand_many(&session_id, &["#{W:#{window_name} }"])
+            .await
+            .unwrap();
+        assert!(names[0].contains("w0; "));
+        assert!(names[0].contains("w49; "));
+
+        // Bare words starting with `%` are directives in config files.
+        let mut batch = CommandBatch::new().mode(mode);
+        batch
+            .add(["rename-window", "-t", window_id.as_str(), "%work"])
+            .add(["set-option", "-t", session_id.as_str(), "@clock", "%H:%M"]);
+        batch.run().await.unwrap();
+        let values = format::expand_many(&window_id, &["#{window_name}", "#{@clock}"])
+            .await
+            .unwrap();
+        assert_eq!(values, ["%work", "%H:%M"]);
+    }
+
+    #[test]
+    fn test_batch_arguments() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("batch-args");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(create_layout(&session_name, BatchMode::Arguments));
+    }
+
+    #[test]
+    fn test_batch_source_file() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("batch-source");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(create_layout(&session_name, BatchMode::SourceFile));
+    }
+
+    #[test]
+    fn test_batch_failure() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        block_on(async {
+            let mut batch = CommandBatch::new();
+            batch
+                .add(["display-message", "-p", "before"])
+                .add(["select-window", "-t", "@999999"]);
+            let result = batch.run().await;
+            assert!(result.is_err(), "Missing window should fail: {:?}", result);
+
+            assert!(CommandBatch::new().run().await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_batch_failure_in_the_middle() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("batch-failure");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let (session_id, _, _) = NewSession::new().name(&session_name).run().await.unwrap();
+            let set = |value: &'static str| {
+                vec!["set-option", "-t", session_id.as_str(), "@batch", value]
+            };
+
+            // Arguments stop at the failing command, source-file runs the commands after it.
+            for (mode, last) in [
+                (BatchMode::Arguments, "first"),
+                (BatchMode::SourceFile, "last"),
+            ] {
+                let mut batch = CommandBatch::new().mode(mode);
+                batch
+                    .add(set("first"))
+                    .add(["select-window", "-t", "@999999"])
+                    .add(set("last"));
+                let result = batch.run().await;
+                assert!(result.is_err(), "{mode:?} should fail: {result:?}");
+
+                let value = format::expand(&session_id, "#{@batch}").await.unwrap();
+                assert_eq!(value, last, "{mode:?}");
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Command Tests
+// ============================================================================
+
+mod command_tests {
+    use super::*;
+    use tmux_lib::{
+        batch::{BatchMode, CommandBatch},
+        command::TmuxCommand,
+    };
+
+    fn tmux_output(args: &[&str]) -> String {
+        let output = Command::new("tmux").args(args).output().unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Key bindings of `table`, as printed by tmux.
+    fn list_keys(table: &str) -> Vec<TmuxCommand> {
+        tmux_output(&["list-keys", "-T", table])
+            .lines()
+            .map(|line| TmuxCommand::from_str(line).unwrap())
+            .collect()
+    }
+
+    async fn bind_and_list(session_name: &str, mode: BatchMode) {
+        let table = format!("{session_name}-keys");
+        let option = format!("@{session_name}");
+        let bindings: Vec<TmuxCommand> = [
+            format!(r#"bind-key -T {table} -r x split-window -h \; send-keys "ls;cd" Enter"#),
+            format!(r#"bind-key -T {table} y display-message "a \$HOME #{{pane_id}} 'q'""#),
+            format!(r#"bind-key -T {table} z set-option -g {option} "x \"y\"; z""#),
+        ]
+        .iter()
+        .map(|line| TmuxCommand::from_str(line).unwrap())
+        .collect();
+
+        let mut batch = CommandBatch::new().mode(mode);
+        for binding in &bindings {
+            batch.add_command(binding);
+        }
+        batch.add_command(&TmuxCommand::from_str(&format!("set -g {option} 'a;b'")).unwrap());
+        batch.run().await.unwrap();
+
+        assert_eq!(list_keys(&table), bindings);
+        assert_eq!(tmux_output(&["show-options", "-gv", &option]), "a;b\n");
+
+        let mut cleanup = CommandBatch::new();
+        cleanup
+            .add(["unbind-key", "-a", "-T", table.as_str()])
+            .add(["set-option", "-gu", option.as_str()]);
+        cleanup.run().await.unwrap();
+    }
+
+    #[test]
+    fn test_command_round_trip_arguments() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("command-args");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            bind_and_list(&session_name, BatchMode::Arguments).await;
+        });
+    }
+
+    #[test]
+    fn test_command_round_trip_source_file() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("command-source");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+            bind_and_list(&session_name, BatchMode::SourceFile).await;
+        });
+    }
+}
+
+// ============================================================================
+// Config Tests
+// ============================================================================
+
+mod config_tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tmux_lib::{
+        command::{self, TmuxCommand},
+        config::Config,
+        format::Format,
+    };
+
+    const CONFIG: &str = r##"# Test config
+set -g @string "a b" ; set -g @single 'c "d"'
+setw -g @escapes "tab\there \$HOME é"
+
+%if "#{==:a,a}"
+set -g @branch if
+%elif 1
+set -g @branch elif
+%else
+set -g @branch else
+%endif
+
+%if 0
+set -g @skipped 1
+%endif
+
+bind-key -T test-table x split-window -h -c ~
+EDITOR=vim
+"##;
+
+    #[test]
+    fn test_config_matches_tmux() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("config");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let _ = server::start(&session_name).await;
+        });
+
+        let path = std::env::temp_dir().join(format!("{session_name}.conf"));
+        std::fs::write(&path, CONFIG).unwrap();
+
+        // With `-nv`, tmux prints the parsed commands without running them.
+        let output = Command::new("tmux")
+            .args(["source-file", "-nv"])
+            .arg(&path)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let expected: Vec<(String, TmuxCommand)> = stdout
+            .lines()
+            .flat_map(|line| {
+                let (location, commands) = line.split_once(": ").unwrap();
+                command::parse_line(commands)
+                    .unwrap()
+                    .into_iter()
+                    .map(|command| (location.to_string(), command))
+            })
+            .collect();
+
+        let config = Config::load(&path).unwrap();
+        let is_true = |condition: &str| {
+            let value = Format::from_str(condition)
+                .unwrap()
+                .evaluate(&HashMap::new());
+            !value.is_empty() && value != "0"
+        };
+        let commands: Vec<(String, TmuxCommand)> = config
+            .commands(is_true)
+            .into_iter()
+            .map(|(location, command)| (location.to_string(), command.clone()))
+            .collect();
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands, expected);
+    }
+}
+
+// ============================================================================
+// Blocking Tests
+// ============================================================================
+
+#[cfg(feature = "blocking")]
+mod blocking_tests {
+    use super::*;
+    use tmux_lib::{
+        blocking,
+        client::DisplayMessage,
+        pane::{SplitDirection, SplitPane},
+        session::NewSession,
+        window::NewWindow,
+    };
+
+    #[test]
+    fn test_blocking_ops() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("blocking");
+        let _guard = SessionGuard::new(&session_name);
+
+        let (session_id, window_id, pane_id) = NewSession::new()
+            .name(&session_name)
+            .window_name("first")
+            .start_directory("/tmp")
+            .run_blocking()
+            .unwrap();
+        assert!(blocking::session::has_session(&session_name).unwrap());
+
+        let sessions = blocking::session::available_sessions().unwrap();
+        assert!(sessions.iter().any(|s| s.id == session_id));
+
+        let (second_id, _) = NewWindow::new()
+            .target_session(&session_id)
+            .name("second")
+            .run_blocking()
+            .unwrap();
+        blocking::window::rename_window(&second_id, "renamed").unwrap();
+        let windows = blocking::window::available_windows().unwrap();
+        let second = windows.iter().find(|w| w.id == second_id).unwrap();
+        assert_eq!(second.name, "renamed");
+
+        let new_pane_id = SplitPane::new()
+            .target_pane(&pane_id)
+            .direction(SplitDirection::Vertical)
+            .run_blocking()
+            .unwrap();
+        blocking::pane::select_pane(&new_pane_id).unwrap();
+        let panes = blocking::pane::available_panes().unwrap();
+        assert!(panes.iter().any(|p| p.id == new_pane_id && p.is_active));
+
+        let command = DisplayMessage::new("#{window_name}")
+            .target(&window_id)
+            .print_blocking()
+            .unwrap();
+        assert_eq!(command, "first");
+
+        blocking::window::kill_window(&second_id).unwrap();
+        let windows = blocking::window::available_windows().unwrap();
+        assert!(windows.iter().all(|w| w.id != second_id));
+
+        assert!(
+            blocking::server::show_options(true)
+                .unwrap()
+                .contains_key("default-shell")
+        );
+        assert!(!blocking::server::default_command().unwrap().is_empty());
+
+        blocking::session::kill_session(&session_id).unwrap();
+        assert!(!blocking::session::has_session(&session_name).unwrap());
+    }
+}
+
+// ============================================================================
+// Fake Server Tests
+// ============================================================================
+
+#[cfg(feature = "fake")]
+mod fake_tests {
+    use super::*;
+    use tmux_lib::{
+        client::DisplayMessage,
+        fake::FakeServer,
+        pane::{PaneSize, SplitDirection, SplitPane},
+        session::NewSession,
+        window::NewWindow,
+    };
+
+    #[test]
+    fn test_fake_server_answers_ops() {
+        let server = FakeServer::new();
+        let _guard = server.install();
+
+        block_on(async {
+            assert!(session::available_sessions().await.is_err());
+
+            let (session_id, window_id, pane_id) = NewSession::new()
+                .name("work")
+                .window_name("edit")
+                .start_directory("/tmp")
+                .run()
+                .await
+                .unwrap();
+            assert_eq!(session_id, SessionId::from_str("$0").unwrap());
+            assert!(session::has_session("work").await.unwrap());
+
+            let (second_id, _) = NewWindow::new()
+                .target_session(&session_id)
+                .name("logs")
+                .run()
+                .await
+                .unwrap();
+            let new_pane_id = SplitPane::new()
+                .target_pane(&pane_id)
+                .direction(SplitDirection::Horizontal)
+                .size(PaneSize::Cells(20))
+                .run()
+                .await
+                .unwrap();
+
+            let windows = window::available_windows().await.unwrap();
+            assert_eq!(windows.len(), 2);
+            let first = windows.iter().find(|w| w.id == window_id).unwrap();
+            assert_eq!(first.name, "edit");
+            assert_eq!(first.sessions, vec!["work".to_string()]);
+            assert_eq!(first.layout, "13d6,80x24,0,0{59x24,0,0,0,20x24,60,0,2}");
+
+            window::set_layout("even-horizontal", &window_id)
+                .await
+                .unwrap();
+            let panes = pane::available_panes().await.unwrap();
+            let new_pane = panes.iter().find(|p| p.id == new_pane_id).unwrap();
+            assert!(!new_pane.is_active);
+            assert_eq!(new_pane.dirpath, std::path::PathBuf::from("/tmp"));
+
+            let name = DisplayMessage::new("#{window_name}")
+                .target(&second_id)
+                .print()
+                .await
+                .unwrap();
+            assert_eq!(name, "logs");
+
+            assert_eq!(server::default_command().await.unwrap(), "/bin/sh");
+
+            window::kill_window(&second_id).await.unwrap();
+            session::detach_clients(&session_id).await.unwrap();
+            session::kill_session(&session_id).await.unwrap();
+            assert!(session::available_sessions().await.is_err());
+        });
+
+        let commands = server.commands();
+        assert_eq!(commands[0][0], "list-sessions");
+        assert_eq!(commands[1][0], "new-session");
+        assert!(commands.iter().any(|c| c[0] == "select-layout"));
+    }
+
+    #[test]
+    fn test_fake_server_is_installed_per_thread() {
+        let server = FakeServer::new();
+        let _guard = server.install();
+        block_on(NewSession::new().name("here").run()).unwrap();
+
+        let other = std::thread::spawn(|| {
+            let server = FakeServer::new();
+            let _guard = server.install();
+            block_on(session::available_sessions()).is_err()
+        });
+        assert!(other.join().unwrap());
+
+        let sessions = block_on(session::available_sessions()).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "here");
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_fake_server_answers_blocking_ops() {
+        use tmux_lib::blocking;
+
+        let server = FakeServer::new();
+        let _guard = server.install();
+
+        let (session_id, _, _) = NewSession::new().name("sync").run_blocking().unwrap();
+        blocking::session::rename_session(&session_id, "renamed").unwrap();
+        let sessions = blocking::session::available_sessions().unwrap();
+        assert_eq!(sessions[0].name, "renamed");
+        assert_eq!(
+            server.commands().last().unwrap()[..2],
+            ["list-sessions".to_string(), "-F".to_string()]
+        );
+    }
+}
+
+// ============================================================================
+// Terminal Emulation Tests
+// ============================================================================
+
+#[cfg(feature = "terminal")]
+mod terminal_tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use tmux_lib::{session::NewSession, terminal::Emulator};
+
+    #[test]
+    fn test_mirror_pane_from_control_mode() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("terminal");
+        let _guard = SessionGuard::new(&session_name);
+
+        let (_, _, pane_id) = block_on(
+            NewSession::new()
+                .name(&session_name)
+                .size(40, 10)
+                .command("sh")
+                .run(),
+        )
+        .unwrap();
+
+        let mut client = Command::new("tmux")
+            .args(["-C", "attach-session", "-t", &format!("={session_name}")])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let stdout = client.stdout.take().unwrap();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).split(b'\n') {
+                if sender.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Let the client attach before producing output.
+        std::thread::sleep(Duration::from_millis(200));
+        Command::new("tmux")
+            .args([
+                "send-keys",
+                "-t",
+                pane_id.as_str(),
+                "echo mir''rored",
+                "Enter",
+            ])
+            .output()
+            .unwrap();
+
+        let mut emulator = Emulator::new(10, 40);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mirrored = loop {
+            let Ok(line) =
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            else {
+                break false;
+            };
+            emulator.process_line(&line).unwrap();
+            if let Some(contents) = emulator.contents(&pane_id)
+                && contents.lines().any(|line| line == "mirrored")
+            {
+                break true;
+            }
+        };
+
+        drop(client.stdin.take());
+        let _ = client.kill();
+        let _ = client.wait();
+        assert!(mirrored, "pane output was not mirrored");
+    }
+}
+
+// ============================================================================
+// Version Tests
+// ============================================================================
+
+mod version_tests {
+    use super::*;
+    use tmux_lib::version::{self, Capability, Version};
+
+    #[test]
+    fn test_version_matches_tmux() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let output = Command::new("tmux").arg("-V").output().unwrap();
+        let expected = String::from_utf8(output.stdout).unwrap();
+
+        let version = block_on(version::version()).unwrap();
+        assert_eq!(format!("tmux {version}\n"), expected);
+        assert_eq!(
+            block_on(version::supports(Capability::Popups)).unwrap(),
+            version >= Version::new(3, 2)
+        );
+    }
+}
+
+// ============================================================================
+// Policy Tests
+// ============================================================================
+
+mod policy_tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tmux_lib::{
+        batch::{BatchMode, CommandBatch},
+        environment::Environment,
+        error::Error,
+        policy::Policy,
+    };
+
+    #[test]
+    fn test_timeout_and_retries() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let name = unique_session_name("policy");
+        let server = server::Server::from_name(&name).unwrap();
+        let config = std::env::temp_dir().join(format!("{name}.conf"));
+        std::fs::write(&config, "set-option -s exit-empty off\n").unwrap();
+
+        block_on(async {
+            server.start(Some(&config)).await.unwrap();
+
+            // `run-shell` waits for the shell command, wedging the client.
+            let policy = Policy::new().timeout(Duration::from_millis(200));
+            let start = Instant::now();
+            let result = policy.scope(server.run(["run-shell", "sleep 5"])).await;
+            assert!(
+                matches!(&result, Err(Error::Io { source }) if source.kind() == std::io::ErrorKind::TimedOut),
+                "expected a timeout, got {result:?}"
+            );
+            assert!(start.elapsed() < Duration::from_secs(2));
+
+            // Plain commands are not retried on lookup failures.
+            server.run(["new-session", "-d"]).await.unwrap();
+            let policy = Policy::new().retries(2).backoff(Duration::from_millis(100));
+            let start = Instant::now();
+            let result = policy
+                .scope(server.run(["select-window", "-t", "@999999"]))
+                .await;
+            assert!(result.is_err());
+            assert!(start.elapsed() < Duration::from_millis(100));
+
+            server.kill().await.unwrap();
+        });
+
+        let _ = server.remove_if_stale();
+        let _ = std::fs::remove_file(config);
+    }
+
+    /// Policy retrying lookups for at least 300ms.
+    fn retrying() -> Policy {
+        Policy::new().retries(2).backoff(Duration::from_millis(100))
+    }
+
+    #[test]
+    fn test_queries_retry_lookups() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("policy-query");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let (_, _, pane_id) = session::NewSession::new()
+                .name(&session_name)
+                .run()
+                .await
+                .unwrap();
+            let panes = pane::available_panes().await.unwrap();
+            let mut missing = panes.into_iter().find(|p| p.id == pane_id).unwrap();
+            missing.id = PaneId::from_str("%999999").unwrap();
+
+            let start = Instant::now();
+            let captured = retrying().scope(missing.capture()).await.unwrap();
+            assert!(captured.is_empty());
+            assert!(start.elapsed() >= Duration::from_millis(300));
+        });
+    }
+
+    #[test]
+    fn test_has_session_is_not_retried() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("policy-has");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            server::start(&session_name).await.unwrap();
+
+            let start = Instant::now();
+            let found = retrying()
+                .scope(session::has_session("policy-missing-session"))
+                .await;
+            assert!(!found.unwrap());
+            assert!(start.elapsed() < Duration::from_millis(100));
+        });
+    }
+
+    #[test]
+    fn test_batches_are_not_retried() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("policy-batch");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let (session_id, _, _) = session::NewSession::new()
+                .name(&session_name)
+                .run()
+                .await
+                .unwrap();
+
+            for mode in [BatchMode::Arguments, BatchMode::SourceFile] {
+                let mut batch = CommandBatch::new().mode(mode);
+                batch
+                    .add(["new-window", "-d", "-t", session_id.as_str()])
+                    .add(["select-window", "-t", "@999999"]);
+                let start = Instant::now();
+                let result = retrying().scope(batch.run()).await;
+                assert!(result.is_err(), "{mode:?} batch should fail");
+                assert!(start.elapsed() < Duration::from_millis(100));
+            }
+
+            // Each batch created its window once.
+            let count = tmux_lib::format::expand(&session_id, "#{session_windows}")
+                .await
+                .unwrap();
+            assert_eq!(count, "3");
+        });
+    }
+
+    #[test]
+    fn test_environment_probe_is_not_retried() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("policy-probe");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let (session_id, _, pane_id) = session::NewSession::new()
+                .name(&session_name)
+                .run()
+                .await
+                .unwrap();
+
+            // A stale pane id is probed, then the session is resolved.
+            let socket_path = tmux_lib::format::expand(&session_id, "#{socket_path}")
+                .await
+                .unwrap();
+            let environment = Environment {
+                socket_path: socket_path.into(),
+                server_pid: 0,
+                session_id: Some(session_id),
+                pane_id: Some(PaneId::from_str("%999999").unwrap()),
+            };
+            let start = Instant::now();
+            let current = retrying().scope(environment.current()).await.unwrap();
+            assert_eq!(current.pane.id, pane_id);
+            assert!(start.elapsed() < Duration::from_millis(100));
+        });
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_timeout_blocking() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let name = unique_session_name("policy-blocking");
+        let server = server::Server::from_name(&name).unwrap();
+        let config = std::env::temp_dir().join(format!("{name}.conf"));
+        std::fs::write(&config, "set-option -s exit-empty off\n").unwrap();
+        server.start_blocking(Some(&config)).unwrap();
+
+        let policy = Policy::new().timeout(Duration::from_millis(200));
+        let start = Instant::now();
+        let result = policy.scope_blocking(|| server.run_blocking(["run-shell", "sleep 5"]));
+        assert!(
+            matches!(&result, Err(Error::Io { source }) if source.kind() == std::io::ErrorKind::TimedOut),
+            "expected a timeout, got {result:?}"
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        server.kill_blocking().unwrap();
+        let _ = server.remove_if_stale();
+        let _ = std::fs::remove_file(config);
+    }
+}
+
+// ============================================================================
+// Environment Tests
+// ============================================================================
+
+mod environment_tests {
+    use super::*;
+    use tmux_lib::environment::Environment;
+
+    #[test]
+    fn test_resolve_current_on_enclosing_server() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        // A dedicated server, as for a tool running inside a nested tmux.
+        let socket_name = unique_session_name("environment");
+        let _guard = ServerGuard(socket_name.clone());
+        let output = Command::new("tmux")
+            .args(["-L", &socket_name, "-f", "/dev/null", "new-session", "-d"])
+            .args(["-s", "inner", "-P", "-F"])
+            .arg("#{socket_path},#{pid},#{session_id}:#{pane_id}")
+            .output()
+            .unwrap();
+        let printed = String::from_utf8(output.stdout).unwrap();
+        let (tmux, pane_id) = printed.trim_end().split_once(':').unwrap();
+        // `$TMUX` holds the session id without its `$`.
+        let tmux = tmux.replace(",$", ",");
+        let second_pane_id = Command::new("tmux")
+            .args(["-L", &socket_name, "split-window", "-d", "-P", "-F"])
+            .args(["#{pane_id}", "-t", pane_id])
+            .output()
+            .unwrap()
+            .stdout;
+        let second_pane_id = String::from_utf8(second_pane_id).unwrap();
+
+        block_on(async {
+            let environment = Environment::parse(&tmux, Some(second_pane_id.trim_end())).unwrap();
+            let current = environment.current().await.unwrap();
+            assert_eq!(current.session.name, "inner");
+            assert_eq!(current.pane.id.as_str(), second_pane_id.trim_end());
+            assert!(!current.pane.is_active);
+            assert_eq!(
+                current.window.pane_ids(),
+                vec![
+                    PaneId::from_str(pane_id).unwrap(),
+                    PaneId::from_str(second_pane_id.trim_end()).unwrap()
+                ]
+            );
+
+            // Without a pane, as in `run-shell`, or with a pane of another server.
+            for tmux_pane in [None, Some("%999")] {
+                let environment = Environment::parse(&tmux, tmux_pane).unwrap();
+                let current = environment.current().await.unwrap();
+                assert_eq!(current.session.name, "inner");
+                assert_eq!(current.pane.id.as_str(), pane_id);
+                assert!(current.pane.is_active);
+            }
+        });
+
+        // A pane of another session, as an outer `$TMUX_PANE` also existing on the inner server.
+        let output = Command::new("tmux")
+            .args([
+                "-L",
+                &socket_name,
+                "new-session",
+                "-d",
+                "-s",
+                "other",
+                "-P",
+                "-F",
+            ])
+            .arg("#{session_id}:#{pane_id}")
+            .output()
+            .unwrap();
+        let printed = String::from_utf8(output.stdout).unwrap();
+        let (other_session_id, other_pane_id) = printed.trim_end().split_once(':').unwrap();
+        block_on(async {
+            let environment = Environment::parse(&tmux, Some(other_pane_id)).unwrap();
+            let current = environment.current().await.unwrap();
+            assert_eq!(current.session.name, "inner");
+            assert_eq!(current.pane.id.as_str(), pane_id);
+        });
+
+        // A pane of a window linked to the session of `$TMUX` and to another session.
+        let status = Command::new("tmux")
+            .args([
+                "-L",
+                &socket_name,
+                "link-window",
+                "-s",
+                "inner:0",
+                "-t",
+                "other:5",
+            ])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let (socket_and_pid, _) = tmux.rsplit_once(',').unwrap();
+        let other_tmux = format!("{socket_and_pid},{}", &other_session_id[1..]);
+        block_on(async {
+            let environment =
+                Environment::parse(&other_tmux, Some(second_pane_id.trim_end())).unwrap();
+            let current = environment.current().await.unwrap();
+            assert_eq!(current.session.name, "other");
+            assert_eq!(current.window.index, 5);
+            assert_eq!(current.pane.id.as_str(), second_pane_id.trim_end());
+        });
+    }
+}
+
+// ============================================================================
+// Pane Log Tests
+// ============================================================================
+
+mod pane_log_tests {
+    use super::*;
+    use std::time::Duration;
+    use tmux_lib::{
+        pane::{is_pane_piped, stop_pipe_pane},
+        pane_log::PaneLogger,
+        session::NewSession,
+    };
+
+    #[test]
+    fn test_log_and_rotate_pane_output() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("pane-log");
+        let _guard = SessionGuard::new(&session_name);
+        let directory = std::env::temp_dir().join(&session_name);
+
+        block_on(async {
+            let (_, _, pane_id) = NewSession::new()
+                .name(&session_name)
+                .command("sh")
+                .run()
+                .await
+                .unwrap();
+            assert!(!is_pane_piped(&pane_id).await.unwrap());
+
+            let mut logger = PaneLogger::new(&directory)
+                .max_size(1)
+                .max_files(1)
+                .strip_ansi(true);
+            logger.start(&pane_id).await.unwrap();
+            assert!(is_pane_piped(&pane_id).await.unwrap());
+
+            let send = |text: &str| {
+                Command::new("tmux")
+                    .args(["send-keys", "-t", pane_id.as_str(), text, "Enter"])
+                    .output()
+                    .unwrap();
+            };
+            send("printf '\\033[31mfir''st\\033[0m\\n'");
+            sleep(Duration::from_millis(300)).await;
+            assert_eq!(logger.rotate().await.unwrap(), vec![pane_id.clone()]);
+            assert!(is_pane_piped(&pane_id).await.unwrap());
+
+            send("echo sec''ond");
+            sleep(Duration::from_millis(300)).await;
+            logger.stop(&pane_id).await.unwrap();
+            assert!(!is_pane_piped(&pane_id).await.unwrap());
+            assert!(logger.panes().is_empty());
+            // Stopping an unpiped pane succeeds.
+            stop_pipe_pane(&pane_id).await.unwrap();
+
+            let mut rotated = logger.log_path(&pane_id).into_os_string();
+            rotated.push(".1");
+            let rotated = std::fs::read_to_string(rotated).unwrap();
+            assert!(rotated.contains("\nfirst\n"));
+            assert!(!rotated.contains('\x1b'));
+            let current = std::fs::read_to_string(logger.log_path(&pane_id)).unwrap();
+            assert!(current.contains("\nsecond\n"));
+            assert!(!current.contains("first"));
+
+            // The log files were only rewritten once their `cat` process had exited.
+            let mut files: Vec<String> = std::fs::read_dir(&directory)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            let log = logger.log_path(&pane_id);
+            let log = log.file_name().unwrap().to_string_lossy();
+            assert_eq!(files, [log.to_string(), format!("{log}.1")]);
+        });
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_stop_logging_closed_pane() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("pane-log-closed");
+        let _guard = SessionGuard::new(&session_name);
+        let directory = std::env::temp_dir().join(&session_name);
+
+        block_on(async {
+            let (_, window_id, pane_id) = NewSession::new()
+                .name(&session_name)
+                .command("sh")
+                .run()
+                .await
+                .unwrap();
+            let split = Command::new("tmux")
+                .args(["split-window", "-d", "-P", "-F", "#{pane_id}"])
+                .args(["-t", window_id.as_str(), "sh"])
+                .output()
+                .unwrap();
+            let second_pane_id =
+                PaneId::from_str(String::from_utf8(split.stdout).unwrap().trim_end()).unwrap();
+
+            let mut logger = PaneLogger::new(&directory).strip_ansi(true);
+            logger.start(&pane_id).await.unwrap();
+            logger.start(&second_pane_id).await.unwrap();
+            Command::new("tmux")
+                .args(["kill-pane", "-t", pane_id.as_str()])
+                .output()
+                .unwrap();
+
+            // The closed pane is still logged, until the next rotation.
+            assert_eq!(logger.panes().len(), 2);
+            logger.stop_all().await.unwrap();
+            assert!(logger.panes().is_empty());
+            assert!(!is_pane_piped(&second_pane_id).await.unwrap());
+        });
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
+
+// ============================================================================
+// Asciicast Tests
+// ============================================================================
+
+mod asciicast_tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tmux_lib::{asciicast, session::NewSession};
+
+    #[test]
+    fn test_record_pane_until_closed() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("asciicast");
+        let _guard = SessionGuard::new(&session_name);
+
+        let (_, _, pane_id) = block_on(
+            NewSession::new()
+                .name(&session_name)
+                .size(40, 10)
+                .command("sh")
+                .run(),
+        )
+        .unwrap();
+        // The recorded pane is not in the current window, which must stay current.
+        let session_target = format!("={session_name}:");
+        Command::new("tmux")
+            .args(["new-window", "-t", &session_target])
+            .output()
+            .unwrap();
+        let current_window = || {
+            let output = Command::new("tmux")
+                .args(["display-message", "-p", "-t", &session_target])
+                .arg("#{window_index}")
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let before = current_window();
+
+        let (sender, receiver) = mpsc::channel();
+        let recorded_pane_id = pane_id.clone();
+        std::thread::spawn(move || {
+            let cast = block_on(asciicast::record(&recorded_pane_id, vec![]));
+            let _ = sender.send(cast);
+        });
+
+        // Let the client attach before producing output.
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(current_window(), before);
+        let tmux = |args: &[&str]| {
+            Command::new("tmux").args(args).output().unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+        };
+        tmux(&[
+            "send-keys",
+            "-t",
+            pane_id.as_str(),
+            "echo rec''orded",
+            "Enter",
+        ]);
+        tmux(&["split-window", "-d", "-h", "-t", pane_id.as_str()]);
+        tmux(&["kill-pane", "-t", pane_id.as_str()]);
+
+        let cast = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("recording did not stop when the pane was closed")
+            .unwrap();
+        let cast = String::from_utf8(cast).unwrap();
+        let mut lines = cast.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with(r#"{"version": 2, "width": 40, "height": 10, "#)
+        );
+        assert!(cast.contains(r#"recorded\r\n"#));
+        assert!(lines.any(|line| line.ends_with(r#", "r", "20x10"]"#)));
+    }
+}
+
+// ============================================================================
+// Control Mode Tests
+// ============================================================================
+
+mod control_tests {
+    use super::*;
+    use std::time::Duration;
+    use tmux_lib::{
+        control::{Connection, Notification, SubscriptionTarget},
+        error::Error,
+        session::NewSession,
+    };
+
+    #[test]
+    fn test_subscribe_to_pane_format() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("control");
+        let _guard = SessionGuard::new(&session_name);
+
+        block_on(async {
+            let (session_id, _, pane_id) =
+                NewSession::new().name(&session_name).run().await.unwrap();
+            let mut connection = Connection::attach(&session_id).await.unwrap();
+
+            let output = connection
+                .command([
+                    "display-message",
+                    "-p",
+                    "-t",
+                    pane_id.as_str(),
+                    "#{pane_id}",
+                ])
+                .await
+                .unwrap();
+            assert_eq!(output, vec![pane_id.as_str().to_string()]);
+
+            let error = connection.command(["bogus-command"]).await.unwrap_err();
+            assert!(matches!(error, Error::ControlCommand { .. }));
+
+            connection
+                .subscribe("title", SubscriptionTarget::Pane(&pane_id), "#{pane_title}")
+                .await
+                .unwrap();
+            Command::new("tmux")
+                .args(["select-pane", "-t", pane_id.as_str(), "-T", "watched"])
+                .output()
+                .unwrap();
+
+            let poll = async {
+                loop {
+                    match connection.next().await.unwrap() {
+                        Some(Notification::SubscriptionChanged(change))
+                            if change.value == "watched" =>
+                        {
+                            break change;
+                        }
+                        Some(_) => {}
+                        None => panic!("control mode client exited"),
+                    }
+                }
+            };
+            let timeout = async {
+                sleep(Duration::from_secs(5)).await;
+                panic!("no subscription change received");
+            };
+            let change = select(poll, timeout).await;
+            assert_eq!(change.name, "title");
+            assert_eq!(change.session_id, session_id);
+            assert_eq!(change.pane_id, Some(pane_id));
+        });
+    }
+
+    /// Await the first of two futures.
+    async fn select<T>(
+        first: impl std::future::Future<Output = T>,
+        second: impl std::future::Future<Output = T>,
+    ) -> T {
+        let mut first = std::pin::pin!(first);
+        let mut second = std::pin::pin!(second);
+        std::future::poll_fn(|cx| {
+            if let std::task::Poll::Ready(value) = first.as_mut().poll(cx) {
+                return std::task::Poll::Ready(value);
+            }
+            second.as_mut().poll(cx)
+        })
+        .await
+    }
+}
+
+// ============================================================================
+// Watch Tests
+// ============================================================================
+
+mod watch_tests {
+    use super::*;
+    use std::time::Duration;
+    use tmux_lib::{
+        session::NewSession,
+        watch::{Event, Watcher},
+    };
+
+    #[test]
+    fn test_watcher_reports_changes() {
+        if !tmux_available() {
+            eprintln!("Skipping test: tmux not available");
+            return;
+        }
+
+        let session_name = unique_session_name("watch");
+        let renamed = format!("{session_name}-renamed");
+        let _guard = SessionGuard::new(&session_name);
+        let _renamed_guard = SessionGuard::new(&renamed);
+
+        block_on(async {
+            let (session_id, window_id, _) =
+                NewSession::new().name(&session_name).run().await.unwrap();
+            let mut watcher = Watcher::new()
+                .interval(Duration::from_millis(50))
+                .start()
+                .await
+                .unwrap();
+
+            session::rename_session(&session_id, &renamed)
+                .await
+                .unwrap();
+            window::rename_window(&window_id, "watched").await.unwrap();
+
+            // Other tests change the server concurrently: only the events of this session are
+            // checked.
+            let expected = [
+                Event::SessionRenamed {
+                    session_id: session_id.clone(),
+                    name: renamed.clone(),
+                },
+                Event::WindowRenamed {
+                    window_id: window_id.clone(),
+                    name: "watched".to_string(),
+                },
+            ];
+            let mut events = vec![];
+            while !expected.iter().all(|event| events.contains(event)) {
+                events.push(watcher.next().await.unwrap());
+            }
+
+            session::kill_session(&session_id).await.unwrap();
+            loop {
+                if let Event::SessionClosed { session_id: id } = watcher.next().await.unwrap()
+                    && id == session_id
+                {
+                    break;
+                }
+            }
+        });
+    }
+}